@@ -1,35 +1,488 @@
-use color_eyre::Result;
-use dotenv::dotenv;
-use serde::Deserialize;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use schemars::{schema_for, JsonSchema};
+use scraped::{
+    chain::Chain,
+    children::ChildRules,
+    expression::Expression,
+    extract::Extractor,
+    lookup::{Lookup, LookupKey},
+    postprocess::PostProcessor,
+    scrub::Scrubber,
+    selection::parse_selector,
+    transforms::Transform,
+    ChildScope, DuplicatePolicy, ParsedDoc, ScrapeConfig,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tokio::fs;
+use tracing::trace;
 
-#[derive(Debug, Deserialize)]
-pub enum LogLevel {
-    Debug(),
-    Info(),
-    Warn(),
-    Error(),
+/// The file formats which a configuration file can be written in; the
+/// format is detected by the file's extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
 }
 
-#[derive(Debug, Deserialize)]
+impl ConfigFormat {
+    /// determines the format of a configuration file from its extension
+    pub fn from_path(path: &Path) -> Result<ConfigFormat> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+
+        match ext.as_deref() {
+            Some("json") => Ok(ConfigFormat::Json),
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("yaml") | Some("yml") => Ok(ConfigFormat::Yaml),
+            _ => Err(eyre!(
+                "the config file '{}' must have a .json, .toml, .yaml or .yml extension",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// The user's configuration of a scrape; this can be expressed in JSON, TOML or
+/// YAML but regardless of format it shares the same schema.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
-    pub log_level: LogLevel,
+    /// selectors where there is expected to be _at most_ one match on the page
+    pub selectors: BTreeMap<String, String>,
+    /// selectors which are expected to bring back a _list_ of results
+    pub list_selectors: BTreeMap<String, String>,
+    /// selectors for `<table>` elements which are extracted into headers and rows
+    pub tables: BTreeMap<String, String>,
+    /// steps which post-process the results of list selectors (e.g., sorting or
+    /// removing duplicates), keyed by the selector's name
+    pub post_process: BTreeMap<String, Vec<PostProcessor>>,
+    /// chained selector expressions which select structured data, e.g.
+    /// `.card >> {title: .t@text, link: a@href}`
+    pub chains: BTreeMap<String, String>,
+    /// selectors which point to child pages that are followed with `--follow`
+    pub children: ChildConfig,
+    /// properties derived from the selections by a JMESPath-style expression
+    /// (e.g., `links[*].full_href`), keyed by the property's name
+    pub properties: BTreeMap<String, String>,
+    /// built-in extractors which emit properties of the same name
+    pub extract: Vec<Extractor>,
+    /// properties which transform the result of a selector, keyed by the property's name
+    pub transforms: BTreeMap<String, TransformConfig>,
+    /// tables whose matching rows are joined to the results of each page
+    pub lookups: Vec<LookupConfig>,
+    /// how pages are requested when following child pages
+    pub scrape: ScrapeConfig,
+    /// the personal data (e.g., email addresses) which is masked in the output
+    pub scrub: Scrubber,
+}
+
+/// A CSV or JSON table whose rows are joined to the pages they match, e.g.:
+///
+/// ```yaml
+/// lookups:
+///   - path: catalog.csv
+///     column: url
+///   - path: stock.json
+///     column: sku
+///     field: sku
+/// ```
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LookupConfig {
+    /// the `.csv` or `.json` file holding the table
+    pub path: PathBuf,
+    /// the column of the table which is matched
+    pub column: String,
+    /// the property or selector of a page which is matched to the column; the
+    /// page's URL is matched when not set
+    #[serde(default)]
+    pub field: Option<String>,
+}
+
+impl LookupConfig {
+    /// loads the table
+    pub fn load(&self) -> Result<Lookup> {
+        let key = match &self.field {
+            Some(field) => LookupKey::Field(field.to_string()),
+            None => LookupKey::Url,
+        };
+
+        Lookup::load(&self.path, &self.column, key)
+    }
+}
+
+/// A property which transforms the result of a selector, e.g.:
+///
+/// ```yaml
+/// transforms:
+///   published:
+///     selector: date
+///     type: date
+///     locale: de
+/// ```
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TransformConfig {
+    /// the name of the selector whose result is transformed
+    pub selector: String,
+    #[serde(flatten)]
+    pub transform: Transform,
+}
+
+/// The selectors whose `href` point to child pages, e.g.:
+///
+/// ```yaml
+/// children:
+///   selectors: [links, next]
+///   scope: relative
+///   rules:
+///     hosts: same_domain
+///     deny: ["/login"]
+///   selector_rules:
+///     next:
+///       path_prefix: /docs/
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChildConfig {
+    /// the names of the selectors which point to child pages
+    pub selectors: Vec<String>,
+    /// which of the selected `href`s are followed
+    pub scope: Scope,
+    /// the rules the child URLs of every selector must satisfy
+    pub rules: ChildRules,
+    /// rules which take the place of `rules` for a selector
+    pub selector_rules: BTreeMap<String, ChildRules>,
+}
+
+/// Mirrors `ChildScope` for use in a configuration file
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    All,
+    Relative,
+    Absolute,
+    #[default]
+    Http,
+    File,
+}
+
+impl From<Scope> for ChildScope {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::All => ChildScope::All(),
+            Scope::Relative => ChildScope::Relative(),
+            Scope::Absolute => ChildScope::Absolute(),
+            Scope::Http => ChildScope::Http(),
+            Scope::File => ChildScope::File(),
+        }
+    }
 }
 
 impl Config {
-    pub fn new() -> Config {
-        Config {
-            log_level: LogLevel::Debug(),
+    /// the JSON Schema which describes the configuration file format
+    pub fn schema() -> Result<String> {
+        Ok(serde_json::to_string_pretty(&schema_for!(Config))?)
+    }
+
+    /// parses the configuration from a string in the given format; errors point
+    /// to the offending key and -- where the format provides it -- the line
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Config> {
+        let config: Config = match format {
+            ConfigFormat::Json => deserialize(&mut serde_json::Deserializer::from_str(content))?,
+            ConfigFormat::Toml => deserialize(&mut toml::Deserializer::new(content))?,
+            ConfigFormat::Yaml => deserialize(serde_yaml::Deserializer::from_str(content))?,
+        };
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// validates the values of the configuration which the schema alone can't
+    /// express (e.g., that selectors are valid CSS)
+    pub fn validate(&self) -> Result<()> {
+        [
+            ("selectors", &self.selectors),
+            ("list_selectors", &self.list_selectors),
+            ("tables", &self.tables),
+        ]
+        .iter()
+        .flat_map(|(section, selectors)| {
+            selectors
+                .iter()
+                .map(move |(name, sel)| (format!("{}.{}", section, name), sel))
+        })
+        .try_for_each(|(key, sel)| {
+            parse_selector(sel)
+                .map(|_| ())
+                .context(format!("invalid config value at `{}`", key))
+        })?;
+
+        self.post_process.iter().try_for_each(|(name, steps)| {
+            steps.iter().enumerate().try_for_each(|(i, step)| {
+                step.validate().context(format!(
+                    "invalid config value at `post_process.{}[{}]`",
+                    name, i
+                ))
+            })
+        })?;
+
+        self.children
+            .rules
+            .validate()
+            .context("invalid config value at `children.rules`")?;
+        self.children
+            .selector_rules
+            .iter()
+            .try_for_each(|(name, rules)| {
+                rules.validate().context(format!(
+                    "invalid config value at `children.selector_rules.{}`",
+                    name
+                ))
+            })?;
+
+        if self.scrape.duplicate_selectors == DuplicatePolicy::Error {
+            let mut sections: BTreeMap<&String, &str> = BTreeMap::new();
+            [
+                ("selectors", self.selectors.keys().collect::<Vec<_>>()),
+                ("list_selectors", self.list_selectors.keys().collect()),
+                ("tables", self.tables.keys().collect()),
+                ("chains", self.chains.keys().collect()),
+            ]
+            .into_iter()
+            .flat_map(|(section, names)| names.into_iter().map(move |name| (section, name)))
+            .try_for_each(|(section, name)| match sections.insert(name, section) {
+                Some(first) => Err(eyre!(
+                    "the name `{}` is used by both `{}` and `{}`",
+                    name,
+                    first,
+                    section
+                ))
+                .context("invalid config: `scrape.duplicate_selectors` is `error`"),
+                None => Ok(()),
+            })?;
         }
+
+        self.chains.iter().try_for_each(|(name, chain)| {
+            Chain::from_str(chain)
+                .map(|_| ())
+                .context(format!("invalid config value at `chains.{}`", name))
+        })?;
+
+        self.properties.iter().try_for_each(|(name, expression)| {
+            Expression::from_str(expression)
+                .map(|_| ())
+                .context(format!("invalid config value at `properties.{}`", name))
+        })
+    }
+
+    /// loads a configuration file from disk, using the file's extension to
+    /// determine which format it is in
+    pub async fn load(path: &Path) -> Result<Config> {
+        let format = ConfigFormat::from_path(path)?;
+        trace!("loading {:?} config from {}", format, path.display());
+        let content = fs::read_to_string(path).await.context(format!(
+            "Failed to read the config file: {}",
+            path.display()
+        ))?;
+
+        Config::parse(&content, format).context(format!(
+            "Failed to parse the config file: {}",
+            path.display()
+        ))
     }
 
-    pub fn from_env() -> Result<Config> {
-        dotenv().ok();
-        let c = Config::new();
-        // c.merge(config::Environment::default())?;
+    /// serializes the configuration in the given format
+    pub fn to_string(&self, format: ConfigFormat) -> Result<String> {
+        let content = match format {
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(self)?,
+        };
+
+        Ok(content)
+    }
+
+    /// writes the configuration to disk, using the file's extension to
+    /// determine which format to write it in
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        let content = self.to_string(ConfigFormat::from_path(path)?)?;
+        fs::write(path, content).await.context(format!(
+            "Failed to write the config file: {}",
+            path.display()
+        ))
+    }
+
+    /// Adds the configured selectors, child selectors and properties to a
+    /// `ParsedDoc`. A selector whose name is taken -- e.g., by a preset such as
+    /// the generic selectors -- is an error when the `duplicate_selectors`
+    /// policy is `error`.
+    pub fn apply(&self, doc: ParsedDoc) -> Result<ParsedDoc> {
+        let doc = self
+            .selectors
+            .iter()
+            .try_fold(doc, |doc, (name, sel)| doc.try_add_selector(name, sel))?;
+        let doc = self
+            .list_selectors
+            .iter()
+            .try_fold(doc, |doc, (name, sel)| doc.try_add_selector_all(name, sel))?;
+        let doc = self.tables.iter().try_fold(doc, |doc, (name, sel)| {
+            doc.try_add_table_selector(name, sel)
+        })?;
+
+        let doc = self.post_process.iter().fold(doc, |doc, (name, steps)| {
+            steps
+                .iter()
+                .fold(doc, |doc, step| doc.add_post_processor(name, step.clone()))
+        });
+        let doc = self
+            .chains
+            .iter()
+            .try_fold(doc, |doc, (name, chain)| doc.try_add_chain(name, chain))?;
+        let doc = self.children.selectors.iter().fold(doc, |doc, name| {
+            let rules = self
+                .children
+                .selector_rules
+                .get(name)
+                .unwrap_or(&self.children.rules);
+            doc.child_selectors_with_rules(
+                vec![name.as_str()],
+                self.children.scope.into(),
+                rules.clone(),
+            )
+        });
+        let doc = self
+            .properties
+            .iter()
+            .try_fold(doc, |doc, (name, e)| doc.try_add_expression(name, e))?;
+        let doc = self
+            .extract
+            .iter()
+            .fold(doc, |doc, e| doc.add_extractor(*e));
+
+        Ok(self.transforms.iter().fold(doc, |doc, (name, t)| {
+            doc.add_transform(name, &t.selector, t.transform.clone())
+        }))
+    }
+}
+
+/// deserializes with a deserializer of any format while tracking the path to
+/// the offending key; each format's own error already reports the line and column
+fn deserialize<'de, D, T>(deserializer: D) -> Result<T>
+where
+    D: serde::Deserializer<'de>,
+    D::Error: std::fmt::Display,
+    T: DeserializeOwned,
+{
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|e| eyre!("invalid config at `{}`: {}", e.path(), e.inner()))
+}
 
-        // c.try_into()
-        //     .context("loading configuration from environment");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the message of the error along with its context
+    fn error(content: &str, format: ConfigFormat) -> String {
+        format!("{:#}", Config::parse(content, format).unwrap_err())
+    }
+
+    #[tokio::test]
+    async fn a_valid_file_is_loaded_in_each_format() {
+        let dir = std::env::temp_dir().join(format!("scraped-cli-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).await.unwrap();
+        let files = [
+            (
+                "scrape.toml",
+                "[selectors]\ntitle = \"h1\"\n\n[scrape]\nconcurrency = 3\ntimeout = \"5s\"\n",
+            ),
+            (
+                "scrape.yml",
+                "selectors:\n  title: h1\nscrape:\n  concurrency: 3\n  timeout: 5s\n",
+            ),
+            (
+                "scrape.json",
+                r#"{"selectors": {"title": "h1"}, "scrape": {"concurrency": 3, "timeout": "5s"}}"#,
+            ),
+        ];
+        for (name, content) in files {
+            let path = dir.join(name);
+            fs::write(&path, content).await.unwrap();
+            let config = Config::load(&path).await.unwrap();
+
+            assert_eq!(config.selectors["title"], "h1", "{}", name);
+            assert_eq!(
+                config.scrape,
+                ScrapeConfig::default()
+                    .concurrency(3)
+                    .timeout(std::time::Duration::from_secs(5)),
+                "{}",
+                name
+            );
+            assert!(config.list_selectors.is_empty());
+        }
+        fs::remove_dir_all(&dir).await.unwrap();
+
+        let error = Config::load(Path::new("scrape.ini")).await.unwrap_err();
+        assert!(error.to_string().contains(".json, .toml, .yaml or .yml"));
+    }
+
+    #[test]
+    fn an_unknown_key_is_reported_with_its_path() {
+        let toml = error(
+            "[selectors]\ntitle = \"h1\"\n\n[scrape]\nconcurency = 3\n",
+            ConfigFormat::Toml,
+        );
+        assert!(
+            toml.starts_with("invalid config at `scrape.concurency`: unknown field `concurency`"),
+            "{}",
+            toml
+        );
+
+        let yaml = error("title: h1\n", ConfigFormat::Yaml);
+        assert!(
+            yaml.starts_with("invalid config at `title`: unknown field `title`"),
+            "{}",
+            yaml
+        );
+        assert!(yaml.contains("line 1"), "{}", yaml);
+    }
+
+    #[test]
+    fn a_parse_error_reports_its_line() {
+        let toml = error(
+            "[selectors]\ntitle = \"h1\"\nlinks = \n",
+            ConfigFormat::Toml,
+        );
+        assert!(toml.ends_with("at line 3 column 9"), "{}", toml);
+
+        let yaml = error(
+            "selectors:\n  title: h1\n list_selectors: [\n",
+            ConfigFormat::Yaml,
+        );
+        assert!(yaml.contains("at line 3"), "{}", yaml);
+
+        let json = error("{\n  \"selectors\": {\"title\": h1}\n}", ConfigFormat::Json);
+        assert!(json.contains("line 2"), "{}", json);
+    }
 
-        Ok(c)
+    #[test]
+    fn an_invalid_value_is_reported_with_its_key() {
+        let yaml = error("selectors:\n  title: \"h1[\"\n", ConfigFormat::Yaml);
+        assert!(
+            yaml.starts_with("invalid config value at `selectors.title`"),
+            "{}",
+            yaml
+        );
     }
 }
@@ -0,0 +1,101 @@
+use crate::config::Config;
+use color_eyre::Result;
+use scraped::Document;
+use std::path::Path;
+use tracing::trace;
+
+/// A selector which _might_ be useful on a page; it is only proposed
+/// when it actually matches something on the page being scraped.
+struct Candidate {
+    name: &'static str,
+    selector: &'static str,
+    /// whether the selector is expected to return a list of results
+    list: bool,
+    /// the minimum number of matches needed before a list is worth proposing
+    min_matches: usize,
+}
+
+const CANDIDATES: [Candidate; 8] = [
+    Candidate {
+        name: "title",
+        selector: "title",
+        list: false,
+        min_matches: 1,
+    },
+    Candidate {
+        name: "description",
+        selector: "meta[name='description']",
+        list: false,
+        min_matches: 1,
+    },
+    Candidate {
+        name: "h1",
+        selector: "h1",
+        list: false,
+        min_matches: 1,
+    },
+    Candidate {
+        name: "headings",
+        selector: "h2, h3",
+        list: true,
+        min_matches: 1,
+    },
+    Candidate {
+        name: "nav_links",
+        selector: "nav a[href]",
+        list: true,
+        min_matches: 1,
+    },
+    Candidate {
+        name: "article",
+        selector: "article",
+        list: false,
+        min_matches: 1,
+    },
+    Candidate {
+        name: "main",
+        selector: "main",
+        list: false,
+        min_matches: 1,
+    },
+    Candidate {
+        name: "list_items",
+        selector: "ul > li",
+        list: true,
+        min_matches: 3,
+    },
+];
+
+/// Fetches the page at `url`, proposes the candidate selectors which match
+/// content on the page and writes them as a starter configuration file.
+pub async fn init(url: &str, output: &Path) -> Result<Config> {
+    let doc = CANDIDATES.iter().fold(
+        Document::new(url)?
+            .load_document()
+            .await?
+            .parse_document()?,
+        |doc, c| match c.list {
+            true => doc.add_selector_all(c.name, c.selector),
+            false => doc.add_selector(c.name, c.selector),
+        },
+    );
+    let results = doc.results()?;
+
+    let mut config = Config::default();
+    CANDIDATES.iter().for_each(|c| {
+        let matches = results.data.get(c.name).map_or(0, |r| r.selections().len());
+        trace!("candidate '{}' matched {} elements", c.name, matches);
+
+        if matches >= c.min_matches {
+            let section = match c.list {
+                true => &mut config.list_selectors,
+                false => &mut config.selectors,
+            };
+            section.insert(c.name.to_string(), c.selector.to_string());
+        }
+    });
+
+    config.save(output).await?;
+
+    Ok(config)
+}
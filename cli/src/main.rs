@@ -1,94 +1,1282 @@
-use clap::Parser;
-use color_eyre::Result;
-use serde_json::json;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Report, Result,
+};
+use serde_json::{json, Value};
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 use tokio::fs;
 use tracing::{debug, info};
-// use config;
+use url::Url;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// The URL to inspect
-    url: String,
+    #[clap(required = true)]
+    url: Option<String>,
 
     #[clap(short, long, parse(from_os_str))]
     /// the file where JSON results will be saved
     output: Option<PathBuf>,
 
+    #[clap(long, parse(from_os_str))]
+    /// write the page's HTML -- with the elements matched by each selector highlighted -- to this file
+    annotate: Option<PathBuf>,
+
     #[clap(short, long)]
     /// Follow document into child links
     follow: bool,
 
+    #[clap(long)]
+    /// List the child links which --follow would request -- and why the others wouldn't be --
+    /// without requesting them
+    dry_run: bool,
+
+    #[clap(long)]
+    /// Submit the pages scraped with --follow to the Wayback Machine's save API
+    archive: bool,
+
+    #[clap(long, parse(from_os_str))]
+    /// Store the pages scraped with --follow in this SQLite database; requires the `sqlite` feature
+    sqlite: Option<PathBuf>,
+
+    #[clap(long, parse(from_os_str))]
+    /// Archive each request and response in this WARC file (gzipped when it ends in .gz);
+    /// requires the `warc` feature
+    warc: Option<PathBuf>,
+
+    #[clap(long)]
+    /// Encrypt the --output file and --sqlite store with the --key-file's key (or that of the
+    /// SCRAPED_ENCRYPTION_KEY environment variable); requires the `encryption` feature
+    encrypt: bool,
+
+    #[clap(long, parse(from_os_str), requires = "encrypt")]
+    /// The file holding the encryption key as hex (see the keygen command)
+    key_file: Option<PathBuf>,
+
+    #[clap(long)]
+    /// The number of child pages to request concurrently when following links [default: 8]
+    concurrency: Option<usize>,
+
+    #[clap(long)]
+    /// The most child pages of the same host to request concurrently
+    max_per_host: Option<usize>,
+
+    #[clap(long)]
+    /// Don't show a progress bar of the child pages while following links
+    no_progress: bool,
+
+    #[clap(long, value_name = "BYTES")]
+    /// Fail pages whose body is larger than this, without downloading the rest of it
+    max_body_bytes: Option<u64>,
+
+    #[clap(long = "accept", value_name = "CONTENT_TYPE")]
+    /// Only accept pages of this content type (e.g. "text/html" or "text/*"); may be repeated
+    accept: Vec<String>,
+
+    #[clap(long, value_name = "FRACTION")]
+    /// Follow this fraction of the child pages, chosen by a hash of their URLs (e.g., "0.1")
+    sample: Option<f64>,
+
+    #[clap(long, value_name = "N")]
+    /// Follow no more than N of the child pages in each directory
+    sample_per_dir: Option<usize>,
+
+    #[clap(long)]
+    /// Connect to this many of the hosts with the most child pages before requesting them
+    warmup: Option<usize>,
+
+    #[clap(long, parse(try_from_str = humantime::parse_duration))]
+    /// The shortest interval between requests to the same host (e.g., "500ms", "2s")
+    delay: Option<Duration>,
+
+    #[clap(long, conflicts_with = "delay")]
+    /// The most requests made to the same host per second (e.g., "0.5")
+    rate: Option<f64>,
+
+    #[clap(long, parse(try_from_str = humantime::parse_duration))]
+    /// The upper bound of a random pause added to each rate limited request (e.g., "1s")
+    jitter: Option<Duration>,
+
+    #[clap(long, parse(try_from_str = humantime::parse_duration))]
+    /// The longest a request may take before it fails (e.g., "30s")
+    timeout: Option<Duration>,
+
+    #[clap(long)]
+    /// The number of times a page is requested when it fails with a connection error, 429 or 5xx
+    retries: Option<u32>,
+
+    #[clap(long)]
+    /// Skip the pages a site's robots.txt disallows and honor its Crawl-delay
+    respect_robots: bool,
+
+    #[clap(long, parse(from_os_str))]
+    /// Cache pages in this directory and only download the pages which changed since the last run
+    cache: Option<PathBuf>,
+
+    #[clap(long, requires = "cache")]
+    /// Serve cached pages which are still fresh -- per their Cache-Control, Age and Expires headers -- without revalidating them, and don't cache no-store pages
+    honor_http_caching: bool,
+
+    #[clap(long)]
+    /// Scrape the latest Wayback Machine snapshot of pages which are gone or unreachable
+    archive_fallback: bool,
+
+    #[clap(long)]
+    /// Abort on the first child page which fails rather than recording the failure
+    fail_fast: bool,
+
+    #[clap(long)]
+    /// Capture the headers and body of error responses in the failure records
+    capture_errors: bool,
+
+    #[clap(long)]
+    /// Repair malformed HTML (e.g., unterminated comments) before it's parsed
+    repair: bool,
+
+    #[clap(long)]
+    /// Include the hydration payloads of single page apps (e.g., `__NEXT_DATA__`) as selections
+    hydration: bool,
+
+    #[clap(long)]
+    /// Include the OpenGraph, Twitter card and JSON-LD metadata of each page
+    metadata: bool,
+
+    #[clap(long)]
+    /// Normalize selection text to plain spaces and ASCII quotes, dashes and ellipses
+    ascii_text: bool,
+
+    #[clap(long)]
+    /// Scrape the same-origin documents embedded with `<iframe src>` along with each page
+    follow_frames: bool,
+
+    #[clap(long)]
+    /// Follow meta refresh and trivial JavaScript redirects
+    follow_redirects: bool,
+
+    #[clap(long)]
+    /// Don't follow HTTP redirects; a page which redirects fails instead
+    no_redirects: bool,
+
+    #[clap(long)]
+    /// Follow the pages which come after the page -- by rel="next" in their Link header or HTML
+    paginate: bool,
+
+    #[clap(long)]
+    /// The most pages followed after the first with --paginate [default: 10]
+    max_pages: Option<usize>,
+
+    #[clap(long)]
+    /// Merge the pages which declare the same rel="canonical" page into one record
+    dedupe_canonical: bool,
+
+    #[clap(long)]
+    /// Check every href and src of each page with a HEAD request into its link_report
+    audit_links: bool,
+
+    #[clap(long, requires = "audit-links")]
+    /// Only check the links to each page's own host with --audit-links
+    audit_same_host: bool,
+
+    #[clap(long)]
+    /// The maximum number of client-side redirects followed for a page
+    max_redirects: Option<usize>,
+
+    #[clap(long)]
+    /// Emit a built-in property extracted from the page: emails, phones, social or url;
+    /// may be repeated
+    extract: Vec<Extractor>,
+
+    #[clap(long = "allow-domain")]
+    /// Refuse to request pages outside of this domain and its subdomains; may be repeated
+    allow_domains: Vec<String>,
+
+    #[clap(long)]
+    /// Refuse to request non-HTTP URLs and hosts which resolve to private or loopback addresses
+    block_private_networks: bool,
+
+    #[clap(long)]
+    /// Resolve hostnames with this DNS-over-HTTPS endpoint (e.g. "https://cloudflare-dns.com/dns-query");
+    /// requires the `doh` feature
+    doh: Option<String>,
+
+    #[clap(long, conflicts_with = "prefer-ipv6")]
+    /// Connect over IPv4 first when a host has both IPv4 and IPv6 addresses
+    prefer_ipv4: bool,
+
+    #[clap(long)]
+    /// Connect over IPv6 first when a host has both IPv4 and IPv6 addresses
+    prefer_ipv6: bool,
+
+    #[clap(long)]
+    /// Render pages in a headless Chrome before parsing them; requires the `render` feature
+    render: bool,
+
+    #[clap(long, requires = "render")]
+    /// When rendering, wait for this CSS selector to match before parsing the page
+    wait_for: Option<String>,
+
+    #[clap(long, requires = "render")]
+    /// When rendering, wait for the page's network requests to settle before parsing it
+    network_idle: bool,
+
+    #[clap(long)]
+    /// Export the spans of the crawl to this OpenTelemetry collector (e.g. "http://localhost:4317");
+    /// requires the `otel` feature
+    otlp: Option<String>,
+
+    #[clap(long = "proxy")]
+    /// Route requests through this proxy (http, https or -- with the `socks` feature -- socks5);
+    /// requests are spread across the proxies in turn when repeated
+    proxies: Vec<Url>,
+
+    #[clap(long)]
+    /// Send the bearer tokens in the SCRAPED_TOKEN and SCRAPED_TOKEN_<DOMAIN> environment variables
+    /// (e.g. SCRAPED_TOKEN_API_GITHUB_COM) with the requests to their domain
+    env_tokens: bool,
+
+    #[clap(long)]
+    /// Make requests from this local address (e.g., that of a network interface)
+    local_address: Option<IpAddr>,
+
+    #[clap(long = "locale")]
+    /// Also follow the page's hreflang alternates for a locale (e.g. "de" or "fr-CA");
+    /// may be repeated
+    locales: Vec<String>,
+
     #[clap(long)]
     /// Flatten results to a JSON array of pages
     flatten: bool,
 
+    #[clap(long)]
+    /// Mask the email addresses, phone numbers and IP addresses in the --output file
+    scrub: bool,
+
+    #[clap(long)]
+    /// Write a hash of each scrubbed value in its mask (e.g. "[email:8f3c0a1b2d4e5f60]")
+    scrub_hash: bool,
+
+    #[clap(long, default_value = "json")]
+    /// The format of the --output file: "json" or "jsonl" (a line of JSON for each page)
+    format: OutputFormat,
+
+    #[clap(long)]
+    /// Report how well each selector matched across the pages scraped
+    coverage: bool,
+
+    #[clap(long)]
+    /// Record in each page's `sources` whether a selector came from a preset, the config file or code
+    provenance: bool,
+
     #[clap(short, long)]
     /// Show a specific _selector_ as part of console output; use "all" to show all selectors and "props"
     /// to show only configured _properties_
     show: Option<String>,
 
     #[clap(short, long)]
-    /// Pass in a JSON configuration file to add your own selectors and properties
+    /// Pass in a configuration file (JSON, TOML or YAML) to add your own selectors and properties
     config: Option<PathBuf>,
 }
 
-use scraped::{results::FlatResult, Document, PropertyCallback};
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Scaffold a starter configuration file from the selectors which match a live page
+    Init {
+        /// The URL of the page to base the configuration on
+        url: String,
+
+        #[clap(short, long, parse(from_os_str), default_value = "scraped.yaml")]
+        /// the configuration file to write (JSON, TOML or YAML)
+        output: PathBuf,
+    },
+    /// Scrape a page again and report how its selections and properties differ from
+    /// the results of an earlier scrape
+    Diff {
+        #[clap(parse(from_os_str))]
+        /// The JSON results of the earlier scrape (e.g., written with --output)
+        old: PathBuf,
+
+        /// The URL of the page to scrape
+        url: String,
+
+        #[clap(short, long)]
+        /// Follow document into child links, as the earlier scrape did
+        follow: bool,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// The configuration file (JSON, TOML or YAML) the earlier scrape used
+        config: Option<PathBuf>,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// the file where the JSON differences will be saved; they're printed otherwise
+        output: Option<PathBuf>,
+    },
+    /// Scrape a page again on an interval and print -- or POST to a webhook -- only what changed
+    Watch {
+        /// The URL of the page to watch
+        url: String,
+
+        #[clap(long, default_value = "5m", parse(try_from_str = humantime::parse_duration))]
+        /// The time between scrapes (e.g., "30s" or "5m")
+        interval: Duration,
+
+        #[clap(short, long)]
+        /// Follow document into child links
+        follow: bool,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// The configuration file (JSON, TOML or YAML) to scrape with
+        config: Option<PathBuf>,
+
+        #[clap(long, parse(from_os_str))]
+        /// Persist the last snapshot to this file so that a restarted watch compares with it
+        state: Option<PathBuf>,
+
+        #[clap(long)]
+        /// POST the JSON differences of each change to this URL
+        webhook: Option<Url>,
+    },
+    /// Scrape the pages stored in a cache with two configurations and report where their
+    /// selectors' match rates and values differ (e.g., before adopting new selectors)
+    Compare {
+        #[clap(parse(from_os_str))]
+        /// The configuration file (JSON, TOML or YAML) currently in use
+        old: PathBuf,
+
+        #[clap(parse(from_os_str))]
+        /// The configuration file (JSON, TOML or YAML) to compare it with
+        new: PathBuf,
+
+        #[clap(long, parse(from_os_str))]
+        /// The cache directory (see --cache) whose pages are scraped
+        cache: PathBuf,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// the file where the JSON comparison will be saved
+        output: Option<PathBuf>,
+    },
+    /// Estimate how long following a page's child links will take and how many requests
+    /// each host will be sent, with the configured rate limits and concurrency
+    Estimate {
+        /// The URL of the page whose child links are followed
+        url: String,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// The configuration file (JSON, TOML or YAML) of the crawl
+        config: Option<PathBuf>,
+
+        #[clap(long, parse(try_from_str = humantime::parse_duration))]
+        /// The time each request is assumed to take (e.g., "300ms") [default: 500ms]
+        latency: Option<Duration>,
+    },
+    /// Summarize what a site asks of crawlers -- its robots.txt, sitemaps, meta robots and
+    /// rate limits -- and how a polite crawl of it should be configured
+    Policy {
+        /// A URL of the site
+        url: String,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// The configuration file (JSON, TOML or YAML) whose user agent and headers are sent
+        config: Option<PathBuf>,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// Write a starter configuration file for a polite crawl of the site
+        output: Option<PathBuf>,
+    },
+    /// Write a new encryption key to a file for use with --encrypt
+    #[cfg(feature = "encryption")]
+    Keygen {
+        #[clap(parse(from_os_str), default_value = "scraped.key")]
+        /// the keyfile to write
+        output: PathBuf,
+    },
+    /// Decrypt a file written with --encrypt
+    #[cfg(feature = "encryption")]
+    Decrypt {
+        #[clap(parse(from_os_str))]
+        /// The encrypted file
+        input: PathBuf,
+
+        #[clap(long, parse(from_os_str))]
+        /// The file holding the encryption key; SCRAPED_ENCRYPTION_KEY is used otherwise
+        key_file: Option<PathBuf>,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// the file where the decrypted results will be saved; they're printed otherwise
+        output: Option<PathBuf>,
+    },
+    /// Work with the configuration file format
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the JSON Schema of the configuration file
+    Schema,
+}
+
+/// the format results are written to the --output file in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// a single JSON document
+    Json,
+    /// a line of JSON for each page (JSON Lines)
+    Jsonl,
+}
+
+impl FromStr for OutputFormat {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" | "ndjson" => Ok(OutputFormat::Jsonl),
+            _ => Err(eyre!("'{}' is not an output format; use json or jsonl", s)),
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+use scraped::encryption::EncryptionKey;
+use scraped::{
+    archive::WaybackSink,
+    compare::Comparison,
+    estimate::{Estimate, DEFAULT_LATENCY},
+    fetch::SharedFetcher,
+    lookup::Lookup,
+    network::IpPreference,
+    policy::{SitePolicy, POLITE_RATE},
+    prelude::*,
+    rate::RateLimit,
+    report,
+    results::{diff::ResultsDiff, writers::JsonLinesWriter},
+    scrub::Scrubber,
+    watch::Watcher,
+};
+mod config;
+mod init;
+mod progress;
 mod show;
-use show::show;
+use config::Config;
+use progress::ProgressBar;
+use show::{show, show_coverage};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
     // let format = tracing_subscriber::fmt::format().compact();
-    tracing_subscriber::fmt::init();
+    init_tracing(args.otlp.as_deref())?;
     // LogTracer::init()?;
     color_eyre::install()?;
 
-    let title: PropertyCallback =
-        |r| json!([r.get("title"), r.get("h1")].into_iter().flatten().next());
-
-    let args = Args::parse();
     debug!("CLI arguments parsed {:?}", args);
 
-    let doc = Document::new(&args.url)?
-        .load_document()
-        .await?
-        .for_docs_rs()
-        .add_generic_selectors()
-        .add_property("title", title);
+    if let Some(command) = &args.command {
+        return run_command(command).await;
+    }
+    let url = args
+        .url
+        .as_deref()
+        .expect("url is required without a subcommand");
+    let config = match &args.config {
+        Some(path) => Some(Config::load(path).await?),
+        None => None,
+    };
 
-    println!("- Parsed {} ", &args.url);
+    let cancel = Cancellation::new();
+    tokio::spawn(cancel_on_ctrl_c(cancel.clone()));
+    let scrape = scrape_config(&args, config.as_ref()).cancellation(cancel.clone());
+    let doc = Document::new(url)?.config(scrape.clone());
+    let paginate = scrape.pagination.follow;
+    let audit = scrape.link_audit.enabled;
+    // the pages which are requested -- rather than read from the cache -- are archived
+    #[cfg(feature = "warc")]
+    let network = match &args.warc {
+        Some(path) => {
+            let writer = scraped::warc::WarcWriter::create(path).await?;
+            let fetcher =
+                scraped::warc::WarcFetcher::new(writer, SharedFetcher::for_config(&scrape)?);
+            Some(SharedFetcher::new(fetcher))
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "warc"))]
+    let network = match args.warc {
+        Some(_) => return Err(eyre!("--warc requires the `warc` feature")),
+        None => None,
+    };
+    let doc = match (&args.cache, network) {
+        (Some(dir), network) => {
+            let network = match network {
+                Some(network) => network,
+                None => SharedFetcher::for_config(&scrape)?,
+            };
+            doc.fetcher(
+                CachingFetcher::new(FsCache::new(dir), network)
+                    .honor_http_caching(scrape.honor_http_caching)
+                    .clock(scrape.clock.0.clone()),
+            )
+        }
+        (None, Some(network)) => Document {
+            fetcher: network,
+            ..doc
+        },
+        (None, None) => doc,
+    };
+    let doc = parse(doc, args.config.as_deref().zip(config.as_ref())).await?;
+    let doc = args
+        .extract
+        .iter()
+        .fold(doc, |doc, e| doc.add_extractor(*e));
+    let doc = match args.archive {
+        true => doc.add_sink(WaybackSink::new(scrape)),
+        false => doc,
+    };
+    #[cfg(feature = "encryption")]
+    let key = encryption_key(args.encrypt, args.key_file.as_deref())?;
+    #[cfg(not(feature = "encryption"))]
+    if args.encrypt {
+        return Err(eyre!("--encrypt requires the `encryption` feature"));
+    }
+    #[cfg(feature = "sqlite")]
+    let doc = match &args.sqlite {
+        Some(path) => {
+            let sink = scraped::sqlite::SqliteSink::open(path)?;
+            #[cfg(feature = "encryption")]
+            let sink = match &key {
+                Some(key) => sink.encrypt(key.clone()),
+                None => sink,
+            };
+            doc.add_sink(sink)
+        }
+        None => doc,
+    };
+    #[cfg(not(feature = "sqlite"))]
+    if args.sqlite.is_some() {
+        return Err(eyre!("--sqlite requires the `sqlite` feature"));
+    }
+
+    println!("- Parsed {} ", url);
 
     show(&doc, &args.show)?;
 
-    match (&args.output, args.follow) {
-        (Some(v), false) => {
-            let results = serde_json::to_string(&doc.results()?)?;
-            fs::write(&v, results).await?;
-        }
-        (Some(v), true) => {
+    if let Some(path) = &args.annotate {
+        fs::write(path, doc.annotated_html()).await?;
+        println!("- Annotated HTML written to {}", path.display());
+    }
+
+    if args.dry_run {
+        let candidates = doc.dry_run().await;
+        println!(
+            "- Dry run: {} of the {} links found would be requested",
+            candidates.iter().filter(|c| c.is_included()).count(),
+            candidates.len()
+        );
+        candidates.iter().for_each(|c| println!("  {}", c));
+    } else if args.output.is_some() || args.coverage {
+        let results = match args.follow {
+            true => {
+                println!(
+                    "- Loading and parsing {} child nodes{}",
+                    &doc.get_child_urls().len(),
+                    if args.flatten { " [flatten] " } else { "" }
+                );
+                if let Some(bar) = ProgressBar::for_terminal().filter(|_| !args.no_progress) {
+                    doc.stats().observe(bar);
+                }
+                doc.results_graph().await?
+            }
+            false if paginate => doc.results_with_pages().await?,
+            false if audit => doc.results_async().await?,
+            false => doc.results()?,
+        };
+
+        if args.follow {
+            let stats = doc.stats().snapshot();
             println!(
-                "- Loading and parsing {} child nodes{}",
-                &doc.get_child_urls().len(),
-                if args.flatten { " [flatten] " } else { "" }
+                "- Loaded {} child pages ({} bytes) with {} errors",
+                stats.done, stats.bytes, stats.errors
             );
+        }
+        if cancel.is_cancelled() {
+            println!("- Cancelled; writing the results of the pages which were scraped");
+        }
 
-            let results = match (args.follow, args.flatten) {
-                (true, true) => {
-                    let r = FlatResult::flatten(&doc.results_graph().await?);
-                    serde_json::to_string(&r)?
-                }
-                (true, false) => serde_json::to_string(&doc.results_graph().await?)?,
-                (false, _) => serde_json::to_string(&doc.results_graph().await?)?,
-            };
+        if args.coverage {
+            show_coverage(&doc.report(&results));
+        }
 
-            fs::write(&v, results).await?;
+        let scrubber = scrubber(&args, config.as_ref());
+        match (&args.output, args.format) {
+            (Some(v), OutputFormat::Jsonl) => {
+                let writer = JsonLinesWriter::create(v).await?.scrub(scrubber);
+                #[cfg(feature = "encryption")]
+                let writer = match &key {
+                    Some(key) => writer.encrypt(key.clone()),
+                    None => writer,
+                };
+                let mut writer = writer;
+                writer.write_flat(&results).await?;
+                writer.finish().await?;
+            }
+            (Some(v), OutputFormat::Json) => {
+                let results = match (args.follow, args.flatten) {
+                    (true, true) => json!(results.flatten()),
+                    _ => json!(results),
+                };
+                let results = serde_json::to_string(&scrubber.scrub(results))?;
+                #[cfg(feature = "encryption")]
+                let results = match &key {
+                    Some(key) => key.encrypt(results.as_bytes())? + "\n",
+                    None => results,
+                };
+                fs::write(&v, results).await?;
+            }
+            (None, _) => {}
         }
-        _ => (),
     }
 
     info!("completed CLI command");
+    #[cfg(feature = "otel")]
+    scraped::telemetry::shutdown();
+
+    Ok(())
+}
+
+/// Cancels the crawl when Ctrl-C is pressed so that the pages in flight finish
+/// and the results so far are written; pressing it again exits at once.
+async fn cancel_on_ctrl_c(cancel: Cancellation) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    println!("- Stopping after the pages in flight (press Ctrl-C again to exit now)");
+    cancel.cancel();
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(130);
+    }
+}
+
+/// loads the document and parses it with the built-in selectors and properties
+/// along with those of the configuration file
+async fn parse(doc: Document, config: Option<(&Path, &Config)>) -> Result<ParsedDoc> {
+    let title: PropertyCallback =
+        |r| json!(["title", "h1"].iter().find_map(|name| r.get(*name)?.text()));
+    let doc = doc
+        .load_document()
+        .await?
+        .for_docs_rs()
+        .add_generic_selectors()
+        .add_property("title", title)
+        .add_crawl_property("external_domains", report::external_domains)
+        .add_crawl_property("broken_images", report::broken_images);
+
+    match config {
+        Some((path, config)) => {
+            let doc = doc.selector_source(SelectorSource::config(&path.display().to_string()));
+            config
+                .lookups
+                .iter()
+                .try_fold(config.apply(doc)?, |doc, l| {
+                    Ok::<_, Report>(doc.add_lookup(l.load()?))
+                })
+        }
+        None => Ok(doc),
+    }
+}
+
+/// scrapes the page -- and with `follow` its child pages -- and compares the
+/// results with those of an earlier scrape
+async fn diff(
+    old: &Path,
+    url: &str,
+    follow: bool,
+    config: Option<&Path>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let previous = fs::read_to_string(old).await.context(format!(
+        "Failed to read the earlier results: {}",
+        old.display()
+    ))?;
+    let previous: Value = serde_json::from_str(&previous).context(format!(
+        "The earlier results aren't JSON: {}",
+        old.display()
+    ))?;
+    let config = match config {
+        Some(path) => Some((path, Config::load(path).await?)),
+        None => None,
+    };
+    let scrape = config
+        .as_ref()
+        .map(|(_, c)| c.scrape.clone())
+        .unwrap_or_default();
+    let doc = Document::new(url)?.config(scrape);
+    let doc = parse(doc, config.as_ref().map(|(path, c)| (*path, c))).await?;
+    let results = match follow {
+        true => doc.results_graph().await?,
+        false => doc.results()?,
+    };
+
+    let diff = ResultsDiff::between(&previous, &json!(results));
+    println!(
+        "- {} pages added, {} removed and {} changed since {}",
+        diff.added_pages.len(),
+        diff.removed_pages.len(),
+        diff.pages.len(),
+        old.display()
+    );
+    match output {
+        Some(path) => fs::write(path, serde_json::to_string(&diff)?).await?,
+        None if !diff.is_empty() => println!("{}", serde_json::to_string_pretty(&diff)?),
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// scrapes the page -- and with `follow` its child pages -- every interval and
+/// prints the differences whenever they change
+async fn watch(
+    url: &str,
+    interval: Duration,
+    follow: bool,
+    config: Option<&Path>,
+    state: Option<&Path>,
+    webhook: Option<&Url>,
+) -> Result<()> {
+    let config = match config {
+        Some(path) => Some((path, Config::load(path).await?)),
+        None => None,
+    };
+    let scrape = config
+        .as_ref()
+        .map(|(_, c)| c.scrape.clone())
+        .unwrap_or_default();
+    let watcher = Watcher::new(interval).config(scrape.clone());
+    let watcher = match state {
+        Some(path) => watcher.state(path),
+        None => watcher,
+    };
+    let mut watcher = match webhook {
+        Some(webhook) => watcher.webhook(webhook.clone()),
+        None => watcher,
+    };
+
+    println!(
+        "- Watching {} every {}",
+        url,
+        humantime::format_duration(interval)
+    );
+    let (scrape, config) = (&scrape, config.as_ref().map(|(path, c)| (*path, c)));
+    watcher
+        .watch(
+            move || async move {
+                let doc = Document::new(url)?.config(scrape.clone());
+                let doc = parse(doc, config).await?;
+                Ok(match follow {
+                    true => doc.results_graph().await?,
+                    false => doc.results()?,
+                })
+            },
+            |diff| match serde_json::to_string_pretty(diff) {
+                Ok(diff) => println!("- {} changed:\n{}", url, diff),
+                Err(e) => tracing::warn!("failed to serialize the changes: {}", e),
+            },
+        )
+        .await
+}
+
+/// parses the cached pages with both configurations and reports how their
+/// selectors compare
+async fn compare(old: &Path, new: &Path, cache: &Path, output: Option<&Path>) -> Result<()> {
+    let (old_config, new_config) = (Config::load(old).await?, Config::load(new).await?);
+    let (old_lookups, new_lookups) = (lookups(&old_config)?, lookups(&new_config)?);
+    let docs = FsCache::new(cache)
+        .pages()
+        .await?
+        .iter()
+        .map(|page| page.to_document())
+        .collect::<Result<Vec<_>>>()?;
+    let configure =
+        |path: &Path, config: &Config, lookups: &[Lookup], doc: ParsedDoc| -> Result<ParsedDoc> {
+            let doc = doc.selector_source(SelectorSource::config(&path.display().to_string()));
+            let doc = lookups
+                .iter()
+                .fold(config.apply(doc)?, |doc, l| doc.add_lookup(l.clone()));
+
+            Ok(doc)
+        };
+    let comparison = Comparison::new(
+        &docs,
+        |doc| configure(old, &old_config, &old_lookups, doc),
+        |doc| configure(new, &new_config, &new_lookups, doc),
+    )?;
+
+    println!(
+        "- Compared {} cached pages: {} differ",
+        comparison.pages,
+        comparison.diff.pages.len()
+    );
+    let rate = |coverage: &Option<SelectorCoverage>| match coverage {
+        Some(c) => format!("{:.0}%", c.match_rate * 100.0),
+        None => "-".to_string(),
+    };
+    comparison.selectors.iter().for_each(|(name, s)| {
+        println!(
+            "  {}: matched {} -> {}, differs on {} pages",
+            name,
+            rate(&s.old),
+            rate(&s.new),
+            s.pages_differing
+        );
+    });
+    if let Some(path) = output {
+        fs::write(path, serde_json::to_string(&comparison)?).await?;
+        println!("- Comparison written to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// loads the lookup tables of the configuration
+fn lookups(config: &Config) -> Result<Vec<Lookup>> {
+    config.lookups.iter().map(|l| l.load()).collect()
+}
+
+/// requests the page and estimates the crawl of it and the child pages which
+/// would be followed
+async fn estimate(url: &str, config: Option<&Path>, latency: Duration) -> Result<()> {
+    let config = match config {
+        Some(path) => Some((path, Config::load(path).await?)),
+        None => None,
+    };
+    let scrape = config
+        .as_ref()
+        .map(|(_, c)| c.scrape.clone())
+        .unwrap_or_default();
+    let doc = Document::new(url)?.config(scrape.clone());
+    let doc = parse(doc, config.as_ref().map(|(path, c)| (*path, c))).await?;
+    let frontier: Vec<Url> = doc
+        .dry_run()
+        .await
+        .into_iter()
+        .filter(|c| c.is_included())
+        .filter_map(|c| c.url)
+        .collect();
+
+    let estimate = Estimate::new(std::iter::once(&doc.url).chain(&frontier), &scrape, latency);
+    let duration = |d: Duration| humantime::format_duration(Duration::from_secs(d.as_secs()));
+    println!(
+        "- {} requests to {} hosts would take about {}",
+        estimate.requests,
+        estimate.hosts.len(),
+        duration(estimate.duration)
+    );
+    estimate.hosts.iter().for_each(|(host, h)| {
+        let rate = match h.per_second {
+            Some(rate) => format!("{}/s", rate),
+            None => "unlimited".to_string(),
+        };
+        println!(
+            "  {}: {} requests ({}) over {}",
+            host,
+            h.requests,
+            rate,
+            duration(h.duration)
+        );
+    });
+
+    Ok(())
+}
+
+/// prints what the site asks of crawlers and -- with `output` -- writes a
+/// starter configuration file for a polite crawl of it
+async fn policy(url: &str, config: Option<&Path>, output: Option<&Path>) -> Result<()> {
+    let config = match config {
+        Some(path) => Config::load(path).await?,
+        None => Config::default(),
+    };
+    let url = Url::parse(url).context(format!("Failed to parse the URL: {}", url))?;
+    let fetcher = SharedFetcher::for_config(&config.scrape)?;
+    let policy = SitePolicy::inspect(&url, &config.scrape, fetcher.0.as_ref()).await?;
+
+    println!(
+        "- {} responded with a {} status",
+        policy.homepage, policy.homepage_status
+    );
+    match policy.robots_status {
+        Some(status) if (200..300).contains(&status) => println!(
+            "- robots.txt {} the homepage and disallows {} paths{}",
+            if policy.homepage_allowed {
+                "allows"
+            } else {
+                "disallows"
+            },
+            policy.disallowed.len(),
+            match policy.crawl_delay {
+                Some(delay) => format!(
+                    " with a crawl delay of {}",
+                    humantime::format_duration(delay)
+                ),
+                None => String::new(),
+            }
+        ),
+        Some(status) => println!("- robots.txt responded with a {} status", status),
+        None => println!("- robots.txt couldn't be requested"),
+    }
+    policy
+        .disallowed
+        .iter()
+        .for_each(|path| println!("  Disallow: {}", path));
+    match policy.sitemaps.is_empty() {
+        true => println!("- No sitemap was found"),
+        false => policy
+            .sitemaps
+            .iter()
+            .for_each(|s| println!("- Sitemap: {}", s)),
+    }
+    if !policy.meta_robots.is_empty() {
+        println!("- Meta robots: {}", policy.meta_robots.join(", "));
+    }
+    if policy.nofollow() {
+        println!("- The homepage asks that its links aren't followed");
+    }
+    policy
+        .rate_limit_headers
+        .iter()
+        .for_each(|(name, value)| println!("- {}: {}", name, value));
+    if let Some(pause) = policy.retry_after {
+        println!("- Retry-After: {}", humantime::format_duration(pause));
+    }
+    let rate = policy.rate_limit();
+    println!(
+        "- Suggested: respect robots.txt, one request at a time and {}/s",
+        rate.per_second.unwrap_or(POLITE_RATE)
+    );
+
+    if let Some(path) = output {
+        let starter = Config {
+            scrape: policy.starter_config(&config.scrape),
+            ..config
+        };
+        starter.save(path).await?;
+        println!("- Starter configuration written to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// the key results are encrypted with when `encrypt` is set: the keyfile's or
+/// else that of the environment
+#[cfg(feature = "encryption")]
+fn encryption_key(encrypt: bool, key_file: Option<&Path>) -> Result<Option<EncryptionKey>> {
+    if !encrypt {
+        return Ok(None);
+    }
+    let key = match key_file {
+        Some(path) => EncryptionKey::from_file(path)?,
+        None => EncryptionKey::from_env()?.ok_or_else(|| {
+            eyre!(
+                "--encrypt needs a --key-file or the {} environment variable",
+                scraped::encryption::KEY_ENV
+            )
+        })?,
+    };
+
+    Ok(Some(key))
+}
+
+/// decrypts a file written with `--encrypt`
+#[cfg(feature = "encryption")]
+async fn decrypt(input: &Path, key_file: Option<&Path>, output: Option<&Path>) -> Result<()> {
+    let key = encryption_key(true, key_file)?.expect("a key is required to decrypt");
+    let content = fs::read_to_string(input)
+        .await
+        .context(format!("Failed to read {}", input.display()))?;
+    let decrypted = key
+        .decrypt_lines(&content)
+        .context(format!("Failed to decrypt {}", input.display()))?;
+    match output {
+        Some(path) => fs::write(path, decrypted).await?,
+        None => print!("{}", decrypted),
+    }
+
+    Ok(())
+}
+
+/// logs to the terminal and -- with the `otel` feature -- exports spans to an
+/// OpenTelemetry collector
+#[cfg(feature = "otel")]
+fn init_tracing(otlp: Option<&str>) -> Result<()> {
+    use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+
+    match otlp {
+        Some(endpoint) => tracing_subscriber::registry()
+            .with(fmt::layer().with_filter(LevelFilter::INFO))
+            .with(scraped::telemetry::otlp_layer(endpoint, "scraped")?)
+            .init(),
+        None => fmt::init(),
+    }
+
+    Ok(())
+}
+
+/// logs to the terminal
+#[cfg(not(feature = "otel"))]
+fn init_tracing(otlp: Option<&str>) -> Result<()> {
+    if otlp.is_some() {
+        return Err(eyre!("--otlp requires the `otel` feature"));
+    }
+    tracing_subscriber::fmt::init();
+
+    Ok(())
+}
+
+/// the configuration file's scrape configuration with any overrides from the CLI's flags
+fn scrape_config(args: &Args, config: Option<&Config>) -> ScrapeConfig {
+    let scrape = config.map(|c| c.scrape.clone()).unwrap_or_default();
+    let scrape = match args.concurrency {
+        Some(concurrency) => scrape.concurrency(concurrency),
+        None => scrape,
+    };
+    let scrape = match args.max_per_host {
+        Some(max) => scrape.max_per_host(max),
+        None => scrape,
+    };
+    let scrape = match args.timeout {
+        Some(timeout) => scrape.timeout(timeout),
+        None => scrape,
+    };
+    let limits = match args.max_body_bytes {
+        Some(max) => scrape.limits.clone().max_body_bytes(max),
+        None => scrape.limits.clone(),
+    };
+    let limits = args
+        .accept
+        .iter()
+        .fold(limits, |limits, content_type| limits.accept(content_type));
+    let limit = scrape.rate_limit;
+    let rate_limit = match (args.rate, args.delay) {
+        (Some(rate), _) => RateLimit::per_second(rate),
+        (None, Some(delay)) => RateLimit::interval(delay),
+        (None, None) => limit,
+    };
+    let rate_limit = rate_limit
+        .burst(limit.burst)
+        .jitter(args.jitter.unwrap_or(limit.jitter));
+    let fail_fast = args.fail_fast || scrape.fail_fast;
+    let capture = args.capture_errors || scrape.capture_error_body;
+    let respect_robots = args.respect_robots || scrape.respect_robots;
+    let archive_fallback = args.archive_fallback || scrape.archive_fallback;
+    let honor_http_caching = args.honor_http_caching || scrape.honor_http_caching;
+    let repair = args.repair || scrape.repair_html;
+    let hydration = args.hydration || scrape.hydration;
+    let metadata = args.metadata || scrape.metadata;
+    let provenance = args.provenance || scrape.provenance;
+    let follow_redirects = args.follow_redirects || scrape.follow_client_redirects;
+    let max_redirects = args.max_redirects.unwrap_or(scrape.max_redirect_hops);
+    let redirects = scrape
+        .redirects
+        .follow(!args.no_redirects && scrape.redirects.follow);
+    let retry = RetryPolicy {
+        max_attempts: args.retries.unwrap_or(scrape.retry.max_attempts),
+        ..scrape.retry
+    };
+    let text = match args.ascii_text {
+        true => TextPolicy::normalized(),
+        false => scrape.text.clone(),
+    };
+    let frames = match args.follow_frames {
+        true => FramePolicy::SameOrigin,
+        false => scrape.frames,
+    };
+    let safety = match args.allow_domains.is_empty() {
+        true => scrape.safety.clone(),
+        false => scrape.safety.clone().allow_domains(&args.allow_domains),
+    };
+    let block = args.block_private_networks || safety.block_private_networks;
+    let safety = safety.block_private_networks(block);
+    let dns = match &args.doh {
+        Some(endpoint) => scrape.dns.clone().doh(endpoint),
+        None => scrape.dns.clone(),
+    };
+    let prefer = match (args.prefer_ipv4, args.prefer_ipv6) {
+        (true, _) => IpPreference::Ipv4,
+        (_, true) => IpPreference::Ipv6,
+        _ => scrape.network.prefer,
+    };
+    let network = scrape.network.clone().prefer(prefer);
+    let network = match args.local_address {
+        Some(addr) => network.local_address(addr),
+        None => network,
+    };
+    let render = scrape
+        .render
+        .clone()
+        .enabled(args.render || scrape.render.enabled)
+        .network_idle(args.network_idle || scrape.render.network_idle);
+    let render = match &args.wait_for {
+        Some(selector) => render.wait_for(selector),
+        None => render,
+    };
+    let tokens = scrape
+        .bearer_tokens
+        .clone()
+        .env(args.env_tokens || scrape.bearer_tokens.env);
+    let scrape = scrape.render(render).bearer_tokens(tokens);
+    let scrape = match args.proxies.is_empty() {
+        true => scrape,
+        false => scrape.proxy_pool(args.proxies.clone()),
+    };
+    let warmup = args.warmup.unwrap_or(scrape.warmup);
+    let sample = match args.sample {
+        Some(fraction) => scrape.sample.clone().fraction(fraction),
+        None => scrape.sample.clone(),
+    };
+    let sample = match args.sample_per_dir {
+        Some(max) => sample.per_directory(max),
+        None => sample,
+    };
+    let pagination = scrape
+        .pagination
+        .follow(args.paginate || scrape.pagination.follow);
+    let pagination = match args.max_pages {
+        Some(max) => pagination.max_pages(max),
+        None => pagination,
+    };
+    let dedupe_canonical = args.dedupe_canonical || scrape.dedupe_canonical;
+    let link_audit = scrape
+        .link_audit
+        .enabled(args.audit_links || scrape.link_audit.enabled)
+        .same_host(args.audit_same_host || scrape.link_audit.same_host);
+    let locales = match args.locales.is_empty() {
+        true => scrape.locales.clone(),
+        false => args.locales.clone(),
+    };
+
+    scrape
+        .rate_limit(rate_limit)
+        .fail_fast(fail_fast)
+        .capture_error_body(capture)
+        .retry(retry)
+        .limits(limits)
+        .respect_robots(respect_robots)
+        .archive_fallback(archive_fallback)
+        .honor_http_caching(honor_http_caching)
+        .repair_html(repair)
+        .hydration(hydration)
+        .metadata(metadata)
+        .provenance(provenance)
+        .text(text)
+        .redirects(redirects)
+        .follow_client_redirects(follow_redirects, max_redirects)
+        .frames(frames)
+        .pagination(pagination)
+        .dedupe_canonical(dedupe_canonical)
+        .link_audit(link_audit)
+        .safety(safety)
+        .dns(dns)
+        .network(network)
+        .locales(locales)
+        .sample(sample)
+        .warmup(warmup)
+}
+
+/// the configuration file's scrubber with any overrides from the CLI's flags
+fn scrubber(args: &Args, config: Option<&Config>) -> Scrubber {
+    let scrubber = config.map(|c| c.scrub.clone()).unwrap_or_default();
+    let scrubber = match args.scrub {
+        true => scrubber.emails(true).phones(true).ips(true),
+        false => scrubber,
+    };
+    let hash = args.scrub_hash || scrubber.hash;
+
+    scrubber.hashed(hash)
+}
+
+/// runs one of the CLI's subcommands
+async fn run_command(command: &Command) -> Result<()> {
+    match command {
+        Command::Init { url, output } => {
+            let config = init::init(url, output).await?;
+            println!(
+                "- Wrote {} selectors and {} list selectors to {}",
+                config.selectors.len(),
+                config.list_selectors.len(),
+                output.display()
+            );
+        }
+        Command::Diff {
+            old,
+            url,
+            follow,
+            config,
+            output,
+        } => diff(old, url, *follow, config.as_deref(), output.as_deref()).await?,
+        Command::Watch {
+            url,
+            interval,
+            follow,
+            config,
+            state,
+            webhook,
+        } => {
+            watch(
+                url,
+                *interval,
+                *follow,
+                config.as_deref(),
+                state.as_deref(),
+                webhook.as_ref(),
+            )
+            .await?
+        }
+        Command::Compare {
+            old,
+            new,
+            cache,
+            output,
+        } => compare(old, new, cache, output.as_deref()).await?,
+        Command::Estimate {
+            url,
+            config,
+            latency,
+        } => {
+            let latency = latency.unwrap_or(DEFAULT_LATENCY);
+            estimate(url, config.as_deref(), latency).await?
+        }
+        Command::Policy {
+            url,
+            config,
+            output,
+        } => policy(url, config.as_deref(), output.as_deref()).await?,
+        #[cfg(feature = "encryption")]
+        Command::Keygen { output } => {
+            fs::write(output, EncryptionKey::generate().to_hex() + "\n").await?;
+            println!("- Encryption key written to {}", output.display());
+        }
+        #[cfg(feature = "encryption")]
+        Command::Decrypt {
+            input,
+            key_file,
+            output,
+        } => decrypt(input, key_file.as_deref(), output.as_deref()).await?,
+        Command::Config {
+            command: ConfigCommand::Schema,
+        } => println!("{}", Config::schema()?),
+    }
 
     Ok(())
 }
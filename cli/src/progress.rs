@@ -0,0 +1,52 @@
+use color_eyre::Report;
+use scraped::observe::{Observer, Progress};
+use std::io::{stderr, IsTerminal, Write};
+use url::Url;
+
+/// the width of the bar, in characters
+const WIDTH: usize = 30;
+
+/// Renders the progress of the child pages being followed as a bar on a
+/// single line of stderr, which is redrawn as each page completes.
+pub struct ProgressBar;
+
+impl ProgressBar {
+    /// a progress bar when stderr is a terminal; none when it's redirected
+    pub fn for_terminal() -> Option<Self> {
+        stderr().is_terminal().then_some(ProgressBar)
+    }
+
+    fn draw(&self, progress: Progress) {
+        let total = progress.total().max(1);
+        let filled = progress.completed() * WIDTH / total;
+        let mut stderr = stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r  [{}{}] {}/{} pages, {} in flight, {} errors",
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            progress.completed(),
+            progress.total(),
+            progress.in_flight,
+            progress.errors
+        );
+        if progress.queued == 0 && progress.in_flight == 0 {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+    }
+}
+
+impl Observer for ProgressBar {
+    fn on_cancelled(&self, _url: &Url, progress: Progress) {
+        self.draw(progress);
+    }
+
+    fn on_page_finished(&self, _url: &Url, _bytes: usize, progress: Progress) {
+        self.draw(progress);
+    }
+
+    fn on_error(&self, _url: &Url, _error: &Report, progress: Progress) {
+        self.draw(progress);
+    }
+}
@@ -1,11 +1,11 @@
 use color_eyre::eyre::Result;
-use scraped::{results::ResultKind, ParsedDoc};
+use scraped::{report::CrawlReport, results::ResultKind, ParsedDoc};
 use tracing::{trace, warn};
 
 /// outputs a set of properties which reside on a `ParsedDoc`.
 pub fn show(doc: &ParsedDoc, show: &Option<String>) -> Result<()> {
     let props = match show {
-        Some(v) => v.split(",").into_iter().collect(),
+        Some(v) => v.split(',').collect(),
         None => vec![],
     };
     trace!("showing properties: {:?}", props);
@@ -28,3 +28,30 @@ pub fn show(doc: &ParsedDoc, show: &Option<String>) -> Result<()> {
 
     Ok(())
 }
+
+/// outputs how well each selector matched across the pages of a crawl
+pub fn show_coverage(report: &CrawlReport) {
+    println!("- Selector coverage across {} page(s)", report.pages);
+    if report.failed > 0 {
+        println!("  - {} page(s) failed to load", report.failed);
+    }
+    report.blocked.iter().for_each(|(challenge, count)| {
+        println!("  - {} page(s) blocked by a {} challenge", count, challenge);
+    });
+    report.properties.iter().for_each(|(name, value)| {
+        println!("  - {}: {}", name, value);
+    });
+    report.coverage.iter().for_each(|(name, c)| {
+        let missing = match c.zero_match_examples.first() {
+            Some(url) => format!("; no matches on {}", url),
+            None => String::new(),
+        };
+        println!(
+            "  - {}: matched on {:.0}% of pages, {:.1} matches per page{}",
+            name,
+            c.match_rate * 100.0,
+            c.avg_matches,
+            missing
+        );
+    });
+}
@@ -0,0 +1,69 @@
+//! Compares the DOM parser with the streaming fast path on synthetic pages of
+//! increasing size: `cargo bench --features streaming --bench parser`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use scraped::{
+    parser::{ParserBackend, Selectors},
+    ScrapeConfig,
+};
+use url::Url;
+
+/// a page with a number of sections, each with a heading, some prose and a
+/// list of links
+fn page(sections: usize) -> String {
+    let body: String = (0..sections)
+        .map(|i| {
+            format!(
+                r#"<section id="s{i}" class="card">
+                    <h2>Section {i}</h2>
+                    <p>Lorem ipsum <b>dolor</b> sit amet, consectetur &amp; adipiscing elit.</p>
+                    <ul>
+                        <li><a href="/a/{i}">first</a></li>
+                        <li><a href="https://example.com/b/{i}" rel="nofollow">second</a></li>
+                        <li><img src="/img/{i}.png" alt=""></li>
+                    </ul>
+                </section>"#
+            )
+        })
+        .collect();
+
+    format!(
+        "<!DOCTYPE html><html><head><title>Benchmark</title></head><body><h1>Benchmark</h1>{}</body></html>",
+        body
+    )
+}
+
+fn selectors(backend: ParserBackend) -> Selectors {
+    Selectors::new()
+        .add_selector("title", "title")
+        .add_selector("h1", "h1")
+        .add_selector_all("headings", "section.card > h2")
+        .add_selector_all("links", "a[href]")
+        .parser(backend)
+}
+
+fn parsers(c: &mut Criterion) {
+    let url = Url::parse("https://example.com").unwrap();
+    let config = ScrapeConfig::default();
+    let mut group = c.benchmark_group("parser");
+
+    for sections in [10, 100, 1_000] {
+        let html = page(sections);
+        group.throughput(Throughput::Bytes(html.len() as u64));
+
+        for (name, backend) in [
+            ("dom", ParserBackend::Dom),
+            ("streaming", ParserBackend::Streaming),
+        ] {
+            let selectors = selectors(backend);
+            group.bench_with_input(BenchmarkId::new(name, sections), &html, |b, html| {
+                b.iter(|| selectors.select(html, &url, &config).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, parsers);
+criterion_main!(benches);
@@ -0,0 +1,129 @@
+use crate::{selection::SelectorKind, ParsedDoc};
+use ego_tree::{iter::Edge, NodeId};
+use scraper::Node;
+use std::collections::HashMap;
+
+/// the colors used to highlight each selector's matches; selectors beyond
+/// the size of the palette will reuse colors
+const PALETTE: [&str; 10] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#bfef45",
+    "#9a6324", "#469990",
+];
+
+/// elements which never have a closing tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// elements whose text content must not be escaped
+const RAW_TEXT_ELEMENTS: [&str; 6] = ["script", "style", "xmp", "iframe", "noembed", "noframes"];
+
+fn escape(text: &str, attr: bool) -> String {
+    let text = text.replace('&', "&amp;").replace('\u{a0}', "&nbsp;");
+    match attr {
+        true => text.replace('"', "&quot;"),
+        false => text.replace('<', "&lt;").replace('>', "&gt;"),
+    }
+}
+
+/// Serializes the document's HTML with every element matched by a selector
+/// highlighted in that selector's color, along with a legend of the selectors.
+pub fn annotate(doc: &ParsedDoc) -> String {
+    let mut names: Vec<&String> = doc.selectors.keys().collect();
+    names.sort();
+
+    // every matched node along with the index of the selectors which matched it
+    let mut matches: HashMap<NodeId, Vec<usize>> = HashMap::new();
+    let mut counts: Vec<usize> = vec![0; names.len()];
+    names.iter().enumerate().for_each(|(idx, name)| {
+        let selected: Vec<NodeId> = match &doc.selectors[*name] {
+            SelectorKind::Item(sel) | SelectorKind::Table(sel) => {
+                doc.html.select(sel).take(1).map(|el| el.id()).collect()
+            }
+            SelectorKind::List(sel) => doc.html.select(sel).map(|el| el.id()).collect(),
+        };
+        counts[idx] = selected.len();
+        selected
+            .into_iter()
+            .for_each(|id| matches.entry(id).or_default().push(idx));
+    });
+
+    let mut html = String::new();
+    let mut raw_text = false;
+    for edge in doc.html.tree.root().traverse() {
+        match edge {
+            Edge::Open(node) => match node.value() {
+                Node::Doctype(doctype) => html.push_str(&format!("<!DOCTYPE {}>", doctype.name())),
+                Node::Comment(comment) => html.push_str(&format!("<!--{}-->", &**comment)),
+                Node::Text(text) if raw_text => html.push_str(text),
+                Node::Text(text) => html.push_str(&escape(text, false)),
+                Node::Element(el) => {
+                    html.push('<');
+                    html.push_str(el.name());
+                    let matched = matches.get(&node.id());
+                    for (name, value) in el.attrs() {
+                        // the highlight replaces any inline style of a matched element
+                        if matched.is_none() || name != "style" {
+                            html.push_str(&format!(" {}=\"{}\"", name, escape(value, true)));
+                        }
+                    }
+                    if let Some(idx) = matched {
+                        let color = PALETTE[idx[0] % PALETTE.len()];
+                        let selectors: Vec<&str> = idx.iter().map(|i| names[*i].as_str()).collect();
+                        html.push_str(&format!(
+                            " data-scraped=\"{}\" style=\"outline: 2px solid {}; outline-offset: 1px;\"",
+                            escape(&selectors.join(" "), true),
+                            color
+                        ));
+                    }
+                    html.push('>');
+                    raw_text = RAW_TEXT_ELEMENTS.contains(&el.name());
+
+                    match el.name() {
+                        // relative assets should still resolve when the file is opened locally
+                        "head" => html.push_str(&format!(
+                            "<base href=\"{}\">",
+                            escape(doc.resolved_url.as_str(), true)
+                        )),
+                        "body" => html.push_str(&legend(&names, &counts)),
+                        _ => (),
+                    }
+                }
+                _ => (),
+            },
+            Edge::Close(node) => {
+                if let Node::Element(el) = node.value() {
+                    raw_text = false;
+                    if !VOID_ELEMENTS.contains(&el.name()) {
+                        html.push_str(&format!("</{}>", el.name()));
+                    }
+                }
+            }
+        }
+    }
+
+    html
+}
+
+/// a fixed position legend which shows the color assigned to each selector
+/// and how many elements it matched
+fn legend(names: &[&String], counts: &[usize]) -> String {
+    let items: String = names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            format!(
+                "<li><span style=\"display: inline-block; width: 12px; height: 12px; margin-right: 6px; background: {};\"></span>{} ({})</li>",
+                PALETTE[idx % PALETTE.len()],
+                escape(name, false),
+                counts[idx]
+            )
+        })
+        .collect();
+
+    format!(
+        "<div id=\"scraped-legend\" style=\"position: fixed; top: 8px; right: 8px; z-index: 2147483647; background: #fff; color: #000; border: 1px solid #ccc; padding: 8px; font: 12px sans-serif;\"><strong>scraped selectors</strong><ul style=\"list-style: none; margin: 4px 0 0; padding: 0;\">{}</ul></div>",
+        items
+    )
+}
@@ -0,0 +1,199 @@
+//! Falls back to the Wayback Machine for pages which have rotted away and
+//! submits the pages which were scraped to it.
+
+use crate::{
+    error::HttpError,
+    fetch::{Fetcher, SharedFetcher},
+    results::PageOutcome,
+    retry,
+    sink::Sink,
+    ScrapeConfig,
+};
+use async_trait::async_trait;
+use color_eyre::{eyre::eyre, Report, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+use tracing::info;
+use url::Url;
+
+/// the Wayback Machine's API for the snapshot of a URL closest to a date
+pub const AVAILABILITY_API: &str = "https://archive.org/wayback/available";
+/// the Wayback Machine's API which captures a page when it's requested with
+/// the page's URL appended
+pub const SAVE_API: &str = "https://web.archive.org/save/";
+/// the default pause between submissions to the save API, which allows about
+/// a dozen anonymous captures a minute
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A snapshot of a page captured by the Wayback Machine.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    /// the URL of the snapshot in the archive
+    #[serde(serialize_with = "crate::util::url_to_string")]
+    pub url: Url,
+    /// when the snapshot was captured, as `YYYYMMDDhhmmss`
+    pub timestamp: String,
+}
+
+impl Snapshot {
+    /// The URL which serves the snapshot as it was captured -- without the
+    /// archive's banner or rewritten links -- by adding the `id_` modifier to
+    /// the snapshot's timestamp.
+    pub fn raw_url(&self) -> Url {
+        let mut url = self.url.clone();
+        let marker = format!("/{}/", self.timestamp);
+        let path = self
+            .url
+            .path()
+            .replacen(&marker, &format!("/{}id_/", self.timestamp), 1);
+        url.set_path(&path);
+
+        url
+    }
+}
+
+/// whether an error suggests the page has rotted away: it's gone (a 404 or
+/// 410 status) or its server can't be reached
+pub fn is_link_rot(error: &Report) -> bool {
+    let gone = error
+        .downcast_ref::<HttpError>()
+        .is_some_and(|e| e.status == 404 || e.status == 410);
+
+    gone || retry::is_transient_error(error)
+}
+
+/// the request for the most recent snapshot of a page
+pub fn availability_url(url: &Url) -> Url {
+    let mut api = Url::parse(AVAILABILITY_API).unwrap();
+    api.query_pairs_mut().append_pair("url", url.as_str());
+
+    api
+}
+
+/// Parses the response of the availability API; `None` when the archive has
+/// no successful snapshot of the page.
+pub fn parse_availability(body: &str) -> Result<Option<Snapshot>> {
+    let response: Value = serde_json::from_str(body)?;
+    let closest = &response["archived_snapshots"]["closest"];
+    let ok = closest["status"]
+        .as_str()
+        .is_none_or(|s| s.starts_with('2'));
+    if closest["available"] != Value::Bool(true) || !ok {
+        return Ok(None);
+    }
+
+    match (closest["url"].as_str(), closest["timestamp"].as_str()) {
+        (Some(url), Some(timestamp)) => Ok(Some(Snapshot {
+            url: Url::parse(url)?,
+            timestamp: timestamp.to_string(),
+        })),
+        _ => Err(eyre!("the snapshot has no URL or timestamp: {}", closest)),
+    }
+}
+
+/// requests the most recent snapshot of a page from the Wayback Machine
+pub async fn latest(
+    url: &Url,
+    config: &ScrapeConfig,
+    fetcher: &dyn Fetcher,
+) -> Result<Option<Snapshot>> {
+    let api = availability_url(url);
+    let page = fetcher.fetch(&api, &config.request_headers()?).await?;
+    if !(200..300).contains(&page.status) {
+        return Err(eyre!("{} responded with a {} status", api, page.status));
+    }
+
+    parse_availability(&page.text())
+}
+
+/// A `Sink` which submits each page that was scraped successfully to the
+/// Wayback Machine's save API so that a crawl doubles as an archival trigger.
+/// Submissions are made one at a time with at least the `interval` between
+/// them; a URL is only submitted once and pages which were themselves loaded
+/// from a snapshot are skipped.
+pub struct WaybackSink {
+    config: ScrapeConfig,
+    fetcher: SharedFetcher,
+    interval: Duration,
+    /// when the last submission was made and the URLs submitted so far
+    state: Mutex<(Option<Instant>, HashSet<Url>)>,
+}
+
+impl Default for WaybackSink {
+    fn default() -> Self {
+        WaybackSink::new(ScrapeConfig::default())
+    }
+}
+
+impl WaybackSink {
+    /// submits pages with the request headers (e.g., `User-Agent`) of the config
+    pub fn new(config: ScrapeConfig) -> Self {
+        WaybackSink {
+            config,
+            fetcher: SharedFetcher::default(),
+            interval: SAVE_INTERVAL,
+            state: Mutex::new((None, HashSet::new())),
+        }
+    }
+
+    /// sets the transport used to make the submissions
+    pub fn fetcher(mut self, fetcher: impl Fetcher + 'static) -> Self {
+        self.fetcher = SharedFetcher::new(fetcher);
+
+        self
+    }
+
+    /// sets the shortest pause between submissions
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+
+        self
+    }
+
+    /// the request which asks the Wayback Machine to capture a page
+    pub fn save_url(url: &Url) -> Result<Url> {
+        Ok(Url::parse(&format!("{}{}", SAVE_API, url))?)
+    }
+}
+
+#[async_trait]
+impl Sink for WaybackSink {
+    async fn send(&self, outcome: &PageOutcome) -> Result<()> {
+        let url = match outcome {
+            PageOutcome::Ok(page) if page.archived.is_none() => &page.url,
+            _ => return Ok(()),
+        };
+        // the lock is held while submitting so submissions are made one at a time
+        let mut state = self.state.lock().await;
+        if !state.1.insert(url.clone()) {
+            return Ok(());
+        }
+        let clock = self.config.clock.0.as_ref();
+        if let Some(last) = state.0 {
+            let elapsed = clock.now().saturating_duration_since(last);
+            clock.sleep(self.interval.saturating_sub(elapsed)).await;
+        }
+        state.0 = Some(clock.now());
+
+        let save = WaybackSink::save_url(url)?;
+        info!("submitting {} to the Wayback Machine", url);
+        let page = self
+            .fetcher
+            .0
+            .fetch(&save, &self.config.request_headers()?)
+            .await?;
+        match (200..400).contains(&page.status) {
+            true => Ok(()),
+            false => Err(eyre!(
+                "the Wayback Machine responded to the submission of {} with a {} status",
+                url,
+                page.status
+            )),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Bearer tokens which authorize requests; a token is either sent with every
+//! request or scoped to a domain (and its subdomains) so that it isn't leaked
+//! to the other sites a crawl reaches.
+
+use crate::{
+    redact::Redacted,
+    safety::{normalize_domain, within_domain},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env};
+
+/// the environment variable holding the token sent with every request; a
+/// domain's token is held by `SCRAPED_TOKEN_<DOMAIN>` (see `env_var()`)
+pub const TOKEN_ENV: &str = "SCRAPED_TOKEN";
+
+/// The name of the environment variable holding a domain's token: the domain
+/// uppercased with its dots and dashes replaced by underscores (e.g.,
+/// `SCRAPED_TOKEN_API_GITHUB_COM` for `api.github.com`).
+pub fn env_var(domain: &str) -> String {
+    let domain: String = normalize_domain(domain)
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+
+    format!("{}_{}", TOKEN_ENV, domain)
+}
+
+/// The bearer tokens sent -- as an `Authorization: Bearer` header -- with
+/// requests. The token of the most specific domain a URL is within is sent;
+/// failing that, the `global` token is.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct BearerTokens {
+    /// the token sent with the requests whose domain has no token of its own
+    pub global: Option<Redacted<String>>,
+    /// the tokens sent with the requests to a domain and its subdomains
+    pub domains: BTreeMap<String, Redacted<String>>,
+    /// also read tokens from the `SCRAPED_TOKEN` and `SCRAPED_TOKEN_<DOMAIN>`
+    /// environment variables; a configured token wins over the environment's
+    /// for the same domain
+    pub env: bool,
+}
+
+impl BearerTokens {
+    /// sets the token sent with the requests whose domain has no token of its own
+    pub fn global(mut self, token: &str) -> Self {
+        self.global = Some(Redacted::new(token.to_string()));
+
+        self
+    }
+
+    /// sets the token sent with the requests to a domain and its subdomains
+    pub fn scoped(mut self, domain: &str, token: &str) -> Self {
+        self.domains
+            .insert(normalize_domain(domain), Redacted::new(token.to_string()));
+
+        self
+    }
+
+    /// sets whether tokens are read from the environment too
+    pub fn env(mut self, env: bool) -> Self {
+        self.env = env;
+
+        self
+    }
+
+    /// the token sent with the requests to the host, if any
+    pub fn token_for(&self, host: &str) -> Option<String> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let configured = self
+            .domains
+            .iter()
+            .map(|(domain, token)| (normalize_domain(domain), token))
+            .filter(|(domain, _)| within_domain(&host, domain))
+            .map(|(domain, token)| (domain.len(), token.expose().to_string()))
+            .max_by_key(|(len, _)| *len);
+        // the host and each of the domains it's within
+        let from_env = match self.env {
+            true => host
+                .char_indices()
+                .filter(|(_, c)| *c == '.')
+                .map(|(i, _)| &host[i + 1..])
+                .chain(std::iter::once(host.as_str()))
+                .filter_map(|domain| Some((domain.len(), env::var(env_var(domain)).ok()?)))
+                .max_by_key(|(len, _)| *len),
+            false => None,
+        };
+
+        match (configured, from_env) {
+            (Some(configured), Some(from_env)) if from_env.0 > configured.0 => Some(from_env.1),
+            (Some((_, token)), _) | (None, Some((_, token))) => Some(token),
+            (None, None) => self
+                .global
+                .as_ref()
+                .map(|t| t.expose().to_string())
+                .or_else(|| match self.env {
+                    true => env::var(TOKEN_ENV).ok(),
+                    false => None,
+                }),
+        }
+    }
+}
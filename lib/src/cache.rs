@@ -0,0 +1,373 @@
+//! Caches the pages which were requested so that re-running a crawl only
+//! downloads the pages which have changed. A cached page is revalidated with
+//! `If-None-Match` and `If-Modified-Since` and is served from the cache when
+//! the server responds with a `304 Not Modified`. When the caching headers of
+//! pages are honored (see `CachingFetcher::honor_http_caching()`), a page which
+//! is still fresh is served without being revalidated and pages marked
+//! `no-store` aren't cached.
+
+use crate::{
+    clock::{Clock, SharedClock},
+    content,
+    fetch::{Bytes, FetchedPage, Fetcher, HeaderMap, SharedFetcher},
+    http_cache::CacheHeaders,
+    id::fnv1a,
+    limits::ResponseLimits,
+    redact, LoadedDocument,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use color_eyre::{eyre::WrapErr, Result};
+use reqwest::header::{
+    HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{debug, warn};
+use url::Url;
+
+/// A page as it's stored in a `Cache`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedPage {
+    /// the URL the page was ultimately served from (e.g., after redirects)
+    pub url: String,
+    pub status: u16,
+    /// the response headers; sensitive headers (e.g., `Set-Cookie`) aren't stored
+    pub headers: BTreeMap<String, String>,
+    /// when the page was stored, in seconds since the Unix epoch; none for
+    /// pages cached before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stored_at: Option<i64>,
+    #[serde(skip)]
+    pub body: Bytes,
+}
+
+impl CachedPage {
+    /// the page as it's stored; `None` when the page has no validator (an
+    /// `ETag` or `Last-Modified` header) to revalidate it with
+    pub fn new(page: &FetchedPage) -> Option<CachedPage> {
+        if !page.headers.contains_key(ETAG) && !page.headers.contains_key(LAST_MODIFIED) {
+            return None;
+        }
+
+        Some(CachedPage::stored(page))
+    }
+
+    /// The page as it's stored when its caching headers are honored: unless
+    /// it's marked `no-store`, a page is cached when it has a validator or a
+    /// freshness lifetime (a `max-age` or an `Expires` header) when it's
+    /// received `now`.
+    pub fn honoring(page: &FetchedPage, now: DateTime<Utc>) -> Option<CachedPage> {
+        let caching = CacheHeaders::from_headers(&page.headers);
+        if caching.no_store() {
+            return None;
+        }
+
+        match caching.lifetime(now) {
+            Some(lifetime) if !lifetime.is_zero() => Some(CachedPage::stored(page)),
+            _ => CachedPage::new(page),
+        }
+    }
+
+    /// the page with its sensitive headers left out
+    fn stored(page: &FetchedPage) -> CachedPage {
+        CachedPage {
+            url: page.url.to_string(),
+            status: page.status,
+            headers: page
+                .headers
+                .iter()
+                .filter(|(k, _)| !redact::is_sensitive(k.as_str()))
+                .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+                .collect(),
+            stored_at: None,
+            body: page.body.clone(),
+        }
+    }
+
+    /// the value of a response header
+    pub fn header(&self, name: &HeaderName) -> Option<&str> {
+        self.headers.get(name.as_str()).map(|v| v.as_str())
+    }
+
+    /// the caching headers the page was stored with
+    pub fn caching(&self) -> CacheHeaders {
+        CacheHeaders::from_pairs(self.headers.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    }
+
+    /// Whether the page can still be served `now` without revalidating it;
+    /// pages without the time they were stored never are.
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        let stored_at = match self
+            .stored_at
+            .and_then(|at| Utc.timestamp_opt(at, 0).single())
+        {
+            Some(stored_at) => stored_at,
+            None => return false,
+        };
+
+        self.caching().is_fresh(stored_at, now)
+    }
+
+    /// Refreshes the page after it was revalidated `now`: the caching headers
+    /// of the `304 Not Modified` response replace those it was stored with.
+    pub fn revalidated(&mut self, headers: &HeaderMap, now: DateTime<Utc>) {
+        for name in ["cache-control", "age", "expires", "date"] {
+            match headers.get(name).and_then(|v| v.to_str().ok()) {
+                Some(value) => {
+                    self.headers.insert(name.to_string(), value.to_string());
+                }
+                None if name == "age" => {
+                    self.headers.remove(name);
+                }
+                None => {}
+            }
+        }
+        self.stored_at = Some(now.timestamp());
+    }
+
+    /// the page as though it had been received from the server
+    pub fn to_page(&self) -> Result<FetchedPage> {
+        let mut headers = HeaderMap::new();
+        for (k, v) in &self.headers {
+            headers.insert(HeaderName::try_from(k.as_str())?, HeaderValue::try_from(v)?);
+        }
+
+        Ok(FetchedPage {
+            url: Url::parse(&self.url)?,
+            status: self.status,
+            headers,
+            body: self.body.clone(),
+        })
+    }
+
+    /// the page as a document which can be parsed without requesting it again
+    pub fn to_document(&self) -> Result<LoadedDocument> {
+        let page = self.to_page()?;
+        let url = page.url.clone();
+        let (data, content) = content::read(&url, page)?;
+
+        Ok(LoadedDocument::new(url.as_str(), &data)?.content(content))
+    }
+}
+
+/// Stores pages keyed by the URL they were requested with.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    /// the cached page requested with the URL, if any
+    async fn get(&self, url: &Url) -> Result<Option<CachedPage>>;
+
+    /// stores a page requested with the URL, replacing any cached version
+    async fn put(&self, url: &Url, page: &CachedPage) -> Result<()>;
+}
+
+/// A `Cache` which stores each page in a directory as two files named after a
+/// hash of its URL: the page's body and -- in JSON -- its URL, status and
+/// headers.
+#[derive(Debug, Clone)]
+pub struct FsCache {
+    dir: PathBuf,
+}
+
+impl FsCache {
+    /// caches pages in the directory, which is created when the first page is stored
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        FsCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Every page in the cache, ordered by URL; entries which are corrupt or
+    /// missing their body are logged and skipped.
+    pub async fn pages(&self) -> Result<Vec<CachedPage>> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => {
+                return Err(e).context(format!("Failed to read the cache: {}", self.dir.display()))
+            }
+        };
+        let mut pages = vec![];
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let page = async {
+                let mut page: CachedPage = serde_json::from_slice(&tokio::fs::read(&path).await?)?;
+                page.body = tokio::fs::read(path.with_extension("body")).await?.into();
+
+                Ok::<_, color_eyre::Report>(page)
+            };
+            match page.await {
+                Ok(page) => pages.push(page),
+                Err(e) => warn!("skipped the cache entry {}: {:#}", path.display(), e),
+            }
+        }
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+
+        Ok(pages)
+    }
+
+    /// the path of the cached page's files without their extension
+    fn path(&self, url: &Url) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(url.as_str())))
+    }
+}
+
+#[async_trait]
+impl Cache for FsCache {
+    async fn get(&self, url: &Url) -> Result<Option<CachedPage>> {
+        let path = self.path(url);
+        let meta = match tokio::fs::read(path.with_extension("json")).await {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut page: CachedPage = serde_json::from_slice(&meta)
+            .wrap_err_with(|| format!("the cache entry of {} is corrupt", url))?;
+        page.body = tokio::fs::read(path.with_extension("body")).await?.into();
+
+        Ok(Some(page))
+    }
+
+    async fn put(&self, url: &Url, page: &CachedPage) -> Result<()> {
+        let path = self.path(url);
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // the body is written first so that an entry is never missing its body
+        tokio::fs::write(path.with_extension("body"), &page.body).await?;
+        tokio::fs::write(path.with_extension("json"), serde_json::to_vec(page)?).await?;
+
+        Ok(())
+    }
+}
+
+/// A `Fetcher` which revalidates the pages it has cached rather than
+/// downloading them again. A cache which fails is logged and bypassed rather
+/// than failing the request.
+#[derive(Clone)]
+pub struct CachingFetcher {
+    cache: Arc<dyn Cache>,
+    fetcher: SharedFetcher,
+    /// whether the caching headers of pages are honored
+    honor_http_caching: bool,
+    /// the time pages are stored at and checked for freshness against
+    clock: SharedClock,
+}
+
+impl CachingFetcher {
+    /// caches the pages requested with the fetcher
+    pub fn new(cache: impl Cache + 'static, fetcher: SharedFetcher) -> Self {
+        CachingFetcher {
+            cache: Arc::new(cache),
+            fetcher,
+            honor_http_caching: false,
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Sets whether the `Cache-Control`, `Age` and `Expires` headers of pages
+    /// are honored: a cached page which is still fresh is served without
+    /// being revalidated, pages marked `no-store` aren't cached and pages
+    /// without a validator are cached for as long as they're fresh. By
+    /// default every page is revalidated.
+    pub fn honor_http_caching(mut self, honor: bool) -> Self {
+        self.honor_http_caching = honor;
+
+        self
+    }
+
+    /// sets the clock which the freshness of cached pages is judged by
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock(clock);
+
+        self
+    }
+
+    /// stores the page, logging rather than failing when the cache fails
+    async fn store(&self, url: &Url, page: &CachedPage) {
+        if let Err(e) = self.cache.put(url, page).await {
+            warn!("failed to cache {}: {:#}", url, e);
+        }
+    }
+}
+
+#[async_trait]
+impl Fetcher for CachingFetcher {
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        self.fetch_within(url, headers, &ResponseLimits::default())
+            .await
+    }
+
+    /// requests the page within the limits, which cached pages are held to too
+    async fn fetch_within(
+        &self,
+        url: &Url,
+        headers: &HeaderMap,
+        limits: &ResponseLimits,
+    ) -> Result<FetchedPage> {
+        let cached = self.cache.get(url).await.unwrap_or_else(|e| {
+            warn!("failed to read {} from the cache: {:#}", url, e);
+            None
+        });
+
+        let now = self.clock.0.utc();
+        if let Some(cached) = cached.as_ref().filter(|_| self.honor_http_caching) {
+            if cached.is_fresh(now) {
+                debug!("{} is still fresh in the cache", url);
+                let page = cached.to_page()?;
+                limits.check(&page)?;
+                return Ok(page);
+            }
+        }
+
+        let mut headers = headers.clone();
+        if let Some(cached) = &cached {
+            let validators = [(ETAG, IF_NONE_MATCH), (LAST_MODIFIED, IF_MODIFIED_SINCE)];
+            for (validator, condition) in validators {
+                if let Some(v) = cached.header(&validator) {
+                    headers.insert(condition, HeaderValue::try_from(v)?);
+                }
+            }
+        }
+
+        let page = self.fetcher.0.fetch_within(url, &headers, limits).await?;
+        if let (304, Some(mut cached)) = (page.status, cached) {
+            debug!("{} hasn't changed since it was cached", url);
+            if self.honor_http_caching {
+                cached.revalidated(&page.headers, now);
+                self.store(url, &cached).await;
+            }
+            let page = cached.to_page()?;
+            limits.check(&page)?;
+            return Ok(page);
+        }
+
+        if (200..300).contains(&page.status) {
+            let entry = match self.honor_http_caching {
+                true => CachedPage::honoring(&page, now),
+                false => CachedPage::new(&page),
+            };
+            if let Some(mut entry) = entry {
+                entry.stored_at = Some(now.timestamp());
+                self.store(url, &entry).await;
+            }
+        }
+
+        Ok(page)
+    }
+
+    /// requests the page's headers; they aren't cached
+    async fn head(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        self.fetcher.0.head(url, headers).await
+    }
+
+    async fn warm(&self, url: &Url, headers: &HeaderMap) -> Result<()> {
+        self.fetcher.0.warm(url, headers).await
+    }
+}
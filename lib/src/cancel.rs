@@ -0,0 +1,43 @@
+//! Stops a crawl gracefully (e.g., when Ctrl-C is pressed): once its
+//! `Cancellation` is cancelled no more pages are requested while those in
+//! flight are allowed to finish, so the pages scraped so far are still
+//! returned -- and sent to the sinks -- and a persisted `Frontier` keeps the
+//! pages which weren't for the crawl to resume.
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tracing::info;
+
+/// A handle which cancels the crawls of the `ScrapeConfig`s it's given to; it
+/// can be cloned and cancelled from another task while they're executing.
+#[derive(Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Cancellation::default()
+    }
+
+    /// stops any more pages from being requested; the pages in flight finish
+    pub fn cancel(&self) {
+        if !self.0.swap(true, Ordering::SeqCst) {
+            info!("the crawl was cancelled; finishing the pages in flight");
+        }
+    }
+
+    /// whether the crawl has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl Debug for Cancellation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Cancellation({})", self.is_cancelled())
+    }
+}
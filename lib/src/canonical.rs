@@ -0,0 +1,127 @@
+//! Merges the results of a crawl's pages which are the same page: those which
+//! declare the same `<link rel="canonical">` -- or whose canonical is another
+//! page of the crawl -- are kept as a single record, the canonical page's, and
+//! the URLs of the others are listed in its `duplicates`. Without this a site
+//! which serves a page under several URLs (e.g., with sorting or tracking
+//! parameters) inflates the number of pages a crawl reports.
+
+use crate::{
+    results::{PageOutcome, ParseResults},
+    url_norm::canonicalize,
+};
+use std::collections::HashMap;
+use tracing::debug;
+use url::Url;
+
+/// The URL which identifies the page of a result: its canonical when it
+/// declares one and otherwise the URL it was served from; URLs are compared
+/// in their canonical form (see `url_norm::canonicalize()`).
+fn key(page: &ParseResults) -> Url {
+    let url = page
+        .canonical
+        .as_ref()
+        .or(page.resolved_url.as_ref())
+        .unwrap_or(&page.url);
+
+    canonicalize(url)
+}
+
+/// whether the page was requested -- or served -- at the given canonical URL
+fn is_at(page: &ParseResults, canonical: &Url) -> bool {
+    std::iter::once(&page.url)
+        .chain(&page.resolved_url)
+        .any(|url| &canonicalize(url) == canonical)
+}
+
+/// merges the duplicate into the page which is kept
+fn merge(page: &mut ParseResults, duplicate: ParseResults) {
+    page.duplicates.push(duplicate.url);
+    page.duplicates.extend(duplicate.duplicates);
+}
+
+/// Merges a page into the page of its group which was kept; the page replaces
+/// it when only the page was requested at the group's canonical URL.
+fn absorb(kept: &mut ParseResults, page: ParseResults, canonical: &Url) {
+    match is_at(&page, canonical) && !is_at(kept, canonical) {
+        true => {
+            let previous = std::mem::replace(kept, page);
+            merge(kept, previous);
+        }
+        false => merge(kept, page),
+    }
+}
+
+/// Merges the pages which are the same page, in the order their first page
+/// appears: each group is kept as the page which was requested at the
+/// canonical URL -- or else the first -- with the URLs of the others in its
+/// `duplicates`.
+pub fn dedupe(pages: Vec<ParseResults>) -> Vec<ParseResults> {
+    let outcomes = pages
+        .into_iter()
+        .map(|page| PageOutcome::Ok(Box::new(page)))
+        .collect();
+
+    dedupe_outcomes(outcomes)
+        .into_iter()
+        .filter_map(|outcome| match outcome {
+            PageOutcome::Ok(page) => Some(*page),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Merges the pages of the successful outcomes of a crawl with `dedupe()`; the
+/// pages which failed or were blocked are kept as they are.
+pub fn dedupe_outcomes(outcomes: Vec<PageOutcome>) -> Vec<PageOutcome> {
+    let count = outcomes.len();
+    let mut deduped: Vec<PageOutcome> = vec![];
+    let mut groups: HashMap<Url, usize> = HashMap::new();
+    for outcome in outcomes {
+        let page = match outcome {
+            PageOutcome::Ok(page) => page,
+            outcome => {
+                deduped.push(outcome);
+                continue;
+            }
+        };
+        let key = key(&page);
+        match groups.get(&key).copied() {
+            Some(i) => {
+                if let PageOutcome::Ok(kept) = &mut deduped[i] {
+                    absorb(kept, *page, &key);
+                }
+            }
+            None => {
+                groups.insert(key, deduped.len());
+                deduped.push(PageOutcome::Ok(page));
+            }
+        }
+    }
+    if deduped.len() < count {
+        debug!(
+            "merged {} pages into the pages they're a duplicate of",
+            count - deduped.len()
+        );
+    }
+
+    deduped
+}
+
+/// Merges the outcomes of the children of a page which are the same page as
+/// it -- or as one another -- with `dedupe_outcomes()`; a child which is the
+/// same page as its parent is merged into the parent.
+pub fn dedupe_children(page: &mut ParseResults, outcomes: Vec<PageOutcome>) -> Vec<PageOutcome> {
+    let parent = key(page);
+    let (duplicates, outcomes): (Vec<PageOutcome>, Vec<PageOutcome>) =
+        outcomes.into_iter().partition(|outcome| match outcome {
+            PageOutcome::Ok(child) => key(child) == parent,
+            _ => false,
+        });
+    for duplicate in duplicates {
+        if let PageOutcome::Ok(duplicate) = duplicate {
+            merge(page, *duplicate);
+        }
+    }
+
+    dedupe_outcomes(outcomes)
+}
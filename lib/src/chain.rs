@@ -0,0 +1,204 @@
+use crate::selection::compile_selector;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Report, Result,
+};
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{Map, Value};
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// What is taken from a selected element
+#[derive(Debug, Clone, PartialEq)]
+enum Accessor {
+    /// the trimmed text of the element (`@text`, the default)
+    Text,
+    /// the inner HTML of the element (`@html`)
+    Html,
+    /// the value of one of the element's attributes (e.g., `@href`)
+    Attr(String),
+}
+
+#[derive(Debug, Clone)]
+enum Output {
+    /// a value taken from the first element matching the selector -- or from
+    /// the element in scope when there is no selector
+    Value(Option<Arc<Selector>>, Accessor),
+    /// an object whose fields are chains evaluated within the element in scope
+    Object(Vec<(String, Chain)>),
+}
+
+/// A chained selector expression which selects structured data without writing
+/// Rust, e.g.:
+///
+/// - `article h2 >> a@href` -- the `href` of the first link in each `article h2`
+/// - `.card >> {title: .t@text, link: a@href}` -- an object for each `.card`
+///
+/// Each selector before a `>>` narrows the scope to the elements it matches
+/// within the previous scope; a chain with scopes evaluates to a list with an
+/// entry for each element in scope whereas one without evaluates to the value
+/// of the first match. The final step takes the `@text` (the default), `@html`
+/// or an `@attribute` of an element, or builds an object from nested chains.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    source: String,
+    scopes: Vec<Arc<Selector>>,
+    output: Output,
+}
+
+impl Chain {
+    /// evaluates the chain against a document
+    pub fn evaluate(&self, html: &Html) -> Value {
+        self.evaluate_within(html.root_element())
+    }
+
+    fn evaluate_within(&self, el: ElementRef) -> Value {
+        if self.scopes.is_empty() {
+            return self.output(el);
+        }
+
+        let mut scope = vec![el];
+        for selector in &self.scopes {
+            scope = scope.iter().flat_map(|el| el.select(selector)).collect();
+        }
+
+        Value::Array(scope.into_iter().map(|el| self.output(el)).collect())
+    }
+
+    fn output(&self, el: ElementRef) -> Value {
+        match &self.output {
+            Output::Value(selector, accessor) => {
+                let el = match selector {
+                    Some(selector) => el.select(selector).next(),
+                    None => Some(el),
+                };
+                el.and_then(|el| access(el, accessor))
+                    .map_or(Value::Null, Value::String)
+            }
+            Output::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, chain)| (name.to_string(), chain.evaluate_within(el)))
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+}
+
+fn access(el: ElementRef, accessor: &Accessor) -> Option<String> {
+    match accessor {
+        Accessor::Text => Some(el.text().collect::<String>().trim().to_string()),
+        Accessor::Html => Some(el.inner_html()),
+        Accessor::Attr(name) => el.value().attr(name).map(|v| v.to_string()),
+    }
+}
+
+impl FromStr for Chain {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse(s).wrap_err(format!("invalid selector chain '{}'", s))
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn parse(source: &str) -> Result<Chain> {
+    let mut steps = split_top_level(source, ">>");
+    let last = steps.pop().unwrap_or_default().trim();
+    let scopes = steps
+        .iter()
+        .map(|s| compile_selector(s.trim()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let output = match last.strip_prefix('{') {
+        Some(object) => {
+            let fields = object
+                .strip_suffix('}')
+                .ok_or_else(|| eyre!("'{}' is missing a closing brace", last))?;
+            Output::Object(
+                split_top_level(fields, ",")
+                    .into_iter()
+                    .filter(|f| !f.trim().is_empty())
+                    .map(parse_field)
+                    .collect::<Result<Vec<_>>>()?,
+            )
+        }
+        None => parse_value(last)?,
+    };
+
+    Ok(Chain {
+        source: source.to_string(),
+        scopes,
+        output,
+    })
+}
+
+fn parse_field(field: &str) -> Result<(String, Chain)> {
+    let (name, chain) = field.split_once(':').ok_or_else(|| {
+        eyre!(
+            "the field '{}' must be written as `name: chain`",
+            field.trim()
+        )
+    })?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(eyre!("the field '{}' has no name", field.trim()));
+    }
+
+    Ok((name.to_string(), parse(chain.trim())?))
+}
+
+fn parse_value(value: &str) -> Result<Output> {
+    let (selector, accessor) = match split_top_level(value, "@").as_slice() {
+        [selector] => (*selector, None),
+        [selector, accessor] => (*selector, Some(accessor.trim())),
+        _ => return Err(eyre!("'{}' has more than one accessor", value)),
+    };
+    let selector = match selector.trim() {
+        "" => None,
+        selector => Some(compile_selector(selector)?),
+    };
+    let accessor = match accessor {
+        None | Some("text") => Accessor::Text,
+        Some("html") => Accessor::Html,
+        Some("") => return Err(eyre!("'{}' has an empty accessor", value)),
+        Some(attr) => Accessor::Attr(attr.to_string()),
+    };
+    if selector.is_none() && value.trim().is_empty() {
+        return Err(eyre!("a chain can't end with an empty step"));
+    }
+
+    Ok(Output::Value(selector, accessor))
+}
+
+/// splits the text on a separator which isn't within quotes, brackets,
+/// parentheses or braces
+fn split_top_level<'a>(text: &'a str, separator: &str) -> Vec<&'a str> {
+    let (mut parts, mut start, mut depth, mut quote) = (vec![], 0, 0, None);
+    let mut chars = text.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(' | '{') => depth += 1,
+            (None, ']' | ')' | '}') => depth -= 1,
+            (None, _) if depth == 0 && text[i..].starts_with(separator) => {
+                parts.push(&text[start..i]);
+                start = i + separator.len();
+                (1..separator.len()).for_each(|_| {
+                    chars.next();
+                });
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+
+    parts
+}
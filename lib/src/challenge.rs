@@ -0,0 +1,107 @@
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use url::Url;
+
+/// The kinds of anti-bot interstitials which can be detected in place of the
+/// page which was requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotChallenge {
+    Cloudflare,
+    Akamai,
+    DataDome,
+    PerimeterX,
+    /// a CAPTCHA served in place of the page's content
+    Captcha,
+}
+
+impl Display for BotChallenge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BotChallenge::Cloudflare => "Cloudflare",
+            BotChallenge::Akamai => "Akamai",
+            BotChallenge::DataDome => "DataDome",
+            BotChallenge::PerimeterX => "PerimeterX",
+            BotChallenge::Captcha => "CAPTCHA",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// markers which are specific enough to a vendor's interstitial that they
+/// identify a challenge regardless of the response's status
+const BODY_MARKERS: [(&str, BotChallenge); 9] = [
+    ("/cdn-cgi/challenge-platform/", BotChallenge::Cloudflare),
+    ("cf_chl_opt", BotChallenge::Cloudflare),
+    ("cf-browser-verification", BotChallenge::Cloudflare),
+    (
+        "<title>Attention Required! | Cloudflare</title>",
+        BotChallenge::Cloudflare,
+    ),
+    ("errors.edgesuite.net", BotChallenge::Akamai),
+    ("captcha-delivery.com", BotChallenge::DataDome),
+    ("px-captcha", BotChallenge::PerimeterX),
+    ("_pxCaptcha", BotChallenge::PerimeterX),
+    ("<title>Just a moment...</title>", BotChallenge::Cloudflare),
+];
+
+/// CAPTCHA widgets are also used legitimately (e.g., on contact forms) so they
+/// only indicate a challenge when the server refused to serve the page
+const CAPTCHA_MARKERS: [&str; 4] = [
+    "g-recaptcha",
+    "www.google.com/recaptcha",
+    "h-captcha",
+    "hcaptcha.com/1/api.js",
+];
+
+impl BotChallenge {
+    /// Detects whether a response is an anti-bot interstitial rather than the
+    /// page which was requested; `headers` are the response's headers with
+    /// lowercase names.
+    pub fn detect(status: u16, headers: &[(&str, &str)], body: &str) -> Option<BotChallenge> {
+        let header = |name: &str| headers.iter().find(|(k, _)| *k == name).map(|(_, v)| *v);
+        let refused = matches!(status, 403 | 429 | 503);
+
+        if header("cf-mitigated") == Some("challenge") {
+            return Some(BotChallenge::Cloudflare);
+        }
+        if header("x-datadome").is_some() && refused {
+            return Some(BotChallenge::DataDome);
+        }
+        if let Some((_, challenge)) = BODY_MARKERS.iter().find(|(m, _)| body.contains(m)) {
+            return Some(*challenge);
+        }
+        if refused && header("server").is_some_and(|s| s.contains("AkamaiGHost")) {
+            return Some(BotChallenge::Akamai);
+        }
+        if refused && CAPTCHA_MARKERS.iter().any(|m| body.contains(m)) {
+            return Some(BotChallenge::Captcha);
+        }
+
+        None
+    }
+}
+
+/// A page which was replaced by an anti-bot interstitial.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockedPage {
+    /// The URL of the page.
+    #[serde(serialize_with = "crate::util::url_to_string")]
+    pub url: Url,
+    /// The kind of challenge which was served in place of the page.
+    pub challenge: BotChallenge,
+    /// The HTTP status code of the response.
+    pub status: u16,
+}
+
+impl Display for BlockedPage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} was blocked by a {} challenge ({} status)",
+            self.url, self.challenge, self.status
+        )
+    }
+}
+
+impl std::error::Error for BlockedPage {}
@@ -0,0 +1,147 @@
+//! Rules which narrow the child URLs of a page which are followed, on top of
+//! the `ChildScope` of their selector: which hosts they may be on, the path
+//! they must be under and regular expressions which allow or deny them.
+
+use crate::safety::within_domain;
+use color_eyre::{eyre::WrapErr, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tracing::warn;
+use url::Url;
+
+/// Which hosts a child URL may be on, relative to the page linking to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum HostScope {
+    /// any host
+    #[default]
+    Any,
+    /// only the page's own host
+    SameHost,
+    /// the page's registrable domain (e.g., `example.co.uk`) and its subdomains
+    SameDomain,
+}
+
+/// The rules a child URL must satisfy to be followed; by default every URL
+/// which its `ChildScope` allows is.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct ChildRules {
+    /// which hosts a child URL may be on
+    pub hosts: HostScope,
+    /// the path a child URL must be under (e.g., `/docs/`)
+    pub path_prefix: Option<String>,
+    /// regular expressions of which a child URL must match at least one
+    pub allow: Vec<String>,
+    /// regular expressions which exclude the child URLs they match; they
+    /// take precedence over `allow`
+    pub deny: Vec<String>,
+}
+
+impl ChildRules {
+    pub fn new() -> Self {
+        ChildRules::default()
+    }
+
+    /// only follows the URLs on the page's own host
+    pub fn same_host(mut self) -> Self {
+        self.hosts = HostScope::SameHost;
+
+        self
+    }
+
+    /// only follows the URLs within the page's registrable domain
+    pub fn same_domain(mut self) -> Self {
+        self.hosts = HostScope::SameDomain;
+
+        self
+    }
+
+    /// only follows the URLs whose path starts with the prefix
+    pub fn path_prefix(mut self, prefix: &str) -> Self {
+        self.path_prefix = Some(prefix.to_string());
+
+        self
+    }
+
+    /// adds a regular expression of which a followed URL must match at least one
+    pub fn allow(mut self, pattern: &str) -> Self {
+        self.allow.push(pattern.to_string());
+
+        self
+    }
+
+    /// adds a regular expression which excludes the URLs it matches
+    pub fn deny(mut self, pattern: &str) -> Self {
+        self.deny.push(pattern.to_string());
+
+        self
+    }
+
+    /// checks that the `allow` and `deny` patterns are valid regular expressions
+    pub fn validate(&self) -> Result<()> {
+        self.allow.iter().chain(&self.deny).try_for_each(|pattern| {
+            Regex::new(pattern)
+                .map(|_| ())
+                .context(format!("'{}' is not a valid regular expression", pattern))
+        })
+    }
+
+    /// Keeps the child URLs of the page which the rules allow; an invalid
+    /// pattern is skipped with a warning.
+    pub fn retain(&self, page: &Url, urls: Vec<Url>) -> Vec<Url> {
+        let compile = |patterns: &[String]| -> Vec<Regex> {
+            patterns
+                .iter()
+                .filter_map(|pattern| match Regex::new(pattern) {
+                    Ok(re) => Some(re),
+                    Err(e) => {
+                        warn!("skipping the child rule with an invalid pattern: {}", e);
+                        None
+                    }
+                })
+                .collect()
+        };
+        let (allow, deny) = (compile(&self.allow), compile(&self.deny));
+        let page_host = page.host_str().map(|h| h.to_lowercase());
+        let domain = page_host.as_deref().map(registrable_domain);
+
+        urls.into_iter()
+            .filter(|url| {
+                let host = url.host_str().map(|h| h.to_lowercase());
+                match self.hosts {
+                    HostScope::Any => true,
+                    HostScope::SameHost => host.is_some() && host == page_host,
+                    HostScope::SameDomain => match (&host, &domain) {
+                        (Some(host), Some(domain)) => within_domain(host, domain),
+                        _ => false,
+                    },
+                }
+            })
+            .filter(|url| match &self.path_prefix {
+                Some(prefix) => url.path().starts_with(prefix.as_str()),
+                None => true,
+            })
+            .filter(|url| allow.is_empty() || allow.iter().any(|re| re.is_match(url.as_str())))
+            .filter(|url| !deny.iter().any(|re| re.is_match(url.as_str())))
+            .collect()
+    }
+}
+
+/// The registrable domain of a host -- the public suffix along with the label
+/// before it (e.g., `example.co.uk` for `www.example.co.uk`) -- or the host
+/// itself when it has none (e.g., an IP address or `localhost`).
+pub fn registrable_domain(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_lowercase();
+    if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+        return host;
+    }
+
+    match psl::domain_str(&host) {
+        Some(domain) => domain.to_string(),
+        None => host,
+    }
+}
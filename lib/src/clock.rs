@@ -0,0 +1,174 @@
+//! The source of the time, pauses and random jitter which rate limits, retries
+//! and sinks rely on. Requests use the `SystemClock` unless the config is given
+//! another; a `MockClock` makes the timing of a scrape deterministic -- and its
+//! pauses instantaneous -- so that it can be tested quickly.
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use lazy_static::lazy_static;
+use rand::Rng;
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A source of time.
+#[async_trait]
+pub trait Clock: Send + Sync {
+    /// the current instant, for measuring the time which has elapsed
+    fn now(&self) -> Instant;
+
+    /// the current date and time, for comparing with the dates of responses
+    fn utc(&self) -> DateTime<Utc>;
+
+    /// pauses for the duration
+    async fn sleep(&self, duration: Duration);
+
+    /// a random pause of up to `max`
+    fn jitter(&self, max: Duration) -> Duration;
+}
+
+/// The system's clock, with pauses which really sleep and random jitter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+
+    fn jitter(&self, max: Duration) -> Duration {
+        match max.is_zero() {
+            true => Duration::ZERO,
+            false => rand::thread_rng().gen_range(Duration::ZERO..=max),
+        }
+    }
+}
+
+/// the time which has passed on a `MockClock` and the pauses taken
+#[derive(Debug, Default)]
+struct MockState {
+    elapsed: Duration,
+    sleeps: Vec<Duration>,
+}
+
+/// A clock which only moves when it's advanced or slept on: a pause returns at
+/// once after advancing the clock by its duration and is recorded so that a
+/// test can check the pauses which were taken. Its jitter is always the same
+/// fraction of the maximum (none by default).
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    utc: DateTime<Utc>,
+    /// the fraction of the maximum jitter which is added
+    jitter: f64,
+    state: Mutex<MockState>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new(Utc.timestamp_opt(0, 0).unwrap())
+    }
+}
+
+impl MockClock {
+    /// a clock which starts at the given date and time
+    pub fn new(utc: DateTime<Utc>) -> Self {
+        MockClock {
+            start: Instant::now(),
+            utc,
+            jitter: 0.0,
+            state: Mutex::new(MockState::default()),
+        }
+    }
+
+    /// sets the fraction (between 0 and 1) of the maximum jitter which is added
+    pub fn jitter_fraction(mut self, fraction: f64) -> Self {
+        self.jitter = fraction.clamp(0.0, 1.0);
+
+        self
+    }
+
+    /// moves the clock forward without recording a pause
+    pub fn advance(&self, by: Duration) {
+        self.state.lock().unwrap().elapsed += by;
+    }
+
+    /// the time which has passed since the clock started
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().elapsed
+    }
+
+    /// the pauses taken, in the order they were taken
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.state.lock().unwrap().sleeps.clone()
+    }
+}
+
+#[async_trait]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn utc(&self) -> DateTime<Utc> {
+        self.utc + chrono::Duration::from_std(self.elapsed()).unwrap_or_default()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.elapsed += duration;
+            state.sleeps.push(duration);
+        }
+        // other tasks get their turn as they would while really sleeping
+        tokio::task::yield_now().await;
+    }
+
+    fn jitter(&self, max: Duration) -> Duration {
+        max.mul_f64(self.jitter)
+    }
+}
+
+lazy_static! {
+    /// the system clock is shared so that the default configs are equal
+    static ref SYSTEM_CLOCK: Arc<dyn Clock> = Arc::new(SystemClock);
+}
+
+/// The `Clock` of a `ScrapeConfig`; configs are equal when they share a clock.
+#[derive(Clone)]
+pub struct SharedClock(pub Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: impl Clock + 'static) -> Self {
+        SharedClock(Arc::new(clock))
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock(SYSTEM_CLOCK.clone())
+    }
+}
+
+impl PartialEq for SharedClock {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for SharedClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedClock")
+    }
+}
@@ -0,0 +1,110 @@
+//! Runs two selector configurations against the same stored pages -- e.g., the
+//! selectors written for a site before and after its redesign -- and reports
+//! how often each selector matched under either along with where their values
+//! differ, so that a new set of selectors can be checked against the old one
+//! before it replaces it. No pages are requested.
+
+use crate::{
+    report::{self, SelectorCoverage},
+    results::{diff::ResultsDiff, FlatResult, ParseResults},
+    LoadedDocument, ParsedDoc,
+};
+use color_eyre::{eyre::WrapErr, Result};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How a single selector fared under each configuration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelectorComparison {
+    /// the coverage under the old configuration; `None` when it has no such selector
+    pub old: Option<SelectorCoverage>,
+    /// the coverage under the new configuration; `None` when it has no such selector
+    pub new: Option<SelectorCoverage>,
+    /// the number of pages where the selector's values differ
+    pub pages_differing: usize,
+}
+
+/// The differences between the results of two configurations on the same pages.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    /// the number of pages compared
+    pub pages: usize,
+    /// the comparison of each selector of either configuration, keyed by its name
+    pub selectors: BTreeMap<String, SelectorComparison>,
+    /// the differences from the old configuration's results to the new one's,
+    /// page by page
+    pub diff: ResultsDiff,
+}
+
+impl Comparison {
+    /// Parses each document with the `old` and then the `new` configuration
+    /// -- functions which add the selectors and properties of each to a
+    /// `ParsedDoc` -- and compares their results.
+    pub fn new<'a>(
+        docs: impl IntoIterator<Item = &'a LoadedDocument>,
+        old: impl Fn(ParsedDoc) -> Result<ParsedDoc>,
+        new: impl Fn(ParsedDoc) -> Result<ParsedDoc>,
+    ) -> Result<Comparison> {
+        let (mut old_pages, mut new_pages) = (vec![], vec![]);
+        let (mut old_names, mut new_names) = (BTreeSet::new(), BTreeSet::new());
+        for doc in docs {
+            old_pages.extend(FlatResult::flatten(&results(doc, &old, &mut old_names)?));
+            new_pages.extend(FlatResult::flatten(&results(doc, &new, &mut new_names)?));
+        }
+
+        let diff = ResultsDiff::between(&json!(old_pages), &json!(new_pages));
+        let mut old_coverage = report::coverage(&old_pages, &old_names);
+        let mut new_coverage = report::coverage(&new_pages, &new_names);
+        let selectors = old_names
+            .union(&new_names)
+            .map(|name| {
+                let comparison = SelectorComparison {
+                    old: old_coverage
+                        .remove(name)
+                        .filter(|_| old_names.contains(name)),
+                    new: new_coverage
+                        .remove(name)
+                        .filter(|_| new_names.contains(name)),
+                    pages_differing: diff
+                        .pages
+                        .values()
+                        .filter(|p| {
+                            let s = &p.selections;
+                            s.added.contains_key(name)
+                                || s.removed.contains_key(name)
+                                || s.changed.contains_key(name)
+                        })
+                        .count(),
+                };
+                (name.to_string(), comparison)
+            })
+            .collect();
+
+        Ok(Comparison {
+            pages: old_pages.len(),
+            selectors,
+            diff,
+        })
+    }
+
+    /// whether both configurations produced the same results
+    pub fn is_empty(&self) -> bool {
+        self.diff.is_empty()
+    }
+}
+
+/// the results of the document parsed with a configuration, noting the names
+/// of the configuration's selectors
+fn results(
+    doc: &LoadedDocument,
+    configure: impl Fn(ParsedDoc) -> Result<ParsedDoc>,
+    names: &mut BTreeSet<String>,
+) -> Result<ParseResults> {
+    let parsed = configure(doc.parse_document()?)?;
+    names.extend(parsed.selectors.keys().cloned());
+
+    parsed
+        .results()
+        .context(format!("Failed to get the results of {}", doc.url))
+}
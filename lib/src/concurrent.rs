@@ -0,0 +1,526 @@
+use crate::{
+    buffer,
+    cancel::Cancellation,
+    canonical,
+    children::ChildRules,
+    error::ScrapedError,
+    fetch::{Fetcher, SharedFetcher},
+    frontier::{Discovery, Frontier, MemoryFrontier},
+    host_limit::HostLimits,
+    observe::Observer,
+    parse_url,
+    results::{PageOutcome, ParseResults},
+    robots,
+    sink::{self, Sink},
+    sitemap,
+    stats::ScrapeStats,
+    url_norm, warmup, Document, LoadedDocument, ParsedDoc, ScrapeConfig,
+};
+use color_eyre::{eyre::WrapErr, Result};
+use futures::{stream, StreamExt};
+use lazy_static::lazy_static;
+use scraper::Selector;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tracing::{debug, info, instrument, trace, warn};
+use url::Url;
+
+/// Configures the selectors, child selectors and properties of a loaded
+/// document; every document in a `ConcurrentScrape` is parsed with the same profile.
+/// It can capture its own state (e.g., selectors loaded at runtime); see
+/// `ConcurrentScrape::profile()`.
+pub type Profile = Arc<dyn Fn(LoadedDocument) -> ParsedDoc + Send + Sync>;
+
+lazy_static! {
+    static ref LINKS: Selector = Selector::parse("a[href]").unwrap();
+}
+
+/// the profile used when none is set: the generic selectors
+fn generic(doc: LoadedDocument) -> ParsedDoc {
+    ParsedDoc::from(doc).add_generic_selectors()
+}
+
+/// the host and first segment of the path of a URL, e.g. `blog` of
+/// `https://example.com/blog/2021/post`
+fn section(url: &Url) -> (Option<String>, String) {
+    let host = url.host_str().map(|h| h.to_lowercase());
+    let segment = url
+        .path_segments()
+        .and_then(|mut s| s.next())
+        .unwrap_or_default();
+
+    (host, segment.to_string())
+}
+
+/// Removes the documents which are the same page as an earlier one (see
+/// `url_norm::dedupe()`); documents which were given their HTML are kept.
+fn dedupe(documents: Vec<Document>) -> Vec<Document> {
+    let mut seen = HashSet::new();
+    let count = documents.len();
+    let documents: Vec<Document> = documents
+        .into_iter()
+        .filter(|doc| doc.data.is_some() || seen.insert(url_norm::canonicalize(&doc.url)))
+        .collect();
+    if documents.len() < count {
+        debug!(
+            "skipping {} documents which duplicate another",
+            count - documents.len()
+        );
+    }
+
+    documents
+}
+
+/// Scrapes a set of independent documents concurrently. Each document is
+/// requested -- along with the frames and next pages it follows -- with its
+/// own `ScrapeConfig` while the scrape's `ScrapeConfig` governs how many are
+/// requested at once and whether results are returned in the order the
+/// documents were added.
+///
+/// The URLs of the documents are queued in a `Frontier` and each page is marked
+/// as seen once it's scraped; with a persisted frontier (e.g., a `FileFrontier`)
+/// a crawl which was stopped resumes with the pages it hadn't scraped and skips
+/// those it had.
+pub struct ConcurrentScrape {
+    documents: Vec<Document>,
+    /// how the documents which weren't given to the scrape were discovered,
+    /// keyed by their canonical URLs
+    discovered: HashMap<Url, Discovery>,
+    /// the queue of URLs to request and the pages which were seen
+    frontier: Arc<dyn Frontier>,
+    profile: Profile,
+    config: ScrapeConfig,
+    /// the transport used to request the documents added by URL; unless it's
+    /// set, it's built for the scrape's config when the first is added
+    fetcher: Option<SharedFetcher>,
+    /// the destinations which receive the outcome of each document
+    sinks: Vec<Arc<dyn Sink>>,
+    stats: ScrapeStats,
+    /// the outcome of each document once the scrape has been executed
+    pub results: Vec<PageOutcome>,
+}
+
+impl Default for ConcurrentScrape {
+    fn default() -> Self {
+        ConcurrentScrape::new(ScrapeConfig::default())
+    }
+}
+
+impl ConcurrentScrape {
+    pub fn new(config: ScrapeConfig) -> Self {
+        ConcurrentScrape {
+            documents: vec![],
+            discovered: HashMap::new(),
+            frontier: Arc::new(MemoryFrontier::new()),
+            profile: Arc::new(generic),
+            config,
+            fetcher: None,
+            sinks: vec![],
+            stats: ScrapeStats::default(),
+            results: vec![],
+        }
+    }
+
+    /// adds a document to the scrape; it shares the scrape's rate limit
+    /// buckets and robots.txt cache -- so that the documents don't each get a
+    /// rate of their own -- and the pages it follows are stopped by the
+    /// scrape's `cancellation()`
+    pub fn add_document(mut self, mut doc: Document) -> Self {
+        doc.config.buckets = self.config.buckets.clone();
+        doc.config.robots = self.config.robots.clone();
+        doc.config.cancel = self.config.cancel.clone();
+        self.documents.push(doc);
+
+        self
+    }
+
+    /// adds a document for each URL; each is requested with the scrape's `ScrapeConfig`
+    pub fn add_urls(mut self, urls: &[&str]) -> Result<Self, ScrapedError> {
+        let fetcher = self.shared_fetcher()?;
+        for url in urls {
+            let doc = Document::new(url)?
+                .config(self.config.clone())
+                .shared_fetcher(fetcher.clone());
+            self.documents.push(doc);
+        }
+
+        Ok(self)
+    }
+
+    /// Adds a document for each page listed by a `sitemap.xml`; sitemap indexes
+    /// and gzipped sitemaps are followed. See `sitemap::urls()`.
+    pub async fn add_sitemap(mut self, url: &str) -> Result<Self, ScrapedError> {
+        let url = parse_url(url)?;
+        let fetcher = self.shared_fetcher()?;
+        let pages = sitemap::urls(&url, &self.config, fetcher.0.as_ref())
+            .await
+            .context(format!("Failed to read the sitemap: {}", url))?;
+        trace!("adding {} pages from the sitemap {}", pages.len(), url);
+        self.add_discovered(pages, Discovery::Sitemap, &fetcher);
+
+        Ok(self)
+    }
+
+    /// Adds the pages of the URL's whole site, preferring its sitemaps: the
+    /// pages listed by the sitemaps of its robots.txt (or else its
+    /// `/sitemap.xml`) are added along with those linked from its homepage in
+    /// the sections -- the first segment of their path, e.g. `/blog` -- which no
+    /// sitemap lists, and the homepage itself. Without a sitemap every page
+    /// linked from the homepage is added. The results of each page record how
+    /// it was discovered.
+    pub async fn add_site(mut self, url: &str) -> Result<Self, ScrapedError> {
+        let homepage = parse_url(url)?
+            .join("/")
+            .map_err(|e| ScrapedError::parse_failure(url, e))?;
+        let fetcher = self.shared_fetcher()?;
+        let robots = robots::load(&homepage, &self.config, fetcher.0.as_ref()).await;
+        let sitemaps = match robots.sitemaps().is_empty() {
+            true => vec![homepage
+                .join("/sitemap.xml")
+                .map_err(|e| ScrapedError::parse_failure(homepage.as_str(), e))?],
+            false => robots.sitemaps().to_vec(),
+        };
+        let mut listed = vec![];
+        for sitemap in sitemaps {
+            match sitemap::urls(&sitemap, &self.config, fetcher.0.as_ref()).await {
+                Ok(pages) => listed.extend(pages),
+                Err(e) => debug!("skipping the sitemap {}: {:#}", sitemap, e),
+            }
+        }
+
+        let sections: HashSet<(Option<String>, String)> = listed.iter().map(section).collect();
+        let linked: Vec<Url> = self
+            .homepage_links(&homepage, &fetcher)
+            .await
+            .into_iter()
+            .filter(|url| !sections.contains(&section(url)))
+            .collect();
+        debug!(
+            "adding {} pages of {} from its sitemaps and {} linked from its homepage",
+            listed.len(),
+            homepage,
+            linked.len()
+        );
+        self.add_discovered(listed, Discovery::Sitemap, &fetcher);
+        // the homepage is scraped whether or not a sitemap lists it
+        self.add_discovered(vec![homepage], Discovery::Seed, &fetcher);
+        self.add_discovered(linked, Discovery::Link, &fetcher);
+
+        Ok(self)
+    }
+
+    /// The pages on the same host which the homepage links to -- relative links
+    /// are resolved against it -- which the config's safety rules and robots.txt
+    /// allow; none when it can't be scraped.
+    async fn homepage_links(&self, homepage: &Url, fetcher: &SharedFetcher) -> Vec<Url> {
+        let doc = Document::from(homepage)
+            .config(self.config.clone())
+            .shared_fetcher(fetcher.clone());
+        let doc = match doc
+            .load_document()
+            .await
+            .and_then(|doc| doc.parse_document())
+        {
+            Ok(doc) => doc,
+            Err(e) => {
+                warn!("failed to scrape the homepage {}: {:#}", homepage, e);
+                return vec![];
+            }
+        };
+
+        // hrefs are resolved against the homepage so that relative links count
+        let mut seen = HashSet::new();
+        let links = doc
+            .html
+            .select(&LINKS)
+            .filter_map(|a| doc.resolved_url.join(a.value().attr("href")?.trim()).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .map(|mut url| {
+                url.set_fragment(None);
+                url
+            })
+            .filter(|url| seen.insert(url_norm::canonicalize(url)))
+            .collect();
+
+        let links = ChildRules::new()
+            .same_host()
+            .retain(&doc.resolved_url, links)
+            .into_iter()
+            .filter(|url| self.config.safety.allows(url))
+            .collect();
+
+        robots::retain_allowed(links, &self.config, fetcher.0.as_ref()).await
+    }
+
+    /// adds a document for each URL, recording how it was discovered
+    fn add_discovered(&mut self, urls: Vec<Url>, discovery: Discovery, fetcher: &SharedFetcher) {
+        for url in urls {
+            self.discovered
+                .entry(url_norm::canonicalize(&url))
+                .or_insert(discovery);
+            let doc = Document::from(&url)
+                .config(self.config.clone())
+                .shared_fetcher(fetcher.clone());
+            self.documents.push(doc);
+        }
+    }
+
+    /// sets the frontier the URLs of the documents are queued in; by default
+    /// it's held in memory
+    pub fn frontier(mut self, frontier: impl Frontier + 'static) -> Self {
+        self.frontier = Arc::new(frontier);
+
+        self
+    }
+
+    /// sets the transport used to request the documents added by URL or sitemap
+    pub fn fetcher(mut self, fetcher: impl Fetcher + 'static) -> Self {
+        self.fetcher = Some(SharedFetcher::new(fetcher));
+
+        self
+    }
+
+    /// the transport shared by the documents added by URL or sitemap
+    fn shared_fetcher(&mut self) -> Result<SharedFetcher> {
+        if self.fetcher.is_none() {
+            self.fetcher = Some(SharedFetcher::for_config(&self.config)?);
+        }
+
+        Ok(self.fetcher.clone().unwrap_or_default())
+    }
+
+    /// adds a destination which receives the outcome of each document as soon
+    /// as it's scraped
+    pub fn add_sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+
+        self
+    }
+
+    /// adds an observer which is called as each page is queued, requested and
+    /// loaded (e.g., to render a progress bar); see `ScrapeStats::observe()`
+    pub fn add_observer(self, observer: impl Observer + 'static) -> Self {
+        self.stats.observe(observer);
+
+        self
+    }
+
+    /// sets the profile used to parse each document -- either a function or a
+    /// closure which captures its own state; by default the generic selectors
+    /// are used
+    pub fn profile<F>(mut self, profile: F) -> Self
+    where
+        F: Fn(LoadedDocument) -> ParsedDoc + Send + Sync + 'static,
+    {
+        self.profile = Arc::new(profile);
+
+        self
+    }
+
+    /// A handle to the statistics of the pages requested; the handle can be
+    /// polled while the scrape is executing.
+    pub fn stats(&self) -> ScrapeStats {
+        self.stats.clone()
+    }
+
+    /// A handle which stops the scrape while it's executing: the pages in
+    /// flight finish -- and are returned along with those already scraped --
+    /// while the rest are returned as `PageOutcome::Cancelled` and put back in
+    /// the frontier. It's the `Cancellation` of the
+    /// scrape's `ScrapeConfig`.
+    pub fn cancellation(&self) -> Cancellation {
+        self.config.cancel.0.clone()
+    }
+
+    /// loads and parses a single document
+    async fn scrape(&self, doc: Document) -> Result<ParseResults> {
+        let url = doc.url.clone();
+        self.stats.started(&url);
+        let doc = match doc.load().await {
+            Ok(doc) => {
+                self.stats.finished(&url, doc.data.len());
+                doc
+            }
+            Err(e) => {
+                self.stats.failed(&url, &e);
+                return Err(e);
+            }
+        };
+        trace!("finished loading {}", url);
+
+        (self.profile)(doc).results_and_pages().await
+    }
+
+    /// Requests and parses every document, recording the outcome of each in
+    /// `results`; when the scrape's `ScrapeConfig` dedupes canonical pages, the
+    /// pages which are the same page are merged -- before they're sent to the
+    /// sinks -- (see `canonical::dedupe()`).
+    /// An error is only returned when the scrape's `ScrapeConfig` is
+    /// set to `fail_fast`; a scrape which is cancelled (see `cancellation()`)
+    /// returns the pages which were scraped before it stopped.
+    #[instrument(name = "crawl", skip(self), fields(documents = self.documents.len()))]
+    pub async fn execute(&mut self) -> Result<&[PageOutcome], ScrapedError> {
+        let documents = dedupe(std::mem::take(&mut self.documents));
+        let documents = self.take_documents(documents).await?;
+        self.stats.queued(documents.len());
+        if self.config.warmup > 0 {
+            // documents which were given their HTML aren't requested
+            let requested = documents
+                .iter()
+                .filter(|doc| doc.data.is_none())
+                .map(|doc| (&doc.url, doc.fetcher.0.as_ref()));
+            let hosts = warmup::busiest_hosts(requested, self.config.warmup);
+            warmup::warm_up(hosts, &self.config).await;
+        }
+
+        self.results = self.scrape_all(documents).await?;
+        sink::finish(&self.sinks).await;
+
+        Ok(&self.results)
+    }
+
+    /// Queues the documents in the frontier and takes every URL which is waiting
+    /// in it -- including those left by an earlier crawl -- as a document; the
+    /// pages which the frontier has seen are skipped. Documents which were given
+    /// their HTML aren't requested and so aren't queued; they come first.
+    async fn take_documents(&mut self, documents: Vec<Document>) -> Result<Vec<Document>> {
+        let (mut taken, requested): (Vec<Document>, Vec<Document>) =
+            documents.into_iter().partition(|doc| doc.data.is_some());
+        let mut pending = HashMap::new();
+        for doc in requested {
+            let canonical = url_norm::canonicalize(&doc.url);
+            let discovery = self.discovered.get(&canonical).copied().unwrap_or_default();
+            self.frontier.enqueue_from(&doc.url, discovery).await?;
+            pending.entry(canonical).or_insert(doc);
+        }
+
+        while let Some(url) = self.frontier.dequeue().await? {
+            // a URL left by an earlier crawl is requested with the scrape's config
+            let doc = match pending.remove(&url_norm::canonicalize(&url)) {
+                Some(doc) => doc,
+                None => Document::from(&url)
+                    .config(self.config.clone())
+                    .shared_fetcher(self.shared_fetcher()?),
+            };
+            taken.push(doc);
+        }
+        if !pending.is_empty() {
+            debug!(
+                "skipping {} documents which the frontier has seen",
+                pending.len()
+            );
+        }
+
+        Ok(taken)
+    }
+
+    /// Scrapes the documents concurrently, with no more than `max_per_host` of
+    /// the same host in flight; each page which was requested is marked as seen
+    /// by the frontier once the sinks have received it, while those which weren't
+    /// requested because the crawl was cancelled are put back in it. When the config dedupes
+    /// canonical pages, the pages are held until the last is scraped so that the
+    /// sinks only receive the merged pages.
+    async fn scrape_all(&self, documents: Vec<Document>) -> Result<Vec<PageOutcome>> {
+        let limits = HostLimits::new(&self.config);
+        let config = limits.buffer_config(&self.config, documents.len());
+        let limits = &limits;
+        let requests = stream::iter(documents).map(|doc| async move {
+            let url = doc.url.clone();
+            let queued = doc.data.is_none();
+            let _permit = match queued {
+                true => Some(limits.acquire(&url).await),
+                false => None,
+            };
+            // once the crawl is cancelled the pages which haven't been requested
+            // aren't
+            if queued && self.config.cancel.0.is_cancelled() {
+                return (url, queued, None);
+            }
+            (url, queued, Some(self.scrape(doc).await))
+        });
+        let pages = buffer(requests, &config);
+        futures::pin_mut!(pages);
+
+        let hold = self.config.dedupe_canonical;
+        let mut held = vec![];
+        let mut results = vec![];
+        let mut cancelled = 0;
+        while let Some((url, queued, result)) = pages.next().await {
+            let result = match result {
+                Some(result) => result,
+                None => {
+                    cancelled += 1;
+                    self.stats.cancelled(&url);
+                    self.requeue(&url).await;
+                    let outcome = PageOutcome::Cancelled(url);
+                    if !hold {
+                        sink::send(&self.sinks, &outcome).await;
+                    }
+                    results.push(outcome);
+                    continue;
+                }
+            };
+            match result {
+                Err(e) if self.config.fail_fast => {
+                    return Err(e).context(format!("Failed to scrape {}", url))
+                }
+                result => {
+                    let result = match (queued, result) {
+                        (true, Ok(mut page)) => {
+                            page.discovery = self.frontier.discovery(&url).await.ok().flatten();
+                            Ok(page)
+                        }
+                        (_, result) => result,
+                    };
+                    let outcome = PageOutcome::new(url.clone(), result);
+                    match hold {
+                        true => held.push((url, queued)),
+                        false => self.deliver(&outcome, &url, queued).await,
+                    }
+                    results.push(outcome);
+                }
+            }
+        }
+        if cancelled > 0 {
+            info!(
+                "the crawl was cancelled with {} pages left to request",
+                cancelled
+            );
+        }
+        if hold {
+            results = canonical::dedupe_outcomes(results);
+            for outcome in &results {
+                sink::send(&self.sinks, outcome).await;
+            }
+            for (url, queued) in held {
+                self.mark_seen(&url, queued).await;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// sends the outcome of a page to the sinks and then marks it as seen
+    async fn deliver(&self, outcome: &PageOutcome, url: &Url, queued: bool) {
+        sink::send(&self.sinks, outcome).await;
+        self.mark_seen(url, queued).await;
+    }
+
+    /// puts a page which wasn't requested back in the frontier
+    async fn requeue(&self, url: &Url) {
+        if let Err(e) = self.frontier.requeue(url).await {
+            warn!("the frontier failed to requeue {}: {:#}", url, e);
+        }
+    }
+
+    /// marks a page which was requested as seen by the frontier
+    async fn mark_seen(&self, url: &Url, queued: bool) {
+        if queued {
+            if let Err(e) = self.frontier.mark_seen(url).await {
+                warn!("the frontier failed to mark {} as seen: {:#}", url, e);
+            }
+        }
+    }
+}
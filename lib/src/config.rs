@@ -0,0 +1,720 @@
+use crate::{
+    auth::BearerTokens,
+    cancel::Cancellation,
+    clock::{Clock, SharedClock},
+    dns::DnsConfig,
+    id::IdStrategy,
+    limits::ResponseLimits,
+    link_audit::LinkAudit,
+    network::NetworkConfig,
+    pagination::Pagination,
+    proxy::ProxyConfig,
+    rate::{RateBuckets, RateLimit},
+    redact::{self, Redacted},
+    redirect::RedirectPolicy,
+    render::RenderConfig,
+    retry::RetryPolicy,
+    robots::RobotsCache,
+    safety::{normalize_domain, within_domain, CrawlSafety},
+    sample::Sampling,
+    selection::TemplateKind,
+    text::TextPolicy,
+    transforms::FormatPolicy,
+};
+use color_eyre::{eyre::WrapErr, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use url::Url;
+
+/// the default number of pages which are requested concurrently
+pub const CONCURRENT_REQUESTS: usize = 8;
+
+/// Configures _how_ pages are scraped -- as opposed to _what_ is scraped from
+/// them -- when following child pages of a document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrapeConfig {
+    /// the number of pages which will be requested concurrently
+    pub concurrency: usize,
+    /// the most pages of the same host which are requested concurrently;
+    /// only the `concurrency` applies when not set
+    pub max_per_host: Option<usize>,
+    /// return pages in the order they were requested rather than as soon as
+    /// each completes
+    pub ordered: bool,
+    /// the rate at which each host is requested to avoid overwhelming a
+    /// server; see `rate::acquire()`
+    pub rate_limit: RateLimit,
+    /// abort the crawl on the first page which fails rather than recording
+    /// the failure and carrying on
+    pub fail_fast: bool,
+    /// the longest a request may take -- from connecting until the body has been
+    /// read -- before it fails with `ScrapedError::Timeout`; unbounded when not set
+    #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    pub timeout: Option<Duration>,
+    /// how requests which fail for transient reasons are retried
+    pub retry: RetryPolicy,
+    /// the size and content types of the responses which are accepted
+    pub limits: ResponseLimits,
+    /// skip the pages which a site's robots.txt disallows and honor its
+    /// `Crawl-delay`
+    pub respect_robots: bool,
+    /// the hard limits on which URLs may be requested
+    pub safety: CrawlSafety,
+    /// how the hostnames of pages are resolved (e.g., with DNS-over-HTTPS)
+    pub dns: DnsConfig,
+    /// the IP version which is preferred and the local address requests are made from
+    pub network: NetworkConfig,
+    /// the proxies requests are routed through
+    pub proxy: ProxyConfig,
+    /// render pages in a headless browser before they're parsed
+    pub render: RenderConfig,
+    /// scrape the most recent snapshot in the Wayback Machine of a page which
+    /// is gone (404 or 410) or can't be reached; see `archive::latest()`
+    pub archive_fallback: bool,
+    /// capture the headers and (truncated) body of error responses so
+    /// that failures can be diagnosed
+    pub capture_error_body: bool,
+    /// honor the `Cache-Control`, `Age` and `Expires` headers of pages in a
+    /// `CachingFetcher`: fresh pages are served without being revalidated and
+    /// `no-store` pages aren't cached
+    pub honor_http_caching: bool,
+    /// the `User-Agent` sent with each request; reqwest's default is used
+    /// when not set
+    pub user_agent: Option<String>,
+    /// headers sent with each request (e.g., `Accept-Language` or `Cookie`);
+    /// their values are redacted when the config is logged or serialized
+    pub headers: BTreeMap<String, Redacted<String>>,
+    /// headers sent with the requests to a domain (and its subdomains) in
+    /// addition to -- or in place of -- the `headers`; a more specific domain
+    /// takes precedence
+    pub domain_headers: BTreeMap<String, BTreeMap<String, Redacted<String>>>,
+    /// the bearer tokens which authorize requests; a token's `Authorization`
+    /// header takes the place of any in the `headers`
+    pub bearer_tokens: BearerTokens,
+    /// which markup within `<template>` elements can be selected
+    pub templates: TemplatePolicy,
+    /// what happens when a selector is added under a name which is taken
+    pub duplicate_selectors: DuplicatePolicy,
+    /// record in each page's results where the selector of each selection
+    /// was defined (a preset, a config file or code); see `SelectorSource`
+    pub provenance: bool,
+    /// how the stable identifier of each page's results is derived
+    pub id: IdStrategy,
+    /// how the text of selections is normalized
+    pub text: TextPolicy,
+    /// how the dates and numbers of transformed properties are written
+    pub format: FormatPolicy,
+    /// include the OpenGraph, Twitter card and JSON-LD metadata of each page
+    /// in its results
+    pub metadata: bool,
+    /// include the hydration payloads of single page apps (e.g., `__NEXT_DATA__`)
+    /// in the selections; see `hydration::payloads()`
+    pub hydration: bool,
+    /// repair malformed HTML (e.g., unterminated comments and double-encoded
+    /// entities) before it's parsed
+    pub repair_html: bool,
+    /// how HTTP redirects are followed
+    pub redirects: RedirectPolicy,
+    /// follow meta refresh and trivial JS redirects
+    pub follow_client_redirects: bool,
+    /// the maximum number of client-side redirects followed for a page
+    pub max_redirect_hops: usize,
+    /// which documents embedded with `<iframe src>` are scraped along with
+    /// the page
+    pub frames: FramePolicy,
+    /// whether the pages which follow a document -- by `rel="next"` in its
+    /// `Link` header or HTML -- are scraped along with it
+    pub pagination: Pagination,
+    /// merge the results of a crawl's pages which are the same page -- by their
+    /// `<link rel="canonical">` -- into one record; see `canonical::dedupe()`
+    pub dedupe_canonical: bool,
+    /// whether -- and which of -- the links of each page are checked with a
+    /// `HEAD` request; see `link_audit`
+    pub link_audit: LinkAudit,
+    /// the locales (e.g. `de` or `fr-CA`) whose hreflang alternates are
+    /// followed along with the child pages
+    pub locales: Vec<String>,
+    /// which of the child URLs are followed, to estimate a site's structure
+    /// before crawling all of it
+    pub sample: Sampling,
+    /// the number of hosts -- those with the most pages to request -- whose
+    /// connections are established before a crawl's pages are requested; see
+    /// `warmup::warm_up()`
+    pub warmup: usize,
+    /// the source of the time, pauses and jitter; the system's clock unless
+    /// it's replaced (e.g., with a `MockClock` in tests)
+    #[serde(skip)]
+    pub clock: SharedClock,
+    /// stops the crawl from requesting any more pages once it's cancelled
+    #[serde(skip)]
+    pub cancel: CrawlHandle<Cancellation>,
+    /// the robots.txt of each origin the crawl has requested; shared by the
+    /// config's clones
+    #[serde(skip)]
+    pub robots: CrawlHandle<RobotsCache>,
+    /// the token buckets which limit the rate each host is requested at;
+    /// shared by the config's clones
+    #[serde(skip)]
+    pub buckets: CrawlHandle<RateBuckets>,
+}
+
+/// A handle to the state of a crawl (e.g., its `Cancellation`) which the clones
+/// of a `ScrapeConfig` share; it isn't a setting, so configs are equal whatever
+/// their handles are.
+#[derive(Debug, Clone, Default)]
+pub struct CrawlHandle<T>(pub T);
+
+impl<T> PartialEq for CrawlHandle<T> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// Which documents embedded with `<iframe src>` are scraped -- with the same
+/// selectors and properties -- and attached to the page's `frames`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FramePolicy {
+    /// frames are not followed
+    #[default]
+    Ignore,
+    /// only frames served from the same origin as the page are followed
+    SameOrigin,
+    /// frames from any HTTP(S) origin are followed
+    All,
+}
+
+impl FramePolicy {
+    /// whether the frame at `url` -- embedded in the page at `page` -- is followed
+    pub fn allows(&self, url: &Url, page: &Url) -> bool {
+        let http = matches!(url.scheme(), "http" | "https");
+        match self {
+            FramePolicy::Ignore => false,
+            FramePolicy::SameOrigin => http && url.origin() == page.origin(),
+            FramePolicy::All => http,
+        }
+    }
+}
+
+/// Which markup within `<template>` elements can be selected; selections
+/// within a template record which kind it was in their `template` property.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TemplatePolicy {
+    /// only what a browser renders: declarative shadow roots are selected but
+    /// inert `<template>` markup is not
+    #[default]
+    Rendered,
+    /// neither shadow roots nor inert templates are selected
+    LightDom,
+    /// all markup is selected, including inert templates
+    All,
+}
+
+impl TemplatePolicy {
+    /// whether markup within the given kind of template can be selected
+    pub fn allows(&self, template: Option<TemplateKind>) -> bool {
+        match (self, template) {
+            (_, None) | (TemplatePolicy::All, _) => true,
+            (TemplatePolicy::Rendered, Some(kind)) => kind == TemplateKind::ShadowRoot,
+            (TemplatePolicy::LightDom, Some(_)) => false,
+        }
+    }
+}
+
+/// What happens when a selector (or chain) is added to a document under the
+/// name of one it already has; a selector which is replaced or ignored is
+/// logged as a warning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// the `try_add_*` methods of a `ParsedDoc` return a
+    /// `ScrapedError::DuplicateSelector` while the infallible `add_*` methods
+    /// panic with it
+    Error,
+    /// the selector replaces the one of the same name
+    #[default]
+    Replace,
+    /// the selector is ignored and the one added first is kept
+    KeepFirst,
+}
+
+/// the default maximum number of client-side redirects followed for a page
+pub const MAX_REDIRECT_HOPS: usize = 5;
+
+/// inserts the headers, replacing those of the same name; the values of
+/// sensitive headers are marked as such
+fn insert_headers(
+    headers: &mut HeaderMap,
+    values: &BTreeMap<String, Redacted<String>>,
+) -> Result<()> {
+    for (name, value) in values {
+        let mut value = HeaderValue::from_str(value.expose())
+            .context(format!("invalid value for the '{}' header", name))?;
+        value.set_sensitive(redact::is_sensitive(name));
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes())
+                .context(format!("invalid header name '{}'", name))?,
+            value,
+        );
+    }
+
+    Ok(())
+}
+
+impl Default for ScrapeConfig {
+    fn default() -> Self {
+        ScrapeConfig {
+            concurrency: CONCURRENT_REQUESTS,
+            max_per_host: None,
+            ordered: true,
+            rate_limit: RateLimit::default(),
+            fail_fast: false,
+            timeout: None,
+            retry: RetryPolicy::default(),
+            limits: ResponseLimits::default(),
+            respect_robots: false,
+            safety: CrawlSafety::default(),
+            dns: DnsConfig::default(),
+            network: NetworkConfig::default(),
+            proxy: ProxyConfig::default(),
+            render: RenderConfig::default(),
+            archive_fallback: false,
+            capture_error_body: false,
+            honor_http_caching: false,
+            user_agent: None,
+            headers: BTreeMap::new(),
+            domain_headers: BTreeMap::new(),
+            bearer_tokens: BearerTokens::default(),
+            templates: TemplatePolicy::default(),
+            duplicate_selectors: DuplicatePolicy::default(),
+            provenance: false,
+            id: IdStrategy::default(),
+            text: TextPolicy::default(),
+            format: FormatPolicy::default(),
+            metadata: false,
+            hydration: false,
+            repair_html: false,
+            redirects: RedirectPolicy::default(),
+            follow_client_redirects: false,
+            max_redirect_hops: MAX_REDIRECT_HOPS,
+            frames: FramePolicy::default(),
+            pagination: Pagination::default(),
+            dedupe_canonical: false,
+            link_audit: LinkAudit::default(),
+            locales: vec![],
+            sample: Sampling::default(),
+            warmup: 0,
+            clock: SharedClock::default(),
+            cancel: CrawlHandle::default(),
+            robots: CrawlHandle::default(),
+            buckets: CrawlHandle::default(),
+        }
+    }
+}
+
+impl ScrapeConfig {
+    /// sets the number of pages which will be requested concurrently
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+
+        self
+    }
+
+    /// sets the most pages of the same host which are requested concurrently,
+    /// so that a crawl across many hosts can run wide while each is sent a few
+    /// requests at a time
+    pub fn max_per_host(mut self, max: usize) -> Self {
+        self.max_per_host = Some(max.max(1));
+
+        self
+    }
+
+    /// sets whether pages are returned in the order they were requested rather
+    /// than as soon as each completes
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+
+        self
+    }
+
+    /// sets the rate at which each host is requested
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = rate_limit;
+
+        self
+    }
+
+    /// sets whether the headers and body of error responses are captured
+    pub fn capture_error_body(mut self, capture: bool) -> Self {
+        self.capture_error_body = capture;
+
+        self
+    }
+
+    /// sets whether the crawl is aborted on the first page which fails
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+
+        self
+    }
+
+    /// sets the longest a request may take before it fails
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+
+        self
+    }
+
+    /// sets the size and content types of the responses which are accepted; a
+    /// response beyond them is abandoned as soon as that's known
+    pub fn limits(mut self, limits: ResponseLimits) -> Self {
+        self.limits = limits;
+
+        self
+    }
+
+    /// sets how requests which fail for transient reasons are retried
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+
+        self
+    }
+
+    /// sets whether the robots.txt of each site is respected
+    pub fn respect_robots(mut self, respect: bool) -> Self {
+        self.respect_robots = respect;
+
+        self
+    }
+
+    /// sets the hard limits on which URLs may be requested
+    pub fn safety(mut self, safety: CrawlSafety) -> Self {
+        self.safety = safety;
+
+        self
+    }
+
+    /// sets how the hostnames of pages are resolved
+    pub fn dns(mut self, dns: DnsConfig) -> Self {
+        self.dns = dns;
+
+        self
+    }
+
+    /// sets the options of the connections made for requests
+    pub fn network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+
+        self
+    }
+
+    /// sets how pages are rendered in a headless browser
+    pub fn render(mut self, render: RenderConfig) -> Self {
+        self.render = render;
+
+        self
+    }
+
+    /// spreads requests across the proxies, in turn
+    pub fn proxy_pool(mut self, proxies: Vec<Url>) -> Self {
+        self.proxy.pool = proxies.iter().map(|p| p.to_string()).collect();
+
+        self
+    }
+
+    /// routes the requests for a domain (and its subdomains) through a proxy
+    /// rather than the pool
+    pub fn domain_proxy(mut self, domain: &str, proxy: Url) -> Self {
+        self.proxy
+            .domains
+            .insert(domain.to_string(), proxy.to_string());
+
+        self
+    }
+
+    /// sets whether pages which have rotted away are scraped from the Wayback Machine
+    pub fn archive_fallback(mut self, fallback: bool) -> Self {
+        self.archive_fallback = fallback;
+
+        self
+    }
+
+    /// sets whether a `CachingFetcher` honors the caching headers of the pages
+    /// it caches; see `CachingFetcher::honor_http_caching()`
+    pub fn honor_http_caching(mut self, honor: bool) -> Self {
+        self.honor_http_caching = honor;
+
+        self
+    }
+
+    /// sets the `User-Agent` sent with each request
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+
+        self
+    }
+
+    /// adds a header which is sent with each request
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .insert(name.to_string(), Redacted::new(value.to_string()));
+
+        self
+    }
+
+    /// adds headers which are sent with the requests to a domain and its subdomains
+    pub fn headers_for<K: ToString, V: ToString>(
+        mut self,
+        domain: &str,
+        headers: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.domain_headers
+            .entry(normalize_domain(domain))
+            .or_default()
+            .extend(
+                headers
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), Redacted::new(v.to_string()))),
+            );
+
+        self
+    }
+
+    /// sets the bearer tokens which authorize requests
+    pub fn bearer_tokens(mut self, tokens: BearerTokens) -> Self {
+        self.bearer_tokens = tokens;
+
+        self
+    }
+
+    /// sets the bearer token sent with the requests whose domain has no token
+    /// of its own
+    pub fn bearer_token(mut self, token: &str) -> Self {
+        self.bearer_tokens = self.bearer_tokens.global(token);
+
+        self
+    }
+
+    /// sets the bearer token sent with the requests to a domain and its subdomains
+    pub fn bearer_token_for(mut self, domain: &str, token: &str) -> Self {
+        self.bearer_tokens = self.bearer_tokens.scoped(domain, token);
+
+        self
+    }
+
+    /// the headers sent with each request
+    pub fn request_headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        insert_headers(&mut headers, &self.headers)?;
+        if let Some(ua) = &self.user_agent {
+            headers.insert(
+                USER_AGENT,
+                HeaderValue::from_str(ua).context("invalid user agent")?,
+            );
+        }
+
+        Ok(headers)
+    }
+
+    /// the headers sent with the request for the URL: those sent with each
+    /// request along with those of the URL's domain, from the least to the most
+    /// specific domain
+    pub fn request_headers_for(&self, url: &Url) -> Result<HeaderMap> {
+        let mut headers = self.request_headers()?;
+        let host = match url.host_str() {
+            Some(host) => host.trim_end_matches('.').to_lowercase(),
+            None => return Ok(headers),
+        };
+
+        let mut domains: Vec<_> = self
+            .domain_headers
+            .iter()
+            .map(|(domain, headers)| (normalize_domain(domain), headers))
+            .filter(|(domain, _)| within_domain(&host, domain))
+            .collect();
+        domains.sort_by_key(|(domain, _)| domain.len());
+        for (_, domain_headers) in domains {
+            insert_headers(&mut headers, domain_headers)?;
+        }
+        if let Some(token) = self.bearer_tokens.token_for(&host) {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .context("invalid bearer token")?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        Ok(headers)
+    }
+
+    /// sets which markup within `<template>` elements can be selected
+    pub fn templates(mut self, policy: TemplatePolicy) -> Self {
+        self.templates = policy;
+
+        self
+    }
+
+    /// sets what happens when a selector is added under a name which is taken
+    pub fn duplicate_selectors(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_selectors = policy;
+
+        self
+    }
+
+    /// sets whether the results record where the selector of each selection
+    /// was defined
+    pub fn provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+
+        self
+    }
+
+    /// sets how the stable identifier of each page's results is derived
+    pub fn id(mut self, strategy: IdStrategy) -> Self {
+        self.id = strategy;
+
+        self
+    }
+
+    /// sets how the text of selections is normalized
+    pub fn text(mut self, policy: TextPolicy) -> Self {
+        self.text = policy;
+
+        self
+    }
+
+    /// sets how the dates and numbers of transformed properties are written
+    pub fn format(mut self, policy: FormatPolicy) -> Self {
+        self.format = policy;
+
+        self
+    }
+
+    /// sets whether the structured metadata of each page is included in its results
+    pub fn metadata(mut self, metadata: bool) -> Self {
+        self.metadata = metadata;
+
+        self
+    }
+
+    /// sets whether the hydration payloads of single page apps are included in
+    /// the selections
+    pub fn hydration(mut self, hydration: bool) -> Self {
+        self.hydration = hydration;
+
+        self
+    }
+
+    /// sets whether malformed HTML is repaired before it's parsed
+    pub fn repair_html(mut self, repair: bool) -> Self {
+        self.repair_html = repair;
+
+        self
+    }
+
+    /// sets how HTTP redirects are followed
+    pub fn redirects(mut self, policy: RedirectPolicy) -> Self {
+        self.redirects = policy;
+
+        self
+    }
+
+    /// sets whether meta refresh and trivial JS redirects are followed and the
+    /// maximum number of hops which will be followed for a page
+    pub fn follow_client_redirects(mut self, follow: bool, max_hops: usize) -> Self {
+        self.follow_client_redirects = follow;
+        self.max_redirect_hops = max_hops;
+
+        self
+    }
+
+    /// sets which documents embedded with `<iframe src>` are scraped
+    pub fn frames(mut self, policy: FramePolicy) -> Self {
+        self.frames = policy;
+
+        self
+    }
+
+    /// sets whether -- and how far -- the pages which follow a document are
+    /// scraped along with it
+    pub fn pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+
+        self
+    }
+
+    /// sets whether the results of a crawl's pages which are the same page are
+    /// merged into one record; the sinks receive the merged pages, which are
+    /// then held until the crawl's last page is scraped
+    pub fn dedupe_canonical(mut self, dedupe: bool) -> Self {
+        self.dedupe_canonical = dedupe;
+
+        self
+    }
+
+    /// sets whether -- and which of -- the links of each page are checked
+    pub fn link_audit(mut self, audit: LinkAudit) -> Self {
+        self.link_audit = audit;
+
+        self
+    }
+
+    /// sets the locales whose hreflang alternates are followed along with
+    /// the child pages
+    pub fn locales(mut self, locales: Vec<String>) -> Self {
+        self.locales = locales;
+
+        self
+    }
+
+    /// sets which of the child URLs are followed
+    pub fn sample(mut self, sample: Sampling) -> Self {
+        self.sample = sample;
+
+        self
+    }
+
+    /// sets the number of hosts whose connections are warmed up before a crawl
+    pub fn warmup(mut self, hosts: usize) -> Self {
+        self.warmup = hosts;
+
+        self
+    }
+
+    /// sets the source of the time, pauses and jitter
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock(clock);
+
+        self
+    }
+
+    /// sets the handle which cancels the crawl
+    pub fn cancellation(mut self, cancel: Cancellation) -> Self {
+        self.cancel = CrawlHandle(cancel);
+
+        self
+    }
+
+    /// sets the cache of the robots.txt files which have been requested, e.g.,
+    /// to share it between scrapes
+    pub fn robots_cache(mut self, cache: RobotsCache) -> Self {
+        self.robots = CrawlHandle(cache);
+
+        self
+    }
+
+    /// sets the token buckets which limit the rate each host is requested at,
+    /// e.g., to share them between scrapes
+    pub fn rate_buckets(mut self, buckets: RateBuckets) -> Self {
+        self.buckets = CrawlHandle(buckets);
+
+        self
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{error::ScrapedError, fetch::FetchedPage, results::ResultKind, selection::Selection};
+#[cfg(feature = "pdf")]
+use color_eyre::eyre::eyre;
+use color_eyre::{eyre::WrapErr, Result};
+use scraper::Html;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use url::Url;
+
+/// The kind of content a document holds. Anything other than HTML has its
+/// text extracted when it's loaded and is exposed as results in place of the
+/// CSS selections.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Content {
+    #[default]
+    Html,
+    /// a PDF whose text was extracted; requires the `pdf` feature
+    Pdf(PdfInfo),
+    /// plain text which is exposed as its `text` and `lines`
+    Text,
+    /// CSV data whose rows are exposed as selections keyed by the header row
+    Csv,
+    /// a JSON document which is exposed as its `json` value
+    Json,
+}
+
+impl Content {
+    pub fn is_html(&self) -> bool {
+        matches!(self, Content::Html)
+    }
+}
+
+/// The metadata of a PDF document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PdfInfo {
+    pub pages: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub producer: Option<String>,
+}
+
+/// the formats a response can be read as
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Html,
+    Pdf,
+    Text,
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// the format of a response; the `Content-Type` header is preferred over
+    /// the extension of the URL's path
+    fn detect(url: &Url, content_type: Option<&str>) -> Format {
+        let mime = content_type
+            .and_then(|c| c.split(';').next())
+            .map(|c| c.trim().to_lowercase());
+
+        let path = url.path().to_lowercase();
+        let extension = path.rsplit_once('.').map(|(_, ext)| ext);
+
+        match (mime.as_deref(), extension) {
+            (Some("text/html"), _) | (Some("application/xhtml+xml"), _) => Format::Html,
+            (Some("application/pdf"), _) => Format::Pdf,
+            (Some("text/csv"), _) => Format::Csv,
+            (Some("application/json"), _) => Format::Json,
+            (Some(mime), _) if mime.ends_with("+json") => Format::Json,
+            // servers often send data files as plain text so the extension is
+            // a better guide to their format
+            (Some("text/plain"), Some("csv")) | (None, Some("csv")) => Format::Csv,
+            (Some("text/plain"), Some("json")) | (None, Some("json")) => Format::Json,
+            (Some("text/plain"), _) => Format::Text,
+            (_, Some("pdf")) => Format::Pdf,
+            (None, Some("txt")) => Format::Text,
+            _ => Format::Html,
+        }
+    }
+}
+
+/// whether a page is HTML
+pub(crate) fn is_html(url: &Url, page: &FetchedPage) -> bool {
+    Format::detect(url, page.content_type()) == Format::Html
+}
+
+/// Reads the body of a successful response as text along with the kind of
+/// content it holds.
+pub(crate) fn read(url: &Url, page: FetchedPage) -> Result<(String, Content)> {
+    match Format::detect(url, page.content_type()) {
+        Format::Html => Ok((page.into_text(), Content::Html)),
+        Format::Pdf => extract_pdf(&page.body)
+            .context(format!("Failed to extract the text of the PDF at {}", url)),
+        Format::Text => Ok((page.into_text(), Content::Text)),
+        Format::Csv => {
+            let data = page.into_text();
+            csv_rows(&data)
+                .map_err(|e| ScrapedError::parse_failure(&data, e))
+                .context(format!("Failed to parse the CSV at {}", url))?;
+            Ok((data, Content::Csv))
+        }
+        Format::Json => {
+            let data = page.into_text();
+            serde_json::from_str::<Value>(&data)
+                .map_err(|e| ScrapedError::parse_failure(&data, e))
+                .context(format!("Failed to parse the JSON at {}", url))?;
+            Ok((data, Content::Json))
+        }
+    }
+}
+
+/// parses CSV data into its header row and a selection for each of its rows
+/// where the row's values are keyed by the header
+fn csv_rows(data: &str) -> Result<(Vec<String>, Vec<Selection>)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(data.as_bytes());
+    let headers: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+
+    let rows = reader
+        .records()
+        .map(|record| {
+            let mut row = Selection::new();
+            row.other = headers
+                .iter()
+                .zip(record?.iter())
+                .map(|(k, v)| (k.clone(), json!(v)))
+                .collect();
+
+            Ok(row)
+        })
+        .collect::<Result<Vec<Selection>>>()?;
+
+    Ok((headers, rows))
+}
+
+/// Extracts the text and metadata of a PDF.
+#[cfg(feature = "pdf")]
+pub fn extract_pdf(bytes: &[u8]) -> Result<(String, Content)> {
+    use pdf_extract::{Document, Object};
+
+    let text = pdf_extract::extract_text_from_mem(bytes).map_err(|e| eyre!("{}", e))?;
+    let doc = Document::load_mem(bytes)?;
+
+    let info = doc
+        .trailer
+        .get(b"Info")
+        .and_then(|info| match info {
+            Object::Reference(id) => doc.get_dictionary(*id),
+            _ => info.as_dict(),
+        })
+        .ok();
+    let field = |key: &[u8]| -> Option<String> {
+        let value = info?.get(key).ok()?.as_str().ok()?;
+        let value = String::from_utf8_lossy(value).trim().to_string();
+        (!value.is_empty()).then_some(value)
+    };
+
+    let info = PdfInfo {
+        pages: doc.get_pages().len(),
+        title: field(b"Title"),
+        author: field(b"Author"),
+        subject: field(b"Subject"),
+        creator: field(b"Creator"),
+        producer: field(b"Producer"),
+    };
+
+    Ok((text, Content::Pdf(info)))
+}
+
+/// Extracting the text of a PDF requires the `pdf` feature.
+#[cfg(not(feature = "pdf"))]
+pub fn extract_pdf(_bytes: &[u8]) -> Result<(String, Content)> {
+    Err(color_eyre::eyre::eyre!(
+        "extracting text from a PDF requires the `pdf` feature"
+    ))
+}
+
+/// Parses a document's text; HTML is parsed into a DOM tree while other content
+/// is converted to the results which stand in for CSS selections.
+pub(crate) fn parse(content: &Content, data: &str) -> (Html, HashMap<String, ResultKind>) {
+    let mut results = HashMap::new();
+
+    match content {
+        Content::Html => return (Html::parse_document(data), results),
+        Content::Pdf(info) => {
+            results.insert("text".to_string(), ResultKind::Property(json!(data)));
+            results.insert("pages".to_string(), ResultKind::Property(json!(info.pages)));
+        }
+        Content::Text => {
+            let lines = data
+                .lines()
+                .map(|line| {
+                    let mut selection = Selection::new();
+                    selection.text = Some(line.to_string());
+                    selection
+                })
+                .collect();
+            results.insert("text".to_string(), ResultKind::Property(json!(data)));
+            results.insert("lines".to_string(), ResultKind::List(lines));
+        }
+        Content::Csv => {
+            // the data was validated when it was read
+            let (columns, rows) = csv_rows(data).unwrap_or_default();
+            results.insert("columns".to_string(), ResultKind::Property(json!(columns)));
+            results.insert("rows".to_string(), ResultKind::List(rows));
+        }
+        Content::Json => {
+            let value: Value = serde_json::from_str(data).unwrap_or_default();
+            results.insert("json".to_string(), ResultKind::Property(value));
+        }
+    }
+
+    (Html::new_document(), results)
+}
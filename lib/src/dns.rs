@@ -0,0 +1,165 @@
+//! Resolves hostnames with DNS-over-HTTPS (DoH) for environments where the
+//! system's DNS is unreliable or filtered. Resolution with DoH requires the
+//! `doh` feature; without it the system resolver is always used.
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::IpAddr;
+
+/// Cloudflare's DoH endpoint which answers JSON queries
+pub const CLOUDFLARE: &str = "https://cloudflare-dns.com/dns-query";
+/// Google's DoH endpoint which answers JSON queries
+pub const GOOGLE: &str = "https://dns.google/resolve";
+
+/// How the hostnames of the pages in a crawl are resolved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct DnsConfig {
+    /// a DoH endpoint which answers JSON queries (`application/dns-json`),
+    /// e.g. `https://cloudflare-dns.com/dns-query`; the system resolver is
+    /// used when not set
+    pub doh: Option<String>,
+    /// resolve with the system resolver when the DoH endpoint fails or has no
+    /// answer for a hostname
+    pub fallback: bool,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        DnsConfig {
+            doh: None,
+            fallback: true,
+        }
+    }
+}
+
+impl DnsConfig {
+    /// resolves hostnames with the given DoH endpoint
+    pub fn doh(mut self, endpoint: &str) -> Self {
+        self.doh = Some(endpoint.to_string());
+
+        self
+    }
+
+    /// sets whether the system resolver is used when the DoH endpoint fails
+    pub fn fallback(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+
+        self
+    }
+}
+
+/// The addresses in a JSON DoH response for the given record type (`1` for
+/// `A` and `28` for `AAAA`); records of other types (e.g., the `CNAME`s which
+/// lead to the addresses) are skipped.
+pub fn parse_answers(body: &str, record_type: u64) -> Result<Vec<IpAddr>> {
+    let response: Value = serde_json::from_str(body)?;
+    match response["Status"].as_u64() {
+        // NOERROR and NXDOMAIN are answers; anything else is a failure
+        Some(0) | Some(3) => {}
+        status => return Err(eyre!("the DoH query failed with the status {:?}", status)),
+    }
+
+    Ok(response["Answer"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|a| a["type"].as_u64() == Some(record_type))
+        .filter_map(|a| a["data"].as_str()?.parse().ok())
+        .collect())
+}
+
+#[cfg(feature = "doh")]
+pub use resolver::DohResolver;
+
+#[cfg(feature = "doh")]
+mod resolver {
+    use super::{parse_answers, DnsConfig};
+    use color_eyre::{eyre::eyre, Result};
+    use hyper::client::connect::dns::Name;
+    use reqwest::dns::{Addrs, Resolve, Resolving};
+    use std::net::{IpAddr, SocketAddr};
+    use tracing::{debug, warn};
+    use url::Url;
+
+    /// the record types which are queried: `A` and `AAAA`
+    const RECORD_TYPES: [(&str, u64); 2] = [("A", 1), ("AAAA", 28)];
+
+    /// A `reqwest` resolver which queries a DoH endpoint and -- when configured
+    /// to -- falls back to the system resolver.
+    #[derive(Debug, Clone)]
+    pub struct DohResolver {
+        endpoint: Url,
+        fallback: bool,
+        /// the endpoint itself is requested with the system resolver
+        client: reqwest::Client,
+    }
+
+    impl DohResolver {
+        pub fn new(config: &DnsConfig) -> Result<DohResolver> {
+            let endpoint = config
+                .doh
+                .as_deref()
+                .ok_or_else(|| eyre!("no DoH endpoint is configured"))?;
+
+            Ok(DohResolver {
+                endpoint: Url::parse(endpoint)?,
+                fallback: config.fallback,
+                client: reqwest::Client::new(),
+            })
+        }
+
+        /// the `A` and `AAAA` addresses of a hostname
+        pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>> {
+            let mut addrs = vec![];
+            for (name, record_type) in RECORD_TYPES {
+                let mut url = self.endpoint.clone();
+                url.query_pairs_mut()
+                    .append_pair("name", host)
+                    .append_pair("type", name);
+                let body = self
+                    .client
+                    .get(url)
+                    .header("accept", "application/dns-json")
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?;
+                addrs.extend(parse_answers(&body, record_type)?);
+            }
+            debug!("resolved {} to {:?} with DoH", host, addrs);
+
+            Ok(addrs)
+        }
+    }
+
+    impl Resolve for DohResolver {
+        fn resolve(&self, name: Name) -> Resolving {
+            let resolver = self.clone();
+            Box::pin(async move {
+                let host = name.as_str().to_string();
+                let error = match resolver.lookup(&host).await {
+                    Ok(addrs) if !addrs.is_empty() => {
+                        let addrs = addrs.into_iter().map(|ip| SocketAddr::new(ip, 0));
+                        return Ok(Box::new(addrs.collect::<Vec<_>>().into_iter()) as Addrs);
+                    }
+                    Ok(_) => eyre!("the DoH endpoint has no addresses for {}", host),
+                    Err(e) => e,
+                };
+                if !resolver.fallback {
+                    return Err(error.into());
+                }
+
+                warn!(
+                    "falling back to the system resolver for {}: {:#}",
+                    host, error
+                );
+                let addrs = tokio::net::lookup_host((host.as_str(), 0)).await?;
+                Ok(Box::new(addrs.collect::<Vec<_>>().into_iter()) as Addrs)
+            })
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Explains which of a page's child URLs a crawl would request -- and why
+//! the others wouldn't be -- without requesting any of them, so that the
+//! boundaries of a crawl can be checked before spending the bandwidth.
+
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use url::Url;
+
+/// Whether a candidate child URL would be requested and, when it wouldn't,
+/// the first check which excluded it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// the URL would be requested
+    Included,
+    /// the `href` isn't within the selector's `ChildScope` (or can't be
+    /// resolved to a URL)
+    OutOfScope,
+    /// the URL doesn't satisfy the selector's `ChildRules`
+    Rules,
+    /// the `CrawlSafety` refuses the URL (e.g., it's outside of the allowed domains)
+    Unsafe,
+    /// the site's robots.txt disallows the URL
+    Robots,
+    /// the URL is the same page as an earlier candidate or as the page itself;
+    /// see `url_norm::canonicalize()`
+    Duplicate,
+    /// the URL was left out of the `Sampling`
+    Sampled,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Verdict::Included => "included",
+            Verdict::OutOfScope => "outside of the selector's scope",
+            Verdict::Rules => "excluded by the child rules",
+            Verdict::Unsafe => "refused by the crawl safety rules",
+            Verdict::Robots => "disallowed by robots.txt",
+            Verdict::Duplicate => "a duplicate of another page",
+            Verdict::Sampled => "left out of the sample",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+/// A link found on a page which a crawl might follow.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    /// the `href` as it appears in the page
+    pub href: String,
+    /// the URL the `href` resolves to, if it's within the selector's scope
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::util::optional_url_to_string"
+    )]
+    pub url: Option<Url>,
+    /// the child selector which found the link; none for an hreflang alternate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    /// whether the URL would be requested
+    pub verdict: Verdict,
+}
+
+impl Candidate {
+    /// whether the URL would be requested
+    pub fn is_included(&self) -> bool {
+        self.verdict == Verdict::Included
+    }
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let url = match &self.url {
+            Some(url) => url.as_str(),
+            None => self.href.as_str(),
+        };
+        let source = self.selector.as_deref().unwrap_or("hreflang");
+        match self.verdict {
+            Verdict::Included => write!(f, "+ {} [{}]", url, source),
+            verdict => write!(f, "- {} [{}]: {}", url, source, verdict),
+        }
+    }
+}
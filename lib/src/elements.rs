@@ -1,3 +1,4 @@
+use crate::selection::TemplateKind;
 use scraper::ElementRef;
 
 pub fn id(el: &ElementRef) -> Option<String> {
@@ -5,7 +6,7 @@ pub fn id(el: &ElementRef) -> Option<String> {
 }
 
 pub fn class(el: &ElementRef) -> Option<String> {
-    let class: String = el.value().classes().into_iter().collect();
+    let class: String = el.value().classes().collect();
 
     if class.is_empty() {
         None
@@ -82,3 +83,18 @@ pub fn disabled(el: &ElementRef) -> Option<bool> {
 }
 
 // pub fn other(el: &ElementRef)-> Option<String> {}
+
+/// Returns the kind of `<template>` the element is nested within; inert templates
+/// take precedence over any shadow roots since their content is never rendered.
+pub fn template(el: &ElementRef) -> Option<TemplateKind> {
+    el.ancestors()
+        .filter_map(|n| n.value().as_element())
+        .filter(|e| e.name() == "template")
+        .map(
+            |e| match e.attr("shadowrootmode").or_else(|| e.attr("shadowroot")) {
+                Some(_) => TemplateKind::ShadowRoot,
+                None => TemplateKind::Template,
+            },
+        )
+        .max_by_key(|kind| *kind == TemplateKind::Template)
+}
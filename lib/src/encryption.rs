@@ -0,0 +1,133 @@
+//! Encrypts results at rest with AES-256-GCM so that crawls of sensitive data
+//! can be written to disk -- as JSON Lines or to a SQLite store -- without
+//! reaching for external tooling. Each record is sealed separately with a
+//! random nonce and written as a line of base64. Requires the `encryption`
+//! feature.
+
+use crate::redact::Redacted;
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use rand::RngCore;
+use std::path::Path;
+
+/// the environment variable the key is read from, as 64 hex digits
+pub const KEY_ENV: &str = "SCRAPED_ENCRYPTION_KEY";
+
+/// the length of a key in bytes
+pub const KEY_LEN: usize = 32;
+
+/// the length of the nonce which prefixes each record
+const NONCE_LEN: usize = 12;
+
+/// A 256-bit AES-GCM key; it's redacted when formatted.
+#[derive(Clone, Debug)]
+pub struct EncryptionKey(Redacted<[u8; KEY_LEN]>);
+
+impl EncryptionKey {
+    pub fn new(key: [u8; KEY_LEN]) -> Self {
+        EncryptionKey(Redacted::new(key))
+    }
+
+    /// a random key
+    pub fn generate() -> Self {
+        let mut key = [0; KEY_LEN];
+        rand::thread_rng().fill_bytes(&mut key);
+
+        EncryptionKey::new(key)
+    }
+
+    /// reads a key written as 64 hex digits
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let hex = hex.trim();
+        if hex.len() != KEY_LEN * 2 || !hex.is_ascii() {
+            return Err(eyre!(
+                "an encryption key must be {} hex digits",
+                KEY_LEN * 2
+            ));
+        }
+        let mut key = [0; KEY_LEN];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .context("an encryption key must be written in hex")?;
+        }
+
+        Ok(EncryptionKey::new(key))
+    }
+
+    /// the key of the `SCRAPED_ENCRYPTION_KEY` environment variable, if it's set
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(KEY_ENV) {
+            Ok(hex) => Ok(Some(
+                EncryptionKey::from_hex(&hex).context(format!("{} is invalid", KEY_ENV))?,
+            )),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// reads the key -- written as hex -- from a keyfile
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let hex = std::fs::read_to_string(path)
+            .context(format!("Failed to read the keyfile: {}", path.display()))?;
+
+        EncryptionKey::from_hex(&hex).context(format!("The keyfile {} is invalid", path.display()))
+    }
+
+    /// the key written as hex, e.g. to save it to a keyfile; take care not to log it
+    pub fn to_hex(&self) -> String {
+        self.0
+            .expose()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(self.0.expose()))
+    }
+
+    /// seals a record, returning its nonce and ciphertext as base64
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<String> {
+        let mut nonce = [0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| eyre!("Failed to encrypt a record"))?;
+
+        Ok(STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+    }
+
+    /// opens a record sealed by `encrypt()`; a record which was sealed with
+    /// another key or tampered with is an error
+    pub fn decrypt(&self, record: &str) -> Result<Vec<u8>> {
+        let sealed = STANDARD
+            .decode(record.trim())
+            .context("An encrypted record isn't base64")?;
+        if sealed.len() < NONCE_LEN {
+            return Err(eyre!("An encrypted record is truncated"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| eyre!("Failed to decrypt a record; was it encrypted with another key?"))
+    }
+
+    /// opens each line of a file written with encryption, returning the
+    /// records one per line
+    pub fn decrypt_lines(&self, content: &str) -> Result<String> {
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(String::from_utf8(self.decrypt(line)?)? + "\n"))
+            .collect()
+    }
+}
@@ -0,0 +1,277 @@
+//! The errors which are specific to scraping. Internally fallible functions
+//! return a `color_eyre::Report` so that context can be added along the way,
+//! but the public entry points of `Document`, `LoadedDocument`, `ParsedDoc`
+//! and `ConcurrentScrape` return a `ScrapedError` so that consumers can match
+//! on the failure.
+
+use crate::{
+    challenge::{BlockedPage, BotChallenge},
+    redact::{self, REDACTED},
+};
+use color_eyre::Report;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    net::IpAddr,
+    time::Duration,
+};
+use url::Url;
+
+/// the maximum number of bytes of an error response's body which are captured
+pub const MAX_ERROR_BODY: usize = 2048;
+
+/// A request which was answered with a non-2xx status code.
+#[derive(Debug, Clone)]
+pub struct HttpError {
+    pub url: Url,
+    pub status: u16,
+    /// the response headers; only captured when configured to do so and the
+    /// values of sensitive headers (e.g., `Set-Cookie`) are redacted
+    pub headers: Option<BTreeMap<String, String>>,
+    /// the (truncated) response body; only captured when configured to do so
+    pub body: Option<String>,
+    /// the pause requested by the response's `Retry-After` header
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpError {
+    /// builds the error from a response, optionally capturing the response's
+    /// headers and the first `MAX_ERROR_BODY` bytes of its body
+    pub fn new(
+        url: &Url,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: &str,
+        capture: bool,
+    ) -> HttpError {
+        if !capture {
+            return HttpError {
+                url: url.clone(),
+                status,
+                headers: None,
+                body: None,
+                retry_after: None,
+            };
+        }
+
+        let mut end = body.len().min(MAX_ERROR_BODY);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        HttpError {
+            url: url.clone(),
+            status,
+            headers: Some(
+                headers
+                    .iter()
+                    .map(|(k, v)| match redact::is_sensitive(k) {
+                        true => (k.to_string(), REDACTED.to_string()),
+                        false => (k.to_string(), v.to_string()),
+                    })
+                    .collect(),
+            ),
+            body: Some(body[..end].to_string()),
+            retry_after: None,
+        }
+    }
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} responded with a {} status", self.url, self.status)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// The errors which are specific to scraping a page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrapedError {
+    /// a CSS selector which couldn't be parsed
+    InvalidSelector { selector: String, reason: String },
+    /// a selector was added under the name of one which had already been
+    /// added -- by the given source -- while the `DuplicatePolicy` is `Error`
+    DuplicateSelector { name: String, first: String },
+    /// the request for the page was answered with a non-2xx status
+    Http { url: Url, status: u16 },
+    /// the request for the page was answered with a 429 status
+    RateLimited {
+        url: Url,
+        /// the pause requested by the `Retry-After` header
+        retry_after: Option<Duration>,
+    },
+    /// the input -- a URL or the content of a document -- couldn't be parsed
+    ParseFailure { input: String, reason: String },
+    /// the request for the page didn't complete within the configured timeout
+    Timeout { url: Url, after: Duration },
+    /// the site's robots.txt disallows requesting the page
+    Disallowed { url: Url },
+    /// the page's domain isn't on the `CrawlSafety` allow-list
+    OutsideAllowList { url: Url },
+    /// the page's host is -- or resolves to -- a private or otherwise
+    /// non-public address while the `CrawlSafety` blocks private networks
+    PrivateAddress { url: Url, ip: IpAddr },
+    /// the page's scheme isn't HTTP while the `CrawlSafety` blocks private networks
+    UnsupportedScheme { url: Url },
+    /// the request for the page was redirected more often than the
+    /// `RedirectPolicy` allows or was redirected in a loop
+    TooManyRedirects { url: Url, hops: usize },
+    /// the response's body was larger than the `ResponseLimits` allow
+    TooLarge { url: Url, limit: u64 },
+    /// the response's content type isn't one the `ResponseLimits` accept
+    UnacceptedContentType { url: Url, content_type: String },
+    /// an anti-bot interstitial was served in place of the page
+    Blocked {
+        url: Url,
+        challenge: BotChallenge,
+        status: u16,
+    },
+    /// a failure which isn't specific to scraping (e.g., I/O or a connection
+    /// which couldn't be made) along with its context
+    Other { reason: String },
+}
+
+impl ScrapedError {
+    /// a timeout of the request for `url` as a `Report`
+    pub fn timeout(url: &Url, after: Duration) -> Report {
+        ScrapedError::Timeout {
+            url: url.clone(),
+            after,
+        }
+        .into()
+    }
+
+    /// A failure which can't be parsed as a `Report`; the `input` is shortened
+    /// when it's long (e.g., the content of a document).
+    pub fn parse_failure(input: &str, reason: impl Display) -> Report {
+        let input = match input.char_indices().nth(80) {
+            Some((end, _)) => format!("{}...", &input[..end]),
+            None => input.to_string(),
+        };
+
+        ScrapedError::ParseFailure {
+            input,
+            reason: reason.to_string(),
+        }
+        .into()
+    }
+}
+
+/// Recovers the typed error from a `Report` raised while scraping. An
+/// `HttpError` is classified as `Http` -- or `RateLimited` for a 429 status --
+/// and a `BlockedPage` as `Blocked`; errors which aren't specific to scraping
+/// are `Other`.
+impl From<Report> for ScrapedError {
+    fn from(report: Report) -> Self {
+        let classified = report.chain().find_map(|e| {
+            if let Some(e) = e.downcast_ref::<ScrapedError>() {
+                return Some(e.clone());
+            }
+            if let Some(blocked) = e.downcast_ref::<BlockedPage>() {
+                return Some(ScrapedError::Blocked {
+                    url: blocked.url.clone(),
+                    challenge: blocked.challenge,
+                    status: blocked.status,
+                });
+            }
+            let http = e.downcast_ref::<HttpError>()?;
+            Some(match http.status {
+                429 => ScrapedError::RateLimited {
+                    url: http.url.clone(),
+                    retry_after: http.retry_after,
+                },
+                status => ScrapedError::Http {
+                    url: http.url.clone(),
+                    status,
+                },
+            })
+        });
+
+        classified.unwrap_or_else(|| ScrapedError::Other {
+            reason: format!("{:#}", report),
+        })
+    }
+}
+
+impl Display for ScrapedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrapedError::InvalidSelector { selector, reason } => {
+                write!(f, "'{}' is not a valid CSS selector: {}", selector, reason)
+            }
+            ScrapedError::DuplicateSelector { name, first } => {
+                write!(
+                    f,
+                    "a selector named '{}' has already been added by {}",
+                    name, first
+                )
+            }
+            ScrapedError::Http { url, status } => {
+                write!(f, "{} responded with a {} status", url, status)
+            }
+            ScrapedError::RateLimited { url, retry_after } => match retry_after {
+                Some(after) => write!(f, "{} is rate limited for {:?}", url, after),
+                None => write!(f, "{} is rate limited", url),
+            },
+            ScrapedError::ParseFailure { input, reason } => {
+                write!(f, "failed to parse '{}': {}", input, reason)
+            }
+            ScrapedError::Timeout { url, after } => {
+                write!(f, "the request for {} timed out after {:?}", url, after)
+            }
+            ScrapedError::Disallowed { url } => {
+                write!(f, "{} is disallowed by the site's robots.txt", url)
+            }
+            ScrapedError::OutsideAllowList { url } => {
+                write!(
+                    f,
+                    "{} is outside of the domains the crawl is allowed to request",
+                    url
+                )
+            }
+            ScrapedError::PrivateAddress { url, ip } => {
+                write!(
+                    f,
+                    "{} was refused as it resolves to the private address {}",
+                    url, ip
+                )
+            }
+            ScrapedError::UnsupportedScheme { url } => {
+                write!(
+                    f,
+                    "{} was refused as only http and https can be requested",
+                    url
+                )
+            }
+            ScrapedError::TooManyRedirects { url, hops } => {
+                write!(f, "{} was redirected {} times", url, hops)
+            }
+            ScrapedError::TooLarge { url, limit } => {
+                write!(f, "the body of {} is larger than {} bytes", url, limit)
+            }
+            ScrapedError::UnacceptedContentType { url, content_type } => {
+                match content_type.is_empty() {
+                    true => write!(f, "{} has no content type", url),
+                    false => write!(
+                        f,
+                        "{} has the unaccepted content type {}",
+                        url, content_type
+                    ),
+                }
+            }
+            ScrapedError::Blocked {
+                url,
+                challenge,
+                status,
+            } => write!(
+                f,
+                "{} was blocked by a {} challenge ({} status)",
+                url, challenge, status
+            ),
+            ScrapedError::Other { reason } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ScrapedError {}
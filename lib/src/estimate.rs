@@ -0,0 +1,119 @@
+//! Estimates how long a crawl will take -- and how many requests each host
+//! will be sent -- from its frontier along with the configured rate limits and
+//! concurrency, so that a polite crawl of a rate-limited site can be planned
+//! before it's run. Retries and redirects aren't accounted for.
+
+use crate::{
+    rate::{self, RateLimit},
+    ScrapeConfig,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, time::Duration};
+use url::Url;
+
+/// the time a request is assumed to take when no latency is given
+pub const DEFAULT_LATENCY: Duration = Duration::from_millis(500);
+
+/// The requests a crawl will send to a host and how long they'll take. When
+/// serialized, durations are human readable strings such as "1m 30s".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostEstimate {
+    /// the number of requests sent to the host
+    pub requests: usize,
+    /// the requests the host is sent per second; unlimited when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_second: Option<f64>,
+    /// the time taken to request every page of the host
+    #[serde(with = "humantime_serde")]
+    pub duration: Duration,
+}
+
+/// The estimated requests and duration of a crawl.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Estimate {
+    /// the number of requests sent
+    pub requests: usize,
+    /// the time the crawl is expected to take
+    #[serde(with = "humantime_serde")]
+    pub duration: Duration,
+    /// the estimate of each host, keyed by its name
+    pub hosts: BTreeMap<String, HostEstimate>,
+}
+
+impl Estimate {
+    /// Estimates a crawl of the URLs with the configuration where each request
+    /// takes `latency`. Each host is requested no faster than its rate limit
+    /// (or the `Crawl-delay` of its robots.txt, once it's been requested)
+    /// allows, while the crawl as a whole can't request more pages at once
+    /// than the concurrency.
+    pub fn new<'a>(
+        urls: impl IntoIterator<Item = &'a Url>,
+        config: &ScrapeConfig,
+        latency: Duration,
+    ) -> Estimate {
+        let mut hosts: BTreeMap<String, (usize, RateLimit)> = BTreeMap::new();
+        for url in urls {
+            let host = match url.host_str() {
+                Some(host) => host.to_lowercase(),
+                None => continue,
+            };
+            hosts
+                .entry(host)
+                .or_insert_with(|| (0, rate::limit(url, config)))
+                .0 += 1;
+        }
+
+        let concurrency = config.concurrency.max(1);
+        let hosts: BTreeMap<String, HostEstimate> = hosts
+            .into_iter()
+            .map(|(host, (requests, limit))| {
+                let estimate = HostEstimate {
+                    requests,
+                    per_second: limit.per_second.filter(|_| limit.is_limited()),
+                    duration: host_duration(requests, limit, concurrency, latency),
+                };
+                (host, estimate)
+            })
+            .collect();
+        let requests: usize = hosts.values().map(|h| h.requests).sum();
+        // however politely each host is requested, no more than the
+        // concurrency are in flight at once
+        let rounds = requests.div_ceil(concurrency);
+        let duration = hosts
+            .values()
+            .map(|h| h.duration)
+            .max()
+            .unwrap_or_default()
+            .max(latency * rounds as u32);
+
+        Estimate {
+            requests,
+            duration,
+            hosts,
+        }
+    }
+}
+
+/// the time taken to send a host its requests: those beyond the burst wait
+/// for the rate limit (along with half of the jitter, on average) while the
+/// last takes the latency to complete
+fn host_duration(
+    requests: usize,
+    limit: RateLimit,
+    concurrency: usize,
+    latency: Duration,
+) -> Duration {
+    if requests == 0 {
+        return Duration::ZERO;
+    }
+    let unlimited = latency * requests.div_ceil(concurrency) as u32;
+    let rate = match limit.per_second {
+        Some(rate) if limit.is_limited() => rate,
+        _ => return unlimited,
+    };
+
+    let waiting = requests.saturating_sub(limit.burst.max(1) as usize) as f64 / rate;
+    let limited = Duration::from_secs_f64(waiting) + limit.jitter / 2 + latency;
+
+    limited.max(unlimited)
+}
@@ -0,0 +1,126 @@
+use color_eyre::{eyre::eyre, Report, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use std::{fmt, str::FromStr};
+
+lazy_static! {
+    static ref STEP: Regex =
+        Regex::new(r"^(?:(\.)?([A-Za-z_][\w-]*)|\[\s*(\*|-?\d+)\s*\])").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// the value of an object's key
+    Field(String),
+    /// an element of an array; negative indexes count from the end
+    Index(i64),
+    /// projects the rest of the path onto every element of an array
+    Wildcard,
+}
+
+/// A JMESPath-style expression which derives a property from the selections of
+/// a page, e.g. `title.text`, `links[*].full_href`, `items[-1].text` or
+/// `price.text || amount.text` (the first alternative which isn't empty).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    alternatives: Vec<Vec<Step>>,
+}
+
+impl Expression {
+    /// evaluates the expression against a JSON value; paths which don't
+    /// exist evaluate to `null`
+    pub fn evaluate(&self, value: &Value) -> Value {
+        self.alternatives
+            .iter()
+            .map(|steps| select(value, steps))
+            .find(is_truthy)
+            .unwrap_or(Value::Null)
+    }
+}
+
+impl FromStr for Expression {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let alternatives = s
+            .split("||")
+            .map(parse_path)
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| eyre!("invalid expression '{}': {}", s, e))?;
+
+        Ok(Expression {
+            source: s.to_string(),
+            alternatives,
+        })
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<Step>> {
+    let mut rest = path.trim();
+    let mut steps = vec![];
+    if rest.is_empty() {
+        return Err(eyre!("an empty path"));
+    }
+
+    while !rest.is_empty() {
+        let caps = STEP
+            .captures(rest)
+            .ok_or_else(|| eyre!("unexpected '{}'", rest))?;
+        let step = match (caps.get(1).is_some(), caps.get(2), caps.get(3)) {
+            (dot, Some(field), _) if dot != steps.is_empty() => {
+                Step::Field(field.as_str().to_string())
+            }
+            (_, Some(field), _) => return Err(eyre!("unexpected '{}'", field.as_str())),
+            (_, None, Some(i)) if i.as_str() == "*" => Step::Wildcard,
+            (_, None, Some(i)) => Step::Index(i.as_str().parse()?),
+            _ => unreachable!("the regex matches a field or an index"),
+        };
+        steps.push(step);
+        rest = &rest[caps[0].len()..];
+    }
+
+    Ok(steps)
+}
+
+fn select(value: &Value, steps: &[Step]) -> Value {
+    match steps.split_first() {
+        None => value.clone(),
+        Some((Step::Field(f), rest)) => value.get(f).map_or(Value::Null, |v| select(v, rest)),
+        Some((Step::Index(i), rest)) => value
+            .as_array()
+            .and_then(|a| {
+                let i = if *i < 0 { a.len() as i64 + i } else { *i };
+                usize::try_from(i).ok().and_then(|i| a.get(i))
+            })
+            .map_or(Value::Null, |v| select(v, rest)),
+        Some((Step::Wildcard, rest)) => match value.as_array() {
+            Some(a) => Value::Array(
+                a.iter()
+                    .map(|v| select(v, rest))
+                    .filter(|v| !v.is_null())
+                    .collect(),
+            ),
+            None => Value::Null,
+        },
+    }
+}
+
+/// JMESPath's notion of truthiness which decides between alternatives
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+        Value::Number(_) => true,
+    }
+}
@@ -0,0 +1,222 @@
+use color_eyre::{eyre::eyre, Report};
+use lazy_static::lazy_static;
+use regex::Regex;
+use scraper::{Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, str::FromStr};
+use url::Url;
+
+use crate::url_parts::UrlParts;
+
+/// Built-in extractors which scan a page's text, links and URL for common
+/// entities; each emits a property of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Extractor {
+    /// email addresses, including obfuscated ones such as "name [at] domain [dot] com"
+    Emails,
+    /// phone numbers from `tel:` links and international or long-form numbers in the text
+    Phones,
+    /// links to social profiles, grouped by network
+    Social,
+    /// the parts of the page's URL: its path segments, slug, query parameters
+    /// and canonical form
+    Url,
+}
+
+impl Extractor {
+    /// the name of the property the extractor emits
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extractor::Emails => "emails",
+            Extractor::Phones => "phones",
+            Extractor::Social => "social",
+            Extractor::Url => "url",
+        }
+    }
+
+    /// runs the extractor against a page
+    pub fn extract(&self, html: &Html, url: &Url) -> Value {
+        match self {
+            Extractor::Url => json!(UrlParts::new(url)),
+            Extractor::Emails => json!(emails(&page_text(html), &hrefs(html))),
+            Extractor::Phones => json!(phones(&page_text(html), &hrefs(html))),
+            Extractor::Social => json!(social(&hrefs(html))),
+        }
+    }
+}
+
+impl FromStr for Extractor {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "emails" | "email" => Ok(Extractor::Emails),
+            "phones" | "phone" => Ok(Extractor::Phones),
+            "social" => Ok(Extractor::Social),
+            "url" => Ok(Extractor::Url),
+            _ => Err(eyre!(
+                "'{}' is not an extractor; use emails, phones, social or url",
+                s
+            )),
+        }
+    }
+}
+
+lazy_static! {
+    static ref HREF: Selector = Selector::parse("[href]").unwrap();
+    pub(crate) static ref EMAIL: Regex =
+        Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b").unwrap();
+    static ref OBFUSCATED_AT: Regex = Regex::new(r"(?i)\s*[\[\(\{<]\s*at\s*[\]\)\}>]\s*").unwrap();
+    static ref OBFUSCATED_DOT: Regex =
+        Regex::new(r"(?i)\s*[\[\(\{<]\s*dot\s*[\]\)\}>]\s*").unwrap();
+    pub(crate) static ref PHONE: Regex =
+        Regex::new(r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{1,4}\)[\s.-]?)?\d{2,4}(?:[\s.-]\d{2,4}){1,4}")
+            .unwrap();
+}
+
+/// the hosts of the social networks recognized, and the name they're grouped by
+const SOCIAL_NETWORKS: [(&str, &str); 11] = [
+    ("twitter.com", "twitter"),
+    ("x.com", "twitter"),
+    ("facebook.com", "facebook"),
+    ("instagram.com", "instagram"),
+    ("linkedin.com", "linkedin"),
+    ("github.com", "github"),
+    ("youtube.com", "youtube"),
+    ("tiktok.com", "tiktok"),
+    ("pinterest.com", "pinterest"),
+    ("reddit.com", "reddit"),
+    ("mastodon.social", "mastodon"),
+];
+
+/// paths which belong to share buttons rather than profiles
+const SHARE_PATHS: [&str; 4] = ["/share", "/sharer", "/intent/", "/sharing/"];
+
+/// elements whose text isn't visible content
+const HIDDEN_ELEMENTS: [&str; 4] = ["script", "style", "noscript", "template"];
+
+/// the visible text of a page
+fn page_text(html: &Html) -> String {
+    html.tree
+        .nodes()
+        .filter_map(|node| match node.value() {
+            Node::Text(text) => {
+                let hidden = node
+                    .parent()
+                    .and_then(|p| p.value().as_element())
+                    .is_some_and(|el| HIDDEN_ELEMENTS.contains(&el.name()));
+                (!hidden).then(|| text.to_string())
+            }
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn hrefs(html: &Html) -> Vec<String> {
+    html.select(&HREF)
+        .filter_map(|el| el.value().attr("href"))
+        .map(|href| href.trim().to_string())
+        .collect()
+}
+
+/// Finds the email addresses in some text and `mailto:` links; obfuscated
+/// addresses such as "name [at] domain [dot] com" are restored.
+pub fn emails(text: &str, hrefs: &[String]) -> Vec<String> {
+    let text = OBFUSCATED_AT.replace_all(text, "@");
+    let text = OBFUSCATED_DOT.replace_all(&text, ".");
+    let linked = hrefs
+        .iter()
+        .filter_map(|h| h.strip_prefix("mailto:"))
+        .map(|h| h.split('?').next().unwrap_or_default().to_string());
+
+    dedupe(
+        EMAIL
+            .find_iter(&text)
+            .map(|m| m.as_str().to_string())
+            .chain(linked)
+            .map(|e| e.to_lowercase()),
+    )
+}
+
+/// Finds the phone numbers in some text and `tel:` links; numbers in the text
+/// must either be international (e.g., "+1 555 123 4567"), have an area code in
+/// parenthesis or be at least 10 digits long to avoid matching other numbers.
+pub fn phones(text: &str, hrefs: &[String]) -> Vec<String> {
+    let linked = hrefs
+        .iter()
+        .filter_map(|h| h.strip_prefix("tel:"))
+        .map(String::from);
+    let found = PHONE
+        .find_iter(text)
+        .map(|m| m.as_str().to_string())
+        .filter(|p| is_phone(p));
+
+    dedupe(
+        linked
+            .chain(found)
+            .map(|p| normalize_phone(&p))
+            .filter(|p| (7..=15).contains(&digits(p))),
+    )
+}
+
+/// whether a match of `PHONE` in some text is taken for a phone number: it
+/// must be international, have an area code in parenthesis or be at least 10
+/// digits long
+pub(crate) fn is_phone(candidate: &str) -> bool {
+    candidate.starts_with('+') || candidate.starts_with('(') || digits(candidate) >= 10
+}
+
+fn digits(text: &str) -> usize {
+    text.chars().filter(|c| c.is_ascii_digit()).count()
+}
+
+/// strips the formatting from a phone number, leaving only its digits and any
+/// leading `+`
+pub(crate) fn normalize_phone(phone: &str) -> String {
+    let phone = phone.trim();
+    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+
+    match phone.starts_with('+') {
+        true => format!("+{}", digits),
+        false => digits,
+    }
+}
+
+/// Finds the links to social profiles, grouped by network.
+pub fn social(hrefs: &[String]) -> BTreeMap<String, Vec<String>> {
+    let mut networks: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    hrefs
+        .iter()
+        .filter_map(|href| Url::parse(href).ok())
+        .filter(|url| url.path().trim_matches('/').len() > 1)
+        .filter(|url| !SHARE_PATHS.iter().any(|p| url.path().starts_with(p)))
+        .for_each(|url| {
+            let host = url.host_str().unwrap_or_default();
+            let host = host.strip_prefix("www.").unwrap_or(host);
+            if let Some((_, network)) = SOCIAL_NETWORKS.iter().find(|(h, _)| *h == host) {
+                let links = networks.entry(network.to_string()).or_default();
+                if !links.contains(&url.to_string()) {
+                    links.push(url.to_string());
+                }
+            }
+        });
+
+    networks
+}
+
+/// removes duplicates while preserving the order they were found in
+fn dedupe(values: impl Iterator<Item = String>) -> Vec<String> {
+    let mut unique: Vec<String> = vec![];
+    values.for_each(|v| {
+        if !unique.contains(&v) {
+            unique.push(v);
+        }
+    });
+
+    unique
+}
@@ -0,0 +1,390 @@
+use crate::{
+    dns::DnsConfig,
+    limits::ResponseLimits,
+    network::{IpPreference, NetworkConfig, PreferringResolver},
+    proxy::ProxyFetcher,
+    ScrapeConfig,
+};
+use async_trait::async_trait;
+pub use bytes::Bytes;
+use bytes::BytesMut;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use encoding_rs::{Encoding, UTF_8};
+use lazy_static::lazy_static;
+pub use reqwest::header::HeaderMap;
+use reqwest::{
+    dns::Resolve,
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    redirect::Policy,
+    ClientBuilder,
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+use url::Url;
+
+/// A page as it was received from a `Fetcher`.
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    /// the URL the page was ultimately served from (e.g., after redirects)
+    pub url: Url,
+    pub status: u16,
+    pub headers: HeaderMap,
+    /// the body as it was received; clones share the same buffer
+    pub body: Bytes,
+}
+
+impl FetchedPage {
+    /// the value of the `Content-Type` header
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok())
+    }
+
+    /// The body decoded as text using the charset of the `Content-Type` header,
+    /// falling back to UTF-8.
+    pub fn text(&self) -> String {
+        encoding(&self.headers).decode(&self.body).0.into_owned()
+    }
+
+    /// Decodes the body as `text()` does, taking the page's buffer rather than
+    /// copying it when the body is already UTF-8.
+    pub fn into_text(self) -> String {
+        decode(self.body, &self.headers)
+    }
+}
+
+/// the encoding given by the charset of the `Content-Type` header, falling
+/// back to UTF-8
+fn encoding(headers: &HeaderMap) -> &'static Encoding {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|c| {
+            c.split(';')
+                .filter_map(|p| p.trim().strip_prefix("charset="))
+                .next()
+        })
+        .and_then(|charset| Encoding::for_label(charset.trim_matches('"').as_bytes()))
+        .unwrap_or(UTF_8)
+}
+
+/// Decodes a body with the encoding of the headers; a UTF-8 body (without a
+/// byte order mark) becomes the `String` without being copied when nothing
+/// else shares its buffer.
+pub(crate) fn decode(body: Bytes, headers: &HeaderMap) -> String {
+    let encoding = encoding(headers);
+    if encoding == UTF_8 && !body.starts_with(b"\xEF\xBB\xBF") {
+        return match String::from_utf8(Vec::from(body)) {
+            Ok(text) => text,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        };
+    }
+
+    encoding.decode(&body).0.into_owned()
+}
+
+/// The transport used to request pages. The default uses `reqwest` but an
+/// implementation can be injected into a `Document` to mock requests in tests,
+/// serve pages from a cache or render them with a headless browser.
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    /// requests a page with the given request headers; responses of any status
+    /// are returned rather than treated as errors
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage>;
+
+    /// Requests a page as `fetch()` does within the limits: a response whose
+    /// content type isn't accepted -- or whose body is too large -- fails with
+    /// a `ScrapedError`. By default the page is checked once it's received;
+    /// a transport which streams the body should stop as soon as it can.
+    async fn fetch_within(
+        &self,
+        url: &Url,
+        headers: &HeaderMap,
+        limits: &ResponseLimits,
+    ) -> Result<FetchedPage> {
+        let page = self.fetch(url, headers).await?;
+        limits.check(&page)?;
+
+        Ok(page)
+    }
+
+    /// Makes a `HEAD` request for the URL (e.g., to check a link; see
+    /// `link_audit`). By default the page is fetched and its body dropped,
+    /// keeping its length in the `Content-Length` header.
+    async fn head(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        let mut page = self.fetch(url, headers).await?;
+        if !page.headers.contains_key(CONTENT_LENGTH) {
+            page.headers.insert(CONTENT_LENGTH, page.body.len().into());
+        }
+        page.body = Bytes::new();
+
+        Ok(page)
+    }
+
+    /// Establishes a connection to the URL's host -- resolving it and
+    /// completing the TLS handshake -- which is kept for the requests which
+    /// follow; see `warmup::warm_up()`. A transport which doesn't pool its
+    /// connections needn't do anything.
+    async fn warm(&self, _url: &Url, _headers: &HeaderMap) -> Result<()> {
+        Ok(())
+    }
+
+    /// The timeout the transport applies to each request itself (e.g., a
+    /// client built with the config's timeout); a request whose config has
+    /// another timeout -- or a transport which applies none -- is wrapped in
+    /// a timeout of its own.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// The default `Fetcher` which requests pages over HTTP with `reqwest`. Its
+/// clients don't follow redirects: they're followed -- as the `RedirectPolicy`
+/// allows -- by the requests made for a document so that each hop is recorded.
+#[derive(Debug, Clone)]
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+    /// the timeout the client was built with
+    timeout: Option<Duration>,
+}
+
+impl Default for ReqwestFetcher {
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .build()
+            .expect("the default client is built");
+
+        ReqwestFetcher::new(client)
+    }
+}
+
+impl ReqwestFetcher {
+    pub fn new(client: reqwest::Client) -> Self {
+        ReqwestFetcher {
+            client,
+            timeout: None,
+        }
+    }
+
+    /// A fetcher whose client is built for the config (e.g., to resolve with
+    /// DoH, make requests from a local address or time them out); `None` when
+    /// the default client suits the config. The config's proxies are left to
+    /// `ProxyFetcher`.
+    pub fn for_config(config: &ScrapeConfig) -> Result<Option<ReqwestFetcher>> {
+        if config.dns == DnsConfig::default()
+            && config.network == NetworkConfig::default()
+            && config.timeout.is_none()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(ReqwestFetcher::built(
+            config,
+            client_builder(config)?,
+        )?))
+    }
+
+    /// a fetcher built for the config whose requests are all routed through
+    /// the proxy (`http`, `https` or -- with the `socks` feature -- `socks5`)
+    pub fn proxied(config: &ScrapeConfig, proxy: &str) -> Result<ReqwestFetcher> {
+        if proxy.starts_with("socks") && !cfg!(feature = "socks") {
+            return Err(eyre!(
+                "the SOCKS proxy {} requires the `socks` feature",
+                proxy
+            ));
+        }
+        let proxy = reqwest::Proxy::all(proxy)
+            .wrap_err_with(|| format!("{} is not a valid proxy", proxy))?;
+
+        ReqwestFetcher::built(config, client_builder(config)?.proxy(proxy))
+    }
+
+    /// a fetcher whose client is built with the config's timeout
+    fn built(config: &ScrapeConfig, builder: ClientBuilder) -> Result<ReqwestFetcher> {
+        Ok(ReqwestFetcher {
+            client: builder.build()?,
+            timeout: config.timeout,
+        })
+    }
+}
+
+/// a client builder which resolves hostnames, connects and times out requests
+/// as configured; resolving with DoH without the `doh` feature is an error
+/// rather than falling back to the system resolver
+fn client_builder(config: &ScrapeConfig) -> Result<ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .local_address(config.network.local_address);
+    if let Some(after) = config.timeout {
+        builder = builder.connect_timeout(after).timeout(after);
+    }
+
+    #[allow(unused_mut)]
+    let mut resolver: Option<Arc<dyn Resolve>> = None;
+    #[cfg(feature = "doh")]
+    if config.dns.doh.is_some() {
+        resolver = Some(Arc::new(crate::dns::DohResolver::new(&config.dns)?));
+    }
+    #[cfg(not(feature = "doh"))]
+    if config.dns.doh.is_some() {
+        return Err(eyre!("resolving with DoH requires the `doh` feature"));
+    }
+    if resolver.is_some() || config.network.prefer != IpPreference::System {
+        builder = builder.dns_resolver(Arc::new(PreferringResolver {
+            resolver,
+            prefer: config.network.prefer,
+        }));
+    }
+
+    Ok(builder)
+}
+
+#[async_trait]
+impl Fetcher for ReqwestFetcher {
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        let mut res = self
+            .client
+            .get(url.as_str())
+            .headers(headers.clone())
+            .send()
+            .await?;
+
+        Ok(FetchedPage {
+            url: res.url().clone(),
+            status: res.status().as_u16(),
+            headers: std::mem::take(res.headers_mut()),
+            body: res.bytes().await?,
+        })
+    }
+
+    /// Streams the body, abandoning the response -- and closing its
+    /// connection -- as soon as its headers or the bytes read so far are
+    /// beyond the limits.
+    async fn fetch_within(
+        &self,
+        url: &Url,
+        headers: &HeaderMap,
+        limits: &ResponseLimits,
+    ) -> Result<FetchedPage> {
+        if limits.is_unbounded() {
+            return self.fetch(url, headers).await;
+        }
+        let mut res = self
+            .client
+            .get(url.as_str())
+            .headers(headers.clone())
+            .send()
+            .await?;
+        let status = res.status().as_u16();
+        limits.check_headers(res.url(), status, res.headers())?;
+
+        let mut body = BytesMut::new();
+        while let Some(chunk) = res.chunk().await? {
+            limits.check_length(res.url(), (body.len() + chunk.len()) as u64)?;
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(FetchedPage {
+            url: res.url().clone(),
+            status,
+            headers: std::mem::take(res.headers_mut()),
+            body: body.freeze(),
+        })
+    }
+
+    async fn head(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        let mut res = self
+            .client
+            .head(url.as_str())
+            .headers(headers.clone())
+            .send()
+            .await?;
+
+        Ok(FetchedPage {
+            url: res.url().clone(),
+            status: res.status().as_u16(),
+            headers: std::mem::take(res.headers_mut()),
+            body: Bytes::new(),
+        })
+    }
+
+    /// makes a `HEAD` request for the URL, leaving its connection in the pool
+    async fn warm(&self, url: &Url, headers: &HeaderMap) -> Result<()> {
+        self.client
+            .head(url.as_str())
+            .headers(headers.clone())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+lazy_static! {
+    /// the default fetcher is shared so that its connection pool is reused
+    static ref DEFAULT_FETCHER: Arc<dyn Fetcher> = Arc::new(ReqwestFetcher::default());
+}
+
+/// A `Fetcher` which is shared by a document and all of its child pages.
+#[derive(Clone)]
+pub struct SharedFetcher(pub Arc<dyn Fetcher>);
+
+impl SharedFetcher {
+    pub fn new(fetcher: impl Fetcher + 'static) -> Self {
+        SharedFetcher(Arc::new(fetcher))
+    }
+
+    /// The default fetcher unless the config renders pages in a headless browser
+    /// (see `ChromeFetcher`), needs a client of its own (see
+    /// `ReqwestFetcher::for_config()`) or routes requests through proxies (see
+    /// `ProxyFetcher`). A proxy which isn't valid is an error rather than
+    /// being bypassed, as is rendering without the `render` feature.
+    pub fn for_config(config: &ScrapeConfig) -> Result<Self> {
+        #[cfg(feature = "render")]
+        if config.render.enabled {
+            let fetcher = crate::render::ChromeFetcher::new(config.render.clone());
+            return Ok(SharedFetcher::new(fetcher));
+        }
+        #[cfg(not(feature = "render"))]
+        if config.render.enabled {
+            return Err(eyre!("rendering pages requires the `render` feature"));
+        }
+        if !config.proxy.is_empty() {
+            return Ok(SharedFetcher::new(ProxyFetcher::new(config)?));
+        }
+
+        Ok(match ReqwestFetcher::for_config(config)? {
+            Some(fetcher) => SharedFetcher::new(fetcher),
+            None => SharedFetcher::default(),
+        })
+    }
+}
+
+impl Default for SharedFetcher {
+    fn default() -> Self {
+        SharedFetcher(DEFAULT_FETCHER.clone())
+    }
+}
+
+impl Debug for SharedFetcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedFetcher")
+    }
+}
+
+/// fetchers are equal when they're the same instance
+impl PartialEq for SharedFetcher {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
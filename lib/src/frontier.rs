@@ -0,0 +1,346 @@
+//! The frontier of a crawl: the queue of URLs waiting to be requested along
+//! with the pages which have been seen. A frontier which persists them (e.g.,
+//! a `FileFrontier`) lets a long crawl be resumed once it's stopped -- or has
+//! crashed -- without losing its queue or requesting pages again.
+
+use crate::url_norm::canonicalize;
+use async_trait::async_trait;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Report, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+use tracing::{debug, warn};
+use url::Url;
+
+/// How a URL came to be queued.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Discovery {
+    /// the URL was given to the crawl
+    #[default]
+    Seed,
+    /// a sitemap of the site listed the URL
+    Sitemap,
+    /// a page of the site linked to the URL
+    Link,
+}
+
+impl Discovery {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Discovery::Seed => "seed",
+            Discovery::Sitemap => "sitemap",
+            Discovery::Link => "link",
+        }
+    }
+}
+
+impl FromStr for Discovery {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "seed" => Ok(Discovery::Seed),
+            "sitemap" => Ok(Discovery::Sitemap),
+            "link" => Ok(Discovery::Link),
+            other => Err(eyre!("'{}' isn't a way of discovering a URL", other)),
+        }
+    }
+}
+
+/// Queues the URLs of a crawl and remembers the pages which were seen; a URL
+/// is the same page as another when their canonical forms match (see
+/// `url_norm::canonicalize()`).
+#[async_trait]
+pub trait Frontier: Send + Sync {
+    /// queues a URL unless the same page has been queued or seen already;
+    /// returns whether it was queued
+    async fn enqueue(&self, url: &Url) -> Result<bool>;
+
+    /// Queues a URL as `enqueue()` does while recording how it was discovered.
+    /// Frontiers which don't record it only queue the URL.
+    async fn enqueue_from(&self, url: &Url, discovery: Discovery) -> Result<bool> {
+        let _ = discovery;
+        self.enqueue(url).await
+    }
+
+    /// how the page was discovered when it was first queued, if it's recorded
+    async fn discovery(&self, url: &Url) -> Result<Option<Discovery>> {
+        let _ = url;
+        Ok(None)
+    }
+
+    /// Takes the next URL from the queue. A URL which is taken but never marked
+    /// as seen (e.g., the crawl crashed while it was being requested) is queued
+    /// again when a persisted frontier is reopened.
+    async fn dequeue(&self) -> Result<Option<Url>>;
+
+    /// Puts a URL which was taken but never requested (e.g., the crawl was
+    /// cancelled first) back in the queue, unless its page was seen. Frontiers
+    /// which forget a URL once it's taken only queue it again.
+    async fn requeue(&self, url: &Url) -> Result<()> {
+        self.enqueue(url).await.map(|_| ())
+    }
+
+    /// records that a page was scraped -- or failed -- so that it's never
+    /// queued again
+    async fn mark_seen(&self, url: &Url) -> Result<()>;
+
+    /// the number of URLs waiting in the queue
+    async fn queued(&self) -> Result<usize>;
+}
+
+/// the queue and seen pages of a frontier held in memory
+#[derive(Debug, Default)]
+struct Queue {
+    /// the URLs waiting to be taken, in the order they were queued
+    waiting: VecDeque<Url>,
+    /// the canonical URLs which were queued and not yet seen
+    queued: HashSet<Url>,
+    /// the canonical URLs of the pages which were seen
+    seen: HashSet<Url>,
+    /// how each canonical URL was discovered when it was queued
+    discovered: HashMap<Url, Discovery>,
+}
+
+impl Queue {
+    fn enqueue(&mut self, url: &Url, discovery: Discovery) -> bool {
+        let canonical = canonicalize(url);
+        if self.seen.contains(&canonical) || !self.queued.insert(canonical.clone()) {
+            return false;
+        }
+        self.discovered.entry(canonical).or_insert(discovery);
+        self.waiting.push_back(url.clone());
+
+        true
+    }
+
+    fn discovery(&self, url: &Url) -> Option<Discovery> {
+        self.discovered.get(&canonicalize(url)).copied()
+    }
+
+    /// the next URL which hasn't been seen while it was waiting
+    fn dequeue(&mut self) -> Option<Url> {
+        while let Some(url) = self.waiting.pop_front() {
+            if !self.seen.contains(&canonicalize(&url)) {
+                return Some(url);
+            }
+        }
+
+        None
+    }
+
+    /// queues a URL which was taken again unless its page was seen
+    fn requeue(&mut self, url: &Url) {
+        let canonical = canonicalize(url);
+        if !self.seen.contains(&canonical) && !self.waiting.contains(url) {
+            self.queued.insert(canonical);
+            self.waiting.push_back(url.clone());
+        }
+    }
+
+    fn mark_seen(&mut self, url: &Url) {
+        let canonical = canonicalize(url);
+        self.queued.remove(&canonical);
+        self.seen.insert(canonical);
+    }
+
+    fn queued(&self) -> usize {
+        self.waiting
+            .iter()
+            .filter(|url| !self.seen.contains(&canonicalize(url)))
+            .count()
+    }
+}
+
+/// A `Frontier` held in memory; it's lost when the crawl ends. It's the
+/// frontier a `ConcurrentScrape` uses unless another is set.
+#[derive(Debug, Default)]
+pub struct MemoryFrontier {
+    queue: Mutex<Queue>,
+}
+
+impl MemoryFrontier {
+    pub fn new() -> Self {
+        MemoryFrontier::default()
+    }
+
+    fn queue(&self) -> Result<MutexGuard<'_, Queue>> {
+        self.queue
+            .lock()
+            .map_err(|_| eyre!("the frontier's queue was poisoned"))
+    }
+}
+
+#[async_trait]
+impl Frontier for MemoryFrontier {
+    async fn enqueue(&self, url: &Url) -> Result<bool> {
+        self.enqueue_from(url, Discovery::Seed).await
+    }
+
+    async fn enqueue_from(&self, url: &Url, discovery: Discovery) -> Result<bool> {
+        Ok(self.queue()?.enqueue(url, discovery))
+    }
+
+    async fn discovery(&self, url: &Url) -> Result<Option<Discovery>> {
+        Ok(self.queue()?.discovery(url))
+    }
+
+    async fn dequeue(&self) -> Result<Option<Url>> {
+        Ok(self.queue()?.dequeue())
+    }
+
+    async fn requeue(&self, url: &Url) -> Result<()> {
+        self.queue()?.requeue(url);
+
+        Ok(())
+    }
+
+    async fn mark_seen(&self, url: &Url) -> Result<()> {
+        self.queue()?.mark_seen(url);
+
+        Ok(())
+    }
+
+    async fn queued(&self) -> Result<usize> {
+        Ok(self.queue()?.queued())
+    }
+}
+
+/// A `Frontier` which is persisted to a log file: a line is appended as each
+/// URL is queued along with how it was discovered (`queued <url> sitemap`) and
+/// as each page is seen (`seen <url>`).
+/// Reopening the file replays the log, so the URLs which were queued -- or
+/// being requested -- when a crawl stopped are queued again while the pages
+/// which were seen are skipped.
+///
+/// Each line is written as it happens but isn't synced to disk; a crash of the
+/// process loses nothing while a crash of the machine may lose the last lines.
+#[derive(Debug)]
+pub struct FileFrontier {
+    path: PathBuf,
+    state: Mutex<(Queue, File)>,
+}
+
+impl FileFrontier {
+    /// opens -- or creates -- the log at the path and replays it; lines which
+    /// can't be read (e.g., one cut short by a crash) are logged and skipped
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let log = match std::fs::read_to_string(path) {
+            Ok(log) => log,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e).context(format!("Failed to read the frontier: {}", path.display()))
+            }
+        };
+        let mut queue = Queue::default();
+        for line in log.lines().filter(|line| !line.trim().is_empty()) {
+            let mut words = line.split(' ');
+            let (op, url) = (words.next(), words.next().map(Url::parse));
+            // the lines written before the discovery was recorded have none
+            let discovery = words.next().map(Discovery::from_str);
+            match (op, url, discovery) {
+                (Some("queued"), Some(Ok(url)), None) => {
+                    queue.enqueue(&url, Discovery::Seed);
+                }
+                (Some("queued"), Some(Ok(url)), Some(Ok(discovery))) => {
+                    queue.enqueue(&url, discovery);
+                }
+                (Some("seen"), Some(Ok(url)), None) => queue.mark_seen(&url),
+                _ => warn!(
+                    "skipped a line of the frontier {}: {}",
+                    path.display(),
+                    line
+                ),
+            }
+        }
+        debug!(
+            "resuming the frontier {} with {} URLs queued and {} pages seen",
+            path.display(),
+            queue.queued(),
+            queue.seen.len()
+        );
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("Failed to open the frontier: {}", path.display()))?;
+
+        Ok(FileFrontier {
+            path: path.to_path_buf(),
+            state: Mutex::new((queue, file)),
+        })
+    }
+
+    fn state(&self) -> Result<MutexGuard<'_, (Queue, File)>> {
+        self.state
+            .lock()
+            .map_err(|_| eyre!("the frontier's queue was poisoned"))
+    }
+
+    /// appends a line to the log
+    fn append(&self, file: &mut File, line: &str) -> Result<()> {
+        file.write_all(format!("{}\n", line).as_bytes())
+            .context(format!(
+                "Failed to write to the frontier: {}",
+                self.path.display()
+            ))
+    }
+}
+
+#[async_trait]
+impl Frontier for FileFrontier {
+    async fn enqueue(&self, url: &Url) -> Result<bool> {
+        self.enqueue_from(url, Discovery::Seed).await
+    }
+
+    async fn enqueue_from(&self, url: &Url, discovery: Discovery) -> Result<bool> {
+        let mut state = self.state()?;
+        let (queue, file) = &mut *state;
+        if !queue.enqueue(url, discovery) {
+            return Ok(false);
+        }
+        // the line is quick enough to write on the crawl's task
+        let line = format!("queued {} {}", url, discovery.as_str());
+        self.append(file, &line)?;
+
+        Ok(true)
+    }
+
+    async fn discovery(&self, url: &Url) -> Result<Option<Discovery>> {
+        Ok(self.state()?.0.discovery(url))
+    }
+
+    async fn dequeue(&self) -> Result<Option<Url>> {
+        Ok(self.state()?.0.dequeue())
+    }
+
+    /// the log still has the URL queued, so only the queue in memory changes
+    async fn requeue(&self, url: &Url) -> Result<()> {
+        self.state()?.0.requeue(url);
+
+        Ok(())
+    }
+
+    async fn mark_seen(&self, url: &Url) -> Result<()> {
+        let mut state = self.state()?;
+        let (queue, file) = &mut *state;
+        queue.mark_seen(url);
+
+        self.append(file, &format!("seen {}", url))
+    }
+
+    async fn queued(&self) -> Result<usize> {
+        Ok(self.state()?.0.queued())
+    }
+}
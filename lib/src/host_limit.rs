@@ -0,0 +1,79 @@
+//! Bounds the number of pages of each host which are in flight at once, so that
+//! a crawl across many hosts can run wide without ever sending one of them more
+//! than `ScrapeConfig::max_per_host` concurrent requests.
+
+use crate::ScrapeConfig;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use url::Url;
+
+/// The slots of a crawl: `concurrency` in all and `max_per_host` for each
+/// host. A page waits for a slot of its host before it takes one of the
+/// crawl's, so pages which are waiting on a busy host don't hold up those of
+/// other hosts.
+pub(crate) struct HostLimits {
+    /// the pages of each host which may be in flight; unlimited when not set
+    per_host: Option<usize>,
+    /// the crawl's slots
+    slots: Arc<Semaphore>,
+    /// the slots of each host, keyed by the lowercased host
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// the slots a page holds while it's in flight; they're released when it's dropped
+pub(crate) struct HostPermit {
+    _host: Option<OwnedSemaphorePermit>,
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl HostLimits {
+    pub(crate) fn new(config: &ScrapeConfig) -> Self {
+        HostLimits {
+            per_host: config.max_per_host.map(|n| n.max(1)),
+            slots: Arc::new(Semaphore::new(config.concurrency.max(1))),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The config the `pages` are buffered with: with a limit per host every
+    /// page is polled at once -- and waits for its slots -- so that pages
+    /// aren't held up behind those of a busy host.
+    pub(crate) fn buffer_config(&self, config: &ScrapeConfig, pages: usize) -> ScrapeConfig {
+        match self.per_host {
+            Some(_) => config.clone().concurrency(pages),
+            None => config.clone(),
+        }
+    }
+
+    /// waits for a slot of the URL's host and then one of the crawl's; without
+    /// a limit per host the slots are taken at once
+    pub(crate) async fn acquire(&self, url: &Url) -> HostPermit {
+        let (per_host, host) = match (self.per_host, url.host_str()) {
+            (Some(per_host), Some(host)) => (per_host, host.to_lowercase()),
+            _ => {
+                return HostPermit {
+                    _host: None,
+                    _slot: None,
+                }
+            }
+        };
+        let semaphore = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(per_host)))
+            .clone();
+        // the semaphores are never closed
+        let host = semaphore.acquire_owned().await.ok();
+        let slot = self.slots.clone().acquire_owned().await.ok();
+
+        HostPermit {
+            _host: host,
+            _slot: slot,
+        }
+    }
+}
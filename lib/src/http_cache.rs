@@ -0,0 +1,184 @@
+//! Parses the headers with which a server says how long its response may be
+//! cached -- `Cache-Control`, `Age` and `Expires` (along with the `Date` they're
+//! relative to) -- so they can be surfaced in the results and honored by a
+//! `CachingFetcher` (see `ScrapeConfig::honor_http_caching`).
+
+use crate::fetch::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+
+/// The directives of a `Cache-Control` header which bear on how long a
+/// response may be cached; others (e.g., `no-transform`) are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CacheControl {
+    /// `max-age`: how long the response is fresh for after it was generated
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<Duration>,
+    /// `s-maxage`: overrides the `max-age` for shared caches (e.g., a CDN)
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub s_maxage: Option<Duration>,
+    /// `stale-while-revalidate`: how long a stale response may still be served
+    /// while it's revalidated
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub stale_while_revalidate: Option<Duration>,
+    /// `no-cache`: the response must be revalidated before each use
+    pub no_cache: bool,
+    /// `no-store`: the response mustn't be cached at all
+    pub no_store: bool,
+    /// `must-revalidate`: the response mustn't be used once stale without
+    /// being revalidated
+    pub must_revalidate: bool,
+    /// `private`: the response is for a single user and mustn't be stored by
+    /// a shared cache
+    pub private: bool,
+    /// `public`: the response may be stored by any cache
+    pub public: bool,
+    /// `immutable`: the response won't change while it's fresh
+    pub immutable: bool,
+}
+
+impl CacheControl {
+    /// Parses the comma separated directives of a `Cache-Control` header;
+    /// directive names are case insensitive and unknown directives -- or
+    /// durations which aren't a number of seconds -- are skipped.
+    pub fn parse(header: &str) -> CacheControl {
+        let mut control = CacheControl::default();
+        for directive in header.split(',') {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name, Some(value.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+            let seconds = || value?.parse().ok().map(Duration::from_secs);
+            match name.trim().to_ascii_lowercase().as_str() {
+                "max-age" => control.max_age = seconds(),
+                "s-maxage" => control.s_maxage = seconds(),
+                "stale-while-revalidate" => control.stale_while_revalidate = seconds(),
+                "no-cache" => control.no_cache = true,
+                "no-store" => control.no_store = true,
+                "must-revalidate" => control.must_revalidate = true,
+                "private" => control.private = true,
+                "public" => control.public = true,
+                "immutable" => control.immutable = true,
+                _ => {}
+            }
+        }
+
+        control
+    }
+}
+
+/// The caching headers of a response, parsed into their typed values.
+/// When serialized, durations are human readable strings such as "5m".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CacheHeaders {
+    /// the directives of the `Cache-Control` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+    /// the `Age` header: how long the response had been held by a cache
+    /// (e.g., a CDN) when it was received
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub age: Option<Duration>,
+    /// the `Expires` header: when the response becomes stale
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::util::optional_date_to_string"
+    )]
+    pub expires: Option<DateTime<Utc>>,
+    /// the `Date` header: when the response was generated
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::util::optional_date_to_string"
+    )]
+    pub date: Option<DateTime<Utc>>,
+}
+
+impl CacheHeaders {
+    /// the caching headers of a response
+    pub fn from_headers(headers: &HeaderMap) -> CacheHeaders {
+        CacheHeaders::from_pairs(
+            headers
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str(), v.to_str().ok()?))),
+        )
+    }
+
+    /// The caching headers among the names and values of a response's headers;
+    /// names are case insensitive. An `Expires` which isn't a valid date (e.g.,
+    /// `0`) is treated as already having passed.
+    pub fn from_pairs<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> CacheHeaders {
+        let mut caching = CacheHeaders::default();
+        let mut cache_control: Vec<&str> = vec![];
+        for (name, value) in headers {
+            let value = value.trim();
+            match name.to_ascii_lowercase().as_str() {
+                "cache-control" => cache_control.push(value),
+                "age" => caching.age = value.parse().ok().map(Duration::from_secs),
+                "expires" => {
+                    caching.expires = Some(http_date(value).unwrap_or(DateTime::<Utc>::MIN_UTC))
+                }
+                "date" => caching.date = http_date(value),
+                _ => {}
+            }
+        }
+        if !cache_control.is_empty() {
+            caching.cache_control = Some(CacheControl::parse(&cache_control.join(",")));
+        }
+
+        caching
+    }
+
+    /// whether the response has none of the caching headers
+    pub fn is_empty(&self) -> bool {
+        self.cache_control.is_none() && self.age.is_none() && self.expires.is_none()
+    }
+
+    /// whether the response asks not to be stored at all
+    pub fn no_store(&self) -> bool {
+        self.cache_control.as_ref().is_some_and(|c| c.no_store)
+    }
+
+    /// How long the response is fresh for after it was generated: its
+    /// `max-age`, else the time from its `Date` -- or when it was `received` --
+    /// until it `Expires`. Zero when it must be revalidated before each use
+    /// (`no-cache`) and none when it gives no lifetime.
+    pub fn lifetime(&self, received: DateTime<Utc>) -> Option<Duration> {
+        if let Some(control) = &self.cache_control {
+            if control.no_cache || control.no_store {
+                return Some(Duration::ZERO);
+            }
+            if let Some(max_age) = control.max_age {
+                return Some(max_age);
+            }
+        }
+        let expires = self.expires?;
+
+        Some(
+            (expires - self.date.unwrap_or(received))
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// how old the response -- which was `received` at the given time -- is
+    /// `now`, including the time it had already spent in other caches
+    pub fn current_age(&self, received: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+        let resident = (now - received).to_std().unwrap_or_default();
+
+        self.age.unwrap_or_default() + resident
+    }
+
+    /// whether the response -- which was `received` at the given time -- can
+    /// still be used `now` without revalidating it
+    pub fn is_fresh(&self, received: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.lifetime(received)
+            .is_some_and(|lifetime| self.current_age(received, now) < lifetime)
+    }
+}
+
+/// parses an HTTP date such as `Wed, 21 Oct 2015 07:28:00 GMT`
+fn http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
@@ -0,0 +1,117 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use std::collections::HashMap;
+
+lazy_static! {
+    /// scripts whose content is the JSON payload, e.g. `<script id="__NEXT_DATA__">`
+    static ref PAYLOAD_SCRIPT: Selector =
+        Selector::parse("script[id^='__'][type='application/json']").unwrap();
+    static ref SCRIPT: Selector = Selector::parse("script:not([src])").unwrap();
+    /// an assignment of state to a global, e.g. `window.__INITIAL_STATE__ = {...}`
+    static ref ASSIGNMENT: Regex =
+        Regex::new(r"(?:window\.|self\.|globalThis\.|var\s+|let\s+|const\s+)?\b(__[A-Z][A-Z0-9_]*__)\s*=\s*")
+            .unwrap();
+    static ref JSON_PARSE: Regex = Regex::new(r#"^JSON\.parse\(\s*(["'])"#).unwrap();
+}
+
+/// The hydration payloads which single page apps embed in their server rendered
+/// HTML -- `__NEXT_DATA__`, `window.__INITIAL_STATE__` and the like -- keyed by
+/// the global's name in snake case (e.g., `next_data` and `initial_state`).
+///
+/// Payloads are read from JSON script blocks and from assignments of a JSON
+/// literal (or a `JSON.parse("...")` of one) to a global; payloads which aren't
+/// valid JSON are skipped.
+pub fn payloads(html: &Html) -> HashMap<String, Value> {
+    let mut payloads = HashMap::new();
+
+    html.select(&PAYLOAD_SCRIPT).for_each(|el| {
+        let id = el.value().attr("id").unwrap_or_default();
+        if let Ok(value) = serde_json::from_str(&el.text().collect::<String>()) {
+            payloads.insert(key(id), value);
+        }
+    });
+
+    html.select(&SCRIPT)
+        .map(|el| el.text().collect::<String>())
+        .for_each(|script| {
+            ASSIGNMENT.captures_iter(&script).for_each(|caps| {
+                let rest = &script[caps.get(0).unwrap().end()..];
+                if let Some(value) = assigned_json(rest) {
+                    payloads.entry(key(&caps[1])).or_insert(value);
+                }
+            });
+        });
+
+    payloads
+}
+
+/// `__NEXT_DATA__` becomes `next_data`
+fn key(global: &str) -> String {
+    global.trim_matches('_').to_lowercase()
+}
+
+/// the JSON value at the start of the right hand side of an assignment
+fn assigned_json(rhs: &str) -> Option<Value> {
+    match JSON_PARSE.captures(rhs) {
+        Some(caps) => {
+            let start = caps.get(0).unwrap().end();
+            let quote = caps[1].chars().next()?;
+            serde_json::from_str(&string_literal(&rhs[start..], quote)?).ok()
+        }
+        None => serde_json::from_str(json_literal(rhs)?).ok(),
+    }
+}
+
+/// the balanced object or array literal at the start of the text
+fn json_literal(text: &str) -> Option<&str> {
+    let open = text.chars().next().filter(|c| *c == '{' || *c == '[')?;
+    let close = if open == '{' { '}' } else { ']' };
+    let (mut depth, mut in_string, mut escaped) = (0, false, false);
+
+    for (i, c) in text.char_indices() {
+        match (in_string, escaped, c) {
+            (true, true, _) => escaped = false,
+            (true, false, '\\') => escaped = true,
+            (true, false, '"') => in_string = false,
+            (true, false, _) => {}
+            (false, _, '"') => in_string = true,
+            (false, _, c) if c == open => depth += 1,
+            (false, _, c) if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// the unescaped content of a JS string literal which ends with `quote`
+fn string_literal(text: &str, quote: char) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == quote => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,42 @@
+use crate::{results::ParseResults, url_norm::canonicalize};
+use serde::{Deserialize, Serialize};
+
+/// How the stable identifier of each page's results is derived so that
+/// downstream stores can upsert pages across runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum IdStrategy {
+    /// results have no identifier
+    #[default]
+    None,
+    /// a hash (64-bit FNV-1a, in hex) of the page's canonical URL
+    UrlHash,
+    /// the page's canonical URL; see `url_norm::canonicalize()`
+    CanonicalUrl,
+    /// the value of a property or selection (e.g., a SKU or slug); pages
+    /// without a value have no identifier
+    Property(String),
+}
+
+impl IdStrategy {
+    /// the identifier of a page's results
+    pub fn id(&self, page: &ParseResults) -> Option<String> {
+        match self {
+            IdStrategy::None => None,
+            IdStrategy::UrlHash => {
+                Some(format!("{:016x}", fnv1a(canonicalize(&page.url).as_str())))
+            }
+            IdStrategy::CanonicalUrl => Some(canonicalize(&page.url).to_string()),
+            IdStrategy::Property(name) => page.field(name).filter(|v| !v.is_empty()),
+        }
+    }
+}
+
+/// the 64-bit FNV-1a hash; unlike the standard library's hasher it's stable
+/// across runs and Rust versions
+pub(crate) fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -1,29 +1,134 @@
+use archive::Snapshot;
+use chain::Chain;
+use challenge::{BlockedPage, BotChallenge};
+use children::ChildRules;
 use color_eyre::{
-    eyre::eyre,
     eyre::{Report, WrapErr},
     Result,
 };
+pub use config::{
+    CrawlHandle, DuplicatePolicy, FramePolicy, ScrapeConfig, TemplatePolicy, CONCURRENT_REQUESTS,
+    MAX_REDIRECT_HOPS,
+};
+use content::Content;
+use dry_run::{Candidate, Verdict};
+use error::{HttpError, ScrapedError};
+use expression::Expression;
+use extract::Extractor;
+use fetch::{FetchedPage, Fetcher, SharedFetcher};
+use futures::{
+    future::BoxFuture,
+    stream::{self, LocalBoxStream},
+    Future, FutureExt, Stream, StreamExt,
+};
+use http_cache::CacheHeaders;
 use lazy_static::lazy_static;
+use link_audit::LinkReport;
+use lookup::Lookup;
+use metadata::Metadata;
+use parser::Selectors;
+use postprocess::PostProcessor;
+use provenance::SelectorSource;
 use regex::Regex;
-use results::{ParseResults, ResultKind};
-use scraper::{Html, Selector};
-use selection::{get_selection, SelectorKind};
+use report::{CrawlProperty, CrawlReport};
+use results::{Alternate, PageOutcome, ParseResults, ResultKind};
+use retry::RetryPolicy;
+use scraper::{ElementRef, Html, Selector};
+use selection::{compile_selector, get_selection, Selection, SelectorKind};
 use serde::Serialize;
-use serde_json::Value;
-use std::collections::HashMap;
-use tokio_stream::StreamExt;
-use tracing::{debug, instrument, trace, warn};
+use serde_json::{json, Value};
+use sink::Sink;
+use stats::ScrapeStats;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use table::Table;
+use tracing::{debug, info, info_span, instrument, trace, warn};
+use transforms::Transform;
 use url::Url;
 
+mod annotate;
+pub mod archive;
+pub mod auth;
+pub mod cache;
+pub mod cancel;
+pub mod canonical;
+pub mod chain;
+pub mod challenge;
+pub mod children;
+pub mod clock;
+pub mod compare;
+pub mod concurrent;
+mod config;
+pub mod content;
+pub mod dns;
+pub mod dry_run;
 mod elements;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod error;
+pub mod estimate;
+pub mod expression;
+pub mod extract;
+pub mod fetch;
+pub mod frontier;
+mod host_limit;
+pub mod http_cache;
+pub mod hydration;
+pub mod id;
+pub mod limits;
+pub mod link_audit;
+pub mod lookup;
+pub mod metadata;
+pub mod network;
+pub mod observe;
+pub mod pagination;
+pub mod parser;
+pub mod policy;
+pub mod postprocess;
+pub mod prelude;
+pub mod provenance;
+pub mod proxy;
+pub mod rate;
+pub mod redact;
+pub mod redirect;
+pub mod render;
+pub mod repair;
+pub mod report;
 pub mod results;
+pub mod retry;
+pub mod robots;
+pub mod safety;
+pub mod sample;
+pub mod scrub;
 pub mod selection;
+pub mod sink;
+pub mod sitemap;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod table;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod text;
+pub mod transforms;
+pub mod url_norm;
+pub mod url_parts;
 mod util;
+#[cfg(feature = "warc")]
+pub mod warc;
+pub mod warmup;
+pub mod watch;
 
 /// receives an unvalidated String and returns a validated Url
 fn parse_url(url: &str) -> Result<Url, Report> {
     Url::parse(url)
-        .map_err(|e| eyre!(e))
+        .map_err(|e| ScrapedError::parse_failure(url, e))
         .context(format!("Failed to parse the URL string recieved: {}", url))
 }
 
@@ -33,6 +138,11 @@ pub struct Document {
     #[serde(serialize_with = "util::url_to_string")]
     pub url: Url,
     pub data: Option<String>,
+    /// configures how the document -- and any child pages -- are requested
+    pub config: ScrapeConfig,
+    /// the transport used to request the document and any child pages
+    #[serde(skip)]
+    pub fetcher: SharedFetcher,
 }
 
 impl From<&Url> for Document {
@@ -40,58 +150,561 @@ impl From<&Url> for Document {
         Document {
             url: url.clone(),
             data: None,
+            config: ScrapeConfig::default(),
+            fetcher: SharedFetcher::default(),
         }
     }
 }
 
 impl Document {
-    pub fn new(url: &str) -> Result<Document> {
+    pub fn new(url: &str) -> Result<Document, ScrapedError> {
         Ok(Document {
             url: parse_url(url)?,
             data: None,
+            config: ScrapeConfig::default(),
+            fetcher: SharedFetcher::default(),
         })
     }
 
+    /// sets the transport used to request this document and its child pages;
+    /// by default pages are requested with `reqwest`
+    pub fn fetcher(mut self, fetcher: impl Fetcher + 'static) -> Self {
+        self.fetcher = SharedFetcher::new(fetcher);
+
+        self
+    }
+
+    /// shares the transport of another document
+    fn shared_fetcher(mut self, fetcher: SharedFetcher) -> Self {
+        self.fetcher = fetcher;
+
+        self
+    }
+
+    /// sets the configuration used to request this document and its child pages
+    pub fn config(mut self, config: ScrapeConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+
+    /// adds headers which are sent with the requests for this document and its
+    /// child pages which are on the domain (or its subdomains); see
+    /// `ScrapeConfig::headers_for()`
+    pub fn headers_for<K: ToString, V: ToString>(
+        mut self,
+        domain: &str,
+        headers: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.config = self.config.headers_for(domain, headers);
+
+        self
+    }
+
+    /// sends a bearer token with the requests for this document and its child
+    /// pages whose domain has no token of its own
+    pub fn bearer_token_global(mut self, token: &str) -> Self {
+        self.config = self.config.bearer_token(token);
+
+        self
+    }
+
+    /// sends a bearer token with the requests for this document and its child
+    /// pages which are on the domain (or its subdomains); the most specific
+    /// domain's token is sent
+    pub fn bearer_token_for(mut self, domain: &str, token: &str) -> Self {
+        self.config = self.config.bearer_token_for(domain, token);
+
+        self
+    }
+
+    /// reads bearer tokens from the `SCRAPED_TOKEN` and `SCRAPED_TOKEN_<DOMAIN>`
+    /// environment variables too; see `auth::env_var()`
+    pub fn bearer_tokens_from_env(mut self, env: bool) -> Self {
+        self.config.bearer_tokens = self.config.bearer_tokens.env(env);
+
+        self
+    }
+
+    /// renders this document and its child pages in a headless browser before
+    /// they're parsed; see `render` for the options
+    pub fn render_js(mut self, render: bool) -> Self {
+        self.config.render.enabled = render;
+
+        self
+    }
+
+    /// routes the requests for this document and its child pages through a
+    /// proxy; see `ScrapeConfig::proxy_pool()` to spread them across several
+    pub fn proxy(mut self, proxy: Url) -> Self {
+        self.config = self.config.proxy_pool(vec![proxy]);
+
+        self
+    }
+
+    /// retries requests which fail for transient reasons (connection errors,
+    /// 429 and 5xx responses) up to `max_attempts` times with exponential
+    /// backoff; see `RetryPolicy`
+    pub fn retry_policy(
+        mut self,
+        max_attempts: u32,
+        base_delay: Duration,
+        jitter: Duration,
+    ) -> Self {
+        self.config.retry = RetryPolicy::new(max_attempts, base_delay, jitter);
+
+        self
+    }
+
+    /// bounds the time taken by each request for this document and its child
+    /// pages; a request which times out fails with `ScrapedError::Timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+
+        self
+    }
+
+    /// fails this document and its child pages when their response's body is
+    /// larger than `max` bytes (with `ScrapedError::TooLarge`); the body is
+    /// abandoned as soon as it's known to be too large rather than once it's
+    /// been downloaded
+    pub fn max_body_bytes(mut self, max: u64) -> Self {
+        self.config.limits = self.config.limits.max_body_bytes(max);
+
+        self
+    }
+
+    /// only accepts successful responses with one of the content types (e.g.
+    /// `text/html` or `text/*`) for this document and its child pages; others
+    /// fail with `ScrapedError::UnacceptedContentType` before their body is read
+    pub fn accept_content_types(mut self, content_types: &[&str]) -> Self {
+        self.config.limits = content_types
+            .iter()
+            .fold(self.config.limits, |limits, c| limits.accept(c));
+
+        self
+    }
+
+    /// follows up to `max_pages` of the pages which follow this document -- by
+    /// `rel="next"` in their `Link` header or HTML -- when its `results_graph()`
+    /// is built; see `pagination`
+    pub fn follow_pagination(mut self, max_pages: usize) -> Self {
+        self.config.pagination = self.config.pagination.follow(true).max_pages(max_pages);
+
+        self
+    }
+
+    /// checks the links of this document and its child pages with `HEAD`
+    /// requests into their `link_report` when their results are built
+    /// asynchronously; see `link_audit`
+    pub fn audit_links(mut self) -> Self {
+        self.config.link_audit = self.config.link_audit.enabled(true);
+
+        self
+    }
+
+    /// sets what happens when a selector is added to the parsed document under
+    /// a name which is taken; see `DuplicatePolicy`
+    pub fn duplicate_selectors(mut self, policy: DuplicatePolicy) -> Self {
+        self.config.duplicate_selectors = policy;
+
+        self
+    }
+
+    /// sets whether the `/robots.txt` of each site is respected: disallowed pages
+    /// aren't requested and the `Crawl-delay` is honored; see `robots`
+    pub fn respect_robots(mut self, respect: bool) -> Self {
+        self.config.respect_robots = respect;
+
+        self
+    }
+
     /// Loads the HTTP page over the network and saves as a string
     /// awaiting further processing.
-    pub async fn load_document(self) -> Result<LoadedDocument, Report> {
-        let resp = match self.data {
-            Some(v) => v,
-            None => reqwest::get(&self.url.to_string()).await?.text().await?,
+    ///
+    /// A response with a non-2xx status code fails with `ScrapedError::Http` --
+    /// or `RateLimited` for a 429 status. When an anti-bot interstitial is
+    /// served in place of the page it fails with `ScrapedError::Blocked`.
+    ///
+    /// When the `ScrapeConfig` is set to `respect_robots` a page which the site's
+    /// robots.txt disallows fails with `ScrapedError::Disallowed`.
+    ///
+    /// A page -- or client-side redirect -- which the `CrawlSafety` refuses (e.g.,
+    /// one outside of its allowed domains) fails with a `ScrapedError`.
+    ///
+    /// A response beyond the `ScrapeConfig`'s `ResponseLimits` -- one whose body
+    /// is too large or whose content type isn't accepted -- fails with a
+    /// `ScrapedError` as soon as that's known.
+    ///
+    /// When the `ScrapeConfig` is set to `repair_html` the HTML is repaired before
+    /// it's parsed; see `repair::repair_html()`.
+    ///
+    /// HTTP redirects are followed as the `ScrapeConfig`'s `RedirectPolicy`
+    /// allows and, when it's set to `follow_client_redirects`, so are meta
+    /// refresh and trivial JS redirects (up to `max_redirect_hops`). Each URL
+    /// which redirected is recorded in the document's `redirects` and the URL
+    /// it was ultimately served from is its `resolved_url`.
+    ///
+    /// When the `ScrapeConfig` is set to `archive_fallback` a page which is gone
+    /// or can't be reached is loaded from its most recent snapshot in the Wayback
+    /// Machine and the snapshot is recorded in the document's `archived`.
+    pub async fn load_document(self) -> Result<LoadedDocument, ScrapedError> {
+        Ok(self.load().await?)
+    }
+
+    /// loads the document as `load_document()` does; the error is the `Report`
+    /// -- e.g., an `HttpError` which captures the response when configured to
+    /// do so -- which the outcomes of the child pages are built from
+    #[instrument(name = "page", skip(self), fields(url = %self.url))]
+    async fn load(self) -> Result<LoadedDocument> {
+        let config = self.config;
+        // unless a fetcher was set, the config picks the client (e.g., one
+        // which resolves with DoH or routes requests through proxies)
+        let fetcher = match self.fetcher == SharedFetcher::default() {
+            true => SharedFetcher::for_config(&config)?,
+            false => self.fetcher,
         };
+        let url = self.url;
+        if self.data.is_none() {
+            config.safety.check_resolved(&url).await?;
+        }
+        if self.data.is_none() && config.respect_robots {
+            let robots = robots::load(&url, &config, fetcher.0.as_ref()).await;
+            if !robots.allows(robots::agent(&config), &url) {
+                return Err(ScrapedError::Disallowed { url }.into());
+            }
+        }
+        let mut archived = None;
+        let mut response = match self.data {
+            Some(data) => Response {
+                data,
+                content: Content::Html,
+                url: url.clone(),
+                redirects: vec![],
+                caching: None,
+                next_page: None,
+            },
+            None => match fetch(&url, &config, fetcher.0.as_ref()).await {
+                Err(e) if config.archive_fallback && archive::is_link_rot(&e) => {
+                    let snapshot = match archive::latest(&url, &config, fetcher.0.as_ref()).await {
+                        Ok(Some(snapshot)) => snapshot,
+                        Ok(None) => return Err(e),
+                        Err(err) => {
+                            warn!("failed to find a snapshot of {}: {:#}", url, err);
+                            return Err(e);
+                        }
+                    };
+                    info!("loading {} from its snapshot {}", url, snapshot.url);
+                    let page = fetch(&snapshot.raw_url(), &config, fetcher.0.as_ref())
+                        .await
+                        .context(format!("Failed to load the snapshot of {}", url))?;
+                    archived = Some(snapshot);
+                    // the snapshot holds the page's original links
+                    Response {
+                        url: url.clone(),
+                        redirects: vec![],
+                        caching: None,
+                        next_page: None,
+                        ..page
+                    }
+                }
+                result => result?,
+            },
+        };
+
+        let client_redirect = |response: &Response| match config.follow_client_redirects
+            && response.content.is_html()
+        {
+            true => redirect::client_redirect(&response.data, &response.url),
+            false => None,
+        };
+        let mut hops = 0;
+        while let Some(next) = client_redirect(&response) {
+            if next == response.url || next == url || response.redirects.contains(&next) {
+                break;
+            }
+            if hops >= config.max_redirect_hops {
+                warn!(
+                    "stopped following client-side redirects at {} after {} hops",
+                    response.url, hops
+                );
+                break;
+            }
+
+            info!(
+                "following a client-side redirect from {} to {}",
+                response.url, next
+            );
+            config.safety.check_resolved(&next).await?;
+            let page = fetch(&next, &config, fetcher.0.as_ref()).await?;
+            let mut redirects = std::mem::take(&mut response.redirects);
+            redirects.push(response.url);
+            redirects.extend(page.redirects.iter().cloned());
+            response = Response { redirects, ..page };
+            hops += 1;
+        }
+        let Response {
+            mut data,
+            content,
+            url: resolved_url,
+            redirects,
+            caching,
+            next_page,
+        } = response;
+        if config.repair_html && content.is_html() {
+            data = repair::repair_html(&data);
+        }
 
         Ok(LoadedDocument {
-            url: self.url,
-            data: resp,
+            url,
+            resolved_url,
+            data,
+            content,
+            config,
+            fetcher,
+            redirects,
+            archived,
+            caching,
+            next_page,
         })
     }
 }
 
+/// runs the requests concurrently as configured by the `ScrapeConfig`; results
+/// are either yielded in the order requested or as soon as they complete
+fn buffer<'a, T: 'a>(
+    requests: impl Stream<Item = impl Future<Output = T> + 'a> + 'a,
+    config: &ScrapeConfig,
+) -> LocalBoxStream<'a, T> {
+    match config.ordered {
+        true => requests.buffered(config.concurrency.max(1)).boxed_local(),
+        false => requests
+            .buffer_unordered(config.concurrency.max(1))
+            .boxed_local(),
+    }
+}
+
+/// a page's body along with the URL it was ultimately served from
+struct Response {
+    data: String,
+    content: Content,
+    url: Url,
+    /// the URLs which redirected -- over HTTP -- to the page
+    redirects: Vec<Url>,
+    /// the caching headers of the response, when it had any
+    caching: Option<CacheHeaders>,
+    /// the next page given by the response's `Link` header
+    next_page: Option<Url>,
+}
+
+/// requests a page -- following its HTTP redirects -- and returns its body
+/// along with the kind of content it holds; see `Document::load_document()`
+/// for the responses which are treated as errors
+#[instrument(name = "fetch", skip(config, fetcher), fields(url = %url, status))]
+async fn fetch(
+    url: &Url,
+    config: &ScrapeConfig,
+    fetcher: &dyn Fetcher,
+) -> Result<Response, Report> {
+    let (page, redirects) =
+        redirect::follow(url, config, |hop| fetch_page(hop, config, fetcher)).await?;
+    tracing::Span::current().record("status", page.status);
+    let success = (200..300).contains(&page.status);
+    let caching = Some(CacheHeaders::from_headers(&page.headers)).filter(|c| !c.is_empty());
+    let next_page = pagination::next_from_headers(&page.headers, &page.url);
+    if success && !content::is_html(&page.url, &page) {
+        let url = page.url.clone();
+        let (data, content) = content::read(&url, page)?;
+        return Ok(Response {
+            data,
+            content,
+            url,
+            redirects,
+            caching,
+            next_page,
+        });
+    }
+
+    // the body is taken from the page rather than copied
+    let FetchedPage {
+        url,
+        status,
+        headers: response_headers,
+        body,
+    } = page;
+    let body = fetch::decode(body, &response_headers);
+    let headers: Vec<(&str, &str)> = response_headers
+        .iter()
+        .filter_map(|(k, v)| Some((k.as_str(), v.to_str().ok()?)))
+        .collect();
+
+    if let Some(challenge) = BotChallenge::detect(status, &headers, &body) {
+        return Err(BlockedPage {
+            url,
+            challenge,
+            status,
+        }
+        .into());
+    }
+    if !success {
+        let capture = config.capture_error_body;
+        let mut error = HttpError::new(&url, status, &headers, &body, capture);
+        error.retry_after = retry::retry_after(&response_headers);
+        return Err(error.into());
+    }
+
+    Ok(Response {
+        data: body,
+        content: Content::Html,
+        url,
+        redirects,
+        caching,
+        next_page,
+    })
+}
+
+/// whether the request failed because the client timed it out
+fn is_timeout(error: &Report) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_timeout())
+}
+
+/// requests a single page -- without following redirects -- retrying the
+/// request when it fails for a transient reason
+async fn fetch_page(url: Url, config: &ScrapeConfig, fetcher: &dyn Fetcher) -> Result<FetchedPage> {
+    let url = &url;
+    let request_headers = config.request_headers_for(url)?;
+    let mut attempt = 1;
+    loop {
+        rate::acquire(url, config).await;
+        let request = fetcher.fetch_within(url, &request_headers, &config.limits);
+        // the default clients time out the request themselves while other
+        // transports are given a timeout of their own
+        let result = match config.timeout {
+            Some(after) if fetcher.timeout() == Some(after) => {
+                request.await.map_err(|e| match is_timeout(&e) {
+                    true => ScrapedError::timeout(url, after),
+                    false => e,
+                })
+            }
+            Some(after) => tokio::time::timeout(after, request)
+                .await
+                .unwrap_or_else(|_| Err(ScrapedError::timeout(url, after))),
+            None => request.await,
+        };
+        let transient = match &result {
+            Ok(page) => retry::is_transient_status(page.status),
+            Err(e) => retry::is_transient_error(e),
+        };
+        if !transient || attempt >= config.retry.max_attempts {
+            return result;
+        }
+
+        let headers = result.as_ref().ok().map(|p| &p.headers);
+        let pause = config
+            .retry
+            .pause_on(attempt, headers, config.clock.0.as_ref());
+        warn!(
+            "retrying {} in {:?} after attempt {} of {} failed",
+            url, pause, attempt, config.retry.max_attempts
+        );
+        config.clock.0.sleep(pause).await;
+        attempt += 1;
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct LoadedDocument {
     #[serde(serialize_with = "util::url_to_string")]
     /// The URL where the html document can be found
     pub url: Url,
-    /// the raw string data recieved via **Reqwest**
+    /// the URL the document was ultimately served from once its redirects
+    /// were followed; its links are relative to this URL
+    #[serde(serialize_with = "util::url_to_string")]
+    pub resolved_url: Url,
+    /// the raw string data recieved via **Reqwest**; for content other than
+    /// HTML this is the text extracted from the document
     pub data: String,
+    /// the kind of content the document holds
+    pub content: Content,
+    /// configures how child pages are requested
+    pub config: ScrapeConfig,
+    /// the transport used to request child pages
+    #[serde(skip)]
+    pub fetcher: SharedFetcher,
+    /// the URLs which redirected -- over HTTP or client-side -- to this
+    /// document, in the order they were visited
+    #[serde(serialize_with = "util::urls_to_strings")]
+    pub redirects: Vec<Url>,
+    /// the snapshot in the Wayback Machine the document was loaded from when
+    /// the page itself had rotted away
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<Snapshot>,
+    /// the `Cache-Control`, `Age` and `Expires` headers of the response, when
+    /// it had any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caching: Option<CacheHeaders>,
+    /// the next page of a paginated response as given by its
+    /// `Link: <...>; rel="next"` header
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "util::optional_url_to_string"
+    )]
+    pub next_page: Option<Url>,
 }
 
 impl LoadedDocument {
-    pub fn new(url: &str, data: &str) -> Result<LoadedDocument> {
+    pub fn new(url: &str, data: &str) -> Result<LoadedDocument, ScrapedError> {
+        let url = parse_url(url)?;
+
         Ok(LoadedDocument {
-            url: parse_url(url)?,
+            resolved_url: url.clone(),
+            url,
             data: data.to_string(),
+            content: Content::Html,
+            config: ScrapeConfig::default(),
+            fetcher: SharedFetcher::default(),
+            redirects: vec![],
+            archived: None,
+            caching: None,
+            next_page: None,
         })
     }
 
+    /// sets the kind of content the document holds; documents are assumed to
+    /// be HTML unless they were loaded from a response of another kind
+    pub fn content(mut self, content: Content) -> Self {
+        self.content = content;
+
+        self
+    }
+
     /// parses a `LoadedDocument` into a `ParsedDoc`
-    pub fn parse_document(&self) -> Result<ParsedDoc> {
+    pub fn parse_document(&self) -> Result<ParsedDoc, ScrapedError> {
         ParsedDoc::new(self)
     }
 
+    /// Evaluates a set of simple selectors without building a `ParsedDoc`;
+    /// they're streamed through `lol_html` when it supports them all. Content
+    /// other than HTML has its extracted results returned.
+    pub fn select(
+        &self,
+        selectors: &Selectors,
+    ) -> Result<HashMap<String, ResultKind>, ScrapedError> {
+        let _span = info_span!("parse", url = %self.url, parser = ?selectors.backend()).entered();
+        if !self.content.is_html() {
+            return Ok(content::parse(&self.content, &self.data).1);
+        }
+
+        Ok(selectors.select(&self.data, &self.resolved_url, &self.config)?)
+    }
+
     /// Parses into a `ParsedDoc` and then adds selectors intended to suit the `docs.rs` site.
     pub fn for_docs_rs(self) -> ParsedDoc {
-        ParsedDoc::from(self)
+        let doc = ParsedDoc::from(self);
+        let source = doc.source.clone();
+        doc.selector_source(SelectorSource::preset("docs_rs"))
             .add_selector("h1", "h1 .in-band a")
             .add_selector_all("h2", "h2")
             .add_selector_all("modules", ".module-item a.mod")
@@ -115,6 +728,7 @@ impl LoadedDocument {
                 ],
                 ChildScope::Relative(),
             )
+            .selector_source(source)
     }
 }
 
@@ -149,65 +763,173 @@ fn validate_child_href(href: &str, scope: &ChildScope, current_page: &Url) -> Op
         href.starts_with("file"),
         REL.captures(href).is_some(),
     ) {
-        (_, ChildScope::All(), false, false, true) => {
-            Some([&current_page.to_string(), href].join("/"))
-        }
+        (_, ChildScope::All(), false, false, true) => Some([current_page.as_str(), href].join("/")),
         (_, ChildScope::All(), _, _, _) => Some(href.to_string()),
         (_, ChildScope::Http(), true, _, _) => Some(href.to_string()),
         (_, ChildScope::Http(), false, _, _) => None,
         (_, ChildScope::File(), _, true, _) => Some(href.to_string()),
         (_, ChildScope::File(), _, false, _) => None,
-        (_, ChildScope::Relative(), _, _, true) => {
-            Some([&current_page.to_string(), href].join("/"))
-        }
+        (_, ChildScope::Relative(), _, _, true) => Some([current_page.as_str(), href].join("/")),
 
         _ => None,
     }?;
 
-    match parse_url(&url) {
-        Ok(url) => Some(url),
-        Err(_e) => {
-            // on error just log the problem to console and return None to skip child
-            None
-        }
-    }
+    // on error just skip the child
+    parse_url(&url).ok()
 }
 
 /// a callback function which is provided a hashmap of all resultant _selectors_
 /// and is expected to turn that into a meaningup JSON-based result.
+///
+/// Closures which capture state (e.g., configuration) can be added as properties
+/// too; see `ParsedDoc::add_property()`.
 pub type PropertyCallback = fn(sel: &HashMap<String, ResultKind>) -> Value;
 
+/// a property callback as it's held by a `ParsedDoc`; it's shared with the
+/// document's child pages
+type Property = Arc<dyn Fn(&HashMap<String, ResultKind>) -> Value + Send + Sync>;
+
+/// a property callback which must be awaited (e.g., to enrich the page with
+/// another request); it's provided with a copy of the selections
+type AsyncProperty =
+    Arc<dyn Fn(HashMap<String, ResultKind>) -> BoxFuture<'static, Value> + Send + Sync>;
+
 /// A `Document` which has been loaded from the network and parsed
 /// into a DOM tree. You can add "selectors" which will be lazily
 /// evaluated when calling `get(selector)` or when exporting as
 /// a JSON payload.
 pub struct ParsedDoc {
     pub url: Url,
+    /// the URL the document was ultimately served from; its links are
+    /// resolved against this URL
+    pub resolved_url: Url,
     pub html: Html,
+    /// the kind of content the document holds
+    pub content: Content,
+    /// the results which stand in for CSS selections when the content is not HTML
+    extracted: HashMap<String, ResultKind>,
     /// a hash of selectors which will be lazily evaluated when
     /// converting to a JSON output or when calling `get(selector)`
     /// to extract a particular selector.
     pub selectors: HashMap<String, SelectorKind>,
+    /// chained selector expressions which select structured data; see `Chain`
+    chains: HashMap<String, Chain>,
+    /// where each selector (or chain) was defined, keyed by its name
+    sources: HashMap<String, SelectorSource>,
+    /// the source which the selectors being added are attributed to
+    source: SelectorSource,
+    /// the post-processors of list selectors, keyed by the selector's name
+    post_processors: HashMap<String, Vec<PostProcessor>>,
     /// allows user to build up a set of selectors which will be looked
     /// as being candidates for selecting
-    child_selectors: Vec<(String, ChildScope)>,
+    child_selectors: Vec<(String, ChildScope, ChildRules)>,
     /// a dictionary of user defined callbacks which leverage the
     /// selectors as input to produce clean outcomes
-    properties: HashMap<String, PropertyCallback>,
+    properties: HashMap<String, Property>,
+    /// user defined callbacks which are awaited; they're only evaluated by the
+    /// async methods (e.g., `results_async()` or `results_graph()`)
+    async_properties: HashMap<String, AsyncProperty>,
+    /// callbacks which aggregate the results of every page in a crawl; they're
+    /// evaluated when building the `CrawlReport`
+    crawl_properties: HashMap<String, CrawlProperty>,
+    /// tables whose matching rows are joined to the results of each page
+    lookups: Vec<Arc<Lookup>>,
+    /// the built-in extractors which emit properties of their own
+    extractors: Vec<Extractor>,
+    /// properties which transform the result of a selector, keyed by the
+    /// property's name
+    transforms: HashMap<String, (String, Transform)>,
+    /// properties derived from the selections by an expression, keyed by the
+    /// property's name
+    expressions: HashMap<String, Expression>,
+    /// configures how child pages are requested
+    config: ScrapeConfig,
+    /// the transport used to request child pages
+    fetcher: SharedFetcher,
+    /// the URLs which redirected -- over HTTP or client-side -- to this document
+    redirects: Vec<Url>,
+    /// the snapshot the document was loaded from when the page had rotted away
+    archived: Option<Snapshot>,
+    /// the caching headers of the response the document was loaded from
+    caching: Option<CacheHeaders>,
+    /// the next page given by the `Link` header of the document's response
+    next_page: Option<Url>,
+    /// the destinations which receive each page scraped by `results_graph()`
+    sinks: Vec<Arc<dyn Sink>>,
+    /// statistics on the pages requested; shared with all child pages
+    stats: ScrapeStats,
 }
 
 impl ParsedDoc {
-    #[instrument]
-    pub fn new(doc: &LoadedDocument) -> Result<ParsedDoc> {
+    #[instrument(name = "parse", skip(doc), fields(url = %doc.url))]
+    pub fn new(doc: &LoadedDocument) -> Result<ParsedDoc, ScrapedError> {
+        let (html, extracted) = content::parse(&doc.content, &doc.data);
+
         Ok(ParsedDoc {
             url: doc.url.clone(),
-            html: Html::parse_document(&doc.data),
+            resolved_url: doc.resolved_url.clone(),
+            html,
+            content: doc.content.clone(),
+            extracted,
             selectors: HashMap::new(),
+            chains: HashMap::new(),
+            sources: HashMap::new(),
+            source: SelectorSource::default(),
+            post_processors: HashMap::new(),
             child_selectors: vec![],
             properties: HashMap::new(),
+            async_properties: HashMap::new(),
+            crawl_properties: HashMap::new(),
+            lookups: vec![],
+            extractors: vec![],
+            transforms: HashMap::new(),
+            expressions: HashMap::new(),
+            config: doc.config.clone(),
+            fetcher: doc.fetcher.clone(),
+            redirects: doc.redirects.clone(),
+            archived: doc.archived.clone(),
+            caching: doc.caching.clone(),
+            next_page: doc.next_page.clone(),
+            sinks: vec![],
+            stats: ScrapeStats::default(),
         })
     }
 
+    /// Parses a child page with the same selectors, child selectors and properties
+    /// as this page so that children inherit their parent's configuration.
+    fn inherit(&self, doc: LoadedDocument) -> ParsedDoc {
+        let (html, extracted) = content::parse(&doc.content, &doc.data);
+
+        ParsedDoc {
+            url: doc.url,
+            resolved_url: doc.resolved_url,
+            html,
+            content: doc.content,
+            extracted,
+            selectors: self.selectors.clone(),
+            chains: self.chains.clone(),
+            sources: self.sources.clone(),
+            source: self.source.clone(),
+            post_processors: self.post_processors.clone(),
+            child_selectors: self.child_selectors.clone(),
+            properties: self.properties.clone(),
+            async_properties: self.async_properties.clone(),
+            crawl_properties: self.crawl_properties.clone(),
+            lookups: self.lookups.clone(),
+            extractors: self.extractors.clone(),
+            transforms: self.transforms.clone(),
+            expressions: self.expressions.clone(),
+            config: self.config.clone(),
+            fetcher: self.fetcher.clone(),
+            redirects: doc.redirects,
+            archived: doc.archived,
+            caching: doc.caching,
+            next_page: doc.next_page,
+            sinks: self.sinks.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+
     /// Adds some useful but generic selectors which includes:
     ///
     /// - `title`
@@ -217,67 +939,328 @@ impl ParsedDoc {
     /// - `styles`
     /// - `meta`
     pub fn add_generic_selectors(self) -> Self {
-        self.add_selector_all("links", "[href]")
+        let source = self.source.clone();
+        self.selector_source(SelectorSource::preset("generic"))
+            .add_selector_all("links", "[href]")
             .add_selector("title", "title")
             .add_selector_all("images", "img")
             .add_selector_all("scripts", "script")
             .add_selector_all("styles", "[rel=\'stylesheet\']")
             .add_selector_all("meta", "meta")
+            .selector_source(source)
+    }
+
+    /// sets the source which the selectors (and chains) added from now on are
+    /// attributed to; they're attributed to `SelectorSource::Programmatic`
+    /// unless it's set. The sources are included in the results when the
+    /// `ScrapeConfig` is set to `provenance`.
+    pub fn selector_source(mut self, source: SelectorSource) -> Self {
+        self.source = source;
+
+        self
+    }
+
+    /// where the selector (or chain) of the given name was defined
+    pub fn source_of(&self, name: &str) -> Option<&SelectorSource> {
+        self.sources.get(name)
     }
 
     /// Add a selector for an item where the expectation is there is only one
     /// (or more specifically _at most_ one)
-    pub fn add_selector(mut self, name: &str, selector: &str) -> Self {
-        let selector = Selector::parse(selector).unwrap();
-        self.selectors
-            .insert(name.to_string(), SelectorKind::Item(selector));
+    pub fn add_selector(self, name: &str, selector: &str) -> Self {
+        let selector = compile_selector(selector).unwrap();
+        self.insert_selector(name, SelectorKind::Item(selector))
+    }
 
-        self
+    /// Adds a selector as `add_selector()` does, returning an error rather than
+    /// panicking when the selector is invalid or -- with `DuplicatePolicy::Error`
+    /// -- its name is taken.
+    pub fn try_add_selector(self, name: &str, selector: &str) -> Result<Self, ScrapedError> {
+        let selector = compile_selector(selector)?;
+        self.check_name(name)?;
+
+        Ok(self.insert_selector(name, SelectorKind::Item(selector)))
     }
 
     /// Add a selector which is expect to bring a _list_ of results
-    pub fn add_selector_all(mut self, name: &str, selector: &str) -> Self {
-        let selector = Selector::parse(selector).unwrap();
-        self.selectors
-            .insert(name.to_string(), SelectorKind::List(selector));
+    pub fn add_selector_all(self, name: &str, selector: &str) -> Self {
+        let selector = compile_selector(selector).unwrap();
+        self.insert_selector(name, SelectorKind::List(selector))
+    }
+
+    /// adds a list selector as `add_selector_all()` does, returning an error
+    /// rather than panicking; see `try_add_selector()`
+    pub fn try_add_selector_all(self, name: &str, selector: &str) -> Result<Self, ScrapedError> {
+        let selector = compile_selector(selector)?;
+        self.check_name(name)?;
+
+        Ok(self.insert_selector(name, SelectorKind::List(selector)))
+    }
+
+    /// Add a selector for a `<table>` which is extracted into its headers and
+    /// rows; only the first match is extracted
+    pub fn add_table_selector(self, name: &str, selector: &str) -> Self {
+        let selector = compile_selector(selector).unwrap();
+        self.insert_selector(name, SelectorKind::Table(selector))
+    }
+
+    /// adds a table selector as `add_table_selector()` does, returning an error
+    /// rather than panicking; see `try_add_selector()`
+    pub fn try_add_table_selector(self, name: &str, selector: &str) -> Result<Self, ScrapedError> {
+        let selector = compile_selector(selector)?;
+        self.check_name(name)?;
+
+        Ok(self.insert_selector(name, SelectorKind::Table(selector)))
+    }
+
+    /// adds a step which post-processes the results of a list selector (e.g.,
+    /// sorting or removing duplicates); steps are applied in the order added
+    pub fn add_post_processor(mut self, selector: &str, step: PostProcessor) -> Self {
+        self.post_processors
+            .entry(selector.to_string())
+            .or_default()
+            .push(step);
 
         self
     }
 
+    /// Add a chained selector expression (e.g., `.card >> {title: .t, link: a@href}`)
+    /// whose structured result is included with the selections; see `Chain`
+    pub fn add_chain(self, name: &str, chain: &str) -> Self {
+        let chain = Chain::from_str(chain).unwrap();
+        self.insert_chain(name, chain)
+    }
+
+    /// adds a chained selector expression as `add_chain()` does, returning an
+    /// error rather than panicking; see `try_add_selector()`
+    pub fn try_add_chain(self, name: &str, chain: &str) -> Result<Self, ScrapedError> {
+        let chain = Chain::from_str(chain)?;
+        self.check_name(name)?;
+
+        Ok(self.insert_chain(name, chain))
+    }
+
+    /// adds the selector unless the name is taken and the first is kept
+    fn insert_selector(mut self, name: &str, selector: SelectorKind) -> Self {
+        if self.claim_name(name) {
+            self.selectors.insert(name.to_string(), selector);
+        }
+
+        self
+    }
+
+    /// adds the chain unless the name is taken and the first is kept
+    fn insert_chain(mut self, name: &str, chain: Chain) -> Self {
+        if self.claim_name(name) {
+            self.chains.insert(name.to_string(), chain);
+        }
+
+        self
+    }
+
+    /// returns a `ScrapedError::DuplicateSelector` when the name is taken and
+    /// the config's `DuplicatePolicy` is `Error`
+    fn check_name(&self, name: &str) -> Result<()> {
+        let taken = self.selectors.contains_key(name) || self.chains.contains_key(name);
+        if taken && self.config.duplicate_selectors == DuplicatePolicy::Error {
+            let first = self.sources.get(name).cloned().unwrap_or_default();
+            return Err(ScrapedError::DuplicateSelector {
+                name: name.to_string(),
+                first: first.to_string(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Whether a selector (or chain) can be added under the name; when the
+    /// name is taken the config's `DuplicatePolicy` decides whether the new
+    /// one replaces the old or is ignored. With the `Error` policy the
+    /// infallible `add_*` methods panic -- as they do for an invalid selector
+    /// -- while the `try_add_*` methods return the error (see `check_name()`).
+    fn claim_name(&mut self, name: &str) -> bool {
+        if !self.selectors.contains_key(name) && !self.chains.contains_key(name) {
+            self.sources.insert(name.to_string(), self.source.clone());
+            return true;
+        }
+        let first = self.sources.get(name).cloned().unwrap_or_default();
+
+        match self.config.duplicate_selectors {
+            DuplicatePolicy::Replace => {
+                warn!(
+                    "replacing the selector '{}' from {} with another of that name from {}",
+                    name, first, self.source
+                );
+                self.selectors.remove(name);
+                self.chains.remove(name);
+                self.sources.insert(name.to_string(), self.source.clone());
+                true
+            }
+            DuplicatePolicy::Error => panic!(
+                "{}",
+                ScrapedError::DuplicateSelector {
+                    name: name.to_string(),
+                    first: first.to_string(),
+                }
+            ),
+            DuplicatePolicy::KeepFirst => {
+                warn!(
+                    "ignoring the selector '{}' from {} as one of that name was added first by {}",
+                    name, self.source, first
+                );
+                false
+            }
+        }
+    }
+
     /// allows for the expression of which selectors are intended to point to a
     /// "child page" of the current page. Those designated selectors which have
     /// an `href` property as well as the correct "scope" will be scraped as well
     /// when the CLI's `--follow` flag is set or when the `results_graph()` function
     /// is called.
-    pub fn child_selectors(mut self, selectors: Vec<&str>, scope: ChildScope) -> Self {
-        let new_selectors: Vec<(String, ChildScope)> = selectors
-            .iter()
-            .map(|s| ((*s).to_string(), scope.clone()))
-            .collect();
+    pub fn child_selectors(self, selectors: Vec<&str>, scope: ChildScope) -> Self {
+        self.child_selectors_with_rules(selectors, scope, ChildRules::default())
+    }
 
-        new_selectors
-            .iter()
-            .for_each(|s| self.child_selectors.push(s.clone()));
+    /// like `child_selectors()` but the child URLs must also satisfy the rules
+    /// (e.g., be on the same host or under a path); each selector can be given
+    /// rules of its own
+    pub fn child_selectors_with_rules(
+        mut self,
+        selectors: Vec<&str>,
+        scope: ChildScope,
+        rules: ChildRules,
+    ) -> Self {
+        selectors.iter().for_each(|s| {
+            self.child_selectors
+                .push(((*s).to_string(), scope.clone(), rules.clone()))
+        });
+
+        self
+    }
+
+    /// sets the configuration used to request child pages when calling
+    /// `get_children()` or `results_graph()`
+    pub fn config(mut self, config: ScrapeConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+
+    /// A handle to the statistics of the pages requested while following child
+    /// pages; the handle can be polled while `results_graph()` is running.
+    pub fn stats(&self) -> ScrapeStats {
+        self.stats.clone()
+    }
+
+    /// sets how many child pages will be requested concurrently
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.config = self.config.concurrency(concurrency);
 
         self
     }
 
-    /// add a property callback to the configuration
-    pub fn add_property(
+    /// sets whether following child pages is aborted on the first failure
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.config = self.config.fail_fast(fail_fast);
+
+        self
+    }
+
+    /// add a property callback to the configuration; either a `PropertyCallback`
+    /// or a closure which captures its own state
+    pub fn add_property<F>(mut self, name: &str, callback: F) -> Self
+    where
+        F: Fn(&HashMap<String, ResultKind>) -> Value + Send + Sync + 'static,
+    {
+        self.properties.insert(name.to_string(), Arc::new(callback));
+
+        self
+    }
+
+    /// Add a property callback which must be awaited (e.g., to enrich the page
+    /// with another request). It's provided with a copy of the selections and
+    /// is only evaluated by the async methods -- `results_async()`,
+    /// `results_graph()` and a `ConcurrentScrape` -- not by `results()`.
+    pub fn add_async_property<F, Fut>(mut self, name: &str, callback: F) -> Self
+    where
+        F: Fn(HashMap<String, ResultKind>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Value> + Send + 'static,
+    {
+        let callback: AsyncProperty = Arc::new(move |sel| callback(sel).boxed());
+        self.async_properties.insert(name.to_string(), callback);
+
+        self
+    }
+
+    /// adds a callback which aggregates the results of every page in a crawl;
+    /// its value is included in the `CrawlReport` (see `report()`)
+    pub fn add_crawl_property(mut self, name: &str, callback: CrawlProperty) -> Self {
+        self.crawl_properties.insert(name.to_string(), callback);
+
+        self
+    }
+
+    /// joins the results of each page with the matching row of a lookup table,
+    /// adding the row's columns to the page's properties; see `Lookup`
+    pub fn add_lookup(mut self, lookup: Lookup) -> Self {
+        self.lookups.push(Arc::new(lookup));
+
+        self
+    }
+
+    /// adds a destination which receives each page scraped by `results_graph()`
+    pub fn add_sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+
+        self
+    }
+
+    /// adds a built-in extractor which emits a property of the same name; a
+    /// property callback with the same name takes precedence
+    pub fn add_extractor(mut self, extractor: Extractor) -> Self {
+        if !self.extractors.contains(&extractor) {
+            self.extractors.push(extractor);
+        }
+
+        self
+    }
+
+    /// adds a property which transforms the result of the `selector` with the
+    /// given name (e.g., parsing its text as a date); a property callback with
+    /// the same name takes precedence
+    pub fn add_transform(mut self, name: &str, selector: &str, transform: Transform) -> Self {
+        self.transforms
+            .insert(name.to_string(), (selector.to_string(), transform));
+
+        self
+    }
+
+    /// adds a property which is derived from the selections by a JMESPath-style
+    /// expression (e.g., `links[*].full_href`); see `Expression` for the syntax.
+    pub fn add_expression(self, name: &str, expression: &str) -> Self {
+        self.try_add_expression(name, expression).unwrap()
+    }
+
+    /// adds an expression as `add_expression()` does, returning an error rather
+    /// than panicking when the expression is invalid
+    pub fn try_add_expression(
         mut self,
         name: &str,
-        callback: fn(sel: &HashMap<String, ResultKind>) -> Value,
-    ) -> Self {
-        self.properties.insert(name.to_string(), callback);
+        expression: &str,
+    ) -> Result<Self, ScrapedError> {
+        let expression = Expression::from_str(expression)?;
+        self.expressions.insert(name.to_string(), expression);
 
-        self
+        Ok(self)
     }
 
     /// Gets the results of a _specific_ selector or property.
     ///
     /// **Note:** if a property of the same name of a selector exists then the
     /// the property will be given precedence; effectively masking the selector value
-    pub fn get(&self, name: &str) -> Result<Option<ResultKind>> {
+    pub fn get(&self, name: &str) -> Result<Option<ResultKind>, ScrapedError> {
         let selections = self.get_selection_results();
         let properties = self
             .get_property_results()
@@ -289,96 +1272,471 @@ impl ParsedDoc {
             match selections.get(name) {
                 Some(v) => Ok(Some(v.clone())),
                 // None => v,
-                _ => Err(eyre!(format!(
-                    "could not find the '{}' selector",
-                    name.to_string()
-                ))),
+                _ => Err(ScrapedError::Other {
+                    reason: format!("could not find the '{}' selector", name),
+                }),
             }
         }
     }
 
+    /// Returns the page's HTML with every element matched by a selector highlighted
+    /// in a color specific to that selector along with a legend of the selectors;
+    /// open it in a browser to visually validate what the selectors capture.
+    pub fn annotated_html(&self) -> String {
+        annotate::annotate(self)
+    }
+
     /// Returns a list of URL's which represent "child URLs". A child
     /// URL is determined by those _selectors_ which were deemed eligible
     /// when:
     /// 1. it is included in a call to `child_selectors(["foo", "bar"], scope)`
     /// 2. has a `href` property defined
     /// 3. the "scope" of the href first that defined in call to `child_selectors`
+    /// 4. it satisfies the selector's `ChildRules` (if any were given)
     pub fn get_child_urls(&self) -> Vec<Url> {
-        let mut children = Vec::new();
         trace!("getting the child URLs for {}", self.url);
+        let children: Vec<Url> = self
+            .plan_children()
+            .into_iter()
+            .filter(Candidate::is_included)
+            .filter_map(|c| c.url)
+            .collect();
+        trace!(
+            "got all child pages [{}] for \"{}\"",
+            children.len(),
+            self.url
+        );
+
+        children
+    }
 
+    /// Every link which the child selectors (and, with `locales`, the hreflang
+    /// alternates) found on the page along with whether it would be followed;
+    /// see `get_child_urls()` for the URLs which are. Only the robots.txt of
+    /// origins which have already been requested is consulted.
+    pub fn plan_children(&self) -> Vec<Candidate> {
+        let mut candidates = vec![];
         for (name, selector) in &self.selectors {
-            if let Some((_, scope)) = self //
+            if let Some((_, scope, rules)) = self //
                 .child_selectors
                 .iter()
-                .find(|(s, _)| s == name)
+                .find(|(s, _, _)| s == name)
             {
-                match selector {
-                    SelectorKind::List(v) => {
-                        // iterate through all elements
-                        self.html.select(v).for_each(|c| {
-                            if let Some(href) = get_selection(c, &self.url).href {
-                                if let Some(href) = validate_child_href(&href, scope, &self.url) {
-                                    children.push(href);
-                                }
-                            }
-                        });
-                    }
-                    SelectorKind::Item(v) => {
-                        if let Some(el) = self.html.select(v).next() {
-                            // if selector returned an element, get href prop (if avail)
-                            if let Some(href) = get_selection(el, &self.url).href {
-                                if let Some(v) = validate_child_href(&href, scope, &self.url) {
-                                    children.push(v)
-                                }
-                            }
-                        }
+                let elements: Vec<ElementRef> = match selector {
+                    SelectorKind::List(v) => self.html.select(v).collect(),
+                    SelectorKind::Table(_) => vec![],
+                    SelectorKind::Item(v) => self.html.select(v).take(1).collect(),
+                };
+                // the elements with an `href` (if avail) and the URL they
+                // resolve to when the scope allows them
+                let hrefs: Vec<(String, Option<Url>)> = elements
+                    .into_iter()
+                    .filter_map(|el| get_selection(el, &self.resolved_url).href)
+                    .map(|href| {
+                        let url = validate_child_href(&href, scope, &self.resolved_url);
+                        (href, url)
+                    })
+                    .collect();
+                let in_scope = hrefs.iter().filter_map(|(_, url)| url.clone()).collect();
+                let allowed: HashSet<Url> = rules
+                    .retain(&self.resolved_url, in_scope)
+                    .into_iter()
+                    .collect();
+                candidates.extend(hrefs.into_iter().map(|(href, url)| {
+                    let verdict = match &url {
+                        None => Verdict::OutOfScope,
+                        Some(url) if !allowed.contains(url) => Verdict::Rules,
+                        Some(_) => Verdict::Included,
+                    };
+                    Candidate {
+                        href,
+                        url,
+                        selector: Some(name.to_string()),
+                        verdict,
                     }
+                }));
+            }
+        }
+        if !self.config.locales.is_empty() {
+            self.alternates()
+                .into_iter()
+                .filter(|a| self.config.locales.iter().any(|l| a.matches(l)))
+                .for_each(|a| {
+                    candidates.push(Candidate {
+                        href: a.url.to_string(),
+                        url: Some(a.url),
+                        selector: None,
+                        verdict: Verdict::Included,
+                    })
+                });
+        }
+
+        // the same page under another URL (e.g., with a fragment or tracking
+        // parameters) -- or the page itself -- is only requested once
+        let mut seen: HashSet<Url> = [&self.url, &self.resolved_url]
+            .map(url_norm::canonicalize)
+            .into_iter()
+            .collect();
+        candidates.iter_mut().for_each(|c| {
+            let url = match (&c.url, c.verdict) {
+                (Some(url), Verdict::Included) => url,
+                _ => return,
+            };
+            if !self.config.safety.allows(url) {
+                c.verdict = Verdict::Unsafe;
+            } else if !robots::allows(url, &self.config) {
+                c.verdict = Verdict::Robots;
+            } else if !seen.insert(url_norm::canonicalize(url)) {
+                c.verdict = Verdict::Duplicate;
+            }
+        });
+
+        if self.config.sample.is_enabled() {
+            let included: Vec<&mut Candidate> =
+                candidates.iter_mut().filter(|c| c.is_included()).collect();
+            let urls: Vec<&Url> = included.iter().filter_map(|c| c.url.as_ref()).collect();
+            let sampled = self.config.sample.sample(&urls);
+            for (c, sampled) in included.into_iter().zip(sampled) {
+                if !sampled {
+                    c.verdict = Verdict::Sampled;
                 }
             }
         }
-        trace!(
-            "got all child pages [{}] for \"{}\"",
-            children.len(),
-            self.url
-        );
 
-        children
+        candidates
     }
 
-    /// Streams in the child HTML pages and parses them into `ParsedDoc`
-    /// structs.
-    pub async fn get_children(&self) -> Result<Vec<ParseResults>> {
+    /// Plans the page's child URLs as `plan_children()` does but -- when the
+    /// `ScrapeConfig` respects robots.txt -- requests the robots.txt of each
+    /// origin as a crawl would; none of the child pages are requested.
+    pub async fn dry_run(&self) -> Vec<Candidate> {
+        let mut candidates = self.plan_children();
+        if !self.config.respect_robots {
+            return candidates;
+        }
+
+        for candidate in candidates.iter_mut().filter(|c| c.is_included()) {
+            if let Some(url) = &candidate.url {
+                let robots = robots::load(url, &self.config, self.fetcher.0.as_ref()).await;
+                if !robots.allows(robots::agent(&self.config), url) {
+                    candidate.verdict = Verdict::Robots;
+                }
+            }
+        }
+
+        candidates
+    }
+
+    /// The alternate versions of this page for other languages and regions as
+    /// declared by `<link rel="alternate" hreflang="...">` elements.
+    pub fn alternates(&self) -> Vec<Alternate> {
+        lazy_static! {
+            static ref ALTERNATE: Selector =
+                Selector::parse("link[rel~='alternate'][hreflang][href]").unwrap();
+        }
+
+        self.html
+            .select(&ALTERNATE)
+            .filter_map(|el| {
+                let hreflang = el.value().attr("hreflang")?.trim();
+                let url = self
+                    .resolved_url
+                    .join(el.value().attr("href")?.trim())
+                    .ok()?;
+
+                Some(Alternate {
+                    hreflang: hreflang.to_string(),
+                    url,
+                })
+            })
+            .collect()
+    }
+
+    /// The canonical URL of this page as declared by a `<link rel="canonical">`.
+    pub fn canonical(&self) -> Option<Url> {
+        lazy_static! {
+            static ref CANONICAL: Selector =
+                Selector::parse("link[rel~='canonical'][href]").unwrap();
+        }
+
+        self.html
+            .select(&CANONICAL)
+            .find_map(|el| self.resolved_url.join(el.value().attr("href")?.trim()).ok())
+    }
+
+    /// Checks every `href` and `src` of the page with a `HEAD` request -- those
+    /// to the page's own host only when the `LinkAudit` is limited to it --
+    /// recording the status, content type and length of each; see `link_audit`.
+    pub async fn audit_links(&self) -> LinkReport {
+        let urls = link_audit::links(&self.html, &self.resolved_url, &self.config.link_audit);
+
+        link_audit::audit(urls, &self.config, self.fetcher.0.as_ref()).await
+    }
+
+    /// The OpenGraph, Twitter card and JSON-LD metadata of the page.
+    pub fn metadata(&self) -> Metadata {
+        Metadata::parse(&self.html)
+    }
+
+    /// The page which follows this one: the target of `rel="next"` in the
+    /// `Link` header of its response or -- when it has none -- of a
+    /// `<link rel="next">` or `<a rel="next">` in its HTML.
+    pub fn next_page(&self) -> Option<Url> {
+        self.next_page
+            .clone()
+            .or_else(|| pagination::next_from_html(&self.html, &self.resolved_url))
+    }
+
+    /// The documents embedded with `<iframe src>` which the `FramePolicy` allows.
+    pub fn frame_urls(&self) -> Vec<Url> {
+        lazy_static! {
+            static ref IFRAME: Selector = Selector::parse("iframe[src]").unwrap();
+        }
+
+        let mut urls: Vec<Url> = vec![];
+        self.html
+            .select(&IFRAME)
+            .filter_map(|el| self.resolved_url.join(el.value().attr("src")?.trim()).ok())
+            .filter(|url| self.config.frames.allows(url, &self.resolved_url))
+            .for_each(|url| {
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            });
+
+        urls
+    }
+
+    /// The results of this page along with those of the documents embedded in
+    /// its frames; frames which fail are recorded in `failures` and `blocked`.
+    async fn results_with_frames(&self) -> Result<ParseResults> {
+        let mut results = self.results_with_callbacks().await?;
+        let urls = self.frame_urls();
+        self.stats.queued(urls.len());
+        if self.config.warmup > 0 {
+            let fetcher = self.fetcher.0.as_ref();
+            let hosts =
+                warmup::busiest_hosts(urls.iter().map(|u| (u, fetcher)), self.config.warmup);
+            warmup::warm_up(hosts, &self.config).await;
+        }
+
+        let requests = stream::iter(urls).map(|url| async move {
+            let result = match self.load_child(&url).await {
+                Ok(frame) => frame.results_with_callbacks().await,
+                Err(e) => Err(e),
+            };
+            (url, result)
+        });
+        let frames = buffer(requests, &self.config);
+        futures::pin_mut!(frames);
+
+        while let Some((url, result)) = frames.next().await {
+            match result {
+                Err(e) if self.config.fail_fast => {
+                    return Err(e.wrap_err(format!("Failed to scrape the frame: {}", url)))
+                }
+                result => match PageOutcome::new(url, result) {
+                    PageOutcome::Ok(frame) => results.frames.push(*frame),
+                    PageOutcome::Failed(failure) => results.failures.push(failure),
+                    PageOutcome::Blocked(blocked) => results.blocked.push(blocked),
+                    PageOutcome::Cancelled(url) => results.cancelled.push(url),
+                },
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Returns the results of the current page along with those of the pages
+    /// which follow it -- by `rel="next"` in their `Link` header or HTML -- in
+    /// its `pages`; child pages aren't followed.
+    pub async fn results_with_pages(&self) -> Result<ParseResults, ScrapedError> {
+        Ok(self.results_and_pages().await?)
+    }
+
+    /// the results of `results_with_pages()`; the error is the `Report` which
+    /// the outcome of a page is built from
+    async fn results_and_pages(&self) -> Result<ParseResults> {
+        let mut results = self.results_with_frames().await?;
+        self.follow_pages(&mut results).await?;
+
+        Ok(results)
+    }
+
+    /// Scrapes the pages which follow this one -- up to the `Pagination`'s
+    /// `max_pages` -- into the results' `pages`, with the same configuration as
+    /// this page. Pagination stops at a page which fails, is recorded in
+    /// `failures` (or `blocked`) or which was already scraped.
+    async fn follow_pages(&self, results: &mut ParseResults) -> Result<()> {
+        let mut seen: HashSet<Url> = [self.url.clone(), self.resolved_url.clone()].into();
+        let mut next = self.next_page();
+        while let Some(url) = next.take() {
+            if results.pages.len() >= self.config.pagination.max_pages
+                || !seen.insert(url.clone())
+                || self.config.cancel.0.is_cancelled()
+            {
+                break;
+            }
+            self.stats.queued(1);
+            let result = match self.load_child(&url).await {
+                Ok(page) => {
+                    next = page.next_page();
+                    page.results_with_frames().await
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(e) if self.config.fail_fast => {
+                    return Err(e.wrap_err(format!("Failed to scrape the next page: {}", url)))
+                }
+                result => match PageOutcome::new(url, result) {
+                    PageOutcome::Ok(page) => results.pages.push(*page),
+                    PageOutcome::Failed(failure) => {
+                        results.failures.push(failure);
+                        break;
+                    }
+                    PageOutcome::Blocked(blocked) => {
+                        results.blocked.push(blocked);
+                        break;
+                    }
+                    PageOutcome::Cancelled(url) => {
+                        results.cancelled.push(url);
+                        break;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads and parses a single child page with the same configuration as this page.
+    async fn get_child(&self, url: &Url) -> Result<ParseResults> {
+        self.load_child(url).await?.results_with_frames().await
+    }
+
+    /// Loads a page with the same configuration as this page.
+    async fn load_child(&self, url: &Url) -> Result<ParsedDoc> {
+        self.stats.started(url);
+        let child = Document::from(url)
+            .config(self.config.clone())
+            .shared_fetcher(self.fetcher.clone());
+        let doc = match child.load().await {
+            Ok(doc) => {
+                self.stats.finished(url, doc.data.len());
+                doc
+            }
+            Err(e) => {
+                self.stats.failed(url, &e);
+                return Err(e);
+            }
+        };
+        trace!("finished loading child: {}", url);
+
+        Ok(self.inherit(doc))
+    }
+
+    /// Streams in the child HTML pages -- requesting pages as configured by the
+    /// `ScrapeConfig` -- and parses them with the same configuration as this page.
+    ///
+    /// Each child's outcome is reported individually so that one failed page doesn't
+    /// lose the rest of the crawl; an error is only returned when the `ScrapeConfig`
+    /// is set to `fail_fast`. Once the crawl is cancelled, the children which
+    /// weren't requested are reported as `PageOutcome::Cancelled`.
+    pub async fn get_child_outcomes(&self) -> Result<Vec<PageOutcome>, ScrapedError> {
+        Ok(self.child_outcomes().await?)
+    }
+
+    /// the outcomes of `get_child_outcomes()`; the error is the `Report` which
+    /// the page's own outcome is built from
+    async fn child_outcomes(&self) -> Result<Vec<PageOutcome>> {
         let urls = self.get_child_urls();
+        let urls = robots::retain_allowed(urls, &self.config, self.fetcher.0.as_ref()).await;
         trace!(
             "retrieving {} child URLs for {} over network",
             urls.len(),
             self.url
         );
-        let mut children: Vec<ParseResults> = vec![];
-        let mut stream = tokio_stream::iter(urls);
+        self.stats.queued(urls.len());
+
+        let limits = host_limit::HostLimits::new(&self.config);
+        let config = limits.buffer_config(&self.config, urls.len());
+        let limits = &limits;
+        let requests = stream::iter(urls).map(|url| async move {
+            let _permit = limits.acquire(&url).await;
+            // once the crawl is cancelled the pages which haven't started aren't requested
+            if self.config.cancel.0.is_cancelled() {
+                return (url, None);
+            }
+            let result = self.get_child(&url).await;
+            (url, Some(result))
+        });
+        let children = buffer(requests, &config);
+        futures::pin_mut!(children);
 
-        while let Some(v) = stream.next().await {
-            let doc = Document::from(&v);
-            let child = doc.load_document().await.unwrap().for_docs_rs();
-            trace!("getting {}", &child.url);
-            children.push(child.results()?);
-            trace!("finished loading child: {}", &v);
+        let mut outcomes = vec![];
+        while let Some((url, result)) = children.next().await {
+            let result = match result {
+                Some(result) => result,
+                None => {
+                    self.stats.cancelled(&url);
+                    outcomes.push(PageOutcome::Cancelled(url));
+                    continue;
+                }
+            };
+            match result {
+                Err(e) if self.config.fail_fast => {
+                    return Err(e.wrap_err(format!("Failed to scrape the child page: {}", url)))
+                }
+                result => outcomes.push(PageOutcome::new(url, result)),
+            }
         }
 
-        Ok(children)
+        Ok(outcomes)
+    }
+
+    /// The child pages which were successfully scraped; see `get_child_outcomes()`
+    /// for the pages which failed.
+    pub async fn get_children(&self) -> Result<Vec<ParseResults>, ScrapedError> {
+        Ok(self
+            .child_outcomes()
+            .await?
+            .into_iter()
+            .filter_map(|outcome| match outcome {
+                PageOutcome::Ok(child) => Some(*child),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// the elements matching a selector which the `TemplatePolicy` allows
+    fn select<'a>(&'a self, sel: &'a Selector) -> impl Iterator<Item = ElementRef<'a>> {
+        self.html
+            .select(sel)
+            .filter(|el| self.config.templates.allows(elements::template(el)))
+    }
+
+    /// the selection of an element with its text normalized by the `TextPolicy`
+    fn selection(&self, el: ElementRef) -> Selection {
+        let mut selection = get_selection(el, &self.resolved_url);
+        selection.text = selection.text.map(|t| self.config.text.apply(&t));
+
+        selection
     }
 
     /// merges all _selectors_ configured with the current page content to arrive at
     /// selection _results_.
     fn get_selection_results(&self) -> HashMap<String, ResultKind> {
+        if !self.content.is_html() {
+            return self.extracted.clone();
+        }
         let mut data: HashMap<String, ResultKind> = HashMap::new();
 
         self.selectors.iter().for_each(|(name, sel)| match sel {
             SelectorKind::Item(sel) => {
                 trace!("getting selection item for {}", &name);
-                if let Some(el) = self.html.select(sel).next() {
-                    let result = Box::new(get_selection(el, &self.url));
+                if let Some(el) = self.select(sel).next() {
+                    let result = Box::new(self.selection(el));
                     data.insert(name.to_string(), ResultKind::Item(result));
                 } else {
                     // skip
@@ -386,17 +1744,37 @@ impl ParsedDoc {
             }
             SelectorKind::List(sel) => {
                 trace!("getting selection list for {}", &name);
-                data.insert(
-                    name.to_string(),
-                    ResultKind::List(
-                        self.html
-                            .select(sel)
-                            .map(|el| get_selection(el, &self.url))
-                            .collect(),
-                    ),
-                );
+                let list = self.select(sel).map(|el| self.selection(el)).collect();
+                let list = self
+                    .post_processors
+                    .get(name)
+                    .into_iter()
+                    .flatten()
+                    .fold(list, |list, step| step.apply(list));
+                data.insert(name.to_string(), ResultKind::List(list));
+            }
+            SelectorKind::Table(sel) => {
+                trace!("getting table for {}", &name);
+                if let Some(el) = self.select(sel).next() {
+                    let table = Box::new(Table::parse(el, &self.config.text));
+                    data.insert(name.to_string(), ResultKind::Table(table));
+                }
             }
         });
+        self.chains.iter().for_each(|(name, chain)| {
+            trace!("evaluating the selector chain for {}", &name);
+            data.insert(
+                name.to_string(),
+                ResultKind::Property(chain.evaluate(&self.html)),
+            );
+        });
+        if self.config.hydration {
+            hydration::payloads(&self.html)
+                .into_iter()
+                .for_each(|(name, value)| {
+                    data.entry(name).or_insert(ResultKind::Property(value));
+                });
+        }
 
         data
     }
@@ -408,50 +1786,186 @@ impl ParsedDoc {
         let selections = self.get_selection_results();
         trace!("all document selections evaluted");
         let mut results: HashMap<String, Value> = HashMap::new();
+        self.extractors.iter().for_each(|e| {
+            results.insert(
+                e.name().to_string(),
+                e.extract(&self.html, &self.resolved_url),
+            );
+        });
+        self.transforms
+            .iter()
+            .for_each(|(name, (selector, transform))| {
+                let value = transform.apply_to(selections.get(selector));
+                results.insert(name.to_string(), self.config.format.apply(transform, value));
+            });
+        if !self.expressions.is_empty() {
+            let value = json!(selections);
+            self.expressions.iter().for_each(|(name, expression)| {
+                results.insert(name.to_string(), expression.evaluate(&value));
+            });
+        }
         trace!("current selections have been loaded; ready to evaluate property callbacks");
 
-        self.properties.keys().for_each(|k| {
+        self.properties.iter().for_each(|(k, cb)| {
             trace!("evaluating property '{}'", k);
-            let cb = self.properties.get(k);
-            if let Some(cb) = cb {
-                trace!("callback was found for '{}'", k);
-                results.insert(k.to_string(), cb(&selections));
-                debug!(
-                    "value for property '{}' inserted into results hashmap: {:?}",
-                    k,
-                    cb(&selections)
-                );
-            }
+            let value = cb(&selections);
+            debug!(
+                "value for property '{}' inserted into results hashmap: {:?}",
+                k, value
+            );
+            results.insert(k.to_string(), value);
         });
         trace!("all properties have been captured in hashmap");
 
         Ok(results)
     }
 
+    /// evaluates the async property callbacks concurrently
+    async fn get_async_property_results(&self) -> HashMap<String, Value> {
+        if self.async_properties.is_empty() {
+            return HashMap::new();
+        }
+        let selections = self.get_selection_results();
+        let values = self.async_properties.iter().map(|(k, cb)| {
+            trace!("evaluating async property '{}'", k);
+            cb(selections.clone()).map(move |value| (k.to_string(), value))
+        });
+
+        futures::future::join_all(values)
+            .await
+            .into_iter()
+            .collect()
+    }
+
     /// Returns all _selectors_ and _properties_ on the current page without recursing
-    /// into child pages.
-    pub fn results(&self) -> Result<ParseResults> {
+    /// into child pages. Async property callbacks are skipped; see `results_async()`.
+    pub fn results(&self) -> Result<ParseResults, ScrapedError> {
         trace!("getting results for {}", self.url);
-        let data = self.get_selection_results();
         let props = self.get_property_results()?;
+
+        Ok(self.page_results(props))
+    }
+
+    /// Returns all _selectors_ and _properties_ -- including those of async
+    /// property callbacks -- on the current page without recursing into child pages.
+    /// When the `ScrapeConfig` audits links, the page's links are checked into
+    /// its `link_report`.
+    pub async fn results_async(&self) -> Result<ParseResults, ScrapedError> {
+        Ok(self.results_with_callbacks().await?)
+    }
+
+    /// the results of `results_async()`; the error is the `Report` which the
+    /// outcome of a frame is built from
+    async fn results_with_callbacks(&self) -> Result<ParseResults> {
+        trace!("getting results for {}", self.url);
+        let mut props = self.get_property_results()?;
+        props.extend(self.get_async_property_results().await);
+        let mut results = self.page_results(props);
+        if self.config.link_audit.enabled && self.content.is_html() {
+            results.link_report = Some(self.audit_links().await);
+        }
+
+        Ok(results)
+    }
+
+    /// the results of the current page with the given property values
+    fn page_results(&self, props: HashMap<String, Value>) -> ParseResults {
+        let data = self.get_selection_results();
         trace!(
             "selectors and props have been retrieved for results: {:?}",
             props
         );
 
-        Ok(ParseResults {
+        let mut results = ParseResults {
             url: self.url.clone(),
+            resolved_url: match self.resolved_url != self.url {
+                true => Some(self.resolved_url.clone()),
+                false => None,
+            },
+            id: None,
             data,
             props,
+            sources: match self.config.provenance {
+                true => self.sources.clone(),
+                false => HashMap::new(),
+            },
             children: vec![],
-        })
+            frames: vec![],
+            content: self.content.clone(),
+            redirects: self.redirects.clone(),
+            archived: self.archived.clone(),
+            caching: self.caching.clone(),
+            alternates: self.alternates(),
+            canonical: self.canonical(),
+            duplicates: vec![],
+            metadata: match self.config.metadata && self.content.is_html() {
+                true => Some(self.metadata()),
+                false => None,
+            },
+            link_report: None,
+            pages: vec![],
+            failures: vec![],
+            blocked: vec![],
+            cancelled: vec![],
+            discovery: None,
+        };
+        self.lookups.iter().for_each(|l| l.enrich(&mut results));
+        results.id = self.config.id.id(&results);
+
+        results
+    }
+
+    /// Builds a `CrawlReport` for results which were produced with this document's
+    /// configuration (e.g., the results of `results_graph()`).
+    pub fn report(&self, results: &ParseResults) -> CrawlReport {
+        CrawlReport::new(results, self.selectors.keys()).aggregate(results, &self.crawl_properties)
     }
 
     /// Returns a tree of `ParseResults` starting with the given URL and
-    /// then following into the children nodes (one level deep).
-    pub async fn results_graph(&self) -> Result<ParseResults, Report> {
-        let mut current_page = self.results()?;
-        current_page.children = self.get_children().await?;
+    /// then following into the children nodes (one level deep). When the
+    /// `ScrapeConfig` has `locales`, the page's alternates for those locales are
+    /// followed as well.
+    /// When the `ScrapeConfig` follows frames, the documents embedded in each page
+    /// with `<iframe src>` are attached to the page's `frames`.
+    /// When it follows pagination, the pages which follow this one -- by
+    /// `rel="next"` in their `Link` header or HTML -- are attached to its
+    /// `pages`; their child pages aren't followed.
+    /// When it dedupes canonical pages, the children which are the same page --
+    /// by their `<link rel="canonical">` -- as the page or one another are merged
+    /// into a single record before they're sent to the sinks -- the page is then
+    /// sent once its children are scraped; see `canonical::dedupe_children()`.
+    /// Child pages which failed to load are recorded in `failures`, those which
+    /// were replaced by an anti-bot interstitial in `blocked` and those which
+    /// weren't requested because the crawl was cancelled in `cancelled`.
+    /// Each page is sent to the document's sinks as well.
+    #[instrument(name = "crawl", skip(self), fields(url = %self.url))]
+    pub async fn results_graph(&self) -> Result<ParseResults, ScrapedError> {
+        let mut current_page = match self.config.pagination.follow {
+            true => self.results_and_pages().await?,
+            false => self.results_with_frames().await?,
+        };
+        // the page is sent ahead of its children unless it may absorb them
+        let dedupe = self.config.dedupe_canonical;
+        if !dedupe {
+            let page = PageOutcome::Ok(Box::new(current_page.clone()));
+            sink::send(&self.sinks, &page).await;
+        }
+        let mut outcomes = self.child_outcomes().await?;
+        if dedupe {
+            outcomes = canonical::dedupe_children(&mut current_page, outcomes);
+            let page = PageOutcome::Ok(Box::new(current_page.clone()));
+            sink::send(&self.sinks, &page).await;
+        }
+        for outcome in outcomes {
+            sink::send(&self.sinks, &outcome).await;
+            match outcome {
+                PageOutcome::Ok(child) => current_page.children.push(*child),
+                PageOutcome::Failed(failure) => current_page.failures.push(failure),
+                PageOutcome::Blocked(blocked) => current_page.blocked.push(blocked),
+                PageOutcome::Cancelled(url) => current_page.cancelled.push(url),
+            }
+        }
+        sink::finish(&self.sinks).await;
 
         Ok(current_page)
     }
@@ -459,12 +1973,36 @@ impl ParsedDoc {
 
 impl From<LoadedDocument> for ParsedDoc {
     fn from(doc: LoadedDocument) -> Self {
+        let _span = info_span!("parse", url = %doc.url).entered();
+        let (html, extracted) = content::parse(&doc.content, &doc.data);
+
         ParsedDoc {
             url: doc.url,
-            html: Html::parse_document(&doc.data),
+            resolved_url: doc.resolved_url,
+            html,
+            content: doc.content,
+            extracted,
             selectors: HashMap::new(),
+            chains: HashMap::new(),
+            sources: HashMap::new(),
+            source: SelectorSource::default(),
+            post_processors: HashMap::new(),
             child_selectors: vec![],
             properties: HashMap::new(),
+            async_properties: HashMap::new(),
+            crawl_properties: HashMap::new(),
+            lookups: vec![],
+            extractors: vec![],
+            transforms: HashMap::new(),
+            expressions: HashMap::new(),
+            config: doc.config.clone(),
+            fetcher: doc.fetcher.clone(),
+            redirects: doc.redirects.clone(),
+            archived: doc.archived.clone(),
+            caching: doc.caching.clone(),
+            next_page: doc.next_page.clone(),
+            sinks: vec![],
+            stats: ScrapeStats::default(),
         }
     }
 }
@@ -0,0 +1,120 @@
+//! Bounds what's read of a response so that a URL which turns out to be a huge
+//! binary -- or any payload which isn't wanted -- is abandoned as soon as
+//! that's known rather than after it has been downloaded. See
+//! `Fetcher::fetch_within()`.
+
+use crate::{
+    error::ScrapedError,
+    fetch::{FetchedPage, HeaderMap},
+};
+use color_eyre::Result;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The size and content types of the responses which are accepted; by default
+/// any response is.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct ResponseLimits {
+    /// the most bytes of a response's body which are read; unbounded when not set
+    pub max_body_bytes: Option<u64>,
+    /// The content types (e.g. `text/html`, or `text/*` for any text) of the
+    /// successful responses which are accepted; any when empty. A response
+    /// without a `Content-Type` is accepted so that its content can be sniffed.
+    pub content_types: Vec<String>,
+}
+
+impl ResponseLimits {
+    pub fn new() -> Self {
+        ResponseLimits::default()
+    }
+
+    /// fails responses whose body is longer than the given number of bytes
+    pub fn max_body_bytes(mut self, max: u64) -> Self {
+        self.max_body_bytes = Some(max);
+
+        self
+    }
+
+    /// adds a content type (e.g. `text/html` or `text/*`) to those accepted
+    pub fn accept(mut self, content_type: &str) -> Self {
+        self.content_types.push(content_type.trim().to_lowercase());
+
+        self
+    }
+
+    /// whether every response is accepted
+    pub fn is_unbounded(&self) -> bool {
+        self.max_body_bytes.is_none() && self.content_types.is_empty()
+    }
+
+    /// Whether a response's `Content-Type` is accepted; its parameters (e.g.,
+    /// the `charset`) are ignored.
+    pub fn accepts(&self, content_type: Option<&str>) -> bool {
+        let content_type = match content_type {
+            Some(content_type) if !self.content_types.is_empty() => essence(content_type),
+            _ => return true,
+        };
+
+        self.content_types.iter().any(|accepted| {
+            let accepted = essence(accepted);
+            match accepted.strip_suffix("/*") {
+                Some(kind) => content_type.split('/').next() == Some(kind),
+                None => accepted == "*/*" || accepted == content_type,
+            }
+        })
+    }
+
+    /// Checks a response before its body is read: a successful response must
+    /// have an accepted content type and a response whose `Content-Length` is
+    /// larger than `max_body_bytes` fails at once.
+    pub fn check_headers(&self, url: &Url, status: u16, headers: &HeaderMap) -> Result<()> {
+        let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok());
+        if (200..300).contains(&status) && !self.accepts(content_type) {
+            return Err(ScrapedError::UnacceptedContentType {
+                url: url.clone(),
+                content_type: content_type.unwrap_or_default().to_string(),
+            }
+            .into());
+        }
+        let length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok()?.trim().parse().ok());
+
+        match length {
+            Some(length) => self.check_length(url, length),
+            None => Ok(()),
+        }
+    }
+
+    /// checks the number of bytes of a body which have been read so far
+    pub fn check_length(&self, url: &Url, read: u64) -> Result<()> {
+        match self.max_body_bytes {
+            Some(limit) if read > limit => Err(ScrapedError::TooLarge {
+                url: url.clone(),
+                limit,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// checks a page which was received in full
+    pub fn check(&self, page: &FetchedPage) -> Result<()> {
+        self.check_headers(&page.url, page.status, &page.headers)?;
+
+        self.check_length(&page.url, page.body.len() as u64)
+    }
+}
+
+/// the lowercase media type of a content type without its parameters
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
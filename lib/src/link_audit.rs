@@ -0,0 +1,210 @@
+//! Checks the links of a page -- the target of every `href` and `src`, be it
+//! another page, a stylesheet, an image or a download -- with a `HEAD` request,
+//! recording what the server answered in the page's `link_report`. Crawling a
+//! docs site with links audited makes for a broken-link checker. See
+//! `ScrapeConfig::link_audit` and `ParsedDoc::audit_links()`.
+
+use crate::{
+    error::ScrapedError,
+    fetch::{FetchedPage, Fetcher},
+    rate, ScrapeConfig,
+};
+use color_eyre::{Report, Result};
+use futures::{stream, StreamExt};
+use lazy_static::lazy_static;
+use reqwest::header::{HeaderName, CONTENT_LENGTH, LOCATION};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::{debug, instrument};
+use url::Url;
+
+lazy_static! {
+    static ref LINKED: Selector = Selector::parse("[href], [src]").unwrap();
+}
+
+/// Whether -- and which of -- the links of each page are audited; by default
+/// none are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct LinkAudit {
+    /// check the links of each page with a `HEAD` request
+    pub enabled: bool,
+    /// only check the links to the page's own host
+    pub same_host: bool,
+}
+
+impl LinkAudit {
+    /// sets whether the links of each page are checked
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+
+        self
+    }
+
+    /// sets whether only the links to the page's own host are checked
+    pub fn same_host(mut self, same_host: bool) -> Self {
+        self.same_host = same_host;
+
+        self
+    }
+}
+
+/// What the server answered for a link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LinkCheck {
+    /// the URL of the link, without its fragment
+    #[serde(serialize_with = "crate::util::url_to_string")]
+    pub url: Url,
+    /// the status of the response; none when the request failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// the value of the `Content-Type` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// the value of the `Content-Length` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_length: Option<u64>,
+    /// where the link redirects to when the response was a redirect
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::util::optional_url_to_string"
+    )]
+    pub location: Option<Url>,
+    /// a description of the error when the request failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LinkCheck {
+    /// the check of a link which the server answered
+    pub fn new(url: Url, page: &FetchedPage) -> LinkCheck {
+        let header = |name: HeaderName| page.headers.get(name).and_then(|v| v.to_str().ok());
+        let content_length = header(CONTENT_LENGTH)
+            .and_then(|v| v.trim().parse().ok())
+            .or_else(|| (!page.body.is_empty()).then_some(page.body.len() as u64));
+        let location = match page.status {
+            300..=399 => header(LOCATION).and_then(|l| page.url.join(l.trim()).ok()),
+            _ => None,
+        };
+
+        LinkCheck {
+            url,
+            status: Some(page.status),
+            content_type: page.content_type().map(str::to_string),
+            content_length,
+            location,
+            error: None,
+        }
+    }
+
+    /// the check of a link which couldn't be requested
+    pub fn failed(url: Url, error: &Report) -> LinkCheck {
+        LinkCheck {
+            url,
+            status: None,
+            content_type: None,
+            content_length: None,
+            location: None,
+            error: Some(format!("{:#}", error)),
+        }
+    }
+
+    /// whether the link couldn't be requested or the server answered with an error
+    pub fn is_broken(&self) -> bool {
+        self.error.is_some() || self.status.is_some_and(|status| status >= 400)
+    }
+}
+
+/// The checks of a page's links, in the order the links appear in the page.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LinkReport {
+    pub links: Vec<LinkCheck>,
+}
+
+impl LinkReport {
+    /// the links which couldn't be requested or which answered with an error
+    pub fn broken(&self) -> impl Iterator<Item = &LinkCheck> {
+        self.links.iter().filter(|link| link.is_broken())
+    }
+
+    /// the check of the given link
+    pub fn get(&self, url: &Url) -> Option<&LinkCheck> {
+        self.links.iter().find(|link| &link.url == url)
+    }
+}
+
+/// The targets of every `href` and `src` in the HTML, resolved against the
+/// page's URL; links which aren't HTTP(S) (e.g., `mailto:`) are skipped, as are
+/// a link's repeats and -- when the audit is limited to the page's host -- the
+/// links to other hosts.
+pub fn links(html: &Html, base: &Url, audit: &LinkAudit) -> Vec<Url> {
+    let mut seen = HashSet::new();
+
+    html.select(&LINKED)
+        .flat_map(|el| {
+            ["href", "src"]
+                .into_iter()
+                .filter_map(move |a| el.value().attr(a))
+        })
+        .map(str::trim)
+        .filter(|link| !link.is_empty() && !link.starts_with('#'))
+        .filter_map(|link| base.join(link).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .filter(|url| !audit.same_host || url.host_str() == base.host_str())
+        .map(|mut url| {
+            url.set_fragment(None);
+            url
+        })
+        .filter(|url| seen.insert(url.clone()))
+        .collect()
+}
+
+/// Checks each link with a `HEAD` request, as concurrently as the config
+/// allows; see `check()`.
+#[instrument(name = "link_audit", skip_all, fields(links = urls.len()))]
+pub async fn audit(urls: Vec<Url>, config: &ScrapeConfig, fetcher: &dyn Fetcher) -> LinkReport {
+    let links = stream::iter(urls)
+        .map(|url| check(url, config, fetcher))
+        .buffered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    LinkReport { links }
+}
+
+/// Checks a link with a `HEAD` request -- or a `GET` request when the server
+/// doesn't allow `HEAD` -- which is subject to the config's safety rules,
+/// rate limit and timeout; redirects aren't followed but recorded in the
+/// check's `location`.
+pub async fn check(url: Url, config: &ScrapeConfig, fetcher: &dyn Fetcher) -> LinkCheck {
+    match request(&url, config, fetcher).await {
+        Ok(page) => LinkCheck::new(url, &page),
+        Err(e) => {
+            debug!("failed to check the link {}: {:#}", url, e);
+            LinkCheck::failed(url, &e)
+        }
+    }
+}
+
+/// requests the link, falling back to `GET` when `HEAD` isn't allowed
+async fn request(url: &Url, config: &ScrapeConfig, fetcher: &dyn Fetcher) -> Result<FetchedPage> {
+    config.safety.check_resolved(url).await?;
+    let headers = config.request_headers_for(url)?;
+    rate::acquire(url, config).await;
+
+    let request = async {
+        let page = fetcher.head(url, &headers).await?;
+        match page.status {
+            405 | 501 => fetcher.fetch(url, &headers).await,
+            _ => Ok(page),
+        }
+    };
+    match config.timeout {
+        Some(after) => tokio::time::timeout(after, request)
+            .await
+            .unwrap_or_else(|_| Err(ScrapedError::timeout(url, after))),
+        None => request.await,
+    }
+}
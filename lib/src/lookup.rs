@@ -0,0 +1,130 @@
+use crate::{results::ParseResults, url_norm::canonicalize};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fs, path::Path};
+use url::Url;
+
+/// What a page is matched to a row of a `Lookup` on
+#[derive(Debug, Clone, PartialEq)]
+pub enum LookupKey {
+    /// the page's URL; URLs are compared in their canonical form
+    Url,
+    /// the value of one of the page's properties or selections (e.g., a SKU)
+    Field(String),
+}
+
+/// A table -- loaded from CSV or JSON -- whose rows are joined to the results
+/// of the pages they match, adding each of the row's columns as a property.
+#[derive(Debug, Clone)]
+pub struct Lookup {
+    key: LookupKey,
+    rows: HashMap<String, Map<String, Value>>,
+}
+
+impl Lookup {
+    /// a lookup whose rows are keyed by the value of their `column`
+    pub fn new(rows: Vec<Map<String, Value>>, column: &str, key: LookupKey) -> Result<Lookup> {
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                let value = row
+                    .get(column)
+                    .and_then(as_text)
+                    .ok_or_else(|| eyre!("a row of the lookup has no '{}' column", column))?;
+                Ok((normalize(&key, &value), row))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        Ok(Lookup { key, rows })
+    }
+
+    /// a lookup from CSV with a header row
+    pub fn from_csv(data: &str, column: &str, key: LookupKey) -> Result<Lookup> {
+        let mut reader = csv::Reader::from_reader(data.as_bytes());
+        let headers = reader.headers()?.clone();
+        let rows = reader
+            .records()
+            .map(|record| {
+                Ok(headers
+                    .iter()
+                    .zip(record?.iter())
+                    .map(|(h, v)| (h.to_string(), Value::String(v.to_string())))
+                    .collect())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Lookup::new(rows, column, key)
+    }
+
+    /// a lookup from a JSON array of objects
+    pub fn from_json(data: &str, column: &str, key: LookupKey) -> Result<Lookup> {
+        let rows: Vec<Map<String, Value>> =
+            serde_json::from_str(data).context("a JSON lookup must be an array of objects")?;
+
+        Lookup::new(rows, column, key)
+    }
+
+    /// loads a lookup from a `.csv` or `.json` file
+    pub fn load(path: &Path, column: &str, key: LookupKey) -> Result<Lookup> {
+        let data = fs::read_to_string(path)
+            .context(format!("Failed to read the lookup: {}", path.display()))?;
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+
+        match ext.to_lowercase().as_str() {
+            "csv" => Lookup::from_csv(&data, column, key),
+            "json" => Lookup::from_json(&data, column, key),
+            _ => Err(eyre!(
+                "the lookup '{}' must be a .csv or .json file",
+                path.display()
+            )),
+        }
+        .context(format!("Failed to parse the lookup: {}", path.display()))
+    }
+
+    /// the row which matches a page, if any
+    pub fn row(&self, page: &ParseResults) -> Option<&Map<String, Value>> {
+        let value = match &self.key {
+            LookupKey::Url => Some(page.url.to_string()),
+            LookupKey::Field(name) => page.field(name),
+        }?;
+
+        self.rows.get(&normalize(&self.key, &value))
+    }
+
+    /// adds the columns of the matching row to the page's properties; properties
+    /// which the page already has are left as they are
+    pub fn enrich(&self, page: &mut ParseResults) {
+        if let Some(row) = self.row(page) {
+            row.iter().for_each(|(column, value)| {
+                page.props
+                    .entry(column.to_string())
+                    .or_insert_with(|| value.clone());
+            });
+        }
+    }
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// URLs are keyed by their canonical form so that e.g. tracking parameters
+/// don't prevent a match
+fn normalize(key: &LookupKey, value: &str) -> String {
+    match key {
+        LookupKey::Url => Url::parse(value.trim())
+            .map(|url| canonicalize(&url).to_string())
+            .unwrap_or_else(|_| value.trim().to_string()),
+        LookupKey::Field(_) => value.trim().to_string(),
+    }
+}
@@ -0,0 +1,152 @@
+//! Parses the structured metadata which pages publish for link previews and
+//! search engines: OpenGraph and Twitter card `<meta>` tags and JSON-LD blocks.
+
+use lazy_static::lazy_static;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+lazy_static! {
+    static ref META: Selector = Selector::parse("meta[content]").unwrap();
+    static ref JSON_LD: Selector = Selector::parse("script[type='application/ld+json']").unwrap();
+}
+
+/// The OpenGraph (`og:*`) properties of a page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OpenGraph {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// the remaining properties without their `og:` prefix (e.g. `image:width`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub other: BTreeMap<String, String>,
+}
+
+/// The Twitter card (`twitter:*`) properties of a page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TwitterCard {
+    /// the kind of card, e.g. `summary_large_image`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub card: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// the `@handle` of the site
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    /// the `@handle` of the author
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator: Option<String>,
+    /// the remaining properties without their `twitter:` prefix
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub other: BTreeMap<String, String>,
+}
+
+/// The structured metadata of a page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Metadata {
+    pub open_graph: OpenGraph,
+    pub twitter: TwitterCard,
+    /// the JSON-LD objects of the page; `@graph` containers are flattened into
+    /// their items and blocks which aren't valid JSON are skipped
+    pub json_ld: Vec<Value>,
+}
+
+impl Metadata {
+    /// parses the metadata of a page
+    pub fn parse(html: &Html) -> Metadata {
+        let mut metadata = Metadata::default();
+
+        html.select(&META).for_each(|el| {
+            let el = el.value();
+            let key = el.attr("property").or_else(|| el.attr("name"));
+            let content = el.attr("content").unwrap_or_default().trim().to_string();
+            match key.map(|k| k.trim().to_lowercase()) {
+                Some(key) if key.starts_with("og:") => metadata.open_graph.set(&key[3..], content),
+                Some(key) if key.starts_with("twitter:") => {
+                    metadata.twitter.set(&key[8..], content)
+                }
+                _ => {}
+            }
+        });
+
+        metadata.json_ld = html
+            .select(&JSON_LD)
+            .filter_map(|el| serde_json::from_str::<Value>(&el.text().collect::<String>()).ok())
+            .flat_map(|value| match value {
+                Value::Array(items) => items,
+                Value::Object(mut o) if o.contains_key("@graph") => match o.remove("@graph") {
+                    Some(Value::Array(items)) => items,
+                    Some(item) => vec![item],
+                    None => vec![],
+                },
+                value => vec![value],
+            })
+            .collect();
+
+        metadata
+    }
+
+    /// the JSON-LD objects of the given `@type` (e.g. `Product` or `Article`)
+    pub fn json_ld_of_type<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Value> {
+        self.json_ld.iter().filter(move |v| match v.get("@type") {
+            Some(Value::String(t)) => t == kind,
+            Some(Value::Array(types)) => types.iter().any(|t| t == kind),
+            _ => false,
+        })
+    }
+}
+
+impl OpenGraph {
+    /// sets a property; the first value of a repeated property wins
+    fn set(&mut self, key: &str, value: String) {
+        let field = match key {
+            "title" => &mut self.title,
+            "description" => &mut self.description,
+            "type" => &mut self.type_,
+            "url" => &mut self.url,
+            "image" | "image:url" => &mut self.image,
+            "site_name" => &mut self.site_name,
+            "locale" => &mut self.locale,
+            _ => {
+                self.other.entry(key.to_string()).or_insert(value);
+                return;
+            }
+        };
+        field.get_or_insert(value);
+    }
+}
+
+impl TwitterCard {
+    /// sets a property; the first value of a repeated property wins
+    fn set(&mut self, key: &str, value: String) {
+        let field = match key {
+            "card" => &mut self.card,
+            "title" => &mut self.title,
+            "description" => &mut self.description,
+            "image" | "image:src" => &mut self.image,
+            "site" => &mut self.site,
+            "creator" => &mut self.creator,
+            _ => {
+                self.other.entry(key.to_string()).or_insert(value);
+                return;
+            }
+        };
+        field.get_or_insert(value);
+    }
+}
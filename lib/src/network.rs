@@ -0,0 +1,87 @@
+//! The options of the connections made for requests: which IP version is
+//! preferred and which local address requests are made from (e.g., on
+//! multi-homed crawl servers or to match an egress allow-list).
+
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+/// Which IP version is tried first when a host has both IPv4 and IPv6
+/// addresses; the other version is still tried when the first can't connect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum IpPreference {
+    /// the order of the resolver's addresses
+    #[default]
+    System,
+    Ipv4,
+    Ipv6,
+}
+
+impl IpPreference {
+    /// orders addresses so that those of the preferred version come first
+    pub fn sort(&self, addrs: &mut [SocketAddr]) {
+        match self {
+            IpPreference::System => {}
+            IpPreference::Ipv4 => addrs.sort_by_key(|a| a.is_ipv6()),
+            IpPreference::Ipv6 => addrs.sort_by_key(|a| a.is_ipv4()),
+        }
+    }
+}
+
+/// The options of the connections made for requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// the IP version which is tried first
+    pub prefer: IpPreference,
+    /// The local address requests are made from. To make requests through a
+    /// network interface, use the interface's address; binding to an IPv4
+    /// address only connects to IPv4 hosts (and likewise for IPv6).
+    pub local_address: Option<IpAddr>,
+}
+
+impl NetworkConfig {
+    /// sets the IP version which is tried first
+    pub fn prefer(mut self, prefer: IpPreference) -> Self {
+        self.prefer = prefer;
+
+        self
+    }
+
+    /// sets the local address requests are made from
+    pub fn local_address(mut self, addr: IpAddr) -> Self {
+        self.local_address = Some(addr);
+
+        self
+    }
+}
+
+/// A `reqwest` resolver which orders the addresses of another resolver -- or
+/// of the system's when there's none -- by the `IpPreference`.
+#[derive(Clone)]
+pub(crate) struct PreferringResolver {
+    pub resolver: Option<Arc<dyn Resolve>>,
+    pub prefer: IpPreference,
+}
+
+impl Resolve for PreferringResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let this = self.clone();
+        Box::pin(async move {
+            let mut addrs: Vec<SocketAddr> = match &this.resolver {
+                Some(resolver) => resolver.resolve(name).await?.collect(),
+                None => tokio::net::lookup_host((name.as_str(), 0)).await?.collect(),
+            };
+            this.prefer.sort(&mut addrs);
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
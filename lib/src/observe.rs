@@ -0,0 +1,55 @@
+//! Hooks which are called as the pages of a crawl are requested, so that its
+//! progress can be shown (e.g., as a progress bar) or fed to a metrics system.
+//! Observers are registered with a crawl's `ScrapeStats` -- see
+//! `ScrapeStats::observe()` -- and so see the pages of every document sharing
+//! it, including the child pages which are followed.
+
+use color_eyre::Report;
+use serde::Serialize;
+use url::Url;
+
+/// The counts of a crawl's pages at the time an `Observer` is called.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    /// pages which are waiting to be requested
+    pub queued: usize,
+    /// pages which have been requested but have not yet completed
+    pub in_flight: usize,
+    /// pages which were successfully loaded
+    pub done: usize,
+    /// pages which failed to load
+    pub errors: usize,
+}
+
+impl Progress {
+    /// the pages which have completed, whether or not they were loaded
+    pub fn completed(&self) -> usize {
+        self.done + self.errors
+    }
+
+    /// every page which has been queued so far
+    pub fn total(&self) -> usize {
+        self.queued + self.in_flight + self.completed()
+    }
+}
+
+/// Called as a crawl's pages are queued, requested and loaded. The calls are
+/// made on the crawl's tasks -- while its pages are in flight -- so they should
+/// return quickly; each hook does nothing unless it's implemented.
+pub trait Observer: Send + Sync {
+    /// pages have been added to the queue of pages to request
+    fn on_queued(&self, _pages: usize, _progress: Progress) {}
+
+    /// a page has been taken off the queue and requested
+    fn on_page_started(&self, _url: &Url, _progress: Progress) {}
+
+    /// a page was taken off the queue but wasn't requested as the crawl was
+    /// cancelled
+    fn on_cancelled(&self, _url: &Url, _progress: Progress) {}
+
+    /// a page was loaded with its body of the given length
+    fn on_page_finished(&self, _url: &Url, _bytes: usize, _progress: Progress) {}
+
+    /// a page failed to load
+    fn on_error(&self, _url: &Url, _error: &Report, _progress: Progress) {}
+}
@@ -0,0 +1,104 @@
+//! Follows the pages of paginated listings and APIs: the next page is given
+//! either by the response's `Link: <...>; rel="next"` header (as many APIs do)
+//! or by a `<link rel="next">` -- or `<a rel="next">` -- in the page's HTML.
+//! See `ParsedDoc::results_graph()`, which attaches the pages which follow a
+//! document to its `pages` when the `ScrapeConfig` follows them.
+
+use crate::fetch::HeaderMap;
+use lazy_static::lazy_static;
+use reqwest::header::LINK;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// the default maximum number of pages followed after the first
+pub const MAX_PAGES: usize = 10;
+
+lazy_static! {
+    static ref NEXT: Selector =
+        Selector::parse("link[rel~='next'][href], a[rel~='next'][href]").unwrap();
+}
+
+/// Whether -- and how far -- the pages which follow a document are followed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct Pagination {
+    /// follow the `rel="next"` pages of the document
+    pub follow: bool,
+    /// the most pages followed after the first
+    pub max_pages: usize,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Pagination {
+            follow: false,
+            max_pages: MAX_PAGES,
+        }
+    }
+}
+
+impl Pagination {
+    /// sets whether the next pages are followed
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+
+        self
+    }
+
+    /// sets the most pages followed after the first
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+
+        self
+    }
+}
+
+/// The next page given by the `Link` headers of a response -- the target of a
+/// link whose `rel` includes `next` -- resolved against the response's URL.
+pub fn next_from_headers(headers: &HeaderMap, base: &Url) -> Option<Url> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(links)
+        .find(|(_, rels)| rels.iter().any(|rel| rel == "next"))
+        .and_then(|(target, _)| base.join(target).ok())
+}
+
+/// the next page given by a `<link rel="next">` or `<a rel="next">` in the HTML
+pub fn next_from_html(html: &Html, base: &Url) -> Option<Url> {
+    html.select(&NEXT)
+        .find_map(|el| base.join(el.value().attr("href")?.trim()).ok())
+}
+
+/// The targets of the links in a `Link` header (e.g., `<https://api.test/?page=2>;
+/// rel="next", <https://api.test/?page=9>; rel="last"`) along with their
+/// lowercase relations; a link may have several (e.g., `rel="next archives"`).
+fn links(header: &str) -> Vec<(&str, Vec<String>)> {
+    let mut links = vec![];
+    let mut rest = header;
+    while let Some(start) = rest.find('<') {
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let target = rest[start + 1..end].trim();
+        rest = &rest[end + 1..];
+        let params = &rest[..rest.find('<').unwrap_or(rest.len())];
+        let rels = params
+            .split(';')
+            .filter_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("rel")
+                    .then(|| value.trim().trim_end_matches(',').trim().trim_matches('"'))
+            })
+            .flat_map(|value| value.split_whitespace().map(str::to_lowercase))
+            .collect();
+        links.push((target, rels));
+    }
+
+    links
+}
@@ -0,0 +1,243 @@
+//! Summarizes what a site asks of crawlers -- its robots.txt, sitemaps, the
+//! meta robots of its homepage and the rate limits its responses advertise --
+//! so that a polite crawl of it can be configured before it's run.
+
+use crate::{
+    fetch::{FetchedPage, Fetcher, HeaderMap},
+    rate::RateLimit,
+    redirect,
+    retry::retry_after,
+    robots::{self, RobotsTxt},
+    sitemap::Sitemap,
+    ScrapeConfig,
+};
+use color_eyre::{eyre::WrapErr, Result};
+use lazy_static::lazy_static;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use std::{collections::BTreeMap, time::Duration};
+use tracing::debug;
+use url::Url;
+
+/// the rate a site is crawled at when it doesn't ask for one
+pub const POLITE_RATE: f64 = 1.0;
+
+/// the response headers which advertise a rate limit, in their lowercase form
+pub const RATE_LIMIT_HEADERS: [&str; 7] = [
+    "ratelimit-limit",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+    "ratelimit-policy",
+    "x-ratelimit-limit",
+    "x-ratelimit-remaining",
+    "x-ratelimit-reset",
+];
+
+lazy_static! {
+    static ref META: Selector = Selector::parse("meta[name][content]").unwrap();
+}
+
+/// What a site asks of crawlers, as requested from its homepage. When
+/// serialized, durations are human readable strings such as "2s".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SitePolicy {
+    /// the site's homepage
+    #[serde(serialize_with = "crate::util::url_to_string")]
+    pub homepage: Url,
+    /// the status the homepage responded with
+    pub homepage_status: u16,
+    /// the status the robots.txt responded with; none when it couldn't be requested
+    pub robots_status: Option<u16>,
+    /// whether the robots.txt allows the homepage to be requested
+    pub homepage_allowed: bool,
+    /// the paths which the robots.txt disallows, as they were written
+    pub disallowed: Vec<String>,
+    /// the pause the robots.txt asks for between requests
+    #[serde(with = "humantime_serde")]
+    pub crawl_delay: Option<Duration>,
+    /// the sitemaps listed by the robots.txt or -- when it lists none -- the
+    /// `/sitemap.xml` when there is one
+    #[serde(serialize_with = "crate::util::urls_to_strings")]
+    pub sitemaps: Vec<Url>,
+    /// the directives of the homepage's meta robots and `X-Robots-Tag`
+    /// header (e.g., `noindex` or `nofollow`), in lowercase
+    pub meta_robots: Vec<String>,
+    /// the rate limit headers of the homepage's response, keyed by their
+    /// lowercase names
+    pub rate_limit_headers: BTreeMap<String, String>,
+    /// the pause the homepage's `Retry-After` header asks for
+    #[serde(with = "humantime_serde")]
+    pub retry_after: Option<Duration>,
+}
+
+impl SitePolicy {
+    /// Requests the robots.txt, the homepage and -- when the robots.txt lists
+    /// no sitemaps -- the `/sitemap.xml` of the URL's site with the config's
+    /// headers and user agent. Only a failure to request the homepage is an
+    /// error.
+    pub async fn inspect(
+        url: &Url,
+        config: &ScrapeConfig,
+        fetcher: &dyn Fetcher,
+    ) -> Result<SitePolicy> {
+        let homepage = url.join("/").context(format!("{} has no homepage", url))?;
+
+        let robots_page = request(&homepage.join("/robots.txt")?, config, fetcher).await;
+        let robots_status = robots_page.as_ref().ok().map(|page| page.status);
+        let robots = match &robots_page {
+            Ok(page) if (200..300).contains(&page.status) => RobotsTxt::parse(&page.text()),
+            _ => RobotsTxt::default(),
+        };
+
+        let page = request(&homepage, config, fetcher)
+            .await
+            .context(format!("Failed to request the homepage {}", homepage))?;
+        let agent = robots::agent(config);
+        let mut sitemaps = robots.sitemaps().to_vec();
+        if sitemaps.is_empty() {
+            let sitemap = homepage.join("/sitemap.xml")?;
+            if is_sitemap(request(&sitemap, config, fetcher).await.ok()) {
+                sitemaps.push(sitemap);
+            }
+        }
+
+        Ok(SitePolicy {
+            homepage_status: page.status,
+            robots_status,
+            homepage_allowed: robots.allows(agent, &homepage),
+            disallowed: robots
+                .disallowed(agent)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            crawl_delay: robots.crawl_delay(agent),
+            sitemaps,
+            meta_robots: meta_robots(&page),
+            rate_limit_headers: rate_limit_headers(&page.headers),
+            retry_after: retry_after(&page.headers),
+            homepage,
+        })
+    }
+
+    /// whether the homepage asks that its links aren't followed
+    pub fn nofollow(&self) -> bool {
+        self.meta_robots
+            .iter()
+            .any(|d| d == "nofollow" || d == "none")
+    }
+
+    /// The rate at which the site should be crawled: the robots.txt's
+    /// `Crawl-delay`, else the quota of a `RateLimit-Policy` header (e.g.,
+    /// `100;w=60` is 100 requests a minute), else the `Retry-After`, else
+    /// `POLITE_RATE` requests per second.
+    pub fn rate_limit(&self) -> RateLimit {
+        if let Some(delay) = self.crawl_delay {
+            return RateLimit::interval(delay);
+        }
+        let quota = self
+            .rate_limit_headers
+            .get("ratelimit-policy")
+            .map(String::as_str)
+            .and_then(quota_per_second);
+
+        match (quota, self.retry_after) {
+            (Some(rate), _) => RateLimit::per_second(rate),
+            (None, Some(pause)) if !pause.is_zero() => RateLimit::interval(pause),
+            _ => RateLimit::per_second(POLITE_RATE),
+        }
+    }
+
+    /// a starter config for a polite crawl of the site: the config's with its
+    /// robots.txt respected, the `rate_limit()` and a request to the site at a time
+    pub fn starter_config(&self, config: &ScrapeConfig) -> ScrapeConfig {
+        config
+            .clone()
+            .respect_robots(true)
+            .rate_limit(self.rate_limit())
+            .max_per_host(1)
+    }
+}
+
+/// requests a page with the config's headers, following its redirects
+async fn request(url: &Url, config: &ScrapeConfig, fetcher: &dyn Fetcher) -> Result<FetchedPage> {
+    config.safety.check_resolved(url).await?;
+    let request = |url: Url| async move {
+        let headers = config.request_headers_for(&url)?;
+        fetcher.fetch(&url, &headers).await
+    };
+
+    Ok(redirect::follow(url, config, request).await?.0)
+}
+
+/// whether the response is a sitemap (or sitemap index) with at least one entry
+fn is_sitemap(page: Option<FetchedPage>) -> bool {
+    let page = match page {
+        Some(page) if (200..300).contains(&page.status) => page,
+        _ => return false,
+    };
+    match Sitemap::parse(&page.text()) {
+        Sitemap::Urls(urls) | Sitemap::Index(urls) => !urls.is_empty(),
+    }
+}
+
+/// the directives of the page's `<meta name="robots">` and `X-Robots-Tag`
+/// header, in the order they appear and without duplicates
+fn meta_robots(page: &FetchedPage) -> Vec<String> {
+    let html = Html::parse_document(&page.text());
+    let meta = html
+        .select(&META)
+        .filter(|el| {
+            el.value()
+                .attr("name")
+                .is_some_and(|name| name.eq_ignore_ascii_case("robots"))
+        })
+        .filter_map(|el| el.value().attr("content").map(String::from));
+    let headers = page
+        .headers
+        .get_all("x-robots-tag")
+        .iter()
+        .filter_map(|value| value.to_str().ok().map(String::from));
+
+    let mut directives: Vec<String> = vec![];
+    for directive in meta.chain(headers).flat_map(|content| {
+        content
+            .split(',')
+            .map(|d| d.trim().to_lowercase())
+            .collect::<Vec<_>>()
+    }) {
+        if !directive.is_empty() && !directives.contains(&directive) {
+            directives.push(directive);
+        }
+    }
+    debug!(
+        "the homepage {} has the meta robots {:?}",
+        page.url, directives
+    );
+
+    directives
+}
+
+/// the headers of a response which advertise a rate limit (see
+/// `RATE_LIMIT_HEADERS`), keyed by their lowercase names
+pub fn rate_limit_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    RATE_LIMIT_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(*name)?.to_str().ok()?;
+            Some((name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// the requests per second of the first quota of a `RateLimit-Policy` header
+/// (e.g., `100;w=60`); none when it has no window
+fn quota_per_second(policy: &str) -> Option<f64> {
+    let mut params = policy.split(',').next()?.split(';').map(str::trim);
+    let quota: f64 = params.next()?.parse().ok()?;
+    let window: f64 = params
+        .find_map(|param| param.strip_prefix("w="))?
+        .parse()
+        .ok()?;
+
+    (quota > 0.0 && window > 0.0).then_some(quota / window)
+}
@@ -0,0 +1,116 @@
+//! Post-processors tidy up the results of list selectors -- sorting, removing
+//! duplicates and filtering -- before they're serialized.
+
+use crate::selection::Selection;
+use color_eyre::{eyre::WrapErr, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+
+fn text() -> String {
+    "text".to_string()
+}
+
+fn href() -> String {
+    "href".to_string()
+}
+
+/// A step which post-processes the selections of a list selector. Each step
+/// works on a `field` of the selections: a property of a `Selection` such as
+/// `text`, `href` or `full_href`, or one of its `other` attributes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum PostProcessor {
+    /// sorts the selections by a field (`text` by default); selections without
+    /// the field sort last
+    Sort {
+        #[serde(default = "text")]
+        by: String,
+        #[serde(default)]
+        descending: bool,
+    },
+    /// removes the selections whose field (`href` by default) repeats that of
+    /// an earlier selection
+    Unique {
+        #[serde(default = "href")]
+        by: String,
+    },
+    /// keeps the selections whose field (`text` by default) matches a regular
+    /// expression -- or those which don't when `exclude` is set
+    Filter {
+        #[serde(default = "text")]
+        by: String,
+        pattern: String,
+        #[serde(default)]
+        exclude: bool,
+    },
+}
+
+impl PostProcessor {
+    /// checks that a filter's pattern is a valid regular expression
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            PostProcessor::Filter { pattern, .. } => Regex::new(pattern)
+                .map(|_| ())
+                .context(format!("'{}' is not a valid regular expression", pattern)),
+            _ => Ok(()),
+        }
+    }
+
+    /// post-processes a list of selections
+    pub fn apply(&self, mut list: Vec<Selection>) -> Vec<Selection> {
+        match self {
+            PostProcessor::Sort { by, descending } => {
+                list.sort_by(|a, b| match (field(a, by), field(b, by)) {
+                    (Some(a), Some(b)) if *descending => b.cmp(&a),
+                    (a, b) => a.is_none().cmp(&b.is_none()).then(a.cmp(&b)),
+                });
+
+                list
+            }
+            PostProcessor::Unique { by } => {
+                let mut seen: Vec<String> = vec![];
+                list.retain(|s| match field(s, by) {
+                    Some(value) if seen.contains(&value) => false,
+                    Some(value) => {
+                        seen.push(value);
+                        true
+                    }
+                    None => true,
+                });
+
+                list
+            }
+            PostProcessor::Filter {
+                by,
+                pattern,
+                exclude,
+            } => match Regex::new(pattern) {
+                Ok(re) => list
+                    .into_iter()
+                    .filter(|s| field(s, by).is_some_and(|v| re.is_match(&v)) != *exclude)
+                    .collect(),
+                Err(e) => {
+                    warn!("skipping the filter with an invalid pattern: {}", e);
+                    list
+                }
+            },
+        }
+    }
+}
+
+/// the value of a selection's field as text
+fn field(selection: &Selection, name: &str) -> Option<String> {
+    let value = json!(selection);
+    let value = value
+        .get(name)
+        .or_else(|| value.get("other").and_then(|o| o.get(name)))?;
+
+    match value {
+        Value::String(s) => Some(s.to_string()),
+        Value::Null => None,
+        v => Some(v.to_string()),
+    }
+}
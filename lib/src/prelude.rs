@@ -0,0 +1,36 @@
+//! Re-exports the types needed for a typical scrape so that a single
+//! `use scraped::prelude::*;` is all that's needed.
+
+pub use crate::auth::BearerTokens;
+pub use crate::cache::{Cache, CachingFetcher, FsCache};
+pub use crate::cancel::Cancellation;
+pub use crate::challenge::{BlockedPage, BotChallenge};
+pub use crate::children::{ChildRules, HostScope};
+pub use crate::concurrent::{ConcurrentScrape, Profile};
+pub use crate::error::ScrapedError;
+pub use crate::expression::Expression;
+pub use crate::extract::Extractor;
+pub use crate::frontier::{Discovery, FileFrontier, Frontier, MemoryFrontier};
+pub use crate::limits::ResponseLimits;
+pub use crate::link_audit::{LinkAudit, LinkCheck, LinkReport};
+pub use crate::metadata::{Metadata, OpenGraph, TwitterCard};
+pub use crate::observe::{Observer, Progress};
+pub use crate::pagination::Pagination;
+pub use crate::parser::{ParserBackend, Selectors};
+pub use crate::provenance::SelectorSource;
+pub use crate::rate::RateLimit;
+pub use crate::report::{CrawlProperty, CrawlReport, SelectorCoverage};
+pub use crate::results::{
+    Alternate, FlatResult, PageFailure, PageOutcome, ParseResults, ResultKind,
+};
+pub use crate::retry::RetryPolicy;
+pub use crate::safety::CrawlSafety;
+pub use crate::selection::{Selection, SelectorKind};
+pub use crate::sink::{Sink, WebhookSink};
+pub use crate::stats::{ScrapeStats, StatsSnapshot};
+pub use crate::table::Table;
+pub use crate::text::TextPolicy;
+pub use crate::{
+    ChildScope, Document, DuplicatePolicy, FramePolicy, LoadedDocument, ParsedDoc,
+    PropertyCallback, ScrapeConfig, TemplatePolicy,
+};
@@ -0,0 +1,45 @@
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+
+/// Where the selector behind a selection was defined, so that the results of
+/// layered configurations (presets, config files and code) can be traced back
+/// to the layer which supplied -- or replaced -- each selector. Sources are
+/// written as `preset:<name>`, `config:<path>` or `programmatic`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SelectorSource {
+    /// one of the library's presets (e.g., `generic` or `docs_rs`)
+    Preset(String),
+    /// a configuration file, by its path
+    Config(String),
+    /// added in code with `add_selector()` and its siblings
+    #[default]
+    Programmatic,
+}
+
+impl SelectorSource {
+    /// one of the library's presets
+    pub fn preset(name: &str) -> SelectorSource {
+        SelectorSource::Preset(name.to_string())
+    }
+
+    /// a configuration file
+    pub fn config(path: &str) -> SelectorSource {
+        SelectorSource::Config(path.to_string())
+    }
+}
+
+impl Display for SelectorSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectorSource::Preset(name) => write!(f, "preset:{}", name),
+            SelectorSource::Config(path) => write!(f, "config:{}", path),
+            SelectorSource::Programmatic => write!(f, "programmatic"),
+        }
+    }
+}
+
+impl Serialize for SelectorSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
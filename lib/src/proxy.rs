@@ -0,0 +1,153 @@
+//! Routes requests through proxies: a pool which requests are spread across
+//! in turn and proxies for particular domains. `http` and `https` proxies are
+//! always supported; `socks5` proxies require the `socks` feature.
+
+use crate::{
+    fetch::{FetchedPage, Fetcher, HeaderMap, ReqwestFetcher},
+    limits::ResponseLimits,
+    safety::{normalize_domain, within_domain},
+    ScrapeConfig,
+};
+use async_trait::async_trait;
+use color_eyre::Result;
+use futures::future;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use url::Url;
+
+/// The proxies requests are routed through.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// the proxies requests are spread across, in turn
+    pub pool: Vec<String>,
+    /// the proxies of particular domains (and their subdomains), which take
+    /// the place of the pool for those domains
+    pub domains: BTreeMap<String, String>,
+}
+
+impl ProxyConfig {
+    /// whether requests are routed through a proxy at all
+    pub fn is_empty(&self) -> bool {
+        self.pool.is_empty() && self.domains.is_empty()
+    }
+
+    /// the proxy of the URL's domain -- the most specific when several match --
+    /// if any
+    pub fn domain_proxy(&self, url: &Url) -> Option<&str> {
+        let host = url.host_str()?.trim_end_matches('.').to_lowercase();
+
+        self.domains
+            .iter()
+            .map(|(domain, proxy)| (normalize_domain(domain), proxy))
+            .filter(|(domain, _)| within_domain(&host, domain))
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(_, proxy)| proxy.as_str())
+    }
+
+    /// all of the proxies which are configured
+    fn proxies(&self) -> impl Iterator<Item = &String> {
+        self.pool.iter().chain(self.domains.values())
+    }
+}
+
+/// A `Fetcher` which routes each request through the proxy of its domain or
+/// else the next proxy of the pool; requests are made directly when neither
+/// applies.
+#[derive(Debug)]
+pub struct ProxyFetcher {
+    config: ProxyConfig,
+    /// a client for each proxy, as they each hold a pool of connections
+    clients: HashMap<String, ReqwestFetcher>,
+    direct: ReqwestFetcher,
+    next: AtomicUsize,
+}
+
+impl ProxyFetcher {
+    /// builds a client for each of the config's proxies; a proxy which isn't
+    /// a valid `http`, `https` or `socks5` URL is an error
+    pub fn new(config: &ScrapeConfig) -> Result<ProxyFetcher> {
+        let clients = config
+            .proxy
+            .proxies()
+            .map(|proxy| Ok((proxy.clone(), ReqwestFetcher::proxied(config, proxy)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(ProxyFetcher {
+            config: config.proxy.clone(),
+            clients,
+            direct: ReqwestFetcher::for_config(config)?.unwrap_or_default(),
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// the proxy the next request for the URL is routed through
+    pub fn proxy_for(&self, url: &Url) -> Option<&str> {
+        if let Some(proxy) = self.config.domain_proxy(url) {
+            return Some(proxy);
+        }
+        match self.config.pool.len() {
+            0 => None,
+            n => Some(&self.config.pool[self.next.fetch_add(1, Ordering::Relaxed) % n]),
+        }
+    }
+}
+
+#[async_trait]
+impl Fetcher for ProxyFetcher {
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        let fetcher = match self.proxy_for(url) {
+            Some(proxy) => &self.clients[proxy],
+            None => &self.direct,
+        };
+
+        fetcher.fetch(url, headers).await
+    }
+
+    async fn fetch_within(
+        &self,
+        url: &Url,
+        headers: &HeaderMap,
+        limits: &ResponseLimits,
+    ) -> Result<FetchedPage> {
+        let fetcher = match self.proxy_for(url) {
+            Some(proxy) => &self.clients[proxy],
+            None => &self.direct,
+        };
+
+        fetcher.fetch_within(url, headers, limits).await
+    }
+
+    async fn head(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        let fetcher = match self.proxy_for(url) {
+            Some(proxy) => &self.clients[proxy],
+            None => &self.direct,
+        };
+
+        fetcher.head(url, headers).await
+    }
+
+    /// warms the client of the URL's proxy or -- for the pool -- of every proxy
+    /// the URL's requests may be routed through
+    async fn warm(&self, url: &Url, headers: &HeaderMap) -> Result<()> {
+        let fetchers: Vec<&ReqwestFetcher> = match self.config.domain_proxy(url) {
+            Some(proxy) => vec![&self.clients[proxy]],
+            None if self.config.pool.is_empty() => vec![&self.direct],
+            None => self.config.pool.iter().map(|p| &self.clients[p]).collect(),
+        };
+
+        future::try_join_all(fetchers.iter().map(|f| f.warm(url, headers))).await?;
+
+        Ok(())
+    }
+
+    /// the clients -- direct and proxied -- are all built with the config's timeout
+    fn timeout(&self) -> Option<Duration> {
+        self.direct.timeout()
+    }
+}
@@ -0,0 +1,178 @@
+//! Limits the rate at which each host is requested with a token bucket per
+//! host. The buckets are held by the `ScrapeConfig` -- and shared by its clones
+//! -- so that a host is never requested faster than allowed, whatever the
+//! concurrency.
+
+use crate::{robots, ScrapeConfig};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use url::Url;
+
+/// The rate at which each host is requested. When serialized, the jitter is
+/// a human readable string such as "500ms".
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimit {
+    /// the requests made to each host per second; unlimited when not set
+    pub per_second: Option<f64>,
+    /// the requests which may be made to a host at once before the rate applies
+    pub burst: u32,
+    /// the upper bound of a random pause added to each request which is
+    /// limited, so that requests don't arrive in lockstep
+    #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub jitter: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            per_second: None,
+            burst: 1,
+            jitter: Duration::ZERO,
+        }
+    }
+}
+
+impl RateLimit {
+    /// limits each host to the given number of requests per second
+    pub fn per_second(per_second: f64) -> Self {
+        RateLimit {
+            per_second: Some(per_second),
+            ..RateLimit::default()
+        }
+    }
+
+    /// spaces the requests to each host by the interval; a zero interval is unlimited
+    pub fn interval(interval: Duration) -> Self {
+        match interval.is_zero() {
+            true => RateLimit::default(),
+            false => RateLimit::per_second(1.0 / interval.as_secs_f64()),
+        }
+    }
+
+    /// sets the requests which may be made to a host at once
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+
+        self
+    }
+
+    /// sets the upper bound of the random pause added to each request
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+
+        self
+    }
+
+    /// whether the rate of requests is limited at all
+    pub fn is_limited(&self) -> bool {
+        self.per_second.is_some_and(|rate| rate > 0.0)
+    }
+}
+
+/// the requests a host may be sent right away and when they were last counted
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// the host and -- as the bits of its rate -- the limit a bucket applies
+type BucketKey = (String, u64, u32);
+
+/// The token bucket of each host a scrape has requested, keyed by the host and
+/// the limit it's requested under. Each `ScrapeConfig` has buckets of its own,
+/// which its clones share, and a bucket which has refilled while it sat idle
+/// is dropped: it's no different from a new one.
+#[derive(Clone, Default)]
+pub struct RateBuckets(Arc<Mutex<HashMap<BucketKey, Bucket>>>);
+
+impl RateBuckets {
+    pub fn new() -> Self {
+        RateBuckets::default()
+    }
+
+    /// the number of hosts whose buckets aren't full
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    /// whether every host's bucket is full
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Takes a token from the host's bucket and returns how long to wait before
+    /// the request may be made. Tokens are reserved even when the bucket is
+    /// empty, so that concurrent requests to a host queue up behind one another.
+    fn reserve(&self, host: &str, rate: f64, burst: u32, now: Instant) -> Duration {
+        let mut buckets = self.0.lock().unwrap();
+        buckets.retain(|(_, rate, burst), bucket| {
+            refilled(bucket, f64::from_bits(*rate), *burst, now) < f64::from((*burst).max(1))
+        });
+
+        let key = (host.to_string(), rate.to_bits(), burst);
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: f64::from(burst.max(1)),
+            updated: now,
+        });
+        bucket.tokens = refilled(bucket, rate, burst, now) - 1.0;
+        bucket.updated = now;
+
+        match bucket.tokens < 0.0 {
+            true => Duration::from_secs_f64(-bucket.tokens / rate),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+impl Debug for RateBuckets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "RateBuckets({})", self.len())
+    }
+}
+
+/// the tokens in the bucket once it has been refilled up to the time given
+fn refilled(bucket: &Bucket, rate: f64, burst: u32, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+
+    (bucket.tokens + elapsed * rate).min(f64::from(burst.max(1)))
+}
+
+/// The rate the URL's host is limited to: the configured rate or -- when the
+/// site's robots.txt is respected and its `Crawl-delay` is slower -- one
+/// request per `Crawl-delay`.
+pub fn limit(url: &Url, config: &ScrapeConfig) -> RateLimit {
+    let crawl_delay = robots::crawl_delay(url, config).map(RateLimit::interval);
+
+    match (config.rate_limit, crawl_delay) {
+        (limit, Some(crawl)) if !limit.is_limited() || crawl.per_second < limit.per_second => {
+            crawl.jitter(limit.jitter)
+        }
+        (limit, _) => limit,
+    }
+}
+
+/// waits until the URL's host may be requested under the configured rate
+pub async fn acquire(url: &Url, config: &ScrapeConfig) {
+    let limit = limit(url, config);
+    let (host, rate) = match (url.host_str(), limit.per_second) {
+        (Some(host), Some(rate)) if rate > 0.0 => (host.to_lowercase(), rate),
+        _ => return,
+    };
+
+    let clock = config.clock.0.as_ref();
+    let wait = config
+        .buckets
+        .0
+        .reserve(&host, rate, limit.burst, clock.now())
+        + clock.jitter(limit.jitter);
+    clock.sleep(wait).await;
+}
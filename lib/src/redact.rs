@@ -0,0 +1,94 @@
+//! Keeps secrets (e.g., auth headers and cookies) out of `Debug` output, logs
+//! and serialized configuration.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// what's written in place of a redacted value
+pub const REDACTED: &str = "[redacted]";
+
+/// A value which is written as `[redacted]` when it's formatted (`Debug` and
+/// `Display`) or serialized; it deserializes as the value itself so that it
+/// can be read from a configuration file. Use `expose()` to get at the value.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Redacted<T>(T);
+
+impl<T> Redacted<T> {
+    pub fn new(value: T) -> Self {
+        Redacted(value)
+    }
+
+    /// the value itself; take care not to log it
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Redacted<T> {
+    fn from(value: T) -> Self {
+        Redacted(value)
+    }
+}
+
+impl<T> Debug for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> Display for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> Serialize for Redacted<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Redacted<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Redacted)
+    }
+}
+
+/// a redacted value has the schema of the value itself
+#[cfg(feature = "schemars")]
+impl<T: schemars::JsonSchema> schemars::JsonSchema for Redacted<T> {
+    fn schema_name() -> String {
+        T::schema_name()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        T::json_schema(gen)
+    }
+
+    fn is_referenceable() -> bool {
+        T::is_referenceable()
+    }
+}
+
+/// whether a header carries credentials (e.g., `Authorization`, `Cookie` or
+/// an API key) and so must never be logged
+pub fn is_sensitive(header: &str) -> bool {
+    let header = header.to_lowercase();
+    let exact = [
+        "authorization",
+        "proxy-authorization",
+        "cookie",
+        "set-cookie",
+    ];
+
+    exact.contains(&header.as_str())
+        || [
+            "token", "secret", "password", "api-key", "apikey", "session",
+        ]
+        .iter()
+        .any(|s| header.contains(s))
+}
@@ -0,0 +1,154 @@
+use crate::{error::ScrapedError, fetch::FetchedPage, ScrapeConfig};
+use color_eyre::Result;
+use futures::Future;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::header::LOCATION;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use url::Url;
+
+/// the default maximum number of HTTP redirects followed for a request
+pub const MAX_REDIRECTS: usize = 10;
+
+lazy_static! {
+    static ref META_REFRESH: Selector = Selector::parse("meta[http-equiv]").unwrap();
+    static ref SCRIPT: Selector = Selector::parse("script:not([src])").unwrap();
+    /// the `url=` part of a meta refresh's `content` (e.g., `0; url='/next'`)
+    static ref REFRESH_URL: Regex =
+        Regex::new(r#"(?i)^\s*\d*(?:\.\d*)?\s*[;,]\s*(?:url\s*=\s*)?['"]?([^'"]+)['"]?\s*$"#)
+            .unwrap();
+    /// a script whose _only_ statement assigns the location (e.g.,
+    /// `window.location.href = "/next";` or `location.replace('/next')`)
+    static ref JS_REDIRECT: Regex = Regex::new(
+        r#"^(?:(?:window|document|self|top)\.)?location(?:\.href\s*=\s*|\s*=\s*|\.replace\(\s*|\.assign\(\s*)['"]([^'"]+)['"]\s*\)?\s*;?$"#
+    )
+    .unwrap();
+}
+
+/// Detects a client-side redirect in a page's HTML -- either a
+/// `<meta http-equiv="refresh">` or a trivial script which does nothing but
+/// assign `window.location` -- and returns the URL it points to.
+///
+/// Scripts which do anything more than redirect are ignored as the redirect
+/// may be conditional.
+pub fn client_redirect(html: &str, base: &Url) -> Option<Url> {
+    let doc = Html::parse_document(html);
+
+    let refresh = doc
+        .select(&META_REFRESH)
+        .filter(|el| {
+            el.value()
+                .attr("http-equiv")
+                .is_some_and(|v| v.eq_ignore_ascii_case("refresh"))
+        })
+        .filter_map(|el| el.value().attr("content"))
+        .find_map(|content| REFRESH_URL.captures(content));
+    let script = || {
+        doc.select(&SCRIPT)
+            .map(|el| el.text().collect::<String>())
+            .find_map(|text| JS_REDIRECT.captures(text.trim()).map(|c| c[1].to_string()))
+    };
+
+    let href = match refresh {
+        Some(captures) => captures[1].trim().to_string(),
+        None => script()?,
+    };
+
+    base.join(&href).ok()
+}
+
+/// How the HTTP redirects (3xx responses with a `Location`) of a request are
+/// followed; see `follow()`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct RedirectPolicy {
+    /// follow redirects; when they're not followed a redirect is an `HttpError`
+    pub follow: bool,
+    /// the most redirects followed for a request before it fails with
+    /// `ScrapedError::TooManyRedirects`
+    pub max_redirects: usize,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy {
+            follow: true,
+            max_redirects: MAX_REDIRECTS,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    /// sets whether redirects are followed
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+
+        self
+    }
+
+    /// sets the most redirects followed for a request
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+
+        self
+    }
+}
+
+/// the URL a response redirects to, if it's an HTTP redirect
+pub fn location(page: &FetchedPage) -> Option<Url> {
+    if !(300..400).contains(&page.status) || page.status == 304 {
+        return None;
+    }
+    let location = page.headers.get(LOCATION)?.to_str().ok()?;
+
+    page.url.join(location.trim()).ok()
+}
+
+/// Requests a page and follows its HTTP redirects as the config's
+/// `RedirectPolicy` allows; each hop is checked against the `CrawlSafety`
+/// before it's requested. Returns the page along with the URLs which
+/// redirected, in the order they were visited. When the fetcher follows
+/// redirects itself (e.g., a headless browser) and serves the page from
+/// another URL, the URL it was asked for is recorded as a redirect.
+pub(crate) async fn follow<F, Fut>(
+    url: &Url,
+    config: &ScrapeConfig,
+    mut request: F,
+) -> Result<(FetchedPage, Vec<Url>)>
+where
+    F: FnMut(Url) -> Fut,
+    Fut: Future<Output = Result<FetchedPage>>,
+{
+    let mut url = url.clone();
+    let mut redirects: Vec<Url> = vec![];
+    loop {
+        let page = request(url.clone()).await?;
+        if page.url != url {
+            redirects.push(std::mem::replace(&mut url, page.url.clone()));
+        }
+        let next = match (config.redirects.follow, location(&page)) {
+            (true, Some(next)) => next,
+            _ => return Ok((page, redirects)),
+        };
+        if next == url
+            || redirects.contains(&next)
+            || redirects.len() >= config.redirects.max_redirects
+        {
+            return Err(ScrapedError::TooManyRedirects {
+                url: redirects.first().unwrap_or(&url).clone(),
+                hops: redirects.len() + 1,
+            }
+            .into());
+        }
+
+        debug!(
+            "following a {} redirect from {} to {}",
+            page.status, url, next
+        );
+        config.safety.check_resolved(&next).await?;
+        redirects.push(std::mem::replace(&mut url, next));
+    }
+}
@@ -0,0 +1,201 @@
+//! Renders pages which build their content with JavaScript in a headless
+//! Chrome before they're parsed, as many sites serve plain HTTP clients an
+//! empty shell. Rendering requires the `render` feature and an installation of
+//! Chrome or Chromium; without the feature pages are requested as usual.
+
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+/// the longest a page may take to render by default
+pub const RENDER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How pages are rendered in a headless browser. When serialized, the timeout
+/// is a human readable string such as "30s".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    /// render pages in a headless browser rather than requesting them directly
+    pub enabled: bool,
+    /// a CSS selector which must match before the page is parsed (e.g., the
+    /// container the page's script fills in)
+    pub wait_for: Option<String>,
+    /// wait until the page has made no network requests for half a second
+    pub network_idle: bool,
+    /// the longest a page may take to render before it fails with
+    /// `ScrapedError::Timeout`
+    #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub timeout: Duration,
+    /// the browser's executable; found on the `PATH` when not set
+    pub chrome: Option<PathBuf>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            enabled: false,
+            wait_for: None,
+            network_idle: false,
+            timeout: RENDER_TIMEOUT,
+            chrome: None,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// sets whether pages are rendered in a headless browser
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+
+        self
+    }
+
+    /// waits for the selector to match before the page is parsed
+    pub fn wait_for(mut self, selector: &str) -> Self {
+        self.wait_for = Some(selector.to_string());
+
+        self
+    }
+
+    /// sets whether to wait until the page's network requests have settled
+    pub fn network_idle(mut self, network_idle: bool) -> Self {
+        self.network_idle = network_idle;
+
+        self
+    }
+}
+
+#[cfg(feature = "render")]
+pub use chrome::ChromeFetcher;
+
+#[cfg(feature = "render")]
+mod chrome {
+    use super::RenderConfig;
+    use crate::{
+        error::ScrapedError,
+        fetch::{FetchedPage, Fetcher, HeaderMap},
+    };
+    use async_trait::async_trait;
+    use chromiumoxide::{
+        cdp::browser_protocol::{
+            network::{Headers, SetExtraHttpHeadersParams},
+            page::EventLifecycleEvent,
+        },
+        Browser, BrowserConfig, Page,
+    };
+    use color_eyre::{eyre::eyre, Result};
+    use futures::StreamExt;
+    use reqwest::header::{HeaderValue, CONTENT_TYPE};
+    use serde_json::{Map, Value};
+    use std::time::Duration;
+    use tokio::sync::OnceCell;
+    use tracing::debug;
+    use url::Url;
+
+    /// how often the `wait_for` selector is looked for
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// A `Fetcher` which renders each page in a tab of a headless Chrome. The
+    /// browser is launched with the first page and closed when the fetcher is
+    /// dropped.
+    pub struct ChromeFetcher {
+        config: RenderConfig,
+        browser: OnceCell<Browser>,
+    }
+
+    impl ChromeFetcher {
+        pub fn new(config: RenderConfig) -> Self {
+            ChromeFetcher {
+                config,
+                browser: OnceCell::new(),
+            }
+        }
+
+        /// the browser, which is launched when it's first needed
+        async fn browser(&self) -> Result<&Browser> {
+            self.browser
+                .get_or_try_init(|| async {
+                    let config = match &self.config.chrome {
+                        Some(path) => BrowserConfig::builder().chrome_executable(path),
+                        None => BrowserConfig::builder(),
+                    };
+                    let config = config.build().map_err(|e| eyre!(e))?;
+                    let (browser, mut handler) = Browser::launch(config).await?;
+                    // the handler drives the connection to the browser
+                    tokio::spawn(async move { while handler.next().await.is_some() {} });
+                    debug!("launched a headless browser to render pages");
+
+                    Ok(browser)
+                })
+                .await
+        }
+
+        /// navigates to the page and waits for it to be ready to be parsed
+        async fn render(&self, page: &Page, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+            let headers: Map<String, Value> = headers
+                .iter()
+                .filter_map(|(k, v)| Some((k.to_string(), Value::from(v.to_str().ok()?))))
+                .collect();
+            if !headers.is_empty() {
+                page.execute(SetExtraHttpHeadersParams::new(Headers::new(Value::Object(
+                    headers,
+                ))))
+                .await?;
+            }
+
+            let mut lifecycle = page.event_listener::<EventLifecycleEvent>().await?;
+            page.goto(url.as_str()).await?;
+            if self.config.network_idle {
+                // the events of the blank tab are skipped by waiting for the
+                // navigation to the page to start
+                let mut started = false;
+                while let Some(event) = lifecycle.next().await {
+                    match event.name.as_str() {
+                        "init" => started = true,
+                        "networkIdle" if started => break,
+                        _ => {}
+                    }
+                }
+            }
+            if let Some(selector) = &self.config.wait_for {
+                while page.find_element(selector.as_str()).await.is_err() {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+
+            let response = page.wait_for_navigation_response().await?;
+            let status = response
+                .as_ref()
+                .and_then(|r| r.response.as_ref())
+                .map_or(200, |r| r.status as u16);
+            let rendered = page.url().await?.and_then(|u| Url::parse(&u).ok());
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/html; charset=utf-8"),
+            );
+
+            Ok(FetchedPage {
+                url: rendered.unwrap_or_else(|| url.clone()),
+                status,
+                headers,
+                body: page.content().await?.into(),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Fetcher for ChromeFetcher {
+        async fn fetch(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+            let page = self.browser().await?.new_page("about:blank").await?;
+            let rendered =
+                tokio::time::timeout(self.config.timeout, self.render(&page, url, headers)).await;
+            if let Err(e) = page.close().await {
+                debug!("failed to close the tab of {}: {}", url, e);
+            }
+
+            rendered.unwrap_or_else(|_| Err(ScrapedError::timeout(url, self.config.timeout)))
+        }
+    }
+}
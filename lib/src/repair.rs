@@ -0,0 +1,81 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+lazy_static! {
+    /// an entity whose ampersand was itself encoded, e.g. `&amp;nbsp;` or `&amp;#39;`
+    static ref DOUBLE_ENCODED: Regex =
+        Regex::new(r"&amp;((?:[a-zA-Z][a-zA-Z0-9]{1,31}|#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6});)").unwrap();
+    /// the start of any tag
+    static ref TAG: Regex = Regex::new(r"</?[a-zA-Z]").unwrap();
+}
+
+/// elements whose content is read as text until their closing tag; when the
+/// closing tag is missing the rest of the document is swallowed
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["title", "textarea"];
+
+/// Repairs HTML which would otherwise confuse the parser -- and therefore the
+/// selectors -- before it's parsed:
+///
+/// - null bytes are stripped
+/// - double-encoded entities (e.g. `&amp;nbsp;`) are decoded once
+/// - an unterminated comment is closed rather than swallowing the rest of the page
+/// - an unclosed `<title>` or `<textarea>` is closed before the next tag
+pub fn repair_html(html: &str) -> String {
+    let html = html.replace('\0', "");
+    let mut html = DOUBLE_ENCODED
+        .replace_all(&html, |c: &Captures| format!("&{}", &c[1]))
+        .to_string();
+
+    if let Some(start) = html.rfind("<!--") {
+        if !html[start..].contains("-->") {
+            // close the comment before the next tag so only its own text is lost
+            let end = TAG
+                .find(&html[start + 4..])
+                .map_or(html.len(), |m| start + 4 + m.start());
+            html.insert_str(end, "-->");
+        }
+    }
+
+    RAW_TEXT_ELEMENTS.iter().for_each(|name| {
+        html = close_raw_text(&html, name);
+    });
+
+    html
+}
+
+/// closes each `<name>` element which has no closing tag before the next tag
+fn close_raw_text(html: &str, name: &str) -> String {
+    let lower = html.to_lowercase();
+    let open = format!("<{}", name);
+    let close = format!("</{}", name);
+
+    let mut repaired = String::with_capacity(html.len());
+    let mut cursor = 0;
+    while let Some(found) = lower[cursor..].find(&open) {
+        let start = cursor + found;
+        // the match must be the whole tag name (e.g. not `<titles>`)
+        let after = lower[start + open.len()..].chars().next();
+        let content = match (after, lower[start..].find('>')) {
+            (Some(c), Some(end)) if c == '>' || c.is_whitespace() || c == '/' => start + end + 1,
+            _ => {
+                repaired.push_str(&html[cursor..start + open.len()]);
+                cursor = start + open.len();
+                continue;
+            }
+        };
+
+        repaired.push_str(&html[cursor..content]);
+        let next_tag = TAG.find(&lower[content..]).map(|m| content + m.start());
+        match next_tag {
+            Some(tag) if !lower[tag..].starts_with(&close) => {
+                repaired.push_str(&html[content..tag]);
+                repaired.push_str(&format!("</{}>", name));
+                cursor = tag;
+            }
+            _ => cursor = content,
+        }
+    }
+    repaired.push_str(&html[cursor..]);
+
+    repaired
+}
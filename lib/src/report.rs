@@ -0,0 +1,190 @@
+use crate::{
+    challenge::BotChallenge,
+    results::{FlatResult, ParseResults, ResultKind},
+    selection::Selection,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use url::Url;
+
+/// A callback which aggregates the results of every page in a crawl into a
+/// single value (e.g., all of the external domains linked to).
+pub type CrawlProperty = fn(pages: &[FlatResult]) -> Value;
+
+/// the maximum number of example URLs kept for a selector with no matches
+const MAX_EXAMPLES: usize = 5;
+
+/// How well a single selector matched across all of the pages in a crawl.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SelectorCoverage {
+    /// the number of pages where the selector matched at least one element
+    pub pages_matched: usize,
+    /// the ratio (0.0 - 1.0) of pages where the selector matched
+    pub match_rate: f64,
+    /// the average number of matched elements per page
+    pub avg_matches: f64,
+    /// a few example pages where the selector didn't match anything
+    pub zero_match_examples: Vec<String>,
+}
+
+/// A summary of a crawl which is useful for judging the quality of the
+/// configured selectors across all of the pages scraped.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrawlReport {
+    /// the number of pages in the crawl
+    pub pages: usize,
+    /// the coverage of each selector, keyed by the selector's name
+    pub coverage: BTreeMap<String, SelectorCoverage>,
+    /// the number of pages which failed to load
+    pub failed: usize,
+    /// the number of pages replaced by an anti-bot interstitial, by the kind
+    /// of challenge
+    pub blocked: BTreeMap<BotChallenge, usize>,
+    /// the values of the crawl properties, keyed by the property's name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, Value>,
+}
+
+/// the number of elements a selector matched on a page
+fn match_count(result: Option<&ResultKind>) -> usize {
+    match result {
+        Some(ResultKind::Item(_)) | Some(ResultKind::Table(_)) | Some(ResultKind::Property(_)) => 1,
+        Some(ResultKind::List(list)) => list.len(),
+        None => 0,
+    }
+}
+
+/// The coverage of each selector across the pages. The `selectors` are the
+/// names of the configured selectors; any selector which never produced a
+/// result will still be reported on.
+pub(crate) fn coverage<'a>(
+    pages: &[FlatResult],
+    selectors: impl IntoIterator<Item = &'a String>,
+) -> BTreeMap<String, SelectorCoverage> {
+    let names: BTreeSet<String> = selectors
+        .into_iter()
+        .cloned()
+        .chain(pages.iter().flat_map(|p| p.data.keys().cloned()))
+        .collect();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let counts: Vec<(&Url, usize)> = pages
+                .iter()
+                .map(|p| (&p.url, match_count(p.data.get(&name))))
+                .collect();
+            let pages_matched = counts.iter().filter(|(_, c)| *c > 0).count();
+            let total: usize = counts.iter().map(|(_, c)| c).sum();
+
+            let coverage = SelectorCoverage {
+                pages_matched,
+                match_rate: ratio(pages_matched, pages.len()),
+                avg_matches: ratio(total, pages.len()),
+                zero_match_examples: counts
+                    .iter()
+                    .filter(|(_, c)| *c == 0)
+                    .take(MAX_EXAMPLES)
+                    .map(|(url, _)| url.to_string())
+                    .collect(),
+            };
+
+            (name, coverage)
+        })
+        .collect()
+}
+
+impl CrawlReport {
+    /// Builds a report from the results of a crawl. The `selectors` are the
+    /// names of the configured selectors; any selector which never produced a
+    /// result will still be reported on.
+    pub fn new<'a>(
+        results: &ParseResults,
+        selectors: impl IntoIterator<Item = &'a String>,
+    ) -> CrawlReport {
+        let pages = results.flatten();
+        let mut report = CrawlReport {
+            pages: pages.len(),
+            coverage: coverage(&pages, selectors),
+            failed: 0,
+            blocked: BTreeMap::new(),
+            properties: BTreeMap::new(),
+        };
+        report.count_unsuccessful(results);
+
+        report
+    }
+
+    /// evaluates the crawl properties across every page of the results
+    pub fn aggregate(
+        mut self,
+        results: &ParseResults,
+        properties: &HashMap<String, CrawlProperty>,
+    ) -> CrawlReport {
+        if properties.is_empty() {
+            return self;
+        }
+        let pages = results.flatten();
+        properties.iter().for_each(|(name, cb)| {
+            self.properties.insert(name.to_string(), cb(&pages));
+        });
+
+        self
+    }
+
+    /// counts the pages which failed or were blocked throughout the results tree
+    fn count_unsuccessful(&mut self, results: &ParseResults) {
+        self.failed += results.failures.len();
+        results
+            .blocked
+            .iter()
+            .for_each(|b| *self.blocked.entry(b.challenge).or_default() += 1);
+        results
+            .pages
+            .iter()
+            .chain(&results.children)
+            .for_each(|c| self.count_unsuccessful(c));
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => count as f64 / total as f64,
+    }
+}
+
+/// the selections of a page -- for every selector -- as a flat list
+fn selections(page: &FlatResult) -> impl Iterator<Item = &Selection> {
+    page.data.values().flat_map(ResultKind::selections)
+}
+
+/// A `CrawlProperty` with the sorted set of the domains -- other than the page's
+/// own -- which are linked to from any page.
+pub fn external_domains(pages: &[FlatResult]) -> Value {
+    let domains: BTreeSet<String> = pages
+        .iter()
+        .flat_map(|page| {
+            selections(page)
+                .filter_map(|s| Url::parse(s.full_href.as_deref()?).ok())
+                .filter_map(|url| url.host_str().map(|h| h.to_string()))
+                .filter(|host| Some(host.as_str()) != page.url.host_str())
+        })
+        .collect();
+
+    json!(domains)
+}
+
+/// A `CrawlProperty` with the number of images -- selected by the `images`
+/// selector -- which have no `src` to load.
+pub fn broken_images(pages: &[FlatResult]) -> Value {
+    let broken = pages
+        .iter()
+        .filter_map(|page| page.data.get("images"))
+        .flat_map(ResultKind::selections)
+        .filter(|image| image.src.as_deref().is_none_or(|src| src.trim().is_empty()))
+        .count();
+
+    json!(broken)
+}
@@ -1,14 +1,25 @@
+pub mod diff;
+pub mod writers;
+
+use color_eyre::Report;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::{self, Display, Formatter},
     vec,
 };
+use tracing::warn;
 use url::Url;
 
 use serde::Serialize;
 use serde_json::{json, Value};
 
-use crate::selection::Selection;
+use diff::ResultsDiff;
+
+use crate::{
+    archive::Snapshot, challenge::BlockedPage, content::Content, error::HttpError,
+    frontier::Discovery, http_cache::CacheHeaders, link_audit::LinkReport, metadata::Metadata,
+    provenance::SelectorSource, selection::Selection, table::Table,
+};
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(untagged)]
@@ -19,14 +30,98 @@ pub enum ResultKind {
     /** a selector with a _list_ of DOM elements as a result */
     #[serde(serialize_with = "crate::util::serialize_selection_list")]
     List(Vec<Selection>),
+    /** a `<table>` extracted into rows and columns */
+    Table(Box<Table>),
     Property(Value),
 }
 
+impl ResultKind {
+    /// the selection of a selector for at most one element
+    pub fn as_element(&self) -> Option<&Selection> {
+        match self {
+            ResultKind::Item(selection) => Some(selection),
+            _ => None,
+        }
+    }
+
+    /// the selections of a selector for a _list_ of elements
+    pub fn as_list(&self) -> Option<&[Selection]> {
+        match self {
+            ResultKind::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// the table of a table selector
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            ResultKind::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// the value of a property
+    pub fn as_property(&self) -> Option<&Value> {
+        match self {
+            ResultKind::Property(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// the selections of either an element or a list; none for a table or
+    /// property
+    pub fn selections(&self) -> &[Selection] {
+        match self {
+            ResultKind::Item(selection) => std::slice::from_ref(selection.as_ref()),
+            ResultKind::List(list) => list,
+            ResultKind::Table(_) | ResultKind::Property(_) => &[],
+        }
+    }
+
+    /// the text of the first selection which has any or -- for a property --
+    /// the property's string value
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            ResultKind::Property(Value::String(text)) => Some(text),
+            _ => self.selections().iter().find_map(|s| s.text.as_deref()),
+        }
+    }
+
+    /// the text of each selection which has any
+    pub fn texts(&self) -> Vec<&str> {
+        self.selections()
+            .iter()
+            .filter_map(|s| s.text.as_deref())
+            .collect()
+    }
+
+    /// the `href` of each selection which has one, as it appears in the page
+    pub fn hrefs(&self) -> Vec<&str> {
+        self.selections()
+            .iter()
+            .filter_map(|s| s.href.as_deref())
+            .collect()
+    }
+
+    /// the fully qualified `href` of each selection which has one
+    pub fn full_hrefs(&self) -> Vec<&str> {
+        self.selections()
+            .iter()
+            .filter_map(|s| s.full_href.as_deref())
+            .collect()
+    }
+
+    /// the text of the first selection which has any, or the default
+    pub fn first_text_or(&self, default: &str) -> String {
+        self.text().unwrap_or(default).to_string()
+    }
+}
+
 impl Display for ResultKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match &self {
-            ResultKind::Property(_) => write!(f, "{}", &self),
-            _ => write!(f, "{}", json!(&self).to_string()),
+            ResultKind::Property(v) => write!(f, "{}", v),
+            _ => write!(f, "{}", json!(&self)),
         }
     }
 }
@@ -39,13 +134,245 @@ pub struct ParseResults {
     /// The URL which was parsed.
     #[serde(serialize_with = "crate::util::url_to_string")]
     pub url: Url,
+    /// The URL the page was ultimately served from when it was redirected;
+    /// the page's links were resolved against it.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::util::optional_url_to_string"
+    )]
+    pub resolved_url: Option<Url>,
+    /// The stable identifier of the page as configured by the `IdStrategy`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     /// The raw data extracted from the CSS selectors specified.
     pub data: HashMap<String, ResultKind>,
     /// Abstracted properties derived from `data` and converted to
     /// abstract JSON representation for serialization.s
     pub props: HashMap<String, Value>,
+    /// Where the selector of each selection was defined, keyed by the
+    /// selector's name, when the `ScrapeConfig` records the provenance.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub sources: HashMap<String, SelectorSource>,
 
     pub children: Vec<ParseResults>,
+    /// The documents embedded in the page with `<iframe src>` when the
+    /// `ScrapeConfig` follows frames.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<ParseResults>,
+    /// The kind of content the page held when it wasn't HTML.
+    #[serde(skip_serializing_if = "Content::is_html")]
+    pub content: Content,
+    /// The URLs which redirected -- over HTTP or client-side -- to this page,
+    /// in the order they were visited.
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "crate::util::urls_to_strings"
+    )]
+    pub redirects: Vec<Url>,
+    /// The snapshot in the Wayback Machine the page was scraped from when the
+    /// page itself had rotted away.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<Snapshot>,
+    /// The `Cache-Control`, `Age` and `Expires` headers of the page's
+    /// response, parsed into their typed values, when it had any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caching: Option<CacheHeaders>,
+    /// The alternate versions of this page for other languages and regions as
+    /// declared by `<link rel="alternate" hreflang="...">`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alternates: Vec<Alternate>,
+    /// The canonical URL of the page as declared by `<link rel="canonical">`.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::util::optional_url_to_string"
+    )]
+    pub canonical: Option<Url>,
+    /// The URLs of the other pages of the crawl which are the same page as
+    /// this one -- by their `canonical` -- and were merged into it when the
+    /// `ScrapeConfig` dedupes canonical pages; see `canonical::dedupe()`.
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "crate::util::urls_to_strings"
+    )]
+    pub duplicates: Vec<Url>,
+    /// The OpenGraph, Twitter card and JSON-LD metadata of the page when the
+    /// `ScrapeConfig` includes it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// The status, content type and length of each of the page's links when
+    /// the `ScrapeConfig` audits them; see `link_audit`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_report: Option<LinkReport>,
+    /// The pages which follow this one -- by `rel="next"` in their `Link`
+    /// header or HTML -- in order, when the `ScrapeConfig` follows pagination.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pages: Vec<ParseResults>,
+    /// Child pages which could not be scraped.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<PageFailure>,
+    /// Child pages which were replaced by an anti-bot interstitial.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked: Vec<BlockedPage>,
+    /// Child pages which weren't requested because the crawl was cancelled.
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "crate::util::urls_to_strings"
+    )]
+    pub cancelled: Vec<Url>,
+    /// How the page was discovered -- given, listed by a sitemap or linked --
+    /// when it was queued in the `Frontier` of a `ConcurrentScrape`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discovery: Option<Discovery>,
+}
+
+/// An alternate version of a page for another language or region.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Alternate {
+    /// The language (and optionally region) of the alternate, e.g. `en-GB`
+    /// or `x-default`.
+    pub hreflang: String,
+    /// The URL of the alternate page.
+    #[serde(serialize_with = "crate::util::url_to_string")]
+    pub url: Url,
+}
+
+impl Alternate {
+    /// whether the alternate is for the given locale; a locale without a
+    /// region (e.g. `en`) matches all of the regions of that language
+    pub fn matches(&self, locale: &str) -> bool {
+        let hreflang = self.hreflang.to_lowercase();
+        let locale = locale.to_lowercase();
+
+        hreflang == locale || hreflang.starts_with(&format!("{}-", locale))
+    }
+}
+
+/// A page which could not be scraped.
+#[derive(Debug, Serialize, Clone)]
+pub struct PageFailure {
+    /// The URL of the page.
+    #[serde(serialize_with = "crate::util::url_to_string")]
+    pub url: Url,
+    /// A description of the error which caused the failure.
+    pub error: String,
+    /// The number of times the page was requested.
+    pub attempts: usize,
+    /// The HTTP status code when the server responded with an error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// The headers of the error response; only captured when the
+    /// `ScrapeConfig` is set to `capture_error_body`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    /// The (truncated) body of the error response; only captured when the
+    /// `ScrapeConfig` is set to `capture_error_body`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+impl PageFailure {
+    /// records the failure of a page along with the details of the
+    /// response when the error was an `HttpError`
+    pub fn new(url: Url, error: &Report, attempts: usize) -> PageFailure {
+        let http = error.downcast_ref::<HttpError>();
+
+        PageFailure {
+            url,
+            error: format!("{:#}", error),
+            attempts,
+            status: http.map(|e| e.status),
+            headers: http.and_then(|e| e.headers.clone()),
+            body: http.and_then(|e| e.body.clone()),
+        }
+    }
+}
+
+/// The outcome of scraping a single page while crawling.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum PageOutcome {
+    Ok(Box<ParseResults>),
+    Failed(PageFailure),
+    /// the page was replaced by an anti-bot interstitial
+    Blocked(BlockedPage),
+    /// the page wasn't requested because the crawl was cancelled; a scrape
+    /// puts it back in its frontier so that it's requested when resumed
+    Cancelled(#[serde(serialize_with = "crate::util::url_to_string")] Url),
+}
+
+impl PageOutcome {
+    /// the outcome of scraping a page; a page which was replaced by an anti-bot
+    /// interstitial is distinguished from one which failed
+    pub fn new(url: Url, result: Result<ParseResults, Report>) -> PageOutcome {
+        let e = match result {
+            Ok(page) => return PageOutcome::Ok(Box::new(page)),
+            Err(e) => e,
+        };
+
+        match e.downcast_ref::<BlockedPage>() {
+            Some(blocked) => {
+                warn!("{}", blocked);
+                PageOutcome::Blocked(blocked.clone())
+            }
+            None => {
+                warn!("failed to scrape the page {}: {:#}", url, e);
+                PageOutcome::Failed(PageFailure::new(url, &e, 1))
+            }
+        }
+    }
+}
+
+impl ParseResults {
+    /// Flattens the page and the tree of pages beneath it into a result for
+    /// each page, depth first: a page is followed by its frames, the pages which
+    /// follow it and then its children (and theirs).
+    pub fn flatten(&self) -> Vec<FlatResult> {
+        let mut flat = vec![];
+        let mut pages = vec![self];
+        while let Some(page) = pages.pop() {
+            flat.push(FlatResult {
+                url: page.url.clone(),
+                id: page.id.clone(),
+                data: page.data.clone(),
+                props: page.props.clone(),
+                sources: page.sources.clone(),
+            });
+            pages.extend(
+                page.frames
+                    .iter()
+                    .chain(&page.pages)
+                    .chain(&page.children)
+                    .rev(),
+            );
+        }
+
+        flat
+    }
+
+    /// The differences between these results and those of a later scrape
+    /// (`newer`) of the same pages; see `ResultsDiff`.
+    pub fn diff(&self, newer: &ParseResults) -> ResultsDiff {
+        ResultsDiff::between(&json!(self), &json!(newer))
+    }
+
+    /// The text of a property or -- when there's no property of that name --
+    /// of a selection; numbers are written out as text.
+    pub fn field(&self, name: &str) -> Option<String> {
+        let text = |value: &Value| match value {
+            Value::String(s) => Some(s.trim().to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+
+        match self.props.get(name) {
+            Some(value) => text(value),
+            None => match self.data.get(name) {
+                Some(ResultKind::Item(selection)) => selection.text.clone(),
+                Some(ResultKind::Property(value)) => text(value),
+                _ => None,
+            },
+        }
+    }
 }
 
 impl Display for ParseResults {
@@ -60,29 +387,24 @@ pub struct FlatResult {
     /// The URL which was parsed.
     #[serde(serialize_with = "crate::util::url_to_string")]
     pub url: Url,
+    /// The stable identifier of the page as configured by the `IdStrategy`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     /// The raw data extracted from the CSS selectors specified.
     pub data: HashMap<String, ResultKind>,
     /// Abstracted properties derived from `data` and converted to
     /// abstract JSON representation for serialization.s
     pub props: HashMap<String, Value>,
+    /// Where the selector of each selection was defined, when the
+    /// `ScrapeConfig` records the provenance.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub sources: HashMap<String, SelectorSource>,
 }
 
 impl FlatResult {
     /// flattens a `ParseResults` struct from it's heirarchical structure to a
-    /// vector of `FlatResult` results.
+    /// vector of `FlatResult` results; see `ParseResults::flatten()`
     pub fn flatten(r: &ParseResults) -> Vec<FlatResult> {
-        let mut flat = vec![FlatResult {
-            url: r.url.clone(),
-            data: r.data.clone(),
-            props: r.props.clone(),
-        }];
-
-        r.children.iter().for_each(|c| {
-            FlatResult::flatten(c)
-                .iter()
-                .for_each(|i| flat.push(i.clone()));
-        });
-
-        flat
+        r.flatten()
     }
 }
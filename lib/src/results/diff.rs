@@ -0,0 +1,157 @@
+//! Compares two scrapes of the same pages -- e.g., yesterday's results with
+//! today's -- to report which selections and properties were added, removed
+//! or changed; the basis of change-monitoring workflows such as price trackers.
+//!
+//! Results are compared as they're serialized so that results which were
+//! written to disk (as a tree of pages or flattened) can be compared with
+//! those of a fresh scrape.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// A value which differs between the two scrapes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// the value in the old scrape
+    pub old: Value,
+    /// the value in the new scrape
+    pub new: Value,
+}
+
+/// The named values (selections or properties) of a page which differ.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Changes {
+    /// the values only found in the new scrape
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub added: BTreeMap<String, Value>,
+    /// the values only found in the old scrape
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub removed: BTreeMap<String, Value>,
+    /// the values found in both scrapes which differ
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub changed: BTreeMap<String, Change>,
+}
+
+impl Changes {
+    /// compares the values of two JSON objects; values which aren't objects
+    /// are treated as empty ones
+    fn between(old: Option<&Value>, new: Option<&Value>) -> Changes {
+        let empty = Map::new();
+        let old = old.and_then(Value::as_object).unwrap_or(&empty);
+        let new = new.and_then(Value::as_object).unwrap_or(&empty);
+        let mut changes = Changes::default();
+
+        for (name, value) in new {
+            match old.get(name) {
+                None => {
+                    changes.added.insert(name.to_string(), value.clone());
+                }
+                Some(previous) if previous != value => {
+                    let change = Change {
+                        old: previous.clone(),
+                        new: value.clone(),
+                    };
+                    changes.changed.insert(name.to_string(), change);
+                }
+                Some(_) => {}
+            }
+        }
+        old.iter()
+            .filter(|(name, _)| !new.contains_key(*name))
+            .for_each(|(name, value)| {
+                changes.removed.insert(name.to_string(), value.clone());
+            });
+
+        changes
+    }
+
+    /// whether nothing differs
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The differences of a page which was in both scrapes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PageDiff {
+    /// the selections -- the results of the CSS selectors -- which differ
+    pub selections: Changes,
+    /// the properties which differ
+    pub properties: Changes,
+}
+
+impl PageDiff {
+    /// whether nothing differs
+    pub fn is_empty(&self) -> bool {
+        self.selections.is_empty() && self.properties.is_empty()
+    }
+}
+
+/// The differences between two scrapes; pages are matched by their URL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResultsDiff {
+    /// the URLs of the pages only in the new scrape
+    pub added_pages: Vec<String>,
+    /// the URLs of the pages only in the old scrape
+    pub removed_pages: Vec<String>,
+    /// the differences of the pages in both scrapes which changed, keyed by URL
+    pub pages: BTreeMap<String, PageDiff>,
+}
+
+impl ResultsDiff {
+    /// Compares the old and new results as they're serialized; either may be
+    /// a page -- along with its `children`, `frames` and `pages` -- or a flattened
+    /// array of pages.
+    pub fn between(old: &Value, new: &Value) -> ResultsDiff {
+        let (old, new) = (pages(old), pages(new));
+        let mut diff = ResultsDiff::default();
+
+        for (url, page) in &new {
+            match old.get(url) {
+                None => diff.added_pages.push(url.to_string()),
+                Some(previous) => {
+                    let page_diff = PageDiff {
+                        selections: Changes::between(previous.get("data"), page.get("data")),
+                        properties: Changes::between(previous.get("props"), page.get("props")),
+                    };
+                    if !page_diff.is_empty() {
+                        diff.pages.insert(url.to_string(), page_diff);
+                    }
+                }
+            }
+        }
+        diff.removed_pages = old
+            .keys()
+            .filter(|url| !new.contains_key(*url))
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    /// whether the two scrapes are the same
+    pub fn is_empty(&self) -> bool {
+        self.added_pages.is_empty() && self.removed_pages.is_empty() && self.pages.is_empty()
+    }
+}
+
+/// the serialized pages of a scrape, keyed by their URL
+fn pages(results: &Value) -> BTreeMap<String, &Value> {
+    let mut pages = BTreeMap::new();
+    let mut queue: Vec<&Value> = match results {
+        Value::Array(flat) => flat.iter().collect(),
+        page => vec![page],
+    };
+    while let Some(page) = queue.pop() {
+        if let Some(url) = page.get("url").and_then(Value::as_str) {
+            pages.entry(url.to_string()).or_insert(page);
+        }
+        ["children", "frames", "pages"]
+            .iter()
+            .filter_map(|key| page.get(*key).and_then(Value::as_array))
+            .for_each(|nested| queue.extend(nested));
+    }
+
+    pages
+}
@@ -0,0 +1,115 @@
+//! Writers which stream results as they're produced rather than serializing
+//! a whole crawl as a single JSON document.
+
+use super::ParseResults;
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionKey;
+use crate::scrub::Scrubber;
+use color_eyre::{eyre::WrapErr, Result};
+use serde::Serialize;
+use std::path::Path;
+use tokio::{
+    fs::File,
+    io::{AsyncWrite, AsyncWriteExt, BufWriter},
+};
+
+/// Writes one JSON value per line ([JSON Lines](https://jsonlines.org)) to a
+/// file or any other `AsyncWrite`; call `finish()` once every record has been
+/// written to flush them.
+pub struct JsonLinesWriter<W: AsyncWrite + Unpin> {
+    writer: W,
+    /// the number of lines written
+    lines: usize,
+    /// scrubs the personal data from each line
+    scrubber: Option<Scrubber>,
+    /// the key each line is encrypted with
+    #[cfg(feature = "encryption")]
+    key: Option<EncryptionKey>,
+}
+
+impl JsonLinesWriter<BufWriter<File>> {
+    /// creates -- or truncates -- the file at the path and writes to it
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .await
+            .context(format!("Failed to create the file {}", path.display()))?;
+
+        Ok(JsonLinesWriter::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesWriter {
+            writer,
+            lines: 0,
+            scrubber: None,
+            #[cfg(feature = "encryption")]
+            key: None,
+        }
+    }
+
+    /// scrubs the personal data (e.g., email addresses) from each line; see
+    /// `Scrubber`
+    pub fn scrub(mut self, scrubber: Scrubber) -> Self {
+        self.scrubber = Some(scrubber);
+
+        self
+    }
+
+    /// encrypts each line with the key; see `EncryptionKey::decrypt_lines()`
+    #[cfg(feature = "encryption")]
+    pub fn encrypt(mut self, key: EncryptionKey) -> Self {
+        self.key = Some(key);
+
+        self
+    }
+
+    /// writes a record as a line of JSON
+    pub async fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        let mut line = match &self.scrubber {
+            Some(scrubber) => serde_json::to_value(record)
+                .and_then(|record| serde_json::to_vec(&scrubber.scrub(record))),
+            None => serde_json::to_vec(record),
+        }
+        .context("Failed to serialize a record")?;
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key {
+            line = key.encrypt(&line)?.into_bytes();
+        }
+        line.push(b'\n');
+        self.writer
+            .write_all(&line)
+            .await
+            .context("Failed to write a line of JSON")?;
+        self.lines += 1;
+
+        Ok(())
+    }
+
+    /// writes a line for each page of the results, flattened as by
+    /// `ParseResults::flatten()`
+    pub async fn write_flat(&mut self, results: &ParseResults) -> Result<()> {
+        for page in results.flatten() {
+            self.write(&page).await?;
+        }
+
+        Ok(())
+    }
+
+    /// the number of lines written
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// flushes the lines written and returns the underlying writer
+    pub async fn finish(mut self) -> Result<W> {
+        self.writer
+            .flush()
+            .await
+            .context("Failed to flush the lines of JSON")?;
+
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    error::ScrapedError,
+    fetch::HeaderMap,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::Report;
+use reqwest::header::RETRY_AFTER;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Retries requests which fail for transient reasons -- connection errors,
+/// timeouts, `429 Too Many Requests` and 5xx responses -- with exponential
+/// backoff. A `Retry-After` header on the response takes the place of the
+/// backoff (up to `max_delay`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// the number of times a page is requested before giving up; `1` never retries
+    pub max_attempts: u32,
+    /// the pause before the first retry; it doubles with each subsequent retry
+    #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub base_delay: Duration,
+    /// the upper bound of the random pause added to each backoff
+    #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub jitter: Duration,
+    /// the longest pause taken before a retry
+    #[serde(with = "humantime_serde")]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(500),
+            jitter: Duration::ZERO,
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, base_delay: Duration, jitter: Duration) -> Self {
+        RetryPolicy {
+            max_attempts,
+            base_delay,
+            jitter,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// the pause before the given retry (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.backoff_on(retry, &SystemClock)
+    }
+
+    /// the pause before the given retry with the clock's jitter
+    pub fn backoff_on(&self, retry: u32, clock: &dyn Clock) -> Duration {
+        let base = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)));
+
+        (base + clock.jitter(self.jitter)).min(self.max_delay)
+    }
+
+    /// the pause before the given retry of a response, respecting its
+    /// `Retry-After` header when present
+    pub fn pause(&self, retry: u32, headers: Option<&HeaderMap>) -> Duration {
+        self.pause_on(retry, headers, &SystemClock)
+    }
+
+    /// like `pause()` but with the clock's jitter and -- for a `Retry-After`
+    /// date -- current time
+    pub fn pause_on(&self, retry: u32, headers: Option<&HeaderMap>, clock: &dyn Clock) -> Duration {
+        match headers.and_then(|h| retry_after_at(h, clock.utc())) {
+            Some(after) => after.min(self.max_delay),
+            None => self.backoff_on(retry, clock),
+        }
+    }
+}
+
+/// whether a response with the given status is worth retrying
+pub fn is_transient_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// whether a failed request is worth retrying
+pub fn is_transient_error(error: &Report) -> bool {
+    let timeout = matches!(
+        error.downcast_ref::<ScrapedError>(),
+        Some(ScrapedError::Timeout { .. })
+    );
+
+    timeout
+        || error
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+/// The pause requested by a `Retry-After` header; the header holds either a
+/// number of seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    retry_after_at(headers, Utc::now())
+}
+
+/// the pause requested by a `Retry-After` header as of the given time
+pub fn retry_after_at(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value).ok()?;
+            Some((at.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use crate::{fetch::Fetcher, redirect, ScrapeConfig};
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+use url::Url;
+
+/// how long a robots.txt is cached before it's requested again
+pub const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A rule which allows or disallows the paths matching its pattern; a pattern
+/// may contain `*` wildcards and end with a `$` anchor.
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    /// the pattern as it was written
+    path: String,
+    /// the length of the pattern; the longest matching rule wins
+    len: usize,
+    pattern: Regex,
+}
+
+impl Rule {
+    fn new(allow: bool, pattern: &str) -> Option<Rule> {
+        let (glob, anchor) = match pattern.strip_suffix('$') {
+            Some(glob) => (glob, "$"),
+            None => (pattern, ""),
+        };
+        let parts: Vec<String> = glob.split('*').map(regex::escape).collect();
+
+        Some(Rule {
+            allow,
+            path: pattern.to_string(),
+            len: pattern.len(),
+            pattern: Regex::new(&format!("^{}{}", parts.join(".*"), anchor)).ok()?,
+        })
+    }
+}
+
+/// The rules which apply to a set of user agents.
+#[derive(Debug, Clone, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+/// The parsed content of a site's `/robots.txt`.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+    /// the sitemaps listed with `Sitemap:`, which apply to every user agent
+    sitemaps: Vec<Url>,
+}
+
+impl RobotsTxt {
+    /// parses the content of a robots.txt; lines which aren't understood are ignored
+    pub fn parse(content: &str) -> RobotsTxt {
+        let mut groups: Vec<Group> = vec![];
+        let mut sitemaps: Vec<Url> = vec![];
+        let mut in_agents = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            if key == "user-agent" {
+                if !in_agents {
+                    groups.push(Group::default());
+                }
+                in_agents = true;
+                if let Some(group) = groups.last_mut() {
+                    group.agents.push(value.to_lowercase());
+                }
+                continue;
+            }
+            in_agents = false;
+            // a sitemap isn't part of any group
+            if key == "sitemap" {
+                sitemaps.extend(Url::parse(value).ok());
+                continue;
+            }
+
+            let group = match groups.last_mut() {
+                Some(group) => group,
+                None => continue,
+            };
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => {
+                    group.rules.extend(Rule::new(key == "allow", value))
+                }
+                "crawl-delay" => {
+                    group.crawl_delay = value.parse::<f64>().ok().map(Duration::from_secs_f64)
+                }
+                _ => {}
+            }
+        }
+
+        RobotsTxt { groups, sitemaps }
+    }
+
+    /// the group for the user agent: the group naming its product token or --
+    /// failing that -- the `*` group
+    fn group(&self, agent: &str) -> Option<&Group> {
+        let token = agent
+            .split(['/', ' '])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        self.groups
+            .iter()
+            .find(|g| !token.is_empty() && g.agents.iter().any(|a| token.starts_with(a.as_str())))
+            .or_else(|| {
+                self.groups
+                    .iter()
+                    .find(|g| g.agents.iter().any(|a| a == "*"))
+            })
+    }
+
+    /// whether the user agent may request the URL; the longest matching rule
+    /// wins and `Allow` wins a tie
+    pub fn allows(&self, agent: &str, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        self.group(agent)
+            .and_then(|g| {
+                g.rules
+                    .iter()
+                    .filter(|r| r.pattern.is_match(&path))
+                    .max_by_key(|r| (r.len, r.allow))
+            })
+            .is_none_or(|r| r.allow)
+    }
+
+    /// the pause the user agent is asked to take between requests
+    pub fn crawl_delay(&self, agent: &str) -> Option<Duration> {
+        self.group(agent).and_then(|g| g.crawl_delay)
+    }
+
+    /// the patterns of the paths which the user agent is disallowed, as they
+    /// were written
+    pub fn disallowed(&self, agent: &str) -> Vec<&str> {
+        self.group(agent)
+            .map(|g| {
+                g.rules
+                    .iter()
+                    .filter(|r| !r.allow)
+                    .map(|r| r.path.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// the sitemaps the robots.txt lists
+    pub fn sitemaps(&self) -> &[Url] {
+        &self.sitemaps
+    }
+}
+
+/// A robots.txt which was requested and when it was.
+#[derive(Debug, Clone)]
+struct Entry {
+    robots: Arc<RobotsTxt>,
+    fetched: Instant,
+}
+
+/// The robots.txt of each origin which a scrape has requested, keyed by the
+/// origin and the user agent it was requested as; an entry expires after
+/// `ROBOTS_TTL`. Each `ScrapeConfig` has a cache of its own, which its clones
+/// -- e.g., those of the pages of a crawl -- share.
+#[derive(Clone, Default)]
+pub struct RobotsCache(Arc<Mutex<HashMap<(String, String), Entry>>>);
+
+impl RobotsCache {
+    pub fn new() -> Self {
+        RobotsCache::default()
+    }
+
+    /// the robots.txt of the URL's origin when it was requested within the TTL
+    fn get(&self, url: &Url, config: &ScrapeConfig) -> Option<Arc<RobotsTxt>> {
+        let now = config.clock.0.now();
+        let mut entries = self.0.lock().unwrap();
+        let key = key(url, config);
+        match entries.get(&key) {
+            Some(entry) if now.saturating_duration_since(entry.fetched) < ROBOTS_TTL => {
+                Some(entry.robots.clone())
+            }
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, url: &Url, config: &ScrapeConfig, robots: Arc<RobotsTxt>) {
+        let entry = Entry {
+            robots,
+            fetched: config.clock.0.now(),
+        };
+        self.0.lock().unwrap().insert(key(url, config), entry);
+    }
+
+    /// forgets every robots.txt which has been requested
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// the origin of the URL and the user agent its robots.txt is requested as
+fn key(url: &Url, config: &ScrapeConfig) -> (String, String) {
+    (
+        url.origin().ascii_serialization(),
+        agent(config).to_string(),
+    )
+}
+
+impl Debug for RobotsCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "RobotsCache({})", self.0.lock().unwrap().len())
+    }
+}
+
+/// the user agent whose rules apply to requests made with the configuration
+pub fn agent(config: &ScrapeConfig) -> &str {
+    config.user_agent.as_deref().unwrap_or("*")
+}
+
+/// The robots.txt of the URL's origin; it's requested with the config's fetcher
+/// and user agent the first time an origin is seen and cached in the config's
+/// `RobotsCache` thereafter. A missing robots.txt allows every request, as does
+/// one which is unreachable or answers with a server error -- but those aren't
+/// cached, so that it's requested again.
+pub async fn load(url: &Url, config: &ScrapeConfig, fetcher: &dyn Fetcher) -> Arc<RobotsTxt> {
+    if let Some(robots) = cached(url, config) {
+        return robots;
+    }
+
+    let robots_url = match url.join("/robots.txt") {
+        Ok(robots_url) => robots_url,
+        Err(_) => return Arc::new(RobotsTxt::default()),
+    };
+    let request = |url: Url| async move {
+        let headers = config.request_headers_for(&url)?;
+        fetcher.fetch(&url, &headers).await
+    };
+    let (robots, cache) = match redirect::follow(&robots_url, config, request).await {
+        Ok((page, _)) if (200..300).contains(&page.status) => {
+            (RobotsTxt::parse(&page.text()), true)
+        }
+        Ok((page, _)) => {
+            debug!("{} responded with a {} status", robots_url, page.status);
+            // a server error or a 429 is transient while a 4xx means there's no robots.txt
+            (
+                RobotsTxt::default(),
+                page.status < 500 && page.status != 429,
+            )
+        }
+        Err(e) => {
+            warn!("failed to request {}: {}", robots_url, e);
+            (RobotsTxt::default(), false)
+        }
+    };
+
+    let robots = Arc::new(robots);
+    if cache {
+        config.robots.0.insert(url, config, robots.clone());
+    }
+
+    robots
+}
+
+/// the robots.txt of the URL's origin, if the config's cache holds one which
+/// hasn't expired
+pub fn cached(url: &Url, config: &ScrapeConfig) -> Option<Arc<RobotsTxt>> {
+    config.robots.0.get(url, config)
+}
+
+/// whether the configuration allows the URL to be requested; origins whose
+/// robots.txt hasn't been requested yet are allowed
+pub fn allows(url: &Url, config: &ScrapeConfig) -> bool {
+    !config.respect_robots || cached(url, config).is_none_or(|r| r.allows(agent(config), url))
+}
+
+/// the `Crawl-delay` of the URL's origin when the site's robots.txt is
+/// respected and has been requested
+pub fn crawl_delay(url: &Url, config: &ScrapeConfig) -> Option<Duration> {
+    match config.respect_robots {
+        true => cached(url, config).and_then(|r| r.crawl_delay(agent(config))),
+        false => None,
+    }
+}
+
+/// the URLs which the configuration allows to be requested, requesting the
+/// robots.txt of any origin which hasn't been seen yet
+pub async fn retain_allowed(
+    urls: Vec<Url>,
+    config: &ScrapeConfig,
+    fetcher: &dyn Fetcher,
+) -> Vec<Url> {
+    if !config.respect_robots {
+        return urls;
+    }
+
+    let mut allowed = vec![];
+    for url in urls {
+        match load(&url, config, fetcher)
+            .await
+            .allows(agent(config), &url)
+        {
+            true => allowed.push(url),
+            false => debug!("skipping {} which is disallowed by robots.txt", url),
+        }
+    }
+
+    allowed
+}
@@ -0,0 +1,176 @@
+//! Hard limits on which URLs a crawl may request, regardless of the links it
+//! discovers along the way.
+
+use crate::error::ScrapedError;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use url::{Host, Url};
+
+/// Guards a crawl against requesting URLs it was never meant to (e.g., when a
+/// misconfigured child selector matches links to third-party sites). A URL
+/// which isn't allowed is refused with a `ScrapedError` before it's requested.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlSafety {
+    /// the only domains -- along with their subdomains -- which may be
+    /// requested; every domain may be requested when empty
+    pub allow_domains: Vec<String>,
+    /// refuse URLs which aren't `http` or `https` and hosts which resolve to a
+    /// private, loopback or otherwise non-public address; essential when the
+    /// selectors or URLs come from an untrusted source
+    pub block_private_networks: bool,
+}
+
+impl CrawlSafety {
+    /// restricts requests to the given domains and their subdomains
+    pub fn allow_domains<S: AsRef<str>>(mut self, domains: impl IntoIterator<Item = S>) -> Self {
+        self.allow_domains = domains
+            .into_iter()
+            .map(|d| normalize_domain(d.as_ref()))
+            .filter(|d| !d.is_empty())
+            .collect();
+
+        self
+    }
+
+    /// sets whether requests to private networks and non-HTTP schemes are refused
+    pub fn block_private_networks(mut self, block: bool) -> Self {
+        self.block_private_networks = block;
+
+        self
+    }
+
+    /// whether the URL's host is one of the allowed domains or a subdomain of
+    /// one; a URL without a host is only allowed without an allow-list
+    pub fn allows(&self, url: &Url) -> bool {
+        if self.allow_domains.is_empty() {
+            return true;
+        }
+        let host = match url.host_str() {
+            Some(host) => host.trim_end_matches('.').to_lowercase(),
+            None => return false,
+        };
+
+        self.allow_domains
+            .iter()
+            .any(|domain| within_domain(&host, domain))
+    }
+
+    /// Refuses a URL which isn't allowed with a `ScrapedError`: one which is
+    /// outside of the allow-list or -- when blocking private networks -- which
+    /// isn't HTTP or whose host is a non-public IP address.
+    pub fn check(&self, url: &Url) -> Result<()> {
+        if !self.allows(url) {
+            return Err(ScrapedError::OutsideAllowList { url: url.clone() }.into());
+        }
+        if !self.block_private_networks {
+            return Ok(());
+        }
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ScrapedError::UnsupportedScheme { url: url.clone() }.into());
+        }
+        let ip = match url.host() {
+            Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+            _ => return Ok(()),
+        };
+
+        refuse_private(url, ip)
+    }
+
+    /// Like `check()` but also resolves the URL's host, refusing it when any
+    /// of its addresses is non-public. The transport resolves the host again
+    /// when it makes the request, so a DNS server which answers differently
+    /// the second time isn't caught; nor is a server-side redirect.
+    pub async fn check_resolved(&self, url: &Url) -> Result<()> {
+        self.check(url)?;
+        if !self.block_private_networks {
+            return Ok(());
+        }
+        let (host, port) = match (url.host(), url.port_or_known_default()) {
+            (Some(Host::Domain(host)), Some(port)) => (host, port),
+            _ => return Ok(()),
+        };
+
+        tokio::net::lookup_host((host, port))
+            .await?
+            .try_for_each(|addr| refuse_private(url, addr.ip()))
+    }
+}
+
+/// a domain as it's matched against hosts: lowercase and without a leading
+/// `*.` or surrounding dots
+pub(crate) fn normalize_domain(domain: &str) -> String {
+    let domain = domain.trim().trim_start_matches("*.");
+
+    domain.trim_matches('.').to_lowercase()
+}
+
+/// whether the (lowercase) host is the normalized domain or a subdomain of it
+pub(crate) fn within_domain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.'))
+}
+
+/// refuses a URL whose address is non-public with `ScrapedError::PrivateAddress`
+fn refuse_private(url: &Url, ip: IpAddr) -> Result<()> {
+    match is_public(&ip) {
+        true => Ok(()),
+        false => Err(ScrapedError::PrivateAddress {
+            url: url.clone(),
+            ip,
+        }
+        .into()),
+    }
+}
+
+/// whether an address is routable on the public internet; private, loopback,
+/// link-local, shared (CGNAT), documentation, multicast and reserved ranges
+/// aren't -- nor are IPv6 addresses which embed one of those IPv4 addresses
+pub fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: &Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || a >= 240
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (18..20).contains(&b)))
+}
+
+fn is_public_v6(ip: &Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_public_v4(&v4);
+    }
+    let segments = ip.segments();
+    // NAT64 addresses (64:ff9b::/96) embed an IPv4 address
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [a, b] = segments[6].to_be_bytes();
+        let [c, d] = segments[7].to_be_bytes();
+        return is_public_v4(&Ipv4Addr::new(a, b, c, d));
+    }
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00
+        || (segments[0] & 0xffc0) == 0xfe80
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+}
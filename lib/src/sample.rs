@@ -0,0 +1,89 @@
+//! Samples the child URLs which a crawl follows so that the structure of a site
+//! -- and how well the selectors cover its pages -- can be estimated cheaply
+//! before committing to a full crawl.
+
+use crate::{id::fnv1a, url_norm::canonicalize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
+
+/// Which of the child URLs are followed when sampling; every URL is by default.
+///
+/// The sample is deterministic: whether a URL is kept depends on a hash of its
+/// canonical form (see `url_norm::canonicalize()`) rather than on chance or
+/// the order of the links, so the same pages are sampled on each run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct Sampling {
+    /// the fraction of the child URLs which are followed (e.g., `0.1` for
+    /// one in ten)
+    pub fraction: Option<f64>,
+    /// the most child URLs in the same directory (e.g., `/blog/2021/`) which
+    /// are followed
+    pub per_directory: Option<usize>,
+}
+
+impl Sampling {
+    pub fn new() -> Self {
+        Sampling::default()
+    }
+
+    /// follows the fraction (between 0 and 1) of the child URLs
+    pub fn fraction(mut self, fraction: f64) -> Self {
+        self.fraction = Some(fraction.clamp(0.0, 1.0));
+
+        self
+    }
+
+    /// follows no more than the given number of child URLs in each directory
+    pub fn per_directory(mut self, max: usize) -> Self {
+        self.per_directory = Some(max);
+
+        self
+    }
+
+    /// whether any of the child URLs are left out
+    pub fn is_enabled(&self) -> bool {
+        self.fraction.is_some_and(|f| f < 1.0) || self.per_directory.is_some()
+    }
+
+    /// Whether each of the URLs is in the sample: a URL must be within the
+    /// `fraction` and among the `per_directory` URLs of its directory with the
+    /// lowest hashes.
+    pub fn sample(&self, urls: &[&Url]) -> Vec<bool> {
+        let hashes: Vec<u64> = urls
+            .iter()
+            .map(|url| fnv1a(canonicalize(url).as_str()))
+            .collect();
+        let mut kept: Vec<bool> = hashes
+            .iter()
+            .map(|hash| match self.fraction {
+                Some(fraction) => (*hash as f64 / u64::MAX as f64) < fraction,
+                None => true,
+            })
+            .collect();
+
+        if let Some(max) = self.per_directory {
+            let mut directories: HashMap<String, Vec<usize>> = HashMap::new();
+            for (i, url) in urls.iter().enumerate().filter(|(i, _)| kept[*i]) {
+                directories.entry(directory(url)).or_default().push(i);
+            }
+            for mut indices in directories.into_values() {
+                indices.sort_by_key(|i| hashes[*i]);
+                indices.into_iter().skip(max).for_each(|i| kept[i] = false);
+            }
+        }
+
+        kept
+    }
+}
+
+/// the origin and path of a URL up to -- and including -- its last `/`
+fn directory(url: &Url) -> String {
+    let url = canonicalize(url);
+    let path = url.path();
+    let path = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+
+    format!("{}{}", url.origin().ascii_serialization(), path)
+}
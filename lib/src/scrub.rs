@@ -0,0 +1,166 @@
+//! Scrubs personal data -- email addresses, phone numbers and IP addresses --
+//! from the text of results before they're written, so that a crawl's dataset
+//! can be shared.
+
+use crate::{
+    extract::{is_phone, normalize_phone, EMAIL, PHONE},
+    id::fnv1a,
+    redact::Redacted,
+};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::IpAddr;
+
+lazy_static! {
+    /// the words which hold a `.` or `:` and so may be an IPv4 or IPv6 address;
+    /// only those which parse as one are scrubbed (so `Foo::bar` isn't)
+    static ref IP: Regex = Regex::new(r"[0-9A-Za-z_:.]*[:.][0-9A-Za-z_:.]*").unwrap();
+}
+
+/// Masks the email addresses, phone numbers and IP addresses found in the
+/// strings of serialized results, e.g. `jane@example.com` is written as
+/// `[email]`. Nothing is scrubbed by default.
+///
+/// With `hash` set the mask carries a hash of the value (e.g.
+/// `[email:8f3c0a1b2d4e5f60]`) so that records which share an address can
+/// still be related. The hash is FNV-1a which is fast rather than secure: set a
+/// `salt` -- and keep it private -- so that the hashes can't be reversed by
+/// hashing guesses.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct Scrubber {
+    /// mask email addresses
+    pub emails: bool,
+    /// mask phone numbers; as with the `phones` extractor, numbers must be
+    /// international, have an area code in parenthesis or be at least 10
+    /// digits long
+    pub phones: bool,
+    /// mask IPv4 and IPv6 addresses
+    pub ips: bool,
+    /// write a hash of each value in its mask
+    pub hash: bool,
+    /// mixed into the hashes; it's redacted when the config is logged or
+    /// serialized
+    pub salt: Option<Redacted<String>>,
+}
+
+impl Scrubber {
+    /// a scrubber which masks email addresses, phone numbers and IP addresses
+    pub fn all() -> Self {
+        Scrubber::default().emails(true).phones(true).ips(true)
+    }
+
+    /// sets whether email addresses are masked
+    pub fn emails(mut self, emails: bool) -> Self {
+        self.emails = emails;
+
+        self
+    }
+
+    /// sets whether phone numbers are masked
+    pub fn phones(mut self, phones: bool) -> Self {
+        self.phones = phones;
+
+        self
+    }
+
+    /// sets whether IP addresses are masked
+    pub fn ips(mut self, ips: bool) -> Self {
+        self.ips = ips;
+
+        self
+    }
+
+    /// sets whether the masks carry a hash of the values
+    pub fn hashed(mut self, hash: bool) -> Self {
+        self.hash = hash;
+
+        self
+    }
+
+    /// sets the salt mixed into the hashes
+    pub fn salt(mut self, salt: &str) -> Self {
+        self.salt = Some(Redacted::new(salt.to_string()));
+
+        self
+    }
+
+    /// whether anything is scrubbed
+    pub fn is_enabled(&self) -> bool {
+        self.emails || self.phones || self.ips
+    }
+
+    /// scrubs the strings of a JSON value, however deeply they're nested;
+    /// the keys of objects are left as they are
+    pub fn scrub(&self, value: Value) -> Value {
+        if !self.is_enabled() {
+            return value;
+        }
+
+        match value {
+            Value::String(text) => Value::String(self.scrub_text(&text)),
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(|v| self.scrub(v)).collect())
+            }
+            Value::Object(map) => {
+                Value::Object(map.into_iter().map(|(k, v)| (k, self.scrub(v))).collect())
+            }
+            value => value,
+        }
+    }
+
+    /// scrubs some text; IP addresses are masked first so that they aren't
+    /// taken for phone numbers
+    pub fn scrub_text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        if self.ips {
+            text = IP
+                .replace_all(&text, |c: &Captures| {
+                    let word = &c[0];
+                    // the address may end a sentence
+                    let address = word.trim_end_matches(['.', ':']);
+                    match address.parse::<IpAddr>() {
+                        Ok(ip) => self.mask("ip", &ip.to_string()) + &word[address.len()..],
+                        Err(_) => word.to_string(),
+                    }
+                })
+                .into_owned();
+        }
+        if self.emails {
+            text = EMAIL
+                .replace_all(&text, |c: &Captures| {
+                    self.mask("email", &c[0].to_lowercase())
+                })
+                .into_owned();
+        }
+        if self.phones {
+            text = PHONE
+                .replace_all(&text, |c: &Captures| {
+                    let phone = normalize_phone(&c[0]);
+                    let digits = phone.trim_start_matches('+').len();
+                    match is_phone(&c[0]) && (7..=15).contains(&digits) {
+                        true => self.mask("phone", &phone),
+                        false => c[0].to_string(),
+                    }
+                })
+                .into_owned();
+        }
+
+        text
+    }
+
+    /// what's written in place of a value of the kind
+    fn mask(&self, kind: &str, value: &str) -> String {
+        match self.hash {
+            true => {
+                let salt = self.salt.as_ref().map(|s| s.expose().as_str());
+                let hash = fnv1a(&format!("{}{}", salt.unwrap_or_default(), value));
+                format!("[{}:{:016x}]", kind, hash)
+            }
+            false => format!("[{}]", kind),
+        }
+    }
+}
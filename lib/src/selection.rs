@@ -1,8 +1,13 @@
-use crate::elements;
+use crate::{elements, error::ScrapedError};
+use color_eyre::Result;
+use lazy_static::lazy_static;
 use scraper::{ElementRef, Selector};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 use tracing::trace;
 use url::Url;
 
@@ -50,6 +55,10 @@ pub struct Selection {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disabled: Option<bool>,
 
+    /// the kind of `<template>` the element was found within, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateKind>,
+
     /// other -- less used props -- can still be stored
     /// but they will be stored as a JSON hash value in
     /// this `other` property to avoid too many props.
@@ -58,7 +67,7 @@ pub struct Selection {
 }
 
 impl Selection {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Selection {
             id: None,
             class: None,
@@ -73,6 +82,7 @@ impl Selection {
             src: None,
             type_: None,
             disabled: None,
+            template: None,
 
             other: HashMap::new(),
         }
@@ -91,14 +101,13 @@ pub fn get_selection(el: ElementRef, url: &Url) -> Selection {
         .into_iter()
         .flatten()
         .next();
+    // an `href` which can't be resolved (e.g., `//[::1`) has no full URL
     selection.full_href = match &selection.href {
         Some(href) => {
             if href.starts_with("http") {
                 Some(href.to_string())
             } else {
-                let url = url.join(href).expect("full url should be parsable");
-
-                Some(format!("{}", url))
+                url.join(href).ok().map(|url| format!("{}", url))
             }
         }
         _ => None,
@@ -110,6 +119,7 @@ pub fn get_selection(el: ElementRef, url: &Url) -> Selection {
     selection.src = elements::src(&el);
     selection.type_ = elements::type_(&el);
     selection.disabled = elements::disabled(&el);
+    selection.template = elements::template(&el);
 
     trace!(
         "[{:?}] selection completed: {:?}, {:?}",
@@ -121,10 +131,80 @@ pub fn get_selection(el: ElementRef, url: &Url) -> Selection {
     selection
 }
 
-#[derive(Debug)]
+/// The kind of `<template>` element which markup was found within
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateKind {
+    /// inert markup which a browser doesn't render until it's cloned by a script
+    Template,
+    /// a declarative shadow root (`<template shadowrootmode="open">`) which a
+    /// browser renders in place of the template
+    ShadowRoot,
+}
+
+#[derive(Debug, Clone)]
 pub enum SelectorKind {
     /** a selector with a single DOM element as result */
-    Item(Selector),
+    Item(Arc<Selector>),
     /** a selector with a _list_ of DOM elements as a result */
-    List(Selector),
+    List(Arc<Selector>),
+    /** a selector for a `<table>` which is extracted into rows and columns */
+    Table(Arc<Selector>),
+}
+
+/// the most compiled selectors which are kept for reuse
+pub const COMPILED_SELECTORS: usize = 1024;
+
+/// the compiled selectors, keyed by their CSS, along with when each was last used
+#[derive(Default)]
+struct CompiledSelectors {
+    selectors: HashMap<String, (Arc<Selector>, u64)>,
+    tick: u64,
+}
+
+lazy_static! {
+    static ref COMPILED: Mutex<CompiledSelectors> = Mutex::new(CompiledSelectors::default());
+}
+
+/// Compiles a CSS selector once and shares it: every document (and every page
+/// of a crawl) which uses the same CSS gets the same `Arc<Selector>`. The
+/// least recently used selectors are dropped once more than
+/// `COMPILED_SELECTORS` have been compiled.
+pub fn compile_selector(selector: &str) -> Result<Arc<Selector>> {
+    let mut compiled = COMPILED.lock().unwrap();
+    compiled.tick += 1;
+    let tick = compiled.tick;
+    if let Some((selector, used)) = compiled.selectors.get_mut(selector) {
+        *used = tick;
+        return Ok(selector.clone());
+    }
+
+    let parsed = Arc::new(parse_selector(selector)?);
+    if compiled.selectors.len() >= COMPILED_SELECTORS {
+        let oldest = compiled
+            .selectors
+            .iter()
+            .min_by_key(|(_, (_, used))| *used)
+            .map(|(css, _)| css.clone());
+        if let Some(oldest) = oldest {
+            compiled.selectors.remove(&oldest);
+        }
+    }
+    compiled
+        .selectors
+        .insert(selector.to_string(), (parsed.clone(), tick));
+
+    Ok(parsed)
+}
+
+/// parses a CSS selector, returning a meaningful error rather than panicking
+/// when the selector is not valid
+pub fn parse_selector(selector: &str) -> Result<Selector> {
+    Selector::parse(selector).map_err(|e| {
+        ScrapedError::InvalidSelector {
+            selector: selector.to_string(),
+            reason: format!("{:?}", e.kind),
+        }
+        .into()
+    })
 }
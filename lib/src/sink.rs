@@ -0,0 +1,151 @@
+//! Destinations which receive the outcome of each page as soon as it's scraped
+//! (e.g., to stream results to a store or trigger work elsewhere).
+
+use crate::{
+    redact::Redacted,
+    results::PageOutcome,
+    retry::{self, RetryPolicy},
+    ScrapeConfig,
+};
+use async_trait::async_trait;
+use color_eyre::{eyre::eyre, Result};
+use reqwest::header::AUTHORIZATION;
+use std::{sync::Arc, time::Duration};
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+/// Receives the outcome of each page scraped by `ParsedDoc::results_graph()`
+/// or a `ConcurrentScrape`. A sink which fails is logged rather than failing
+/// the scrape.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// receives the outcome of a page
+    async fn send(&self, outcome: &PageOutcome) -> Result<()>;
+
+    /// called once every page has been sent; a sink which buffers its outcomes
+    /// should flush them
+    async fn finish(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// sends the outcome of a page to each of the sinks in turn
+#[instrument(name = "sink", skip_all, fields(sinks = sinks.len()))]
+pub(crate) async fn send(sinks: &[Arc<dyn Sink>], outcome: &PageOutcome) {
+    for sink in sinks {
+        if let Err(e) = sink.send(outcome).await {
+            warn!("a sink failed to receive a page: {:#}", e);
+        }
+    }
+}
+
+/// finishes each of the sinks
+pub(crate) async fn finish(sinks: &[Arc<dyn Sink>]) {
+    for sink in sinks {
+        if let Err(e) = sink.finish().await {
+            warn!("a sink failed to finish: {:#}", e);
+        }
+    }
+}
+
+/// A `Sink` which POSTs the results of each page that was scraped -- as JSON
+/// -- to an HTTP endpoint (e.g., the ingest service of a pipeline). Pages
+/// which failed or were blocked aren't sent. Requests which fail for
+/// transient reasons are retried.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    url: Url,
+    /// the `Authorization` header sent with each request
+    auth: Option<Redacted<String>>,
+    retry: RetryPolicy,
+    /// the request headers (e.g., `User-Agent`) sent and the clock which
+    /// paces the retries
+    config: ScrapeConfig,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    /// POSTs to the endpoint; requests are attempted three times by default
+    pub fn new(url: Url) -> Self {
+        WebhookSink {
+            url,
+            auth: None,
+            retry: RetryPolicy::new(3, Duration::from_millis(500), Duration::ZERO),
+            config: ScrapeConfig::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// sets the `Authorization` header (e.g., `Bearer <token>`)
+    pub fn auth(mut self, value: &str) -> Self {
+        self.auth = Some(Redacted::new(value.to_string()));
+
+        self
+    }
+
+    /// sets how requests which fail for transient reasons are retried
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+
+        self
+    }
+
+    /// sets the configuration whose request headers are sent and whose clock
+    /// paces the retries
+    pub fn config(mut self, config: ScrapeConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    async fn send(&self, outcome: &PageOutcome) -> Result<()> {
+        let page = match outcome {
+            PageOutcome::Ok(page) => page,
+            _ => return Ok(()),
+        };
+        let mut headers = self.config.request_headers()?;
+        if let Some(auth) = &self.auth {
+            headers.insert(AUTHORIZATION, auth.expose().parse()?);
+        }
+        let clock = self.config.clock.0.as_ref();
+
+        let mut attempt = 1;
+        loop {
+            let request = self
+                .client
+                .post(self.url.clone())
+                .headers(headers.clone())
+                .json(page);
+            let response = request.send().await;
+            let retryable = match &response {
+                Ok(r) => retry::is_transient_status(r.status().as_u16()),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !retryable || attempt >= self.retry.max_attempts {
+                let response = response?;
+                return match response.status().is_success() {
+                    true => Ok(()),
+                    false => Err(eyre!(
+                        "the webhook {} responded to the results of {} with a {} status",
+                        self.url,
+                        page.url,
+                        response.status()
+                    )),
+                };
+            }
+
+            let pause =
+                self.retry
+                    .pause_on(attempt, response.as_ref().ok().map(|r| r.headers()), clock);
+            debug!(
+                "retrying the webhook {} in {:?} (attempt {})",
+                self.url, pause, attempt
+            );
+            clock.sleep(pause).await;
+            attempt += 1;
+        }
+    }
+}
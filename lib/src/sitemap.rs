@@ -0,0 +1,113 @@
+use crate::{fetch::Fetcher, redirect, text::decode_entities, ScrapeConfig};
+use color_eyre::{eyre::eyre, Result};
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::io::Read;
+use tracing::{debug, warn};
+use url::Url;
+
+/// the most sitemaps which are requested when following sitemap indexes
+pub const MAX_SITEMAPS: usize = 1000;
+
+lazy_static! {
+    static ref LOC: Regex =
+        Regex::new(r"(?s)<(?:\w+:)?loc>\s*(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?\s*</(?:\w+:)?loc>")
+            .unwrap();
+    static ref INDEX: Regex = Regex::new(r"<(?:\w+:)?sitemapindex[\s>]").unwrap();
+}
+
+/// The content of a `sitemap.xml`: either the pages of a site or -- for a
+/// sitemap index -- the sitemaps which list them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sitemap {
+    Urls(Vec<Url>),
+    Index(Vec<Url>),
+}
+
+impl Sitemap {
+    /// parses a sitemap or sitemap index; `<loc>` values which aren't valid
+    /// URLs are skipped
+    pub fn parse(xml: &str) -> Sitemap {
+        let locs = LOC
+            .captures_iter(xml)
+            .filter_map(|caps| Url::parse(decode_entities(caps[1].trim()).as_str()).ok())
+            .collect();
+
+        match INDEX.is_match(xml) {
+            true => Sitemap::Index(locs),
+            false => Sitemap::Urls(locs),
+        }
+    }
+}
+
+/// decodes the body of a sitemap which was served gzipped (e.g., `sitemap.xml.gz`)
+fn decode(body: &[u8]) -> Result<String> {
+    match body.starts_with(&[0x1f, 0x8b]) {
+        true => {
+            let mut xml = String::new();
+            GzDecoder::new(body).read_to_string(&mut xml)?;
+            Ok(xml)
+        }
+        false => Ok(String::from_utf8_lossy(body).to_string()),
+    }
+}
+
+/// Requests a sitemap -- following any sitemap indexes -- and returns the URLs
+/// of the pages it lists. A sitemap listed by an index which can't be
+/// requested is skipped; only the failure of the first sitemap is an error.
+pub async fn urls(url: &Url, config: &ScrapeConfig, fetcher: &dyn Fetcher) -> Result<Vec<Url>> {
+    let mut queue = vec![url.clone()];
+    let mut seen: Vec<Url> = vec![];
+    let mut urls: Vec<Url> = vec![];
+
+    while let Some(sitemap) = queue.pop() {
+        if seen.contains(&sitemap) {
+            continue;
+        }
+        if seen.len() >= MAX_SITEMAPS {
+            warn!("stopped following sitemaps after {}", MAX_SITEMAPS);
+            break;
+        }
+        seen.push(sitemap.clone());
+
+        let request = |url: Url| async move {
+            let headers = config.request_headers_for(&url)?;
+            fetcher.fetch(&url, &headers).await
+        };
+        let result = match config.safety.check_resolved(&sitemap).await {
+            Ok(()) => redirect::follow(&sitemap, config, request)
+                .await
+                .map(|(page, _)| page),
+            Err(e) => Err(e),
+        };
+        let page = match result {
+            Ok(page) if (200..300).contains(&page.status) => page,
+            Ok(page) if seen.len() == 1 => {
+                return Err(eyre!("{} responded with a {} status", sitemap, page.status))
+            }
+            Err(e) if seen.len() == 1 => return Err(e),
+            _ => {
+                warn!(
+                    "skipping the sitemap {} which couldn't be requested",
+                    sitemap
+                );
+                continue;
+            }
+        };
+
+        match Sitemap::parse(&decode(&page.body)?) {
+            Sitemap::Index(sitemaps) => {
+                debug!("{} is an index of {} sitemaps", sitemap, sitemaps.len());
+                queue.extend(sitemaps.into_iter().rev());
+            }
+            Sitemap::Urls(pages) => pages.into_iter().for_each(|url| {
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }),
+        }
+    }
+
+    Ok(urls)
+}
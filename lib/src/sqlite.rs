@@ -0,0 +1,376 @@
+//! Persists the outcome of each page to a SQLite database -- one row per URL,
+//! replaced whenever the page is scraped again -- so that crawls can be
+//! resumed incrementally and their results queried with SQL rather than by
+//! re-parsing JSON dumps. The frontier of a crawl can be kept in the same
+//! database. Requires the `sqlite` feature.
+
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionKey;
+use crate::{
+    clock::{Clock, SharedClock},
+    frontier::{Discovery, Frontier},
+    results::PageOutcome,
+    sink::Sink,
+    url_norm::canonicalize,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use url::Url;
+
+/// The migrations which bring the schema up to date; the database's
+/// `user_version` is the number of migrations which have been applied.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE pages (
+        url TEXT PRIMARY KEY,
+        resolved_url TEXT,
+        outcome TEXT NOT NULL,
+        status INTEGER,
+        headers TEXT,
+        selections TEXT,
+        properties TEXT,
+        error TEXT,
+        fetched_at TEXT NOT NULL
+    );
+    CREATE INDEX pages_fetched_at ON pages (fetched_at);
+",
+    "
+    CREATE TABLE frontier (
+        url TEXT PRIMARY KEY,
+        requested_url TEXT NOT NULL,
+        state TEXT NOT NULL
+    );
+    CREATE INDEX frontier_state ON frontier (state);
+",
+    "
+    ALTER TABLE frontier ADD COLUMN discovery TEXT NOT NULL DEFAULT 'seed';
+",
+];
+
+/// A `Sink` which stores the outcome of each page in the `pages` table of a
+/// SQLite database:
+///
+/// - `url`, `resolved_url` and `fetched_at` (an RFC 3339 timestamp)
+/// - `outcome`: `ok`, `failed`, `blocked` or `cancelled`
+/// - `selections` and `properties` as JSON, for pages which were scraped
+/// - `status`, `headers` (as JSON) and `error`, for pages which failed or were
+///   blocked; the headers are only captured with `capture_error_body`
+///
+/// The schema is created -- or migrated -- when the database is opened.
+#[derive(Debug, Clone)]
+pub struct SqliteSink {
+    connection: Arc<Mutex<Connection>>,
+    /// the source of the fetch times
+    clock: SharedClock,
+    /// the key the headers, selections, properties and errors are encrypted with
+    #[cfg(feature = "encryption")]
+    key: Option<EncryptionKey>,
+}
+
+impl SqliteSink {
+    /// opens -- or creates -- the database at the path
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)
+            .context(format!("Failed to open the database: {}", path.display()))?;
+
+        SqliteSink::new(connection)
+    }
+
+    /// stores the pages in a database which was already opened (e.g., in memory)
+    pub fn new(mut connection: Connection) -> Result<Self> {
+        migrate(&mut connection)?;
+
+        Ok(SqliteSink {
+            connection: Arc::new(Mutex::new(connection)),
+            clock: SharedClock::default(),
+            #[cfg(feature = "encryption")]
+            key: None,
+        })
+    }
+
+    /// Encrypts the `headers`, `selections`, `properties` and `error` of each
+    /// page with the key -- see `EncryptionKey::decrypt()` -- while the URLs,
+    /// outcomes and fetch times remain queryable.
+    #[cfg(feature = "encryption")]
+    pub fn encrypt(mut self, key: EncryptionKey) -> Self {
+        self.key = Some(key);
+
+        self
+    }
+
+    /// the value of a column which may be encrypted
+    fn seal(&self, value: Option<String>) -> Result<Option<String>> {
+        #[cfg(feature = "encryption")]
+        if let (Some(key), Some(value)) = (&self.key, &value) {
+            return Ok(Some(key.encrypt(value.as_bytes())?));
+        }
+
+        Ok(value)
+    }
+
+    /// sets the clock which the fetch times are taken from
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock(clock);
+
+        self
+    }
+
+    /// When the page was last stored, if it has been; an incremental crawl
+    /// can skip the pages which were fetched recently.
+    pub fn fetched_at(&self, url: &Url) -> Result<Option<DateTime<Utc>>> {
+        let connection = self.connection()?;
+        let fetched_at: Option<String> = connection
+            .query_row(
+                "SELECT fetched_at FROM pages WHERE url = ?1",
+                params![url.as_str()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match fetched_at {
+            Some(at) => Ok(Some(DateTime::parse_from_rfc3339(&at)?.with_timezone(&Utc))),
+            None => Ok(None),
+        }
+    }
+
+    fn connection(&self) -> Result<MutexGuard<'_, Connection>> {
+        lock(&self.connection)
+    }
+}
+
+fn lock(connection: &Mutex<Connection>) -> Result<MutexGuard<'_, Connection>> {
+    connection
+        .lock()
+        .map_err(|_| eyre!("the database connection was poisoned"))
+}
+
+/// Applies the migrations which haven't been applied yet, each in a transaction
+/// of its own: a migration which fails is rolled back, leaving the database at
+/// the version before it.
+fn migrate(connection: &mut Connection) -> Result<()> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction
+            .execute_batch(&format!("{} PRAGMA user_version = {};", migration, i + 1))
+            .and_then(|_| transaction.commit())
+            .context(format!(
+                "Failed to migrate the database to version {}",
+                i + 1
+            ))?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl Sink for SqliteSink {
+    async fn send(&self, outcome: &PageOutcome) -> Result<()> {
+        let fetched_at = self
+            .clock
+            .0
+            .utc()
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        let (url, resolved_url, kind, status, headers, selections, properties, error) =
+            match outcome {
+                PageOutcome::Ok(page) => (
+                    &page.url,
+                    page.resolved_url.as_ref().map(|u| u.to_string()),
+                    "ok",
+                    None,
+                    None,
+                    Some(json!(page.data).to_string()),
+                    Some(json!(page.props).to_string()),
+                    None,
+                ),
+                PageOutcome::Failed(failure) => (
+                    &failure.url,
+                    None,
+                    "failed",
+                    failure.status,
+                    failure.headers.as_ref().map(|h| json!(h).to_string()),
+                    None,
+                    None,
+                    Some(failure.error.clone()),
+                ),
+                PageOutcome::Blocked(blocked) => (
+                    &blocked.url,
+                    None,
+                    "blocked",
+                    Some(blocked.status),
+                    None,
+                    None,
+                    None,
+                    Some(blocked.to_string()),
+                ),
+                PageOutcome::Cancelled(url) => {
+                    (url, None, "cancelled", None, None, None, None, None)
+                }
+            };
+
+        let (headers, selections, properties, error) = (
+            self.seal(headers)?,
+            self.seal(selections)?,
+            self.seal(properties)?,
+            self.seal(error)?,
+        );
+
+        // the write blocks on the disk -- and on the lock -- so it's kept off
+        // the scrape's tasks
+        let connection = self.connection.clone();
+        let url = url.to_string();
+        tokio::task::spawn_blocking(move || {
+            lock(&connection)?.execute(
+                "INSERT OR REPLACE INTO pages
+                    (url, resolved_url, outcome, status, headers, selections, properties, error, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    url,
+                    resolved_url,
+                    kind,
+                    status,
+                    headers,
+                    selections,
+                    properties,
+                    error,
+                    fetched_at
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await?
+    }
+}
+
+/// A `Frontier` which is kept in the `frontier` table of a SQLite database
+/// (which may be that of a `SqliteSink`): a row for each page, keyed by its
+/// canonical URL, along with the URL it was queued with, its `discovery` --
+/// `seed`, `sitemap` or `link` -- and its `state` -- `queued`, `taken` or
+/// `seen`. URLs are taken in the order they were queued; those which were
+/// taken but never seen are queued again when the database is reopened.
+#[derive(Debug, Clone)]
+pub struct SqliteFrontier {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteFrontier {
+    /// opens -- or creates -- the database at the path
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)
+            .context(format!("Failed to open the database: {}", path.display()))?;
+
+        SqliteFrontier::new(connection)
+    }
+
+    /// keeps the frontier in a database which was already opened (e.g., in memory)
+    pub fn new(mut connection: Connection) -> Result<Self> {
+        migrate(&mut connection)?;
+        // the pages being requested when the last crawl stopped are requested again
+        connection.execute(
+            "UPDATE frontier SET state = 'queued' WHERE state = 'taken'",
+            [],
+        )?;
+
+        Ok(SqliteFrontier {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    fn connection(&self) -> Result<MutexGuard<'_, Connection>> {
+        lock(&self.connection)
+    }
+}
+
+#[async_trait]
+impl Frontier for SqliteFrontier {
+    async fn enqueue(&self, url: &Url) -> Result<bool> {
+        self.enqueue_from(url, Discovery::Seed).await
+    }
+
+    async fn enqueue_from(&self, url: &Url, discovery: Discovery) -> Result<bool> {
+        let queued = self.connection()?.execute(
+            "INSERT OR IGNORE INTO frontier (url, requested_url, state, discovery)
+             VALUES (?1, ?2, 'queued', ?3)",
+            params![canonicalize(url).as_str(), url.as_str(), discovery.as_str()],
+        )?;
+
+        Ok(queued > 0)
+    }
+
+    async fn discovery(&self, url: &Url) -> Result<Option<Discovery>> {
+        let discovery: Option<String> = self
+            .connection()?
+            .query_row(
+                "SELECT discovery FROM frontier WHERE url = ?1",
+                params![canonicalize(url).as_str()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        discovery.map(|d| d.parse()).transpose()
+    }
+
+    async fn dequeue(&self) -> Result<Option<Url>> {
+        let connection = self.connection()?;
+        let next: Option<(i64, String)> = connection
+            .query_row(
+                "SELECT rowid, requested_url FROM frontier WHERE state = 'queued'
+                 ORDER BY rowid LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (id, url) = match next {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        connection.execute(
+            "UPDATE frontier SET state = 'taken' WHERE rowid = ?1",
+            params![id],
+        )?;
+
+        Ok(Some(Url::parse(&url)?))
+    }
+
+    async fn requeue(&self, url: &Url) -> Result<()> {
+        self.connection()?.execute(
+            "UPDATE frontier SET state = 'queued' WHERE url = ?1 AND state = 'taken'",
+            params![canonicalize(url).as_str()],
+        )?;
+
+        Ok(())
+    }
+
+    async fn mark_seen(&self, url: &Url) -> Result<()> {
+        self.connection()?.execute(
+            "INSERT INTO frontier (url, requested_url, state) VALUES (?1, ?2, 'seen')
+             ON CONFLICT (url) DO UPDATE SET state = 'seen'",
+            params![canonicalize(url).as_str(), url.as_str()],
+        )?;
+
+        Ok(())
+    }
+
+    async fn queued(&self) -> Result<usize> {
+        let queued: usize = self.connection()?.query_row(
+            "SELECT COUNT(*) FROM frontier WHERE state = 'queued'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(queued)
+    }
+}
@@ -0,0 +1,164 @@
+use crate::observe::{Observer, Progress};
+use color_eyre::Report;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+use url::Url;
+
+/// The statistics for a single host.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct HostStats {
+    /// pages which were successfully loaded
+    pub done: usize,
+    /// pages which failed to load
+    pub errors: usize,
+    /// the bytes of page content received
+    pub bytes: u64,
+}
+
+/// A point in time copy of the statistics of a scrape.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct StatsSnapshot {
+    /// pages which are waiting to be requested
+    pub queued: usize,
+    /// pages which have been requested but have not yet completed
+    pub in_flight: usize,
+    /// pages which were successfully loaded
+    pub done: usize,
+    /// pages which failed to load
+    pub errors: usize,
+    /// the bytes of page content received
+    pub bytes: u64,
+    /// the statistics broken down by host
+    pub hosts: HashMap<String, HostStats>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+    done: AtomicUsize,
+    errors: AtomicUsize,
+    bytes: AtomicU64,
+    hosts: Mutex<HashMap<String, HostStats>>,
+    observers: Observers,
+}
+
+/// the observers which are called as the counters change
+#[derive(Default)]
+struct Observers(RwLock<Vec<Arc<dyn Observer>>>);
+
+impl Debug for Observers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let count = self.0.read().map_or(0, |o| o.len());
+        write!(f, "Observers({})", count)
+    }
+}
+
+/// A handle to the statistics of a scrape which can be cloned and polled
+/// -- from another task -- while the scrape is running.
+#[derive(Debug, Clone, Default)]
+pub struct ScrapeStats {
+    counters: Arc<Counters>,
+}
+
+impl ScrapeStats {
+    /// a copy of the statistics at this point in time
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let c = &self.counters;
+        StatsSnapshot {
+            queued: c.queued.load(Ordering::Relaxed),
+            in_flight: c.in_flight.load(Ordering::Relaxed),
+            done: c.done.load(Ordering::Relaxed),
+            errors: c.errors.load(Ordering::Relaxed),
+            bytes: c.bytes.load(Ordering::Relaxed),
+            hosts: c.hosts.lock().expect("stats lock").clone(),
+        }
+    }
+
+    /// the counts of pages at this point in time; unlike a `snapshot()` it
+    /// leaves out the statistics of each host
+    pub fn progress(&self) -> Progress {
+        let c = &self.counters;
+        Progress {
+            queued: c.queued.load(Ordering::Relaxed),
+            in_flight: c.in_flight.load(Ordering::Relaxed),
+            done: c.done.load(Ordering::Relaxed),
+            errors: c.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Registers an observer which is called as pages are queued, requested
+    /// and loaded by every scrape sharing these statistics.
+    pub fn observe(&self, observer: impl Observer + 'static) {
+        self.counters
+            .observers
+            .0
+            .write()
+            .expect("stats lock")
+            .push(Arc::new(observer));
+    }
+
+    /// calls each observer with the progress
+    fn notify(&self, call: impl Fn(&dyn Observer, Progress)) {
+        let observers = self.counters.observers.0.read().expect("stats lock");
+        if observers.is_empty() {
+            return;
+        }
+        let progress = self.progress();
+        observers.iter().for_each(|o| call(o.as_ref(), progress));
+    }
+
+    fn host(&self, url: &Url, update: impl FnOnce(&mut HostStats)) {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let mut hosts = self.counters.hosts.lock().expect("stats lock");
+        update(hosts.entry(host).or_default());
+    }
+
+    /// pages have been added to the queue of pages to request
+    pub(crate) fn queued(&self, pages: usize) {
+        self.counters.queued.fetch_add(pages, Ordering::Relaxed);
+        self.notify(|o, progress| o.on_queued(pages, progress));
+    }
+
+    /// a page has been taken off the queue and requested
+    pub(crate) fn started(&self, url: &Url) {
+        self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+        self.counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        self.notify(|o, progress| o.on_page_started(url, progress));
+    }
+
+    /// a page was taken off the queue but not requested as the crawl was
+    /// cancelled
+    pub(crate) fn cancelled(&self, url: &Url) {
+        self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+        self.notify(|o, progress| o.on_cancelled(url, progress));
+    }
+
+    /// a page was successfully loaded
+    pub(crate) fn finished(&self, url: &Url, bytes: usize) {
+        let c = &self.counters;
+        c.in_flight.fetch_sub(1, Ordering::Relaxed);
+        c.done.fetch_add(1, Ordering::Relaxed);
+        c.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.host(url, |h| {
+            h.done += 1;
+            h.bytes += bytes as u64;
+        });
+        self.notify(|o, progress| o.on_page_finished(url, bytes, progress));
+    }
+
+    /// a page failed to load
+    pub(crate) fn failed(&self, url: &Url, error: &Report) {
+        self.counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.counters.errors.fetch_add(1, Ordering::Relaxed);
+        self.host(url, |h| h.errors += 1);
+        self.notify(|o, progress| o.on_error(url, error, progress));
+    }
+}
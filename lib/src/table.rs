@@ -0,0 +1,163 @@
+//! Extracts HTML tables into rows and columns.
+
+use crate::text::TextPolicy;
+use scraper::ElementRef;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// the largest `colspan` and `rowspan` honored; larger spans are clamped
+const MAX_SPAN: usize = 1000;
+
+/// A `<table>` normalized to a grid: a cell which spans several columns or
+/// rows (`colspan`/`rowspan`) has its text repeated in each position it
+/// covers, so every row has the same number of columns.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Table {
+    /// the text of the table's `<caption>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// the column headers; taken from `<thead>` or -- without one -- a first
+    /// row of only `<th>` cells. Several header rows are joined per column.
+    pub headers: Vec<String>,
+    /// the text of each cell of the body rows
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// parses a `<table>` element; cell text is normalized with the `TextPolicy`
+    pub fn parse(table: ElementRef, policy: &TextPolicy) -> Table {
+        let text = |el: ElementRef| {
+            let text = el.text().collect::<Vec<_>>().join(" ");
+            policy.apply(&text.split_whitespace().collect::<Vec<_>>().join(" "))
+        };
+
+        let mut caption = None;
+        // the rows of the table -- but not of nested tables -- and whether
+        // each is within the `<thead>`
+        let mut rows: Vec<(ElementRef, bool)> = vec![];
+        children(table).for_each(|el| match el.value().name() {
+            "caption" => caption = Some(text(el)),
+            "tr" => rows.push((el, false)),
+            section @ ("thead" | "tbody" | "tfoot") => children(el)
+                .filter(|r| r.value().name() == "tr")
+                .for_each(|r| rows.push((r, section == "thead"))),
+            _ => {}
+        });
+
+        let grid = grid(&rows, text);
+        let width = grid.iter().map(|r| r.len()).max().unwrap_or_default();
+        let mut grid = grid.into_iter().map(|mut row| {
+            row.resize(width, None);
+            row.into_iter()
+                .map(|c| c.unwrap_or_default())
+                .collect::<Vec<_>>()
+        });
+
+        let header_rows = match rows.iter().take_while(|(_, thead)| *thead).count() {
+            0 => match rows.first() {
+                Some((row, _)) if cells(*row).all(|c| c.value().name() == "th") => 1,
+                _ => 0,
+            },
+            n => n,
+        };
+        let headers =
+            (&mut grid)
+                .take(header_rows)
+                .fold(vec![String::new(); width], |mut headers, row| {
+                    headers.iter_mut().zip(row).for_each(|(header, cell)| {
+                        if !cell.is_empty() && *header != cell {
+                            if !header.is_empty() {
+                                header.push(' ');
+                            }
+                            header.push_str(&cell);
+                        }
+                    });
+                    headers
+                });
+
+        Table {
+            caption,
+            headers: match header_rows {
+                0 => vec![],
+                _ => headers,
+            },
+            rows: grid.collect(),
+        }
+    }
+
+    /// the cells of the column with the given header
+    pub fn column(&self, header: &str) -> Option<Vec<&str>> {
+        let idx = self.headers.iter().position(|h| h == header)?;
+
+        Some(self.rows.iter().map(|r| r[idx].as_str()).collect())
+    }
+
+    /// each body row keyed by the column headers; columns without a header
+    /// are skipped
+    pub fn records(&self) -> Vec<BTreeMap<String, String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                self.headers
+                    .iter()
+                    .zip(row)
+                    .filter(|(header, _)| !header.is_empty())
+                    .map(|(header, cell)| (header.to_string(), cell.to_string()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// the child elements of an element
+fn children(el: ElementRef) -> impl Iterator<Item = ElementRef> {
+    el.children().filter_map(ElementRef::wrap)
+}
+
+/// the `<td>` and `<th>` cells of a row
+fn cells(row: ElementRef) -> impl Iterator<Item = ElementRef> {
+    children(row).filter(|c| matches!(c.value().name(), "td" | "th"))
+}
+
+/// the span of a cell in one direction; a missing or invalid span is 1
+fn span(cell: ElementRef, attr: &str) -> usize {
+    cell.value()
+        .attr(attr)
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(1)
+        .min(MAX_SPAN)
+}
+
+/// lays the cells of the rows out on a grid, repeating spanned cells; a
+/// `rowspan` never extends the table beyond its last row
+fn grid(
+    rows: &[(ElementRef, bool)],
+    text: impl Fn(ElementRef) -> String,
+) -> Vec<Vec<Option<String>>> {
+    let mut grid: Vec<Vec<Option<String>>> = vec![vec![]; rows.len()];
+
+    rows.iter().enumerate().for_each(|(r, (row, _))| {
+        let mut col = 0;
+        cells(*row).for_each(|cell| {
+            // skip the positions already taken by a rowspan from above
+            while grid[r].get(col).is_some_and(|c| c.is_some()) {
+                col += 1;
+            }
+            let (text, colspan) = (text(cell), span(cell, "colspan"));
+            let rowspan = span(cell, "rowspan").min(rows.len() - r);
+
+            grid[r..r + rowspan].iter_mut().for_each(|row| {
+                if row.len() < col + colspan {
+                    row.resize(col + colspan, None);
+                }
+                row[col..col + colspan]
+                    .iter_mut()
+                    .for_each(|c| *c = Some(text.clone()));
+            });
+            col += colspan;
+        });
+    });
+
+    grid
+}
@@ -0,0 +1,47 @@
+//! Exports the spans of a crawl to an OpenTelemetry collector over OTLP so that
+//! crawls running in production appear in existing tracing backends. A crawl
+//! (`crawl`) is the parent of the span of each page (`page`), which is the
+//! parent of its request (`fetch`), parsing (`parse`) and delivery to the sinks
+//! (`sink`). Requires the `otel` feature.
+
+use color_eyre::Result;
+use opentelemetry::{
+    sdk::{trace, Resource},
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// the default endpoint of an OpenTelemetry collector's OTLP/gRPC receiver
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:4317";
+
+/// A `tracing` layer which exports spans in batches to the OTLP endpoint
+/// under the given service name. It must be created within a multi-threaded
+/// Tokio runtime and `shutdown()` should be called before exiting so that the
+/// spans which are still buffered are exported.
+pub fn otlp_layer<S>(
+    endpoint: &str,
+    service: &str,
+) -> Result<OpenTelemetryLayer<S, opentelemetry::sdk::trace::Tracer>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint);
+    let resource = Resource::new(vec![KeyValue::new("service.name", service.to_string())]);
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace::config().with_resource(resource))
+        .install_batch(opentelemetry::runtime::Tokio)?;
+
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// exports the spans which are still buffered and stops exporting
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
@@ -0,0 +1,138 @@
+//! `proptest` generators of the base URLs pages are served from and the
+//! `href`s found on them -- from ordinary links to the malformed values seen
+//! in the wild -- for testing that links are resolved and classified without
+//! panicking. Requires the `testing` feature.
+
+use proptest::{prelude::*, sample::select};
+use url::Url;
+
+/// a host name of one to three labels and a public suffix (e.g., `a-1.b.com`)
+pub fn host() -> impl Strategy<Value = String> {
+    (
+        prop::collection::vec("[a-z0-9]([a-z0-9-]{0,8}[a-z0-9])?", 1..=3),
+        select(vec!["com", "org", "co.uk", "dev", "io"]),
+    )
+        .prop_map(|(labels, suffix)| format!("{}.{}", labels.join("."), suffix))
+}
+
+/// a path of up to four segments which may contain reserved and
+/// percent-encoded characters
+pub fn path() -> impl Strategy<Value = String> {
+    prop::collection::vec(
+        prop_oneof![
+            "[a-zA-Z0-9_.~-]{1,10}",
+            Just("..".to_string()),
+            Just(".".to_string()),
+            Just("%20".to_string()),
+            Just("%zz".to_string()),
+            Just("caf\u{e9}".to_string()),
+        ],
+        0..=4,
+    )
+    .prop_map(|segments| format!("/{}", segments.join("/")))
+}
+
+/// An `http` or `https` URL which a page might be served from, with an
+/// optional port, query and trailing slash.
+pub fn base_url() -> impl Strategy<Value = Url> {
+    (
+        select(vec!["http", "https"]),
+        host(),
+        prop::option::of(1u16..),
+        path(),
+        prop::option::of("[a-z]{1,5}=[a-z0-9]{0,5}"),
+        any::<bool>(),
+    )
+        .prop_map(|(scheme, host, port, path, query, slash)| {
+            let mut url = format!("{}://{}", scheme, host);
+            if let Some(port) = port {
+                url.push_str(&format!(":{}", port));
+            }
+            url.push_str(&path);
+            if slash && !url.ends_with('/') {
+                url.push('/');
+            }
+            if let Some(query) = query {
+                url.push_str(&format!("?{}", query));
+            }
+
+            Url::parse(&url).expect("generated base URL should be parsable")
+        })
+}
+
+/// the `href`s which are malformed or otherwise unusual but turn up on pages
+fn unusual_href() -> impl Strategy<Value = String> {
+    select(vec![
+        "",
+        " ",
+        "#",
+        "?",
+        "//",
+        "///",
+        "http:",
+        "https://",
+        "http://[",
+        "http://[::1",
+        "//[::1",
+        "http://exa mple.com/",
+        "http://example.com:99999/",
+        "https-guide.html",
+        "\\\\server\\share",
+        "javascript:void(0)",
+        "javascript:alert('hi')",
+        "mailto:",
+        "tel:+1-555-0100",
+        "data:text/html,<p>hi</p>",
+        "file:///etc/passwd",
+        "file:",
+        "about:blank",
+        "%",
+        "%%",
+        "..",
+        "../../../../..",
+        "\u{0}",
+        "\u{feff}/bom",
+        "/\u{1F600}",
+    ])
+    .prop_map(String::from)
+}
+
+/// An `href` as found on a page: absolute, protocol-relative, root-relative
+/// and relative links, fragments and queries, links with other schemes,
+/// malformed values and arbitrary strings.
+pub fn href() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (base_url(), prop::option::of("[a-z]{1,6}")).prop_map(|(url, fragment)| match fragment {
+            Some(fragment) => format!("{}#{}", url, fragment),
+            None => url.to_string(),
+        }),
+        (host(), path()).prop_map(|(host, path)| format!("//{}{}", host, path)),
+        path(),
+        path().prop_map(|path| path.trim_start_matches('/').to_string()),
+        "[a-zA-Z0-9_.#]{1,12}",
+        "#[a-z0-9-]{0,8}",
+        "\\?[a-z]{1,5}=[a-z0-9%&=]{0,8}",
+        "[a-z][a-z0-9+.-]{0,6}:[^ ]{0,12}",
+        unusual_href(),
+        ".{0,20}",
+        any::<String>(),
+    ]
+}
+
+/// An HTML page with a link to each `href`; the `href`s are escaped as
+/// attribute values so that they reach the parser intact.
+pub fn page_with_links(hrefs: &[String]) -> String {
+    let links: String = hrefs
+        .iter()
+        .map(|href| {
+            let href = href
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            format!("<a class=\"link\" href=\"{}\">link</a>\n", href)
+        })
+        .collect();
+
+    format!("<html><body><nav>\n{}</nav></body></html>", links)
+}
@@ -0,0 +1,144 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref ENTITY: Regex =
+        Regex::new(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[a-zA-Z][a-zA-Z0-9]{1,31});").unwrap();
+    static ref WHITESPACE: Regex = Regex::new(r"\s+").unwrap();
+}
+
+/// named entities which are left in text by pages which encode twice
+const ENTITIES: [(&str, char); 20] = [
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("ndash", '\u{2013}'),
+    ("mdash", '\u{2014}'),
+    ("hellip", '\u{2026}'),
+    ("lsquo", '\u{2018}'),
+    ("rsquo", '\u{2019}'),
+    ("sbquo", '\u{201a}'),
+    ("ldquo", '\u{201c}'),
+    ("rdquo", '\u{201d}'),
+    ("bdquo", '\u{201e}'),
+    ("laquo", '\u{ab}'),
+    ("raquo", '\u{bb}'),
+    ("copy", '\u{a9}'),
+    ("reg", '\u{ae}'),
+    ("euro", '\u{20ac}'),
+];
+
+/// typographic characters and their ASCII equivalents
+const TYPOGRAPHIC: [(char, &str); 14] = [
+    ('\u{2018}', "'"),
+    ('\u{2019}', "'"),
+    ('\u{201a}', "'"),
+    ('\u{201b}', "'"),
+    ('\u{201c}', "\""),
+    ('\u{201d}', "\""),
+    ('\u{201e}', "\""),
+    ('\u{ab}', "\""),
+    ('\u{bb}', "\""),
+    ('\u{2010}', "-"),
+    ('\u{2013}', "-"),
+    ('\u{2014}', "-"),
+    ('\u{2212}', "-"),
+    ('\u{2026}', "..."),
+];
+
+/// Controls how the text of a selection is normalized.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct TextPolicy {
+    /// decode entities which are left in the text because the page encoded
+    /// them twice (e.g. `&amp;nbsp;`)
+    pub decode_entities: bool,
+    /// replace non-breaking and other unicode spaces with a plain space
+    pub plain_spaces: bool,
+    /// replace curly quotes, dashes and ellipses with their ASCII equivalents
+    pub ascii_punctuation: bool,
+    /// collapse runs of whitespace into a single space
+    pub collapse_whitespace: bool,
+}
+
+impl Default for TextPolicy {
+    fn default() -> Self {
+        TextPolicy {
+            decode_entities: true,
+            plain_spaces: false,
+            ascii_punctuation: false,
+            collapse_whitespace: false,
+        }
+    }
+}
+
+impl TextPolicy {
+    /// a policy which produces text which is easy to match on: entities are
+    /// decoded, spaces are plain and punctuation is ASCII
+    pub fn normalized() -> Self {
+        TextPolicy {
+            decode_entities: true,
+            plain_spaces: true,
+            ascii_punctuation: true,
+            collapse_whitespace: true,
+        }
+    }
+
+    /// normalizes text according to the policy
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = match self.decode_entities {
+            true => decode_entities(text),
+            false => text.to_string(),
+        };
+        if self.plain_spaces {
+            text = text
+                .chars()
+                .map(|c| match c.is_whitespace() && c != '\n' && c != '\t' {
+                    true => ' ',
+                    false => c,
+                })
+                .collect();
+        }
+        if self.ascii_punctuation {
+            text = text
+                .chars()
+                .map(|c| match TYPOGRAPHIC.iter().find(|(t, _)| *t == c) {
+                    Some((_, ascii)) => ascii.to_string(),
+                    None => c.to_string(),
+                })
+                .collect();
+        }
+        if self.collapse_whitespace {
+            text = WHITESPACE.replace_all(text.trim(), " ").to_string();
+        }
+
+        text
+    }
+}
+
+/// Decodes the numeric and common named entities in text.
+pub fn decode_entities(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |c: &Captures| {
+            let entity = &c[1];
+            let decoded = match entity.strip_prefix('#') {
+                Some(n) => match n.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => n.parse().ok(),
+                }
+                .and_then(char::from_u32),
+                None => ENTITIES
+                    .iter()
+                    .find(|(name, _)| *name == entity)
+                    .map(|(_, c)| *c),
+            };
+
+            decoded.map_or_else(|| c[0].to_string(), String::from)
+        })
+        .to_string()
+}
@@ -0,0 +1,268 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Hints which help to parse dates which are ambiguous without knowing where
+/// they came from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct DateHints {
+    /// the locale of the page (e.g. `en-US` or `de`); numeric dates are read
+    /// month first for `en-US` and `en` and day first for all other locales
+    pub locale: Option<String>,
+    /// explicitly sets whether numeric dates are read day first (e.g. 03/01/2024
+    /// is the 3rd of January), overriding the locale
+    pub day_first: Option<bool>,
+}
+
+impl DateHints {
+    pub fn locale(locale: &str) -> Self {
+        DateHints {
+            locale: Some(locale.to_string()),
+            day_first: None,
+        }
+    }
+
+    fn day_first(&self) -> bool {
+        self.day_first.unwrap_or_else(|| {
+            let locale = self.locale.as_deref().unwrap_or("en").to_lowercase();
+            !(locale == "en" || locale.starts_with("en-us") || locale.starts_with("en_us"))
+        })
+    }
+}
+
+/// month names and abbreviations in English, German, French and Spanish
+const MONTHS: &[(&str, u32)] = &[
+    ("january", 1),
+    ("jan", 1),
+    ("januar", 1),
+    ("jän", 1),
+    ("janvier", 1),
+    ("janv", 1),
+    ("enero", 1),
+    ("ene", 1),
+    ("february", 2),
+    ("feb", 2),
+    ("februar", 2),
+    ("février", 2),
+    ("fevrier", 2),
+    ("févr", 2),
+    ("fév", 2),
+    ("febrero", 2),
+    ("march", 3),
+    ("mar", 3),
+    ("märz", 3),
+    ("maerz", 3),
+    ("mär", 3),
+    ("mrz", 3),
+    ("mars", 3),
+    ("marzo", 3),
+    ("april", 4),
+    ("apr", 4),
+    ("avril", 4),
+    ("avr", 4),
+    ("abril", 4),
+    ("abr", 4),
+    ("may", 5),
+    ("mai", 5),
+    ("mayo", 5),
+    ("june", 6),
+    ("jun", 6),
+    ("juni", 6),
+    ("juin", 6),
+    ("junio", 6),
+    ("july", 7),
+    ("jul", 7),
+    ("juli", 7),
+    ("juillet", 7),
+    ("juil", 7),
+    ("julio", 7),
+    ("august", 8),
+    ("aug", 8),
+    ("août", 8),
+    ("aout", 8),
+    ("agosto", 8),
+    ("ago", 8),
+    ("september", 9),
+    ("sep", 9),
+    ("sept", 9),
+    ("septembre", 9),
+    ("septiembre", 9),
+    ("october", 10),
+    ("oct", 10),
+    ("oktober", 10),
+    ("okt", 10),
+    ("octobre", 10),
+    ("octubre", 10),
+    ("november", 11),
+    ("nov", 11),
+    ("noviembre", 11),
+    ("december", 12),
+    ("dec", 12),
+    ("dezember", 12),
+    ("dez", 12),
+    ("décembre", 12),
+    ("decembre", 12),
+    ("déc", 12),
+    ("diciembre", 12),
+];
+
+/// words for "today" and "yesterday" along with how many days ago they are
+const DAYS_AGO: [(&str, i64); 9] = [
+    ("today", 0),
+    ("heute", 0),
+    ("aujourd'hui", 0),
+    ("hoy", 0),
+    ("yesterday", 1),
+    ("gestern", 1),
+    ("hier", 1),
+    ("ayer", 1),
+    ("now", 0),
+];
+
+lazy_static! {
+    /// relative dates such as "2 days ago", "vor 2 Tagen", "il y a 2 jours" or "hace 2 días"
+    static ref RELATIVE: Regex = Regex::new(
+        r"(?i)^(?:(?P<en>\d+|an?|one)\s+(?P<en_unit>\w+?)s?\s+ago|vor\s+(?P<de>\d+|einem|einer)\s+(?P<de_unit>\w+)|il\s+y\s+a\s+(?P<fr>\d+|une?)\s+(?P<fr_unit>\w+)|hace\s+(?P<es>\d+|una?)\s+(?P<es_unit>\w+))$"
+    )
+    .unwrap();
+    static ref TIME: Regex =
+        Regex::new(r"(?i)\b(\d{1,2}):(\d{2})(?::(\d{2}))?\s*(am|pm)?\b").unwrap();
+}
+
+/// Parses a human readable date -- e.g. "Jan 3, 2024", "3. Januar 2024",
+/// "03/01/2024", "vor 2 Tagen" or an ISO 8601 string -- into an RFC 3339
+/// timestamp. Relative dates are relative to the current time.
+pub fn parse_date(text: &str, hints: &DateHints) -> Option<String> {
+    parse_date_at(text, hints, Utc::now())
+}
+
+/// Parses a human readable date as `parse_date()` does, with relative dates
+/// being relative to `now`.
+pub fn parse_date_at(text: &str, hints: &DateHints, now: DateTime<Utc>) -> Option<String> {
+    let text = text.trim();
+    let format = |dt: DateTime<Utc>| dt.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(text) {
+        return Some(dt.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    for fmt in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, fmt) {
+            return Some(format(dt.and_utc()));
+        }
+    }
+
+    if let Some(ago) = relative(text) {
+        return Some(format(now - ago));
+    }
+
+    let (date, time) = absolute(text, hints)?;
+    Some(format(date.and_time(time).and_utc()))
+}
+
+/// the duration described by a relative date such as "2 days ago"
+fn relative(text: &str) -> Option<Duration> {
+    let lower = text.to_lowercase();
+    if let Some((_, days)) = DAYS_AGO.iter().find(|(word, _)| *word == lower) {
+        return Some(Duration::days(*days));
+    }
+
+    let captures = RELATIVE.captures(text)?;
+    let (count, unit) = ["en", "de", "fr", "es"].iter().find_map(|lang| {
+        let count = captures.name(lang)?.as_str();
+        let unit = captures.name(&format!("{}_unit", lang))?.as_str();
+        Some((count, unit.to_lowercase()))
+    })?;
+    let count: i64 = count.parse().unwrap_or(1);
+
+    let seconds = match unit.as_str() {
+        u if u.starts_with("sec") || u.starts_with("sek") || u.starts_with("seg") => 1,
+        u if u.starts_with("min") => 60,
+        u if u.starts_with("hour") || u.starts_with("stund") || u.starts_with("heure") => 3_600,
+        u if u.starts_with("hora") => 3_600,
+        u if u.starts_with("day") || u.starts_with("tag") || u.starts_with("jour") => 86_400,
+        u if u.starts_with("día") || u.starts_with("dia") => 86_400,
+        u if u.starts_with("week") || u.starts_with("woche") || u.starts_with("semain") => 604_800,
+        u if u.starts_with("semana") => 604_800,
+        u if u.starts_with("month") || u.starts_with("monat") || u == "mois" => 2_592_000,
+        u if u.starts_with("mes") => 2_592_000,
+        u if u.starts_with("year") || u.starts_with("jahr") || u.starts_with("an") => 31_536_000,
+        u if u.starts_with("año") || u.starts_with("ano") => 31_536_000,
+        _ => return None,
+    };
+
+    Some(Duration::seconds(count * seconds))
+}
+
+/// an absolute date with an optional time of day, e.g. "Jan 3, 2024 10:30 pm"
+fn absolute(text: &str, hints: &DateHints) -> Option<(NaiveDate, NaiveTime)> {
+    let lower = text.to_lowercase();
+    let time = TIME.captures(&lower).and_then(|c| {
+        let mut hour: u32 = c[1].parse().ok()?;
+        match c.get(4).map(|m| m.as_str()) {
+            Some("pm") if hour < 12 => hour += 12,
+            Some("am") if hour == 12 => hour = 0,
+            _ => (),
+        }
+        let min = c[2].parse().ok()?;
+        let sec = c.get(3).and_then(|s| s.as_str().parse().ok()).unwrap_or(0);
+        NaiveTime::from_hms_opt(hour, min, sec)
+    });
+    let date = TIME.replace_all(&lower, " ");
+
+    let mut month_name: Option<u32> = None;
+    let mut numbers: Vec<(u32, usize)> = vec![];
+    date.split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|word| !word.is_empty())
+        .for_each(|word| {
+            if let Some((_, m)) = MONTHS.iter().find(|(name, _)| *name == word) {
+                month_name = month_name.or(Some(*m));
+                return;
+            }
+            // ordinals such as "1st" or "1er"
+            let digits: String = word.chars().take_while(|c| c.is_ascii_digit()).collect();
+            let suffix = &word[digits.len()..];
+            if !digits.is_empty() && ["", "st", "nd", "rd", "th", "er", "e", "º"].contains(&suffix)
+            {
+                if let Ok(n) = digits.parse() {
+                    numbers.push((n, digits.len()));
+                }
+            }
+        });
+
+    let (year, month, day) = match (month_name, numbers.as_slice()) {
+        (Some(month), [(a, _), (b, b_len)]) => match *b_len == 4 || *b > 31 {
+            true => (*b, month, *a),
+            false => (*a, month, *b),
+        },
+        (None, [(y, 4), (m, _), (d, _)]) => (*y, *m, *d),
+        (None, [(a, _), (b, _), (y, y_len)]) => {
+            let year = match y_len {
+                2 => 2000 + y,
+                _ => *y,
+            };
+            let day_first = match (*a, *b) {
+                (a, _) if a > 12 => true,
+                (_, b) if b > 12 => false,
+                _ => hints.day_first(),
+            };
+            match day_first {
+                true => (year, *b, *a),
+                false => (year, *a, *b),
+            }
+        }
+        _ => return None,
+    };
+
+    let date = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+    Some((date, time.unwrap_or(NaiveTime::MIN)))
+}
@@ -0,0 +1,114 @@
+use super::Transform;
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// How the dates which transforms parse are written.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DateFormat {
+    /// an RFC 3339 (ISO 8601) timestamp in UTC (e.g. `2024-01-03T00:00:00Z`)
+    #[default]
+    Rfc3339,
+    /// an ISO 8601 calendar date without the time (e.g. `2024-01-03`)
+    Date,
+    /// the number of seconds since the Unix epoch
+    Unix,
+    /// the numeric date of a locale (e.g. `01/03/2024` for `en-US` or
+    /// `03.01.2024` for `de`)
+    Locale(String),
+}
+
+impl DateFormat {
+    /// the `strftime` pattern of a locale's numeric dates
+    fn locale_pattern(locale: &str) -> &'static str {
+        let locale = locale.to_lowercase().replace('_', "-");
+        let language = locale.split('-').next().unwrap_or_default();
+        match language {
+            "en" if locale == "en" || locale.starts_with("en-us") => "%m/%d/%Y",
+            "de" | "ru" | "pl" | "cs" | "fi" | "nb" | "no" | "da" | "tr" | "uk" => "%d.%m.%Y",
+            "ja" | "zh" => "%Y/%m/%d",
+            "sv" | "lt" => "%Y-%m-%d",
+            "nl" => "%d-%m-%Y",
+            _ => "%d/%m/%Y",
+        }
+    }
+
+    /// rewrites an RFC 3339 timestamp; anything else is left as it is
+    fn format(&self, timestamp: &str) -> Value {
+        let dt = match DateTime::parse_from_rfc3339(timestamp) {
+            Ok(dt) => dt,
+            Err(_) => return json!(timestamp),
+        };
+        match self {
+            DateFormat::Rfc3339 => json!(dt.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            DateFormat::Date => json!(dt.format("%Y-%m-%d").to_string()),
+            DateFormat::Unix => json!(dt.timestamp()),
+            DateFormat::Locale(locale) => {
+                json!(dt.format(DateFormat::locale_pattern(locale)).to_string())
+            }
+        }
+    }
+}
+
+/// How the typed values which transforms produce are written, so that the
+/// results are consistent for their consumers whatever the locale of the pages
+/// they were scraped from. By default dates are RFC 3339 timestamps and numbers
+/// are JSON numbers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatPolicy {
+    /// how dates are written
+    pub dates: DateFormat,
+    /// writes numbers -- including the amounts of prices and the seconds of
+    /// durations -- as strings with this decimal separator (e.g. `,`)
+    pub decimal_separator: Option<char>,
+}
+
+impl FormatPolicy {
+    /// sets how dates are written
+    pub fn dates(mut self, format: DateFormat) -> Self {
+        self.dates = format;
+
+        self
+    }
+
+    /// writes numbers as strings with the decimal separator
+    pub fn decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = Some(separator);
+
+        self
+    }
+
+    /// Writes a value which the transform produced as the policy says; the
+    /// values of a list are each written in turn.
+    pub fn apply(&self, transform: &Transform, value: Value) -> Value {
+        match (transform, value) {
+            (_, Value::Array(values)) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|v| self.apply(transform, v))
+                    .collect(),
+            ),
+            (Transform::Date(_), Value::String(timestamp)) => self.dates.format(&timestamp),
+            (Transform::Price(_), Value::Object(mut price)) => {
+                if let Some(amount) = price.remove("amount") {
+                    price.insert("amount".to_string(), self.number(amount));
+                }
+                Value::Object(price)
+            }
+            (Transform::Number { .. } | Transform::Duration, number) => self.number(number),
+            (_, value) => value,
+        }
+    }
+
+    /// writes a number with the decimal separator, when there's one
+    fn number(&self, value: Value) -> Value {
+        match (self.decimal_separator, value.as_f64()) {
+            (Some(separator), Some(n)) => json!(n.to_string().replace('.', &separator.to_string())),
+            _ => value,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Transforms turn the text of a selector's result into typed values so that
+//! properties such as dates, prices and counts come out machine-usable without writing a callback.
+
+mod date;
+mod format;
+mod number;
+mod price;
+
+pub use date::{parse_date, parse_date_at, DateHints};
+pub use format::{DateFormat, FormatPolicy};
+pub use number::{parse_duration, parse_number};
+pub use price::{parse_price, Price, PriceHints};
+
+use crate::{results::ResultKind, selection::Selection};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A transform which derives a property from the text of a selector's result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Transform {
+    /// parses a human readable date into an RFC 3339 timestamp
+    Date(DateHints),
+    /// parses a price into its `{ amount, currency }`
+    Price(PriceHints),
+    /// parses a number with an optional magnitude suffix (e.g. "1.2k") or a
+    /// percentage, which results in a ratio
+    Number {
+        /// whether a comma is the decimal separator when it can't be inferred
+        #[serde(default)]
+        decimal_comma: Option<bool>,
+    },
+    /// parses a duration (e.g. "2 min read" or "1:05:30") into seconds
+    Duration,
+}
+
+impl Transform {
+    /// transforms a single piece of text; text which can't be transformed
+    /// results in `null`
+    pub fn apply(&self, text: &str) -> Value {
+        match self {
+            Transform::Date(hints) => json!(parse_date(text, hints)),
+            Transform::Price(hints) => json!(parse_price(text, hints)),
+            Transform::Number { decimal_comma } => json!(parse_number(text, *decimal_comma)),
+            Transform::Duration => json!(parse_duration(text)),
+        }
+    }
+
+    /// transforms a selector's result; a list results in a list of values
+    pub fn apply_to(&self, result: Option<&ResultKind>) -> Value {
+        match result {
+            Some(ResultKind::Item(selection)) => self.apply_to_selection(selection),
+            Some(ResultKind::List(list)) => {
+                Value::Array(list.iter().map(|s| self.apply_to_selection(s)).collect())
+            }
+            Some(ResultKind::Property(Value::String(text))) => self.apply(text),
+            _ => Value::Null,
+        }
+    }
+
+    /// a selection's `content` attribute (e.g., of a `meta` tag) is preferred
+    /// over its text
+    fn apply_to_selection(&self, selection: &Selection) -> Value {
+        match selection.content.as_ref().or(selection.text.as_ref()) {
+            Some(text) => self.apply(text),
+            None => Value::Null,
+        }
+    }
+}
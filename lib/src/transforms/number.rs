@@ -0,0 +1,72 @@
+use super::price::normalize;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref NUMBER: Regex =
+        Regex::new(r"(?i)(-?\d[\d.,'\u{a0}]*)\s*(k|m|b|bn|thousand|million|billion)?\b")
+            .unwrap();
+    static ref DURATION_PART: Regex = Regex::new(
+        r"(?i)(\d+(?:[.,]\d+)?)\s*(h|hrs?|hours?|std|stunden?|m|mins?|minutes?|minuten?|s|secs?|seconds?|sek|sekunden?|d|days?|tage?n?)\b"
+    )
+    .unwrap();
+    static ref CLOCK: Regex = Regex::new(r"^(?:(\d+):)?(\d{1,2}):(\d{2})$").unwrap();
+}
+
+/// Parses a number with an optional magnitude suffix -- e.g. "1.2k stars" or
+/// "3.4M downloads" -- into its value. Percentages (e.g. "85%") are parsed
+/// into a ratio (0.85).
+pub fn parse_number(text: &str, decimal_comma: Option<bool>) -> Option<f64> {
+    let captures = NUMBER.captures(text)?;
+    let digits = captures
+        .get(1)?
+        .as_str()
+        .trim_end_matches(|c: char| !c.is_ascii_digit());
+    let value: f64 = normalize(digits, decimal_comma)?.parse().ok()?;
+    let value = match digits.starts_with('-') {
+        true => -value,
+        false => value,
+    };
+
+    let suffix = captures.get(2).map(|s| s.as_str().to_lowercase());
+    let multiplier = match suffix.as_deref() {
+        Some("k") | Some("thousand") => 1e3,
+        Some("m") | Some("million") => 1e6,
+        Some("b") | Some("bn") | Some("billion") => 1e9,
+        _ if text[captures.get(0)?.end()..].trim_start().starts_with('%') => 0.01,
+        _ => 1.0,
+    };
+
+    Some(value * multiplier)
+}
+
+/// Parses a duration -- e.g. "2 min read", "1h 30m", "90 seconds" or "1:05:30"
+/// -- into a number of seconds.
+pub fn parse_duration(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if let Some(c) = CLOCK.captures(text) {
+        let part = |i: usize| c.get(i).and_then(|p| p.as_str().parse::<f64>().ok());
+        return Some(part(1).unwrap_or(0.0) * 3_600.0 + part(2)? * 60.0 + part(3)?);
+    }
+
+    let seconds: Vec<f64> = DURATION_PART
+        .captures_iter(text)
+        .filter_map(|c| {
+            let value: f64 = c[1].replace(',', ".").parse().ok()?;
+            let unit = c[2].to_lowercase();
+            let seconds = match unit.chars().next()? {
+                'd' | 't' => 86_400.0,
+                'h' => 3_600.0,
+                'm' => 60.0,
+                _ if unit.starts_with("std") => 3_600.0,
+                _ => 1.0,
+            };
+            Some(value * seconds)
+        })
+        .collect();
+
+    match seconds.is_empty() {
+        true => None,
+        false => Some(seconds.iter().sum()),
+    }
+}
@@ -0,0 +1,121 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Hints which help to parse prices which don't say everything about themselves.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct PriceHints {
+    /// the ISO 4217 currency code used when the price doesn't include one
+    pub currency: Option<String>,
+    /// explicitly sets whether a comma is the decimal separator (e.g. 1.299,00)
+    /// for prices where it can't be inferred
+    pub decimal_comma: Option<bool>,
+}
+
+/// A price parsed into its amount and currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Price {
+    pub amount: f64,
+    /// the ISO 4217 currency code, when known
+    pub currency: Option<String>,
+}
+
+/// currency symbols and the ISO 4217 code they stand for; longer symbols are
+/// listed before those they contain
+const SYMBOLS: [(&str, &str); 16] = [
+    ("US$", "USD"),
+    ("C$", "CAD"),
+    ("A$", "AUD"),
+    ("NZ$", "NZD"),
+    ("R$", "BRL"),
+    ("zł", "PLN"),
+    ("Kč", "CZK"),
+    ("Fr.", "CHF"),
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("₹", "INR"),
+    ("₽", "RUB"),
+    ("₩", "KRW"),
+    ("₺", "TRY"),
+    ("$", "USD"),
+];
+
+lazy_static! {
+    static ref AMOUNT: Regex = Regex::new(r"\d[\d.,'\s\u{a0}\u{202f}]*").unwrap();
+    static ref CODE: Regex = Regex::new(r"\b[A-Z]{3}\b").unwrap();
+}
+
+/// Parses a price such as "€1.299,00", "$12.99", "12,99 zł" or "CHF 1'200.50"
+/// into its amount and currency; locale specific thousands and decimal
+/// separators are inferred from the price itself where possible.
+pub fn parse_price(text: &str, hints: &PriceHints) -> Option<Price> {
+    let amount = AMOUNT
+        .find(text)?
+        .as_str()
+        .trim_end_matches(|c: char| !c.is_ascii_digit());
+    let negative = text[..text.find(amount)?].trim_end().ends_with('-');
+
+    let currency = CODE
+        .find(text)
+        .map(|m| m.as_str().to_string())
+        .or_else(|| {
+            SYMBOLS
+                .iter()
+                .find(|(symbol, _)| text.contains(symbol))
+                .map(|(_, code)| code.to_string())
+        })
+        .or_else(|| hints.currency.clone());
+
+    let amount = normalize(amount, hints.decimal_comma)?
+        .parse::<f64>()
+        .ok()?;
+
+    Some(Price {
+        amount: if negative { -amount } else { amount },
+        currency,
+    })
+}
+
+/// converts an amount with any thousands and decimal separators into one
+/// which can be parsed as a float; a single separator followed by three digits
+/// is taken to separate thousands unless `decimal_comma` says otherwise
+pub(super) fn normalize(amount: &str, decimal_comma: Option<bool>) -> Option<String> {
+    let amount: String = amount
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect();
+    let dots = amount.matches('.').count();
+    let commas = amount.matches(',').count();
+
+    let decimal = match (dots, commas) {
+        (0, 0) => None,
+        // the last separator is the decimal separator when both are used
+        (_, c) if c > 0 && dots > 0 => amount.rfind(['.', ','].as_ref()).map(|i| &amount[i..=i]),
+        // a separator which is repeated must separate thousands
+        (d, 0) if d > 1 => None,
+        (0, c) if c > 1 => None,
+        _ => {
+            let separator = if dots == 1 { "." } else { "," };
+            let fraction = amount.rsplit(separator).next()?.len();
+            match (fraction, decimal_comma) {
+                (3, None) => None,
+                (3, Some(decimal_comma)) => {
+                    (decimal_comma == (separator == ",")).then_some(separator)
+                }
+                _ => Some(separator),
+            }
+        }
+    };
+
+    Some(match decimal {
+        Some(decimal) => {
+            let (whole, fraction) = amount.rsplit_once(decimal)?;
+            let whole: String = whole.chars().filter(|c| c.is_ascii_digit()).collect();
+            format!("{}.{}", whole, fraction)
+        }
+        None => amount.chars().filter(|c| c.is_ascii_digit()).collect(),
+    })
+}
@@ -0,0 +1,53 @@
+//! Normalizes URLs so that the same page is recognized under trivially
+//! different URLs (e.g., with a fragment, tracking parameters or its query
+//! parameters in another order) and requested only once.
+
+use std::collections::HashSet;
+use url::Url;
+
+/// query parameters which only track where a visitor came from
+const TRACKING_PARAMS: [&str; 6] = ["fbclid", "gclid", "msclkid", "mc_cid", "mc_eid", "_ga"];
+
+/// whether a query parameter only tracks where a visitor came from
+pub fn is_tracking(param: &str) -> bool {
+    param.starts_with("utm_") || TRACKING_PARAMS.contains(&param)
+}
+
+/// Canonicalizes a URL by removing its fragment, tracking parameters and any
+/// trailing slash and by sorting its query parameters. Parsing a `Url` has
+/// already lowercased its scheme and host, dropped a default port and resolved
+/// the `.` and `..` segments of its path.
+pub fn canonicalize(url: &Url) -> Url {
+    let mut canonical = url.clone();
+    canonical.set_fragment(None);
+
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| !is_tracking(k))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    params.sort();
+    match params.is_empty() {
+        true => canonical.set_query(None),
+        false => {
+            canonical.query_pairs_mut().clear().extend_pairs(params);
+        }
+    }
+
+    if canonical.path().len() > 1 && canonical.path().ends_with('/') {
+        let path = canonical.path().trim_end_matches('/').to_string();
+        canonical.set_path(&path);
+    }
+
+    canonical
+}
+
+/// Removes the URLs which are the same page as an earlier one -- i.e., which
+/// have the same canonical URL -- keeping the first as it was given.
+pub fn dedupe(urls: Vec<Url>) -> Vec<Url> {
+    let mut seen = HashSet::new();
+
+    urls.into_iter()
+        .filter(|url| seen.insert(canonicalize(url)))
+        .collect()
+}
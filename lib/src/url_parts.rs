@@ -0,0 +1,44 @@
+pub use crate::url_norm::canonicalize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// The parts of a page's URL which are useful for grouping crawl results.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UrlParts {
+    pub host: Option<String>,
+    /// the non-empty segments of the path
+    pub segments: Vec<String>,
+    /// the last segment of the path without any file extension
+    pub slug: Option<String>,
+    /// the query parameters; a parameter may be repeated
+    pub query: BTreeMap<String, Vec<String>>,
+    /// the URL without its fragment, tracking parameters or trailing slash
+    /// and with its query parameters sorted
+    pub canonical: String,
+}
+
+impl UrlParts {
+    pub fn new(url: &Url) -> UrlParts {
+        let segments: Vec<String> = url
+            .path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default();
+        let slug = segments.last().map(|s| match s.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+            _ => s.to_string(),
+        });
+
+        let mut query: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        url.query_pairs()
+            .for_each(|(k, v)| query.entry(k.to_string()).or_default().push(v.to_string()));
+
+        UrlParts {
+            host: url.host_str().map(String::from),
+            segments,
+            slug,
+            query,
+            canonical: canonicalize(url).to_string(),
+        }
+    }
+}
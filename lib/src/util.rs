@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use serde_json::{json, Value};
 use url::Url;
@@ -11,7 +12,31 @@ where
     url.to_string().serialize(serializer)
 }
 
-pub fn serialize_selection_list<S>(list: &Vec<Selection>, serializer: S) -> Result<S::Ok, S::Error>
+pub fn optional_url_to_string<S>(url: &Option<Url>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    url.as_ref().map(|u| u.as_str()).serialize(serializer)
+}
+
+pub fn urls_to_strings<S>(urls: &[Url], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_seq(urls.iter().map(|u| u.as_str()))
+}
+
+pub fn optional_date_to_string<S>(
+    date: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    date.map(|d| d.to_rfc3339()).serialize(serializer)
+}
+
+pub fn serialize_selection_list<S>(list: &[Selection], serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
@@ -21,7 +46,7 @@ where
         .serialize(serializer)
 }
 
-pub fn serialize_selection<S>(selection: &Box<Selection>, serializer: S) -> Result<S::Ok, S::Error>
+pub fn serialize_selection<S>(selection: &Selection, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
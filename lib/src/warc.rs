@@ -0,0 +1,257 @@
+//! Archives the raw HTTP exchanges of a scrape as WARC 1.1 records so that
+//! the archive can be replayed -- or scraped again with other selectors --
+//! independently of the configuration it was scraped with. Requires the
+//! `warc` feature.
+
+use crate::{
+    clock::{Clock, SharedClock},
+    fetch::{FetchedPage, Fetcher, HeaderMap, SharedFetcher},
+    limits::ResponseLimits,
+    redact,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use color_eyre::{eyre::WrapErr, Result};
+use flate2::{write::GzEncoder, Compression};
+use rand::Rng;
+use reqwest::{
+    header::{CONTENT_LENGTH, TRANSFER_ENCODING},
+    StatusCode,
+};
+use std::{io::Write, path::Path, sync::Arc};
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use tracing::warn;
+use url::Url;
+
+/// Writes WARC records to a file; each record is a gzip member of its own
+/// when the file's name ends in `.gz`, as archival tools expect.
+#[derive(Debug)]
+pub struct WarcWriter {
+    file: Mutex<File>,
+    gzip: bool,
+    /// the source of the records' dates
+    clock: SharedClock,
+}
+
+impl WarcWriter {
+    /// creates -- or truncates -- the file and writes its `warcinfo` record
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .await
+            .context(format!("Failed to create the WARC file {}", path.display()))?;
+        let writer = WarcWriter {
+            file: Mutex::new(file),
+            gzip: path.extension().is_some_and(|e| e == "gz"),
+            clock: SharedClock::default(),
+        };
+
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        let info = format!(
+            "software: scraped/{}\r\nformat: WARC File Format 1.1\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        let headers = [("WARC-Filename", filename.to_string())];
+        let id = record_id();
+        writer
+            .write_record(
+                "warcinfo",
+                &id,
+                None,
+                "application/warc-fields",
+                &headers,
+                info.as_bytes(),
+            )
+            .await?;
+
+        Ok(writer)
+    }
+
+    /// sets the clock which the records' dates are taken from
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock(clock);
+
+        self
+    }
+
+    /// writes a `request` record and the `response` record it was answered
+    /// with; the response's body is written as it was received while
+    /// credentials and cookies are left out of the headers
+    pub async fn write_exchange(
+        &self,
+        url: &Url,
+        request_headers: &HeaderMap,
+        page: &FetchedPage,
+    ) -> Result<()> {
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\n",
+            &url[url::Position::BeforePath..url::Position::AfterQuery],
+            &url[url::Position::BeforeHost..url::Position::AfterPort],
+        );
+        append_headers(&mut request, request_headers);
+        request.push_str("\r\n");
+
+        let status = StatusCode::from_u16(page.status).ok();
+        let reason = status
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or_default();
+        let mut response = format!("HTTP/1.1 {} {}\r\n", page.status, reason);
+        // the body has already been de-chunked (and its length may have changed)
+        let mut headers = page.headers.clone();
+        headers.remove(TRANSFER_ENCODING);
+        headers.insert(CONTENT_LENGTH, page.body.len().into());
+        append_headers(&mut response, &headers);
+        response.push_str("\r\n");
+        let response = [response.as_bytes(), &page.body].concat();
+
+        let response_id = record_id();
+        let concurrent = [("WARC-Concurrent-To", response_id.clone())];
+        self.write_record(
+            "response",
+            &response_id,
+            Some(url),
+            "application/http;msgtype=response",
+            &[],
+            &response,
+        )
+        .await?;
+        self.write_record(
+            "request",
+            &record_id(),
+            Some(url),
+            "application/http;msgtype=request",
+            &concurrent,
+            request.as_bytes(),
+        )
+        .await
+    }
+
+    /// writes a record of the type with its ID, target URI, content type and
+    /// any other headers
+    async fn write_record(
+        &self,
+        kind: &str,
+        id: &str,
+        target: Option<&Url>,
+        content_type: &str,
+        headers: &[(&str, String)],
+        block: &[u8],
+    ) -> Result<()> {
+        let date = warc_date(self.clock.0.utc());
+        let mut record = format!(
+            "WARC/1.1\r\nWARC-Type: {}\r\nWARC-Record-ID: {}\r\nWARC-Date: {}\r\n",
+            kind, id, date
+        );
+        if let Some(url) = target {
+            record.push_str(&format!("WARC-Target-URI: {}\r\n", url));
+        }
+        for (name, value) in headers {
+            record.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        record.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            content_type,
+            block.len()
+        ));
+        let mut record = record.into_bytes();
+        record.extend_from_slice(block);
+        record.extend_from_slice(b"\r\n\r\n");
+
+        if self.gzip {
+            let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+            gz.write_all(&record)?;
+            record = gz.finish()?;
+        }
+        // records are written whole -- and flushed -- one at a time
+        let mut file = self.file.lock().await;
+        file.write_all(&record)
+            .await
+            .context("Failed to write a WARC record")?;
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+/// appends the headers as `name: value` lines; credentials (e.g., the
+/// `Authorization` header) and values which aren't text are left out
+fn append_headers(to: &mut String, headers: &HeaderMap) {
+    headers
+        .iter()
+        .filter(|(k, _)| !redact::is_sensitive(k.as_str()))
+        .filter_map(|(k, v)| Some((k, v.to_str().ok()?)))
+        .for_each(|(k, v)| to.push_str(&format!("{}: {}\r\n", k, v)));
+}
+
+/// a random (version 4) UUID as a WARC record ID
+fn record_id() -> String {
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// the date of a record, in UTC to the second
+fn warc_date(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// A `Fetcher` which archives each request it makes -- and the response to
+/// it -- with a `WarcWriter`; every hop of a redirect is archived. An archive
+/// which fails is logged rather than failing the request.
+#[derive(Clone)]
+pub struct WarcFetcher {
+    writer: Arc<WarcWriter>,
+    fetcher: SharedFetcher,
+}
+
+impl WarcFetcher {
+    /// archives the requests made with the fetcher
+    pub fn new(writer: WarcWriter, fetcher: SharedFetcher) -> Self {
+        WarcFetcher {
+            writer: Arc::new(writer),
+            fetcher,
+        }
+    }
+}
+
+#[async_trait]
+impl Fetcher for WarcFetcher {
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        self.fetch_within(url, headers, &ResponseLimits::default())
+            .await
+    }
+
+    /// archives the pages received within the limits; those abandoned aren't
+    async fn fetch_within(
+        &self,
+        url: &Url,
+        headers: &HeaderMap,
+        limits: &ResponseLimits,
+    ) -> Result<FetchedPage> {
+        let page = self.fetcher.0.fetch_within(url, headers, limits).await?;
+        if let Err(e) = self.writer.write_exchange(url, headers, &page).await {
+            warn!("failed to archive {}: {:#}", url, e);
+        }
+
+        Ok(page)
+    }
+
+    /// requests the page's headers; they aren't archived
+    async fn head(&self, url: &Url, headers: &HeaderMap) -> Result<FetchedPage> {
+        self.fetcher.0.head(url, headers).await
+    }
+
+    async fn warm(&self, url: &Url, headers: &HeaderMap) -> Result<()> {
+        self.fetcher.0.warm(url, headers).await
+    }
+}
@@ -0,0 +1,87 @@
+//! Establishes the connections to the busiest hosts of a crawl -- resolving
+//! their names and completing the TLS handshakes -- before its pages are
+//! requested, so that the first requests of a large multi-host crawl don't all
+//! wait on connecting at once.
+
+use crate::{error::ScrapedError, fetch::Fetcher, rate, robots, ScrapeConfig};
+use color_eyre::Result;
+use futures::{future, stream, StreamExt};
+use std::{cmp::Reverse, collections::HashMap};
+use tracing::{debug, instrument};
+use url::Url;
+
+/// The first URL of each of the `n` hosts with the most URLs, busiest first,
+/// along with what accompanied it; hosts with as many URLs are in the order
+/// they first appear.
+pub fn busiest_hosts<'a, T>(
+    targets: impl IntoIterator<Item = (&'a Url, T)>,
+    n: usize,
+) -> Vec<(&'a Url, T)> {
+    let mut hosts: Vec<((&'a Url, T), usize)> = vec![];
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (url, target) in targets {
+        let host = match url.host_str() {
+            Some(host) => host,
+            None => continue,
+        };
+        match index.get(host) {
+            Some(i) => hosts[*i].1 += 1,
+            None => {
+                index.insert(host, hosts.len());
+                hosts.push(((url, target), 1));
+            }
+        }
+    }
+    hosts.sort_by_key(|(_, count)| Reverse(*count));
+
+    hosts
+        .into_iter()
+        .take(n)
+        .map(|(target, _)| target)
+        .collect()
+}
+
+/// Warms up the connection to the host of each URL with the fetcher which
+/// will request it; the requests are made as concurrently as the config
+/// allows and are subject to its safety rules, robots.txt and rate limit. A
+/// failure is logged and otherwise ignored. Returns the number of hosts warmed.
+#[instrument(name = "warmup", skip_all, fields(hosts = targets.len()))]
+pub async fn warm_up(targets: Vec<(&Url, &dyn Fetcher)>, config: &ScrapeConfig) -> usize {
+    stream::iter(targets)
+        .map(|(url, fetcher)| async move {
+            match warm(url, fetcher, config).await {
+                Ok(warmed) => warmed,
+                Err(e) => {
+                    debug!("failed to warm up the connection to {}: {:#}", url, e);
+                    false
+                }
+            }
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .filter(|warmed| future::ready(*warmed))
+        .count()
+        .await
+}
+
+/// warms up the connection to the URL's host unless the URL can't be requested
+async fn warm(url: &Url, fetcher: &dyn Fetcher, config: &ScrapeConfig) -> Result<bool> {
+    config.safety.check_resolved(url).await?;
+    if robots::retain_allowed(vec![url.clone()], config, fetcher)
+        .await
+        .is_empty()
+    {
+        return Ok(false);
+    }
+    let headers = config.request_headers_for(url)?;
+    rate::acquire(url, config).await;
+
+    let request = fetcher.warm(url, &headers);
+    match config.timeout {
+        Some(after) => tokio::time::timeout(after, request)
+            .await
+            .unwrap_or_else(|_| Err(ScrapedError::timeout(url, after)))?,
+        None => request.await?,
+    };
+
+    Ok(true)
+}
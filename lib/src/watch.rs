@@ -0,0 +1,180 @@
+//! Watches pages for changes: they're scraped again on an interval and only
+//! the differences from the previous scrape are reported (and, with a webhook,
+//! POSTed as JSON). The last snapshot can be persisted to a file so that a
+//! watch which is restarted picks up where it left off.
+
+use crate::{
+    results::{diff::ResultsDiff, ParseResults},
+    ScrapeConfig,
+};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use futures::Future;
+use serde_json::{json, Value};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::fs;
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// Re-scrapes on an interval and reports the changes since the previous scrape.
+pub struct Watcher {
+    interval: Duration,
+    /// the request headers of the webhook and the clock which paces the checks
+    config: ScrapeConfig,
+    /// the file the last snapshot is persisted to
+    state: Option<PathBuf>,
+    /// the endpoint which is POSTed the differences of each change
+    webhook: Option<Url>,
+    /// the number of checks after which the watch stops; unbounded when not set
+    checks: Option<usize>,
+    /// the results of the previous scrape, as they're serialized
+    previous: Option<Value>,
+}
+
+impl Watcher {
+    /// watches with a scrape every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Watcher {
+            interval,
+            config: ScrapeConfig::default(),
+            state: None,
+            webhook: None,
+            checks: None,
+            previous: None,
+        }
+    }
+
+    /// sets the configuration whose request headers are sent to the webhook
+    /// and whose clock paces the checks
+    pub fn config(mut self, config: ScrapeConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+
+    /// persists the last snapshot to the file; when the file already holds
+    /// one, the first scrape is compared with it
+    pub fn state(mut self, path: impl AsRef<Path>) -> Self {
+        self.state = Some(path.as_ref().to_path_buf());
+
+        self
+    }
+
+    /// POSTs the differences of each change as JSON to the endpoint
+    pub fn webhook(mut self, url: Url) -> Self {
+        self.webhook = Some(url);
+
+        self
+    }
+
+    /// stops the watch after the number of checks
+    pub fn checks(mut self, checks: usize) -> Self {
+        self.checks = Some(checks);
+
+        self
+    }
+
+    /// the snapshot of the previous scrape, loading it from the state file
+    /// when there's one
+    async fn previous(&mut self) -> Result<Option<Value>> {
+        if self.previous.is_some() {
+            return Ok(self.previous.take());
+        }
+        let path = match &self.state {
+            Some(path) if path.exists() => path,
+            _ => return Ok(None),
+        };
+        let content = fs::read_to_string(path)
+            .await
+            .context(format!("Failed to read the snapshot: {}", path.display()))?;
+
+        Ok(Some(serde_json::from_str(&content).context(format!(
+            "Failed to parse the snapshot: {}",
+            path.display()
+        ))?))
+    }
+
+    /// Compares the results with those of the previous scrape and keeps them
+    /// -- persisting them to the state file -- as the snapshot the next scrape
+    /// is compared with; there's no difference for the first scrape.
+    pub async fn check(&mut self, results: &ParseResults) -> Result<Option<ResultsDiff>> {
+        let current = json!(results);
+        let diff = self
+            .previous()
+            .await?
+            .map(|previous| ResultsDiff::between(&previous, &current));
+        if let Some(path) = &self.state {
+            fs::write(path, serde_json::to_string(&current)?)
+                .await
+                .context(format!("Failed to write the snapshot: {}", path.display()))?;
+        }
+        self.previous = Some(current);
+
+        Ok(diff)
+    }
+
+    /// POSTs the differences to the webhook
+    async fn notify(&self, diff: &ResultsDiff) -> Result<()> {
+        let webhook = match &self.webhook {
+            Some(webhook) => webhook,
+            None => return Ok(()),
+        };
+        let response = reqwest::Client::new()
+            .post(webhook.clone())
+            .headers(self.config.request_headers()?)
+            .json(diff)
+            .send()
+            .await?;
+
+        match response.status().is_success() {
+            true => Ok(()),
+            false => Err(eyre!(
+                "the webhook {} responded with a {} status",
+                webhook,
+                response.status()
+            )),
+        }
+    }
+
+    /// Scrapes with `scrape` every interval, calling `on_change` with -- and
+    /// POSTing to the webhook -- the differences whenever the results change.
+    /// A scrape or webhook which fails is logged and the watch carries on;
+    /// only a snapshot which can't be persisted ends it.
+    pub async fn watch<F, Fut>(
+        &mut self,
+        mut scrape: F,
+        mut on_change: impl FnMut(&ResultsDiff),
+    ) -> Result<()>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<ParseResults>>,
+    {
+        let mut checked = 0;
+        loop {
+            match scrape().await {
+                Ok(results) => match self.check(&results).await? {
+                    Some(diff) if !diff.is_empty() => {
+                        info!("the results changed since the previous scrape");
+                        on_change(&diff);
+                        if let Err(e) = self.notify(&diff).await {
+                            warn!("failed to notify the webhook of a change: {:#}", e);
+                        }
+                    }
+                    _ => debug!("the results are unchanged"),
+                },
+                Err(e) => warn!("failed to scrape while watching: {:#}", e),
+            }
+
+            checked += 1;
+            if self.checks.is_some_and(|checks| checked >= checks) {
+                return Ok(());
+            }
+            self.config.clock.0.sleep(self.interval).await;
+        }
+    }
+}
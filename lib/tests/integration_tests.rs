@@ -1,8 +1,76 @@
-use std::fs;
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
 use claim::{assert_err, assert_ok, assert_some};
-use scraped::{Document, LoadedDocument};
-use serde_json::json;
+use flate2::{write::GzEncoder, Compression};
+use scraped::archive::WaybackSink;
+use scraped::auth::{self, BearerTokens};
+use scraped::cache::{Cache, CachedPage, CachingFetcher, FsCache};
+use scraped::cancel::Cancellation;
+use scraped::chain::Chain;
+use scraped::children::{registrable_domain, ChildRules};
+use scraped::clock::MockClock;
+use scraped::compare::Comparison;
+use scraped::concurrent::ConcurrentScrape;
+use scraped::dns::{self, DnsConfig};
+use scraped::dry_run::Verdict;
+use scraped::error::{HttpError, ScrapedError, MAX_ERROR_BODY};
+use scraped::estimate::Estimate;
+use scraped::expression::Expression;
+use scraped::fetch::{Bytes, FetchedPage, Fetcher, HeaderMap, ReqwestFetcher, SharedFetcher};
+use scraped::frontier::{Discovery, FileFrontier, Frontier, MemoryFrontier};
+use scraped::id::IdStrategy;
+use scraped::limits::ResponseLimits;
+use scraped::link_audit::LinkAudit;
+use scraped::lookup::{Lookup, LookupKey};
+use scraped::metadata::Metadata;
+use scraped::network::{IpPreference, NetworkConfig};
+use scraped::observe::{Observer, Progress};
+use scraped::pagination::{self, Pagination};
+use scraped::parser::{ParserBackend, Selectors};
+use scraped::policy::{rate_limit_headers, SitePolicy, POLITE_RATE};
+use scraped::postprocess::PostProcessor;
+use scraped::provenance::SelectorSource;
+use scraped::proxy::ProxyConfig;
+use scraped::rate::{self, RateLimit};
+use scraped::redact::{Redacted, REDACTED};
+use scraped::redirect::RedirectPolicy;
+use scraped::render::{RenderConfig, RENDER_TIMEOUT};
+use scraped::repair::repair_html;
+use scraped::report::{broken_images, external_domains, CrawlProperty};
+use scraped::results::{diff::ResultsDiff, writers::JsonLinesWriter, FlatResult, PageOutcome};
+use scraped::retry::{retry_after, RetryPolicy};
+use scraped::robots::{self, RobotsTxt, ROBOTS_TTL};
+use scraped::safety::{is_public, CrawlSafety};
+use scraped::sample::Sampling;
+use scraped::scrub::Scrubber;
+use scraped::selection::{compile_selector, TemplateKind};
+use scraped::sink::{Sink, WebhookSink};
+use scraped::table::Table;
+use scraped::text::TextPolicy;
+use scraped::transforms::{
+    parse_date_at, parse_duration, parse_number, parse_price, DateFormat, DateHints, FormatPolicy,
+    PriceHints, Transform,
+};
+use scraped::watch::Watcher;
+use scraped::{
+    challenge::BotChallenge, content::Content, extract::Extractor, redirect::client_redirect,
+    results::ResultKind, stats::HostStats, stats::StatsSnapshot, url_norm, warmup, ChildScope,
+    Document, DuplicatePolicy, FramePolicy, LoadedDocument, ParsedDoc, PropertyCallback,
+    ScrapeConfig, TemplatePolicy,
+};
+use serde_json::{json, Value};
 use url::Url;
 
 #[test]
@@ -10,58 +78,4830 @@ fn valid_string_url_is_accepted() {
     let url = String::from("https://dev.null");
 
     assert_ok!(Document::new(&url));
-    assert_ok!(LoadedDocument::new(&url, &"".to_string()));
+    assert_ok!(LoadedDocument::new(&url, ""));
 }
 
 #[test]
 fn invalid_string_url_is_rejected() {
     let url = String::from("\\x!//");
     assert_err!(Document::new(&url));
-    assert_err!(LoadedDocument::new(&url, &"".to_string()));
+    assert_err!(LoadedDocument::new(&url, ""));
 }
 
 #[test]
 fn document_from_url() {
     let url = Url::parse("https://google.com").unwrap();
-    assert_eq!(Document::from(&url), Document { url, data: None });
+    assert_eq!(
+        Document::from(&url),
+        Document {
+            url,
+            data: None,
+            config: ScrapeConfig::default(),
+            fetcher: SharedFetcher::default(),
+        }
+    );
+}
+
+/// serves the same page for every URL
+struct MockFetcher(&'static str);
+
+#[async_trait]
+impl Fetcher for MockFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::copy_from_slice(self.0.as_bytes()),
+        })
+    }
+}
+
+#[test]
+fn page_bodies_are_decoded_with_their_charset() {
+    let page = |content_type: &str, body: &'static [u8]| {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", content_type.parse().unwrap());
+        FetchedPage {
+            url: Url::parse("https://dev.null").unwrap(),
+            status: 200,
+            headers,
+            body: Bytes::from_static(body),
+        }
+    };
+
+    let latin1 = page("text/html; charset=iso-8859-1", b"caf\xe9");
+    assert_eq!(latin1.text(), "café");
+    assert_eq!(latin1.into_text(), "café");
+
+    let bom = page("text/html", b"\xEF\xBB\xBFhello");
+    assert_eq!(bom.text(), "hello");
+    assert_eq!(bom.into_text(), "hello");
+
+    let invalid = page("text/html; charset=utf-8", b"bad \xff byte");
+    assert_eq!(invalid.text(), invalid.clone().into_text());
+}
+
+#[tokio::test]
+async fn injected_fetcher_is_used_for_requests() {
+    let doc = Document::new("https://dev.null")
+        .expect("Document created")
+        .fetcher(MockFetcher("<html><h1>Mocked</h1></html>"))
+        .load_document()
+        .await
+        .expect("document loaded with the mock fetcher")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("h1", "h1");
+
+    let results = doc.results().expect("results successfully extracted");
+    match results.data.get("h1") {
+        Some(ResultKind::Item(h1)) => assert_eq!(h1.text.as_deref(), Some("Mocked")),
+        _ => panic!("expected the h1 of the mocked page"),
+    }
+}
+
+/// responds with a page which echoes the request's headers
+struct EchoFetcher;
+
+#[async_trait]
+impl Fetcher for EchoFetcher {
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let body = format!(
+            "<html><h1>{}</h1><p>{}</p></html>",
+            header("user-agent").unwrap_or_default(),
+            header("accept-language").unwrap_or_default()
+        );
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn configured_headers_are_sent_with_requests() {
+    let config = ScrapeConfig::default()
+        .user_agent("scraped-test")
+        .header("Accept-Language", "de");
+    let results = Document::new("https://dev.null")
+        .expect("Document created")
+        .config(config)
+        .fetcher(EchoFetcher)
+        .load_document()
+        .await
+        .expect("document loaded with the echo fetcher")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("h1", "h1")
+        .add_selector("p", "p")
+        .results()
+        .expect("results successfully extracted");
+
+    let text = |name: &str| match results.data.get(name) {
+        Some(ResultKind::Item(el)) => el.text.clone(),
+        _ => None,
+    };
+    assert_eq!(text("h1").as_deref(), Some("scraped-test"));
+    assert_eq!(text("p").as_deref(), Some("de"));
+}
+
+#[tokio::test]
+async fn domain_headers_are_sent_to_their_domain() {
+    let language = |url: &str| {
+        let url = url.to_string();
+        async move {
+            Document::new(&url)
+                .expect("Document created")
+                .config(ScrapeConfig::default().header("Accept-Language", "de"))
+                .headers_for("*.dev.null", [("Accept-Language", "en")])
+                .headers_for("api.dev.null", [("Accept-Language", "fr")])
+                .fetcher(EchoFetcher)
+                .load_document()
+                .await
+                .expect("document loaded with the echo fetcher")
+                .parse_document()
+                .expect("ParsedDoc created")
+                .add_selector("p", "p")
+                .results()
+                .expect("results successfully extracted")
+                .field("p")
+        }
+    };
+
+    assert_eq!(language("https://dev.null").await.as_deref(), Some("en"));
+    assert_eq!(
+        language("https://v1.api.dev.null").await.as_deref(),
+        Some("fr")
+    );
+    assert_eq!(language("https://null.dev").await.as_deref(), Some("de"));
+}
+
+#[test]
+fn bearer_tokens_are_scoped_to_their_domain() {
+    std::env::set_var(auth::env_var("scoped.dev.null"), "from-env");
+    let config = ScrapeConfig::default()
+        .bearer_token("s3cret")
+        .bearer_token_for("dev.null", "dev")
+        .bearer_token_for("API.dev.null.", "api");
+    assert_eq!(
+        config.bearer_tokens,
+        BearerTokens::default()
+            .global("s3cret")
+            .scoped("dev.null", "dev")
+            .scoped("api.dev.null", "api")
+    );
+    let token = |config: &ScrapeConfig, url: &str| {
+        config
+            .request_headers_for(&Url::parse(url).unwrap())
+            .expect("headers built")
+            .get("authorization")
+            .map(|v| v.to_str().unwrap().to_string())
+    };
+
+    assert_eq!(
+        auth::env_var("api.my-site.com"),
+        "SCRAPED_TOKEN_API_MY_SITE_COM"
+    );
+    assert_eq!(
+        token(&config, "https://v2.api.dev.null").as_deref(),
+        Some("Bearer api")
+    );
+    assert_eq!(
+        token(&config, "https://dev.null").as_deref(),
+        Some("Bearer dev")
+    );
+    assert_eq!(
+        token(&config, "https://github.com").as_deref(),
+        Some("Bearer s3cret")
+    );
+    assert_eq!(
+        token(&config, "https://scoped.dev.null").as_deref(),
+        Some("Bearer dev")
+    );
+    assert!(!format!("{:?}", config).contains("s3cret"));
+
+    let tokens = config.bearer_tokens.clone().env(true);
+    let config = config.bearer_tokens(tokens);
+    assert_eq!(
+        token(&config, "https://a.scoped.dev.null").as_deref(),
+        Some("Bearer from-env")
+    );
+    assert_eq!(
+        token(&config, "https://api.dev.null").as_deref(),
+        Some("Bearer api")
+    );
+    assert_eq!(token(&ScrapeConfig::default(), "https://dev.null"), None);
+}
+
+#[test]
+fn secrets_are_redacted() {
+    let config = ScrapeConfig::default()
+        .header("Authorization", "Bearer s3cret")
+        .header("Accept-Language", "de");
+
+    assert!(!format!("{:?}", config).contains("s3cret"));
+    let json = serde_json::to_string(&config).unwrap();
+    assert!(!json.contains("s3cret"));
+    assert!(json.contains(REDACTED));
+
+    let headers = config.request_headers().unwrap();
+    assert_eq!(headers["authorization"], "Bearer s3cret");
+    assert!(headers["authorization"].is_sensitive());
+    assert!(!headers["accept-language"].is_sensitive());
+
+    let config: ScrapeConfig =
+        serde_json::from_str(r#"{"headers": {"Cookie": "session=abc"}}"#).unwrap();
+    assert_eq!(config.headers["Cookie"].expose(), "session=abc");
+    assert_eq!(format!("{}", Redacted::new("abc")), REDACTED);
+
+    let url = Url::parse("https://dev.null").unwrap();
+    let headers = [("set-cookie", "session=abc"), ("content-type", "text/html")];
+    let error = HttpError::new(&url, 500, &headers, "", true);
+    let captured = error.headers.unwrap();
+    assert_eq!(captured["set-cookie"], REDACTED);
+    assert_eq!(captured["content-type"], "text/html");
+}
+
+#[test]
+fn preferred_ip_version_is_tried_first() {
+    let v4: std::net::SocketAddr = "93.184.216.34:0".parse().unwrap();
+    let v6: std::net::SocketAddr = "[2606:2800:220:1::1]:0".parse().unwrap();
+    let mut addrs = vec![v6, v4];
+    IpPreference::Ipv4.sort(&mut addrs);
+    assert_eq!(addrs, vec![v4, v6]);
+    IpPreference::Ipv6.sort(&mut addrs);
+    assert_eq!(addrs, vec![v6, v4]);
+
+    let config: ScrapeConfig =
+        serde_json::from_str(r#"{"network": {"prefer": "ipv6", "local_address": "127.0.0.1"}}"#)
+            .unwrap();
+    let network = NetworkConfig::default()
+        .prefer(IpPreference::Ipv6)
+        .local_address("127.0.0.1".parse().unwrap());
+    assert_eq!(config.network, network);
+    assert!(ReqwestFetcher::for_config(&config).unwrap().is_some());
+    assert!(ReqwestFetcher::for_config(&ScrapeConfig::default())
+        .unwrap()
+        .is_none());
+}
+
+/// serves a page with an `ETag` and answers requests which carry it with a 304
+#[derive(Default)]
+struct EtagFetcher {
+    unchanged: AtomicUsize,
+}
+
+#[async_trait]
+impl Fetcher for EtagFetcher {
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let mut page = FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"<html><h1>Cached</h1></html>"),
+        };
+        page.headers.insert("etag", "\"v1\"".parse()?);
+        page.headers.insert("set-cookie", "session=abc".parse()?);
+        if headers.get("if-none-match").is_some_and(|v| v == "\"v1\"") {
+            self.unchanged.fetch_add(1, Ordering::SeqCst);
+            page.status = 304;
+            page.body = Bytes::new();
+        }
+
+        Ok(page)
+    }
+}
+
+#[tokio::test]
+async fn unchanged_pages_are_served_from_the_cache() {
+    let dir = std::env::temp_dir().join(format!("scraped-cache-{}", std::process::id()));
+    let origin = Arc::new(EtagFetcher::default());
+    let fetcher = CachingFetcher::new(FsCache::new(&dir), SharedFetcher(origin.clone()));
+
+    for _ in 0..2 {
+        let results = Document::new("https://dev.null")
+            .expect("Document created")
+            .fetcher(fetcher.clone())
+            .load_document()
+            .await
+            .expect("document loaded through the cache")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector("h1", "h1")
+            .results()
+            .expect("results successfully extracted");
+        match results.data.get("h1") {
+            Some(ResultKind::Item(h1)) => assert_eq!(h1.text.as_deref(), Some("Cached")),
+            _ => panic!("expected the h1 selection"),
+        }
+    }
+    assert_eq!(origin.unchanged.load(Ordering::SeqCst), 1);
+
+    let url = Url::parse("https://dev.null").unwrap();
+    let cached = FsCache::new(&dir).get(&url).await.unwrap().unwrap();
+    assert_eq!(cached.headers["etag"], "\"v1\"");
+    assert!(!cached.headers.contains_key("set-cookie"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// answers each request with the `Cache-Control` and `Age` of the path
+/// `/<cache-control>/<age>` (e.g. `/max-age=60/10`), counting the requests
+#[derive(Default)]
+struct CacheHeaderFetcher {
+    requests: AtomicUsize,
+}
+
+#[async_trait]
+impl Fetcher for CacheHeaderFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        let mut segments = url.path_segments().unwrap();
+        let mut page = FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"<html><h1>Fresh</h1></html>"),
+        };
+        page.headers
+            .insert("cache-control", segments.next().unwrap().parse()?);
+        page.headers
+            .insert("age", segments.next().unwrap().parse()?);
+        page.headers
+            .insert("expires", "Thu, 01 Jan 1970 00:10:00 GMT".parse()?);
+
+        Ok(page)
+    }
+}
+
+#[tokio::test]
+async fn caching_headers_are_parsed_and_honored() {
+    let dir = std::env::temp_dir().join(format!("scraped-http-cache-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let origin = Arc::new(CacheHeaderFetcher::default());
+    let clock = Arc::new(MockClock::default());
+    let fetcher = CachingFetcher::new(FsCache::new(&dir), SharedFetcher(origin.clone()))
+        .honor_http_caching(true)
+        .clock(clock.clone());
+    let requests = || origin.requests.load(Ordering::SeqCst);
+
+    let results = Document::new("https://dev.null/max-age=60,public/10")
+        .expect("Document created")
+        .fetcher(fetcher.clone())
+        .load_document()
+        .await
+        .expect("document loaded")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .results()
+        .expect("results extracted");
+    let caching = results.caching.expect("caching headers");
+    let control = caching.cache_control.clone().expect("cache control");
+    assert_eq!(control.max_age, Some(Duration::from_secs(60)));
+    assert!(control.public && !control.no_store);
+    assert_eq!(caching.age, Some(Duration::from_secs(10)));
+    assert_eq!(
+        caching.expires.map(|e| e.timestamp()),
+        Some(600),
+        "the Expires header is parsed"
+    );
+    assert_eq!(
+        json!(caching)["cache_control"]["max_age"],
+        json!("1m"),
+        "durations are serialized as human readable strings"
+    );
+    assert_eq!(requests(), 1);
+
+    // fresh for the 50 seconds left of its max-age once its age is counted
+    let fresh = Url::parse("https://dev.null/max-age=60,public/10").unwrap();
+    fetcher.fetch(&fresh, &HeaderMap::new()).await.unwrap();
+    clock.advance(Duration::from_secs(49));
+    fetcher.fetch(&fresh, &HeaderMap::new()).await.unwrap();
+    assert_eq!(requests(), 1, "fresh pages aren't requested again");
+    clock.advance(Duration::from_secs(1));
+    fetcher.fetch(&fresh, &HeaderMap::new()).await.unwrap();
+    assert_eq!(requests(), 2, "stale pages are requested again");
+
+    let no_store = Url::parse("https://dev.null/no-store/0").unwrap();
+    fetcher.fetch(&no_store, &HeaderMap::new()).await.unwrap();
+    fetcher.fetch(&no_store, &HeaderMap::new()).await.unwrap();
+    assert_eq!(requests(), 4);
+    assert!(FsCache::new(&dir).get(&no_store).await.unwrap().is_none());
+
+    // without honoring the headers, pages without a validator aren't cached
+    let ignoring = CachingFetcher::new(FsCache::new(&dir), SharedFetcher(origin.clone()));
+    let other = Url::parse("https://dev.null/max-age=600/0").unwrap();
+    ignoring.fetch(&other, &HeaderMap::new()).await.unwrap();
+    ignoring.fetch(&other, &HeaderMap::new()).await.unwrap();
+    assert_eq!(requests(), 6);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// an HTTP proxy on localhost which answers every request with its own name
+async fn stub_proxy(name: &'static str) -> Url {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = [0; 4096];
+            let _ = socket.read(&mut request).await;
+            let body = format!("<html><h1>{}</h1></html>", name);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    Url::parse(&format!("http://{}", addr)).unwrap()
+}
+
+/// a server on localhost which answers `/page` with a small HTML page, `/image`
+/// with a PNG and any other path with a chunked binary body of up to 64 MB,
+/// counting the bytes of that body which it managed to send
+async fn stub_downloads(sent: Arc<AtomicUsize>) -> Url {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let sent = sent.clone();
+            tokio::spawn(async move {
+                let mut request = [0; 4096];
+                let n = socket.read(&mut request).await.unwrap_or_default();
+                let request = String::from_utf8_lossy(&request[..n]).to_string();
+                let (content_type, body) = match request.split(' ').nth(1) {
+                    Some("/page") => ("text/html; charset=utf-8", "<html><h1>Page</h1></html>"),
+                    Some("/image") => ("image/png", "PNG"),
+                    _ => ("application/octet-stream", ""),
+                };
+                if !body.is_empty() {
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        content_type,
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    return;
+                }
+                let head = "HTTP/1.1 200 OK\r\ncontent-type: application/octet-stream\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n";
+                if socket.write_all(head.as_bytes()).await.is_err() {
+                    return;
+                }
+                let chunk = vec![b'x'; 64 * 1024];
+                for _ in 0..1024 {
+                    let framed =
+                        [format!("{:x}\r\n", chunk.len()).as_bytes(), &chunk, b"\r\n"].concat();
+                    if socket.write_all(&framed).await.is_err() {
+                        return;
+                    }
+                    sent.fetch_add(chunk.len(), Ordering::SeqCst);
+                }
+                let _ = socket.write_all(b"0\r\n\r\n").await;
+            });
+        }
+    });
+
+    Url::parse(&format!("http://{}", addr)).unwrap()
+}
+
+#[tokio::test]
+async fn responses_beyond_the_limits_are_abandoned_early() {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let server = stub_downloads(sent.clone()).await;
+    let load = |path: &str| {
+        let url = server.join(path).unwrap();
+        async move {
+            Document::new(url.as_str())
+                .expect("Document created")
+                .max_body_bytes(256 * 1024)
+                .accept_content_types(&["text/html", "application/*"])
+                .load_document()
+                .await
+        }
+    };
+
+    let page = load("/page").await.expect("the page is within the limits");
+    assert!(page.data.contains("<h1>Page</h1>"));
+
+    let error = load("/huge.bin").await.expect_err("the body is too large");
+    assert!(matches!(
+        error,
+        ScrapedError::TooLarge { limit: 262_144, .. }
+    ));
+    // the download stopped well short of the 64 MB which would have been sent
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(sent.load(Ordering::SeqCst) < 16 * 1024 * 1024);
+
+    let error = load("/image").await.expect_err("images aren't accepted");
+    assert_eq!(
+        error,
+        ScrapedError::UnacceptedContentType {
+            url: server.join("/image").unwrap(),
+            content_type: "image/png".to_string(),
+        }
+    );
+
+    // transports which don't stream are held to the limits once they respond
+    let limits = ResponseLimits::new().max_body_bytes(8).accept("text/*");
+    let page = |body: &'static str| FetchedPage {
+        url: Url::parse("https://dev.null").unwrap(),
+        status: 200,
+        headers: HeaderMap::new(),
+        body: Bytes::from_static(body.as_bytes()),
+    };
+    assert_ok!(limits.check(&page("<p>ok</p")));
+    assert_err!(limits.check(&page("<p>too long</p>")));
+    assert!(limits.accepts(Some("text/plain; charset=utf-8")));
+    assert!(limits.accepts(None));
+    assert!(!limits.accepts(Some("application/pdf")));
+}
+
+#[tokio::test]
+async fn requests_are_routed_through_proxies() {
+    let (a, b, docs) = (
+        stub_proxy("a").await,
+        stub_proxy("b").await,
+        stub_proxy("docs").await,
+    );
+    let config = ScrapeConfig::default()
+        .proxy_pool(vec![a, b])
+        .domain_proxy("*.docs.rs", docs);
+    let heading = |url: &str| {
+        let config = config.clone();
+        let url = url.to_string();
+        async move {
+            let results = Document::new(&url)
+                .expect("Document created")
+                .config(config)
+                .load_document()
+                .await
+                .expect("document loaded through the proxy")
+                .parse_document()
+                .expect("ParsedDoc created")
+                .add_selector("h1", "h1")
+                .results()
+                .expect("results successfully extracted");
+            results.field("h1")
+        }
+    };
+
+    // each document has its own pool so its first request goes to the first proxy
+    assert_eq!(heading("http://scraped.test").await.as_deref(), Some("a"));
+    assert_eq!(
+        heading("http://docs.rs/scraper").await.as_deref(),
+        Some("docs")
+    );
+
+    // the profile captures the selector it's given
+    let selector = String::from("h1");
+    let mut scrape = ConcurrentScrape::new(config.clone().concurrency(1))
+        .profile(move |doc| scraped::ParsedDoc::from(doc).add_selector("h1", &selector))
+        .add_urls(&["http://scraped.test/1", "http://scraped.test/2"])
+        .unwrap();
+    let names: Vec<_> = scrape
+        .execute()
+        .await
+        .unwrap()
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => page.field("h1"),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, vec![Some("a".to_string()), Some("b".to_string())]);
+
+    let proxies: ProxyConfig = serde_json::from_str(r#"{"domains": {"docs.rs": "x"}}"#).unwrap();
+    let url = |u: &str| Url::parse(u).unwrap();
+    assert_eq!(proxies.domain_proxy(&url("https://api.docs.rs")), Some("x"));
+    assert_eq!(proxies.domain_proxy(&url("https://notdocs.rs")), None);
+
+    let invalid = ScrapeConfig::default().proxy_pool(vec![url("ftp://proxy.test")]);
+    assert_err!(
+        Document::new("http://scraped.test")
+            .unwrap()
+            .config(invalid)
+            .load_document()
+            .await
+    );
+}
+
+#[test]
+fn rendering_is_configured() {
+    let doc = Document::new("https://dev.null")
+        .expect("Document created")
+        .render_js(true);
+    assert!(doc.config.render.enabled);
+    assert_eq!(doc.config.render.timeout, RENDER_TIMEOUT);
+
+    let config: ScrapeConfig = serde_json::from_str(
+        r##"{"render": {"enabled": true, "wait_for": "#app li", "timeout": "10s"}}"##,
+    )
+    .unwrap();
+    assert_eq!(config.render.wait_for.as_deref(), Some("#app li"));
+    assert_eq!(config.render.timeout, Duration::from_secs(10));
+    // without the `render` feature the pages aren't requested unrendered
+    #[cfg(not(feature = "render"))]
+    assert!(SharedFetcher::for_config(&config).is_err());
+    assert_eq!(
+        RenderConfig::default().enabled(true).wait_for("#app li"),
+        RenderConfig {
+            timeout: RENDER_TIMEOUT,
+            ..config.render
+        }
+    );
+}
+
+#[test]
+fn doh_answers_are_parsed() {
+    let body = r#"{
+        "Status": 0,
+        "Answer": [
+            {"name": "www.rust-lang.org", "type": 5, "data": "rust-lang.org."},
+            {"name": "rust-lang.org", "type": 1, "data": "13.35.93.44"},
+            {"name": "rust-lang.org", "type": 28, "data": "2600:9000:2000::1"}
+        ]
+    }"#;
+    let v4 = dns::parse_answers(body, 1).unwrap();
+    assert_eq!(v4, vec!["13.35.93.44".parse::<std::net::IpAddr>().unwrap()]);
+    assert_eq!(dns::parse_answers(body, 28).unwrap().len(), 1);
+    assert!(dns::parse_answers(r#"{"Status": 3}"#, 1)
+        .unwrap()
+        .is_empty());
+    assert_err!(dns::parse_answers(r#"{"Status": 2}"#, 1));
+
+    let config: ScrapeConfig =
+        serde_json::from_str(r#"{"dns": {"doh": "https://dns.google/resolve"}}"#).unwrap();
+    assert_eq!(config.dns, DnsConfig::default().doh(dns::GOOGLE));
+    assert!(config.dns.fallback);
+    // without the `doh` feature the system resolver isn't used in its place
+    #[cfg(not(feature = "doh"))]
+    assert!(ReqwestFetcher::for_config(&config).is_err());
+}
+
+#[test]
+fn expressions_derive_properties_from_selections() {
+    let html = r#"<html><h1>Title</h1><a href="/a">A</a><a href="/b">B</a></html>"#;
+    let results = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("h1", "h1")
+        .add_selector_all("links", "a")
+        .add_expression("title", "title.text || h1.text")
+        .add_expression("hrefs", "links[*].full_href")
+        .add_expression("last", "links[-1].text")
+        .results()
+        .expect("results successfully extracted");
+
+    assert_eq!(results.props.get("title"), Some(&json!("Title")));
+    assert_eq!(
+        results.props.get("hrefs"),
+        Some(&json!(["https://dev.null/a", "https://dev.null/b"]))
+    );
+    assert_eq!(results.props.get("last"), Some(&json!("B")));
+    assert!(Expression::from_str("links[*]..text").is_err());
+    let invalid = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .try_add_expression("text", "links[*]..text");
+    assert!(invalid.is_err());
+}
+
+/// responds with `503 Service Unavailable` until the given number of
+/// requests have failed
+struct FlakyFetcher {
+    failures: usize,
+    requests: AtomicUsize,
+}
+
+#[async_trait]
+impl Fetcher for FlakyFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let request = self.requests.fetch_add(1, Ordering::SeqCst);
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "0".parse()?);
+
+        Ok(match request < self.failures {
+            true => FetchedPage {
+                url: url.clone(),
+                status: 503,
+                headers,
+                body: Bytes::new(),
+            },
+            false => FetchedPage {
+                url: url.clone(),
+                status: 200,
+                headers: HeaderMap::new(),
+                body: Bytes::from_static(b"<html><h1>Recovered</h1></html>"),
+            },
+        })
+    }
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried() {
+    let flaky = || FlakyFetcher {
+        failures: 2,
+        requests: AtomicUsize::new(0),
+    };
+    let delay = Duration::from_millis(1);
+
+    assert_err!(
+        Document::new("https://dev.null")
+            .expect("Document created")
+            .fetcher(flaky())
+            .load_document()
+            .await
+    );
+    assert_err!(
+        Document::new("https://dev.null")
+            .expect("Document created")
+            .fetcher(flaky())
+            .retry_policy(2, delay, Duration::ZERO)
+            .load_document()
+            .await
+    );
+    assert_ok!(
+        Document::new("https://dev.null")
+            .expect("Document created")
+            .fetcher(flaky())
+            .retry_policy(3, delay, Duration::ZERO)
+            .load_document()
+            .await
+    );
+}
+
+#[test]
+fn rate_limits_are_parsed_with_a_human_readable_jitter() {
+    let limit: RateLimit =
+        serde_json::from_str(r#"{"per_second": 2.0, "jitter": "500ms"}"#).unwrap();
+    assert_eq!(
+        limit,
+        RateLimit::per_second(2.0).jitter(Duration::from_millis(500))
+    );
+    assert_eq!(
+        serde_json::to_value(limit).unwrap(),
+        json!({"per_second": 2.0, "burst": 1, "jitter": "500ms"})
+    );
+    let limit: RateLimit = serde_json::from_str(r#"{"per_second": 1.0}"#).unwrap();
+    assert_eq!(limit.jitter, Duration::ZERO);
+
+    assert_err!(serde_json::from_str::<RateLimit>(r#"{"jitter": "soon"}"#));
+    assert_err!(serde_json::from_str::<RateLimit>(r#"{"jitter": 500}"#));
+    assert_err!(serde_json::from_str::<RateLimit>(r#"{"jitter": "-1s"}"#));
+}
+
+#[tokio::test]
+async fn a_mock_clock_makes_pauses_deterministic() {
+    let clock = Arc::new(MockClock::default().jitter_fraction(0.5));
+    let config = ScrapeConfig::default()
+        .rate_limit(RateLimit::per_second(1.0).jitter(Duration::from_secs(1)))
+        .clock(clock.clone());
+    let doc = Document::new("https://clock.dev.null")
+        .expect("Document created")
+        .config(config)
+        .fetcher(FlakyFetcher {
+            failures: 2,
+            requests: AtomicUsize::new(0),
+        })
+        .retry_policy(3, Duration::from_secs(10), Duration::ZERO)
+        .load_document()
+        .await;
+    assert_ok!(doc);
+
+    // the first attempt only waits for the half second of jitter while the
+    // retries -- which the responses ask for right away -- also wait for the
+    // rest of the second the rate limit allows
+    let (half, second) = (Duration::from_millis(500), Duration::from_secs(1));
+    assert_eq!(
+        clock.sleeps(),
+        vec![half, Duration::ZERO, second, Duration::ZERO, second]
+    );
+    assert_eq!(clock.elapsed(), Duration::from_millis(2500));
+
+    let policy = RetryPolicy::new(3, Duration::from_secs(10), Duration::from_secs(2));
+    assert_eq!(
+        policy.backoff_on(2, clock.as_ref()),
+        Duration::from_secs(21)
+    );
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "retry-after",
+        "Thu, 01 Jan 1970 00:00:22 GMT".parse().unwrap(),
+    );
+    assert_eq!(
+        policy.pause_on(1, Some(&headers), clock.as_ref()),
+        Duration::from_millis(19_500)
+    );
+}
+
+/// never responds within a reasonable time
+struct HungFetcher;
+
+#[async_trait]
+impl Fetcher for HungFetcher {
+    async fn fetch(&self, _url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Err(color_eyre::eyre::eyre!("the server hung up"))
+    }
+}
+
+#[tokio::test]
+async fn requests_to_a_host_are_rate_limited() {
+    let config = ScrapeConfig::default()
+        .concurrency(8)
+        .rate_limit(RateLimit::per_second(20.0));
+    let urls = [
+        "https://rate.test/1",
+        "https://rate.test/2",
+        "https://rate.test/3",
+        "https://other.rate.test/1",
+    ];
+    let started = std::time::Instant::now();
+    let mut scrape = ConcurrentScrape::new(config)
+        .fetcher(MockFetcher("<html><h1>Limited</h1></html>"))
+        .add_urls(&urls)
+        .unwrap();
+    scrape.execute().await.unwrap();
+
+    // the first request is made right away and the others 50ms apart
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+
+    assert_eq!(
+        RateLimit::interval(Duration::from_millis(250)).per_second,
+        Some(4.0)
+    );
+    assert!(!RateLimit::interval(Duration::ZERO).is_limited());
+}
+
+#[tokio::test]
+async fn rate_limit_buckets_belong_to_a_config() {
+    let clock = Arc::new(MockClock::default());
+    let config = ScrapeConfig::default()
+        .rate_limit(RateLimit::per_second(1.0))
+        .clock(clock.clone());
+    let url = |u: &str| Url::parse(u).unwrap();
+
+    rate::acquire(&url("https://rate.test/1"), &config).await;
+    rate::acquire(&url("https://rate.test/2"), &config.clone()).await;
+    // another scrape -- or another limit -- has buckets of its own
+    let other = ScrapeConfig::default()
+        .rate_limit(RateLimit::per_second(1.0))
+        .clock(clock.clone());
+    rate::acquire(&url("https://rate.test/3"), &other).await;
+    assert_eq!(
+        clock.sleeps(),
+        vec![Duration::ZERO, Duration::from_secs(1), Duration::ZERO]
+    );
+    assert_eq!(config.buckets.0.len(), 1);
+
+    // a bucket which has refilled while it sat idle is dropped
+    clock.advance(Duration::from_secs(5));
+    rate::acquire(&url("https://other.rate.test/1"), &config).await;
+    assert_eq!(config.buckets.0.len(), 1);
+    clock.advance(Duration::from_secs(5));
+    rate::acquire(&url("https://rate.test/4"), &config).await;
+    assert_eq!(clock.sleeps().last(), Some(&Duration::ZERO));
+}
+
+/// records the most requests which were in flight to each host at once
+#[derive(Default, Clone)]
+struct InFlightFetcher {
+    in_flight: Arc<Mutex<HashMap<String, usize>>>,
+    most: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+#[async_trait]
+impl Fetcher for InFlightFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let host = url.host_str().unwrap_or_default().to_string();
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let count = in_flight.entry(host.clone()).or_default();
+            *count += 1;
+            let mut most = self.most.lock().unwrap();
+            let most = most.entry(host.clone()).or_default();
+            *most = (*most).max(*count);
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        *self.in_flight.lock().unwrap().get_mut(&host).unwrap() -= 1;
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"<html><h1>Busy</h1></html>"),
+        })
+    }
+}
+
+#[tokio::test]
+async fn requests_in_flight_to_a_host_are_limited() {
+    let fetcher = InFlightFetcher::default();
+    let mut urls: Vec<String> = (0..6).map(|i| format!("https://busy.test/{}", i)).collect();
+    urls.extend((0..2).map(|i| format!("https://quiet.test/{}", i)));
+    let urls: Vec<&str> = urls.iter().map(|u| u.as_str()).collect();
+
+    let mut scrape = ConcurrentScrape::new(ScrapeConfig::default().concurrency(8).max_per_host(2))
+        .fetcher(fetcher.clone())
+        .add_urls(&urls)
+        .unwrap();
+    let scraped: Vec<String> = scrape
+        .execute()
+        .await
+        .unwrap()
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => page.url.to_string(),
+            _ => panic!("expected every page to be scraped"),
+        })
+        .collect();
+    // the pages are still returned in the order they were given
+    assert_eq!(scraped, urls);
+
+    // the quiet host isn't held up behind the busy one
+    let most = fetcher.most.lock().unwrap().clone();
+    assert_eq!(most.get("busy.test"), Some(&2));
+    assert_eq!(most.get("quiet.test"), Some(&2));
+
+    assert_eq!(
+        ScrapeConfig::default().max_per_host(0).max_per_host,
+        Some(1)
+    );
+}
+
+#[tokio::test]
+async fn hung_requests_time_out() {
+    let after = Duration::from_millis(20);
+    let error = Document::new("https://dev.null")
+        .expect("Document created")
+        .fetcher(HungFetcher)
+        .timeout(after)
+        .load_document()
+        .await
+        .expect_err("the request timed out");
+
+    assert_eq!(
+        error,
+        ScrapedError::Timeout {
+            url: Url::parse("https://dev.null").unwrap(),
+            after
+        }
+    );
+}
+
+#[tokio::test]
+async fn hung_servers_are_timed_out_by_the_client() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    // the connection is accepted but never answered
+    tokio::spawn(async move {
+        let mut sockets = vec![];
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+
+    let after = Duration::from_millis(50);
+    let config = ScrapeConfig::default().timeout(after);
+    let fetcher = ReqwestFetcher::for_config(&config)
+        .unwrap()
+        .expect("the timeout needs a client of its own");
+    assert_eq!(fetcher.timeout(), Some(after));
+    let error = Document::new(url.as_str())
+        .expect("Document created")
+        .config(config)
+        .load_document()
+        .await
+        .expect_err("the request timed out");
+
+    assert_eq!(error, ScrapedError::Timeout { url, after });
+}
+
+#[test]
+fn retry_backoff_is_exponential_and_respects_retry_after() {
+    let policy = RetryPolicy::new(5, Duration::from_millis(100), Duration::ZERO);
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(3), Duration::from_millis(400));
+    assert_eq!(policy.backoff(20), policy.max_delay);
+
+    let mut headers = HeaderMap::new();
+    headers.insert("retry-after", "7".parse().unwrap());
+    assert_eq!(policy.pause(1, Some(&headers)), Duration::from_secs(7));
+    headers.insert(
+        "retry-after",
+        "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+    );
+    assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+}
+
+/// serves pages by their URL and a 404 for any other URL
+struct SiteFetcher(Vec<(&'static str, &'static str)>);
+
+#[async_trait]
+impl Fetcher for SiteFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let page = self.0.iter().find(|(u, _)| *u == url.as_str());
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: if page.is_some() { 200 } else { 404 },
+            headers: HeaderMap::new(),
+            body: page
+                .map(|(_, body)| Bytes::copy_from_slice(body.as_bytes()))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn rotted_pages_are_loaded_from_the_archive() {
+    let site = || {
+        SiteFetcher(vec![
+            (
+                "https://archive.org/wayback/available?url=https%3A%2F%2Fgone.dev%2Fpage",
+                r#"{"url": "https://gone.dev/page", "archived_snapshots": {"closest": {
+                    "status": "200", "available": true, "timestamp": "20200102030405",
+                    "url": "http://web.archive.org/web/20200102030405/https://gone.dev/page"}}}"#,
+            ),
+            (
+                "http://web.archive.org/web/20200102030405id_/https://gone.dev/page",
+                "<html><h1>Archived</h1></html>",
+            ),
+            (
+                "https://archive.org/wayback/available?url=https%3A%2F%2Fgone.dev%2Fnever",
+                r#"{"url": "https://gone.dev/never", "archived_snapshots": {}}"#,
+            ),
+        ])
+    };
+    let load = |url: &'static str, fallback: bool| async move {
+        Document::new(url)
+            .unwrap()
+            .fetcher(site())
+            .config(ScrapeConfig::default().archive_fallback(fallback))
+            .load_document()
+            .await
+    };
+
+    assert_err!(load("https://gone.dev/page", false).await);
+    assert_err!(load("https://gone.dev/never", true).await);
+
+    let doc = load("https://gone.dev/page", true).await.unwrap();
+    let snapshot = assert_some!(doc.archived.clone());
+    assert_eq!(snapshot.timestamp, "20200102030405");
+    let results = doc
+        .parse_document()
+        .unwrap()
+        .add_selector("h1", "h1")
+        .results()
+        .unwrap();
+    assert_eq!(results.url.as_str(), "https://gone.dev/page");
+    assert_eq!(results.field("h1").as_deref(), Some("Archived"));
+    assert_eq!(
+        json!(results)["archived"]["url"],
+        json!("http://web.archive.org/web/20200102030405/https://gone.dev/page")
+    );
+}
+
+/// serves pages by their URL; a page whose body is `-> <location>` is a 301
+/// redirect to that location
+struct RedirectFetcher(Vec<(&'static str, &'static str)>);
+
+#[async_trait]
+impl Fetcher for RedirectFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let (_, body) = self
+            .0
+            .iter()
+            .find(|(u, _)| *u == url.as_str())
+            .expect("only the site's pages are requested");
+        let mut headers = HeaderMap::new();
+        let status = match body.strip_prefix("-> ") {
+            Some(location) => {
+                headers.insert("location", location.parse()?);
+                301
+            }
+            None => 200,
+        };
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status,
+            headers,
+            body: Bytes::copy_from_slice(body.as_bytes()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn redirect_chains_are_recorded() {
+    let load = |url: &'static str, policy: RedirectPolicy| async move {
+        Document::new(url)
+            .unwrap()
+            .fetcher(RedirectFetcher(vec![
+                ("http://dev.null/old", "-> https://dev.null/old"),
+                ("https://dev.null/old", "-> /docs/"),
+                (
+                    "https://dev.null/docs/",
+                    r#"<a href="guide.html">Guide</a>"#,
+                ),
+                ("https://dev.null/loop", "-> /loop"),
+            ]))
+            .config(ScrapeConfig::default().redirects(policy))
+            .load_document()
+            .await
+    };
+
+    let doc = load("http://dev.null/old", RedirectPolicy::default())
+        .await
+        .expect("redirects followed");
+    assert_eq!(doc.url.as_str(), "http://dev.null/old");
+    assert_eq!(doc.resolved_url.as_str(), "https://dev.null/docs/");
+    let results = doc
+        .parse_document()
+        .unwrap()
+        .add_selector("link", "a")
+        .results()
+        .unwrap();
+    assert_eq!(
+        json!(results)["redirects"],
+        json!(["http://dev.null/old", "https://dev.null/old"])
+    );
+    assert_eq!(
+        json!(results)["resolved_url"],
+        json!("https://dev.null/docs/")
+    );
+    // links are relative to the URL the page was served from
+    assert_eq!(
+        json!(results)["data"]["link"]["full_href"],
+        json!("https://dev.null/docs/guide.html")
+    );
+
+    let error = |policy| async move { load("http://dev.null/old", policy).await.unwrap_err() };
+    assert_eq!(
+        error(RedirectPolicy::default().follow(false)).await,
+        ScrapedError::Http {
+            url: Url::parse("http://dev.null/old").unwrap(),
+            status: 301
+        }
+    );
+    assert_eq!(
+        error(RedirectPolicy::default().max_redirects(1)).await,
+        ScrapedError::TooManyRedirects {
+            url: Url::parse("http://dev.null/old").unwrap(),
+            hops: 2
+        }
+    );
+    let looped = load("https://dev.null/loop", RedirectPolicy::default()).await;
+    assert!(matches!(
+        looped.unwrap_err(),
+        ScrapedError::TooManyRedirects { .. }
+    ));
+}
+
+/// records the URLs requested, answering each with an empty page
+#[derive(Default, Clone)]
+struct RecordingFetcher(Arc<Mutex<Vec<String>>>);
+
+#[async_trait]
+impl Fetcher for RecordingFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        self.0.lock().unwrap().push(url.to_string());
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn scraped_pages_are_submitted_to_the_archive() {
+    let submissions = RecordingFetcher::default();
+    let sink = WaybackSink::default()
+        .fetcher(submissions.clone())
+        .interval(Duration::ZERO);
+    let site = SiteFetcher(vec![
+        ("https://dev.null/a", "<html><h1>A</h1></html>"),
+        ("https://dev.null/b", "<html><h1>B</h1></html>"),
+    ]);
+
+    let mut scrape = ConcurrentScrape::default()
+        .fetcher(site)
+        .add_urls(&[
+            "https://dev.null/a",
+            "https://dev.null/b",
+            "https://dev.null/a",
+            "https://dev.null/missing",
+        ])
+        .unwrap()
+        .add_sink(sink);
+    scrape.execute().await.unwrap();
+
+    let mut submitted = submissions.0.lock().unwrap().clone();
+    submitted.sort();
+    assert_eq!(
+        submitted,
+        vec![
+            "https://web.archive.org/save/https://dev.null/a",
+            "https://web.archive.org/save/https://dev.null/b",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn frames_are_followed_by_policy() {
+    let frame_urls = |policy: FramePolicy| async move {
+        let site = SiteFetcher(vec![
+            (
+                "https://dev.null/",
+                r#"<html><h1>Parent</h1>
+                <iframe src="/frame"></iframe>
+                <iframe src="https://other.example/frame"></iframe></html>"#,
+            ),
+            ("https://dev.null/frame", "<html><h1>Same</h1></html>"),
+            ("https://other.example/frame", "<html><h1>Other</h1></html>"),
+        ]);
+        let results = Document::new("https://dev.null/")
+            .expect("Document created")
+            .config(ScrapeConfig::default().frames(policy))
+            .fetcher(site)
+            .load_document()
+            .await
+            .expect("document loaded")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector("h1", "h1")
+            .results_graph()
+            .await
+            .expect("results successfully extracted");
+
+        results
+            .frames
+            .iter()
+            .map(|f| f.url.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert!(frame_urls(FramePolicy::default()).await.is_empty());
+    assert_eq!(
+        frame_urls(FramePolicy::SameOrigin).await,
+        vec!["https://dev.null/frame"]
+    );
+    assert_eq!(
+        frame_urls(FramePolicy::All).await,
+        vec!["https://dev.null/frame", "https://other.example/frame"]
+    );
+}
+
+#[test]
+fn robots_txt_rules_are_matched() {
+    let robots = RobotsTxt::parse(
+        "User-agent: *\nDisallow: /private\nAllow: /private/open\nDisallow: /*.pdf$\nCrawl-delay: 2\n\n\
+         User-agent: scraped\nDisallow: /\n",
+    );
+    let url = |path: &str| Url::parse(&format!("https://dev.null{}", path)).unwrap();
+
+    assert!(robots.allows("*", &url("/public")));
+    assert!(!robots.allows("*", &url("/private/page")));
+    assert!(robots.allows("*", &url("/private/open/page")));
+    assert!(!robots.allows("*", &url("/docs/file.pdf")));
+    assert!(robots.allows("*", &url("/docs/file.pdf?download")));
+    assert!(!robots.allows("scraped/1.0", &url("/public")));
+    assert_eq!(robots.crawl_delay("*"), Some(Duration::from_secs(2)));
+    assert_eq!(robots.crawl_delay("scraped/1.0"), None);
+}
+
+#[tokio::test]
+async fn site_policies_summarize_what_crawlers_are_asked() {
+    let site = SiteFetcher(vec![
+        (
+            "https://policy.test/robots.txt",
+            "User-agent: *\nDisallow: /admin\nCrawl-delay: 2\n\nSitemap: https://policy.test/pages.xml\n",
+        ),
+        (
+            "https://policy.test/",
+            r#"<html><head><meta name="ROBOTS" content="noindex, NoFollow"></head></html>"#,
+        ),
+    ]);
+    let url = Url::parse("https://policy.test/docs/page").unwrap();
+    let policy = SitePolicy::inspect(&url, &ScrapeConfig::default(), &site)
+        .await
+        .expect("policy inspected");
+
+    assert_eq!(policy.homepage.as_str(), "https://policy.test/");
+    assert_eq!(
+        (policy.homepage_status, policy.robots_status),
+        (200, Some(200))
+    );
+    assert!(policy.homepage_allowed);
+    assert_eq!(policy.disallowed, vec!["/admin"]);
+    assert_eq!(
+        policy.sitemaps,
+        vec![Url::parse("https://policy.test/pages.xml").unwrap()]
+    );
+    assert_eq!(policy.meta_robots, vec!["noindex", "nofollow"]);
+    assert!(policy.nofollow());
+    assert_eq!(policy.rate_limit().per_second, Some(0.5));
+    let starter = policy.starter_config(&ScrapeConfig::default());
+    assert!(starter.respect_robots);
+    assert_eq!(starter.max_per_host, Some(1));
+
+    // without a crawl delay the advertised quota -- or a polite default -- applies
+    let mut headers = HeaderMap::new();
+    headers.insert("RateLimit-Policy", "120;w=60".parse().unwrap());
+    headers.insert("Content-Type", "text/html".parse().unwrap());
+    let policy = SitePolicy {
+        crawl_delay: None,
+        rate_limit_headers: rate_limit_headers(&headers),
+        ..policy
+    };
+    assert_eq!(
+        policy.rate_limit_headers.keys().collect::<Vec<_>>(),
+        vec!["ratelimit-policy"]
+    );
+    assert_eq!(policy.rate_limit().per_second, Some(2.0));
+    let policy = SitePolicy {
+        rate_limit_headers: Default::default(),
+        ..policy
+    };
+    assert_eq!(policy.rate_limit().per_second, Some(POLITE_RATE));
+}
+
+#[tokio::test]
+async fn robots_txt_is_cached_per_config_until_it_expires() {
+    let clock = Arc::new(MockClock::default());
+    let fetcher = FlakyFetcher {
+        failures: 1,
+        requests: AtomicUsize::new(0),
+    };
+    let config = ScrapeConfig::default()
+        .respect_robots(true)
+        .clock(clock.clone());
+    let page = Url::parse("https://robots.test/page").unwrap();
+    let check = |config: ScrapeConfig| {
+        let (page, fetcher) = (page.clone(), &fetcher);
+        async move { robots::retain_allowed(vec![page], &config, fetcher).await }
+    };
+    let requests = || fetcher.requests.load(Ordering::SeqCst);
+
+    // a server error isn't cached
+    check(config.clone()).await;
+    check(config.clone()).await;
+    assert_eq!(requests(), 2);
+    check(config.clone()).await;
+    assert_eq!(requests(), 2);
+
+    // another scrape has a cache of its own
+    check(ScrapeConfig::default().respect_robots(true)).await;
+    assert_eq!(requests(), 3);
+
+    clock.advance(ROBOTS_TTL);
+    check(config.clone()).await;
+    assert_eq!(requests(), 4);
+}
+
+#[tokio::test]
+async fn robots_txt_disallowed_children_are_skipped() {
+    let site = || {
+        SiteFetcher(vec![
+            (
+                "https://robots.test/robots.txt",
+                "User-agent: *\nDisallow: /private\n",
+            ),
+            (
+                "https://robots.test/",
+                r#"<html><a href="https://robots.test/public">public</a>
+                <a href="https://robots.test/private">private</a></html>"#,
+            ),
+            ("https://robots.test/public", "<html><h1>Public</h1></html>"),
+            (
+                "https://robots.test/private",
+                "<html><h1>Private</h1></html>",
+            ),
+        ])
+    };
+    let children = |respect: bool| async move {
+        Document::new("https://robots.test/")
+            .expect("Document created")
+            .fetcher(site())
+            .respect_robots(respect)
+            .load_document()
+            .await
+            .expect("document loaded")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector_all("links", "a")
+            .child_selectors(vec!["links"], ChildScope::Http())
+            .get_children()
+            .await
+            .expect("children scraped")
+            .iter()
+            .map(|c| c.url.path().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(children(false).await, vec!["/public", "/private"]);
+    assert_eq!(children(true).await, vec!["/public"]);
+
+    let error = Document::new("https://robots.test/private")
+        .expect("Document created")
+        .fetcher(site())
+        .respect_robots(true)
+        .load_document()
+        .await
+        .expect_err("the page is disallowed");
+    assert!(matches!(error, ScrapedError::Disallowed { .. }));
+}
+
+#[tokio::test]
+async fn requests_are_limited_to_the_allowed_domains() {
+    let safety = CrawlSafety::default().allow_domains(["*.Dev.Null", "example.com."]);
+    let allows = |url: &str| safety.allows(&Url::parse(url).unwrap());
+    assert!(allows("https://dev.null/a"));
+    assert!(allows("https://docs.dev.null/a"));
+    assert!(allows("http://example.com/"));
+    assert!(!allows("https://notdev.null/a"));
+    assert!(!allows("https://example.com.evil.test/"));
+    assert!(!allows("file:///etc/passwd"));
+
+    let site = || {
+        SiteFetcher(vec![
+            (
+                "https://dev.null/",
+                r#"<html><a href="https://dev.null/a">A</a><a href="https://docs.dev.null/b">B</a>
+                <a href="https://elsewhere.test/c">C</a></html>"#,
+            ),
+            ("https://dev.null/a", "<html><h1>A</h1></html>"),
+            ("https://docs.dev.null/b", "<html><h1>B</h1></html>"),
+            ("https://elsewhere.test/c", "<html><h1>C</h1></html>"),
+        ])
+    };
+    let config = ScrapeConfig::default().safety(CrawlSafety::default().allow_domains(["dev.null"]));
+
+    let results = Document::new("https://dev.null/")
+        .unwrap()
+        .fetcher(site())
+        .config(config.clone())
+        .load_document()
+        .await
+        .unwrap()
+        .parse_document()
+        .unwrap()
+        .add_selector_all("links", "a")
+        .child_selectors(vec!["links"], ChildScope::Http())
+        .results_graph()
+        .await
+        .unwrap();
+    let children: Vec<&str> = results.children.iter().map(|c| c.url.as_str()).collect();
+    assert_eq!(
+        children,
+        vec!["https://dev.null/a", "https://docs.dev.null/b"]
+    );
+    assert!(results.failures.is_empty());
+
+    let error = Document::new("https://elsewhere.test/c")
+        .unwrap()
+        .fetcher(site())
+        .config(config)
+        .load_document()
+        .await
+        .expect_err("the page is outside of the allow-list");
+    assert!(matches!(error, ScrapedError::OutsideAllowList { .. }));
+}
+
+#[tokio::test]
+async fn private_networks_are_blocked() {
+    [
+        "10.0.0.1",
+        "127.0.0.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "::1",
+        "fd00::1",
+        "::ffff:192.168.0.1",
+        "64:ff9b::a00:1",
+    ]
+    .iter()
+    .for_each(|ip| assert!(!is_public(&ip.parse().unwrap()), "{} is private", ip));
+    ["93.184.216.34", "2606:2800:220:1::", "64:ff9b::5db8:d822"]
+        .iter()
+        .for_each(|ip| assert!(is_public(&ip.parse().unwrap()), "{} is public", ip));
+
+    let safety = CrawlSafety::default().block_private_networks(true);
+    let refused = |url: &str| {
+        let url = Url::parse(url).unwrap();
+        let safety = safety.clone();
+        async move {
+            let error = safety
+                .check_resolved(&url)
+                .await
+                .expect_err("the URL is refused");
+            error.downcast_ref::<ScrapedError>().cloned()
+        }
+    };
+    assert!(matches!(
+        refused("http://169.254.169.254/latest/meta-data").await,
+        Some(ScrapedError::PrivateAddress { .. })
+    ));
+    assert!(matches!(
+        refused("http://[::1]:8080/").await,
+        Some(ScrapedError::PrivateAddress { .. })
+    ));
+    assert!(matches!(
+        refused("http://localhost/admin").await,
+        Some(ScrapedError::PrivateAddress { .. })
+    ));
+    assert!(matches!(
+        refused("file:///etc/passwd").await,
+        Some(ScrapedError::UnsupportedScheme { .. })
+    ));
+    assert_ok!(safety.check(&Url::parse("https://93.184.216.34/").unwrap()));
+
+    let error = Document::new("http://127.0.0.1/")
+        .unwrap()
+        .fetcher(EchoFetcher)
+        .config(ScrapeConfig::default().safety(safety.clone()))
+        .load_document()
+        .await
+        .expect_err("the page is refused");
+    assert!(matches!(error, ScrapedError::PrivateAddress { .. }));
+}
+
+/// answers every request with the given status and a `Retry-After` of 7 seconds
+struct StatusFetcher(u16);
+
+#[async_trait]
+impl Fetcher for StatusFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "7".parse()?);
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: self.0,
+            headers,
+            body: Bytes::new(),
+        })
+    }
+}
+
+/// answers every request with Cloudflare's interstitial
+struct ChallengeFetcher;
+
+#[async_trait]
+impl Fetcher for ChallengeFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let mut headers = HeaderMap::new();
+        headers.insert("server", "cloudflare".parse()?);
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 503,
+            headers,
+            body: Bytes::from("<html><head><title>Just a moment...</title></head></html>"),
+        })
+    }
+}
+
+#[tokio::test]
+async fn failures_are_classified() {
+    let load = |status: u16| async move {
+        Document::new("https://dev.null/")
+            .unwrap()
+            .fetcher(StatusFetcher(status))
+            .load_document()
+            .await
+            .expect_err("the request fails")
+    };
+    let url = Url::parse("https://dev.null/").unwrap();
+
+    assert_eq!(
+        load(404).await,
+        ScrapedError::Http {
+            url: url.clone(),
+            status: 404
+        }
+    );
+    assert_eq!(
+        load(429).await,
+        ScrapedError::RateLimited {
+            url,
+            retry_after: Some(Duration::from_secs(7))
+        }
+    );
+    let blocked = Document::new("https://dev.null/")
+        .unwrap()
+        .fetcher(ChallengeFetcher)
+        .load_document()
+        .await
+        .expect_err("the page is blocked");
+    assert_eq!(
+        blocked,
+        ScrapedError::Blocked {
+            url: Url::parse("https://dev.null/").unwrap(),
+            challenge: BotChallenge::Cloudflare,
+            status: 503
+        }
+    );
+    assert!(matches!(
+        Document::new("not a url").unwrap_err(),
+        ScrapedError::ParseFailure { .. }
+    ));
+    assert!(matches!(
+        ScrapedError::from(Chain::from_str("div:nope >> p").unwrap_err()),
+        ScrapedError::InvalidSelector { .. }
+    ));
+    assert_eq!(
+        ScrapedError::from(color_eyre::eyre::eyre!("something else")),
+        ScrapedError::Other {
+            reason: "something else".to_string()
+        }
+    );
+}
+
+/// serves sitemaps -- some gzipped -- and a page for any other URL
+struct SitemapFetcher(Vec<(&'static str, Vec<u8>)>);
+
+#[async_trait]
+impl Fetcher for SitemapFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let body = self
+            .0
+            .iter()
+            .find(|(u, _)| *u == url.as_str())
+            .map(|(_, body)| body.clone())
+            .unwrap_or_else(|| b"<html><title>Page</title></html>".to_vec());
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn sitemap_urls_are_scraped() {
+    let mut gzipped = GzEncoder::new(vec![], Compression::default());
+    gzipped
+        .write_all(
+            br#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <url><loc><![CDATA[https://dev.null/c?a=1&b=2]]></loc></url></urlset>"#,
+        )
+        .unwrap();
+    let fetcher = SitemapFetcher(vec![
+        (
+            "https://dev.null/sitemap.xml",
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+            <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <sitemap><loc>https://dev.null/pages.xml</loc></sitemap>
+              <sitemap><loc>https://dev.null/more.xml.gz</loc></sitemap>
+            </sitemapindex>"#
+                .to_vec(),
+        ),
+        (
+            "https://dev.null/pages.xml",
+            br#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://dev.null/a</loc><lastmod>2024-01-01</lastmod></url>
+              <url><loc> https://dev.null/b?x=1&amp;y=2 </loc></url>
+            </urlset>"#
+                .to_vec(),
+        ),
+        ("https://dev.null/more.xml.gz", gzipped.finish().unwrap()),
+    ]);
+
+    let mut scrape = ConcurrentScrape::default()
+        .fetcher(fetcher)
+        .add_sitemap("https://dev.null/sitemap.xml")
+        .await
+        .expect("sitemap read");
+    let urls: Vec<String> = scrape
+        .execute()
+        .await
+        .expect("scrape executed")
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => page.url.to_string(),
+            _ => panic!("expected every page to be scraped"),
+        })
+        .collect();
+
+    assert_eq!(
+        urls,
+        vec![
+            "https://dev.null/a",
+            "https://dev.null/b?x=1&y=2",
+            "https://dev.null/c?a=1&b=2"
+        ]
+    );
+}
+
+/// a paginated API: `?page=1` and `?page=3` give the next page in a `Link`
+/// header while `?page=2` links to it from its HTML; every page has a next
+struct PagedFetcher;
+
+#[async_trait]
+impl Fetcher for PagedFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let page: usize = url.query().unwrap_or("page=1")[5..].parse()?;
+        let mut headers = HeaderMap::new();
+        let mut body = format!("<html><h1>Page {}</h1>", page);
+        if page % 2 == 1 {
+            let link = format!(
+                r#"</items?page=1>; rel="first", </items?page={}>; rel="next prefetch""#,
+                page + 1
+            );
+            headers.insert("link", link.parse()?);
+        } else {
+            body += &format!(r#"<a rel="next" href="?page={}">Next</a>"#, page + 1);
+        }
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers,
+            body: Bytes::from(body + "</html>"),
+        })
+    }
+}
+
+#[tokio::test]
+async fn paginated_pages_are_followed_from_link_headers_and_html() {
+    let results = Document::new("https://api.test/items")
+        .expect("Document created")
+        .fetcher(PagedFetcher)
+        .follow_pagination(3)
+        .load_document()
+        .await
+        .expect("document loaded")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("h1", "h1")
+        .results_graph()
+        .await
+        .expect("results extracted");
+
+    let pages: Vec<String> = results.pages.iter().map(|p| p.url.to_string()).collect();
+    assert_eq!(
+        pages,
+        vec![
+            "https://api.test/items?page=2",
+            "https://api.test/items?page=3",
+            "https://api.test/items?page=4",
+        ]
+    );
+    match results.pages[2].data.get("h1") {
+        Some(ResultKind::Item(h1)) => assert_eq!(h1.text.as_deref(), Some("Page 4")),
+        _ => panic!("expected the pages to be parsed with the same selectors"),
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "link",
+        r#"<https://cdn.test/style.css>; rel=preload"#.parse().unwrap(),
+    );
+    headers.append(
+        "link",
+        r#"<?cursor=abc,def>; rel="Next"; title="more""#.parse().unwrap(),
+    );
+    assert_eq!(
+        pagination::next_from_headers(&headers, &Url::parse("https://api.test/v1").unwrap()),
+        Some(Url::parse("https://api.test/v1?cursor=abc,def").unwrap())
+    );
+    assert!(!Pagination::default().follow);
+}
+
+#[tokio::test]
+async fn concurrent_scrape_returns_results_in_order() {
+    let doc = |url: &str| {
+        Document::new(url)
+            .expect("Document created")
+            .fetcher(MockFetcher("<html><title>Mocked</title></html>"))
+    };
+    let mut scrape = ConcurrentScrape::default()
+        .add_document(doc("https://dev.null/a"))
+        .add_document(doc("https://dev.null/b"));
+
+    let urls: Vec<String> = scrape
+        .execute()
+        .await
+        .expect("scrape executed")
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => page.url.to_string(),
+            _ => panic!("expected every page to be scraped"),
+        })
+        .collect();
+    assert_eq!(urls, vec!["https://dev.null/a", "https://dev.null/b"]);
+    assert_eq!(scrape.stats().snapshot().done, 2);
+}
+
+/// records the URL of each page it receives
+#[derive(Clone, Default)]
+struct RecordingSink(Arc<Mutex<Vec<String>>>);
+
+#[async_trait]
+impl Sink for RecordingSink {
+    async fn send(&self, outcome: &PageOutcome) -> color_eyre::Result<()> {
+        if let PageOutcome::Ok(page) = outcome {
+            self.0.lock().unwrap().push(page.url.to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn pages_with_the_same_canonical_are_merged_into_one_record() {
+    let doc = |url: &str, html: &'static str| {
+        Document::new(url)
+            .expect("Document created")
+            .fetcher(MockFetcher(html))
+    };
+    let shoes = r#"<html><head><link rel="canonical" href="/shoes"></head></html>"#;
+    let sink = RecordingSink::default();
+    let mut scrape = ConcurrentScrape::new(ScrapeConfig::default().dedupe_canonical(true))
+        .add_sink(sink.clone())
+        .add_document(doc("https://dev.null/shoes?sort=price", shoes))
+        .add_document(doc(
+            "https://dev.null/hats",
+            "<html><title>Hats</title></html>",
+        ))
+        .add_document(doc("https://dev.null/shoes", shoes))
+        .add_document(doc("https://dev.null/shoes?sort=size", shoes));
+
+    let pages: Vec<(String, Vec<String>)> = scrape
+        .execute()
+        .await
+        .expect("scrape executed")
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => (
+                page.url.to_string(),
+                page.duplicates.iter().map(|u| u.to_string()).collect(),
+            ),
+            _ => panic!("expected every page to be scraped"),
+        })
+        .collect();
+    assert_eq!(
+        pages,
+        vec![
+            (
+                "https://dev.null/shoes".to_string(),
+                vec![
+                    "https://dev.null/shoes?sort=price".to_string(),
+                    "https://dev.null/shoes?sort=size".to_string(),
+                ]
+            ),
+            ("https://dev.null/hats".to_string(), vec![]),
+        ]
+    );
+    match &scrape.results[0] {
+        PageOutcome::Ok(page) => assert_eq!(
+            page.canonical.as_ref().map(|u| u.as_str()),
+            Some("https://dev.null/shoes")
+        ),
+        _ => panic!("expected the canonical page to be kept"),
+    }
+    // the sinks only receive the merged pages
+    assert_eq!(
+        *sink.0.lock().unwrap(),
+        vec!["https://dev.null/shoes", "https://dev.null/hats"]
+    );
+    assert!(!ScrapeConfig::default().dedupe_canonical);
+}
+
+/// a docs site whose `/docs` page links to pages and assets which answer a
+/// `HEAD` request in different ways; `/no-head` only answers a `GET`
+struct LinkedFetcher;
+
+#[async_trait]
+impl Fetcher for LinkedFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let (status, content_type, body) = match url.path() {
+            "/docs" => (200, "text/html", DOCS_PAGE),
+            "/no-head" => (200, "application/pdf", "%PDF-1.4"),
+            _ => (404, "text/html", "Not Found"),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", content_type.parse()?);
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status,
+            headers,
+            body: Bytes::from(body),
+        })
+    }
+
+    async fn head(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let (status, headers) = match (url.host_str(), url.path()) {
+            (Some("docs.test"), "/docs/ok") => (200, "text/html;1024"),
+            (Some("docs.test"), "/img/logo.png") => (200, "image/png;2048"),
+            (Some("docs.test"), "/old") => (301, ""),
+            (Some("docs.test"), "/no-head") => (405, ""),
+            _ => (404, ""),
+        };
+        let mut map = HeaderMap::new();
+        if let Some((content_type, length)) = headers.split_once(';') {
+            map.insert("content-type", content_type.parse()?);
+            map.insert("content-length", length.parse()?);
+        }
+        if status == 301 {
+            map.insert("location", "/docs/ok".parse()?);
+        }
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status,
+            headers: map,
+            body: Bytes::new(),
+        })
+    }
+}
+
+const DOCS_PAGE: &str = r##"<html><body>
+    <a href="/docs/ok">Getting started</a>
+    <a href="/docs/gone">Removed</a>
+    <img src="/img/logo.png">
+    <a href="/old">Moved</a>
+    <a href="/no-head">Report</a>
+    <a href="/docs/ok#install">Installing</a>
+    <a href="mailto:docs@docs.test">Mail us</a>
+    <a href="#top">Top</a>
+    <a href="https://elsewhere.test/">Elsewhere</a>
+</body></html>"##;
+
+#[tokio::test]
+async fn links_are_audited_with_head_requests() {
+    let audit = |link_audit: LinkAudit| async move {
+        Document::new("https://docs.test/docs")
+            .expect("Document created")
+            .config(ScrapeConfig::default().link_audit(link_audit))
+            .fetcher(LinkedFetcher)
+            .load_document()
+            .await
+            .expect("document loaded")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .results_async()
+            .await
+            .expect("results extracted")
+            .link_report
+            .expect("links audited")
+    };
+    let report = audit(LinkAudit::default().enabled(true)).await;
+
+    let links: Vec<(&str, Option<u16>)> = report
+        .links
+        .iter()
+        .map(|l| (l.url.as_str(), l.status))
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            ("https://docs.test/docs/ok", Some(200)),
+            ("https://docs.test/docs/gone", Some(404)),
+            ("https://docs.test/img/logo.png", Some(200)),
+            ("https://docs.test/old", Some(301)),
+            ("https://docs.test/no-head", Some(200)),
+            ("https://elsewhere.test/", Some(404)),
+        ]
+    );
+    let logo = assert_some!(report.get(&Url::parse("https://docs.test/img/logo.png").unwrap()));
+    assert_eq!(logo.content_type.as_deref(), Some("image/png"));
+    assert_eq!(logo.content_length, Some(2048));
+    assert_eq!(
+        report.links[3].location.as_ref().map(|u| u.as_str()),
+        Some("https://docs.test/docs/ok")
+    );
+    // a server which doesn't allow HEAD is asked with a GET
+    assert_eq!(
+        report.links[4].content_type.as_deref(),
+        Some("application/pdf")
+    );
+    assert_eq!(report.links[4].content_length, Some(8));
+    let broken: Vec<&str> = report.broken().map(|l| l.url.as_str()).collect();
+    assert_eq!(
+        broken,
+        vec!["https://docs.test/docs/gone", "https://elsewhere.test/"]
+    );
+
+    let report = audit(LinkAudit::default().enabled(true).same_host(true)).await;
+    assert_eq!(report.links.len(), 5);
+    assert!(!LinkAudit::default().enabled);
+}
+
+/// records each call to its hooks as `<hook> <url>`, along with the last progress
+#[derive(Clone, Default)]
+struct RecordingObserver {
+    calls: Arc<Mutex<Vec<String>>>,
+    last: Arc<Mutex<Progress>>,
+}
+
+impl Observer for RecordingObserver {
+    fn on_queued(&self, pages: usize, progress: Progress) {
+        self.calls.lock().unwrap().push(format!("queued {}", pages));
+        *self.last.lock().unwrap() = progress;
+    }
+
+    fn on_page_started(&self, url: &Url, progress: Progress) {
+        self.calls.lock().unwrap().push(format!("started {}", url));
+        *self.last.lock().unwrap() = progress;
+    }
+
+    fn on_page_finished(&self, url: &Url, _bytes: usize, progress: Progress) {
+        self.calls.lock().unwrap().push(format!("finished {}", url));
+        *self.last.lock().unwrap() = progress;
+    }
+
+    fn on_error(&self, url: &Url, _error: &color_eyre::Report, progress: Progress) {
+        self.calls.lock().unwrap().push(format!("error {}", url));
+        *self.last.lock().unwrap() = progress;
+    }
+}
+
+#[tokio::test]
+async fn observers_are_called_as_pages_are_scraped() {
+    let site = SiteFetcher(vec![(
+        "https://observed.test/a",
+        "<html><title>A</title></html>",
+    )]);
+    let observer = RecordingObserver::default();
+    let mut scrape = ConcurrentScrape::new(ScrapeConfig::default().concurrency(1))
+        .fetcher(site)
+        .add_urls(&["https://observed.test/a", "https://observed.test/missing"])
+        .unwrap()
+        .add_observer(observer.clone());
+
+    scrape.execute().await.expect("scrape executed");
+
+    assert_eq!(
+        *observer.calls.lock().unwrap(),
+        vec![
+            "queued 2",
+            "started https://observed.test/a",
+            "finished https://observed.test/a",
+            "started https://observed.test/missing",
+            "error https://observed.test/missing",
+        ]
+    );
+    let progress = *observer.last.lock().unwrap();
+    assert_eq!(
+        (progress.done, progress.errors, progress.in_flight),
+        (1, 1, 0)
+    );
+    assert_eq!(progress.total(), 2);
+    assert_eq!(progress, scrape.stats().progress());
+}
+
+#[tokio::test]
+async fn crawl_statistics_count_each_page() {
+    let site = SiteFetcher(vec![
+        (
+            "https://stats.test/",
+            r#"<html><a href="https://stats.test/a">a</a><a href="https://stats.test/b">b</a>
+            <a href="https://stats.test/gone">gone</a></html>"#,
+        ),
+        ("https://stats.test/a", "<html><h1>A</h1></html>"),
+        ("https://stats.test/b", "<html><h1>Bee</h1></html>"),
+    ]);
+    let doc = Document::new("https://stats.test/")
+        .expect("Document created")
+        .fetcher(site)
+        .load_document()
+        .await
+        .expect("document loaded")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector_all("links", "a")
+        .child_selectors(vec!["links"], ChildScope::Http());
+    let outcomes = doc.get_child_outcomes().await.expect("children scraped");
+    assert_eq!(outcomes.len(), 3);
+
+    let bytes = ("<html><h1>A</h1></html>".len() + "<html><h1>Bee</h1></html>".len()) as u64;
+    let host = HostStats {
+        done: 2,
+        errors: 1,
+        bytes,
+    };
+    assert_eq!(
+        doc.stats().snapshot(),
+        StatsSnapshot {
+            queued: 0,
+            in_flight: 0,
+            done: 2,
+            errors: 1,
+            bytes,
+            hosts: HashMap::from([("stats.test".to_string(), host)]),
+        }
+    );
+}
+
+#[tokio::test]
+async fn crawls_resume_from_a_persisted_frontier() {
+    let path = std::env::temp_dir().join(format!("scraped-{}.frontier", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let url = |u: &str| Url::parse(u).unwrap();
+
+    // a crawl which stopped while requesting `b`, having scraped `a`
+    let frontier = FileFrontier::open(&path).expect("frontier opened");
+    for page in [
+        "https://dev.null/a",
+        "https://dev.null/b",
+        "https://dev.null/c",
+    ] {
+        assert!(frontier.enqueue(&url(page)).await.unwrap());
+    }
+    assert!(!frontier
+        .enqueue(&url("https://dev.null/a#top"))
+        .await
+        .unwrap());
+    assert_eq!(
+        frontier.dequeue().await.unwrap(),
+        Some(url("https://dev.null/a"))
+    );
+    frontier
+        .mark_seen(&url("https://dev.null/a"))
+        .await
+        .unwrap();
+    assert_eq!(
+        frontier.dequeue().await.unwrap(),
+        Some(url("https://dev.null/b"))
+    );
+    drop(frontier);
+    // a line cut short by the crash is skipped
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"queu")
+        .unwrap();
+
+    let frontier = FileFrontier::open(&path).expect("frontier reopened");
+    assert_eq!(frontier.queued().await.unwrap(), 2);
+    let site = SiteFetcher(vec![
+        ("https://dev.null/a", "<html><h1>A</h1></html>"),
+        ("https://dev.null/b", "<html><h1>B</h1></html>"),
+        ("https://dev.null/c", "<html><h1>C</h1></html>"),
+        ("https://dev.null/d", "<html><h1>D</h1></html>"),
+    ]);
+    let mut scrape = ConcurrentScrape::default()
+        .fetcher(site)
+        .frontier(frontier)
+        .add_urls(&["https://dev.null/a", "https://dev.null/d"])
+        .unwrap();
+    let urls: Vec<String> = scrape
+        .execute()
+        .await
+        .expect("scrape executed")
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => page.url.to_string(),
+            _ => panic!("expected every page to be scraped"),
+        })
+        .collect();
+    assert_eq!(
+        urls,
+        vec![
+            "https://dev.null/b",
+            "https://dev.null/c",
+            "https://dev.null/d"
+        ]
+    );
+
+    // every page has been seen so there's nothing left to resume
+    let frontier = FileFrontier::open(&path).expect("frontier reopened");
+    assert_eq!(frontier.queued().await.unwrap(), 0);
+    assert!(!frontier.enqueue(&url("https://dev.null/c")).await.unwrap());
+    fs::remove_file(&path).unwrap();
+
+    let memory = MemoryFrontier::new();
+    assert!(memory.enqueue(&url("https://dev.null/a")).await.unwrap());
+    memory.mark_seen(&url("https://dev.null/a")).await.unwrap();
+    assert_eq!(memory.dequeue().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn sites_are_scraped_from_their_sitemaps_and_homepage_links() {
+    let site = SiteFetcher(vec![
+        (
+            "https://sitewide.test/robots.txt",
+            "User-agent: *\nDisallow: /private\nSitemap: https://sitewide.test/pages.xml\n",
+        ),
+        (
+            "https://sitewide.test/pages.xml",
+            r#"<urlset><url><loc>https://sitewide.test/blog/a</loc></url>
+            <url><loc>https://sitewide.test/blog/b</loc></url></urlset>"#,
+        ),
+        (
+            "https://sitewide.test/",
+            r#"<html><a href="/blog/a">a</a><a href="/blog/c">c</a><a href="/shop/x">x</a>
+            <a href="/private/y">y</a><a href="https://elsewhere.test/z">z</a></html>"#,
+        ),
+        ("https://sitewide.test/blog/a", "<html><h1>A</h1></html>"),
+        ("https://sitewide.test/blog/b", "<html><h1>B</h1></html>"),
+        ("https://sitewide.test/shop/x", "<html><h1>X</h1></html>"),
+    ]);
+
+    let mut scrape = ConcurrentScrape::new(ScrapeConfig::default().respect_robots(true))
+        .fetcher(site)
+        .add_site("https://sitewide.test/blog/a")
+        .await
+        .expect("site added");
+    let mut pages: Vec<(String, Option<Discovery>)> = scrape
+        .execute()
+        .await
+        .expect("scrape executed")
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => (page.url.path().to_string(), page.discovery),
+            _ => panic!("expected every page to be scraped"),
+        })
+        .collect();
+    pages.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // the blog is listed by the sitemap so only the shop is found by its links
+    assert_eq!(
+        pages,
+        vec![
+            ("/".to_string(), Some(Discovery::Seed)),
+            ("/blog/a".to_string(), Some(Discovery::Sitemap)),
+            ("/blog/b".to_string(), Some(Discovery::Sitemap)),
+            ("/shop/x".to_string(), Some(Discovery::Link)),
+        ]
+    );
+}
+
+/// cancels the crawl as soon as the first page is requested
+struct CancellingFetcher(Cancellation);
+
+#[async_trait]
+impl Fetcher for CancellingFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        self.0.cancel();
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"<html><h1>First</h1></html>"),
+        })
+    }
+}
+
+#[tokio::test]
+async fn cancelled_crawls_return_the_pages_in_flight() {
+    let path = std::env::temp_dir().join(format!("scraped-{}.cancelled", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let cancel = Cancellation::new();
+    let mut scrape = ConcurrentScrape::new(
+        ScrapeConfig::default()
+            .concurrency(1)
+            .cancellation(cancel.clone()),
+    )
+    .fetcher(CancellingFetcher(cancel.clone()))
+    .frontier(FileFrontier::open(&path).expect("frontier opened"))
+    .add_urls(&[
+        "https://dev.null/a",
+        "https://dev.null/b",
+        "https://dev.null/c",
+    ])
+    .unwrap();
+
+    // the page in flight when the crawl was cancelled is finished
+    let outcomes = scrape.execute().await.expect("scrape executed");
+    assert_eq!(outcomes.len(), 3);
+    assert!(matches!(&outcomes[0], PageOutcome::Ok(page) if page.url.path() == "/a"));
+    assert!(cancel.is_cancelled());
+
+    // while the pages which weren't requested are reported and left to resume
+    let cancelled: Vec<_> = outcomes[1..]
+        .iter()
+        .filter_map(|outcome| match outcome {
+            PageOutcome::Cancelled(url) => Some(url.path()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(cancelled, vec!["/b", "/c"]);
+    assert_eq!(scrape.stats().progress().queued, 0);
+    assert!(scrape.cancellation().is_cancelled());
+    let frontier = FileFrontier::open(&path).expect("frontier reopened");
+    assert_eq!(frontier.queued().await.unwrap(), 2);
+    fs::remove_file(&path).unwrap();
+}
+
+/// serves the pages of the `PagedFetcher`, cancelling the crawl as the second
+/// is requested
+struct CancelOnSecondPage(Cancellation);
+
+#[async_trait]
+impl Fetcher for CancelOnSecondPage {
+    async fn fetch(&self, url: &Url, headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        if url.query() == Some("page=2") {
+            self.0.cancel();
+        }
+        PagedFetcher.fetch(url, headers).await
+    }
+}
+
+#[tokio::test]
+async fn cancelled_crawls_stop_following_the_pages_of_added_documents() {
+    let scrape = ConcurrentScrape::default();
+    let doc = Document::new("https://api.test/items")
+        .expect("Document created")
+        .fetcher(CancelOnSecondPage(scrape.cancellation()))
+        .follow_pagination(5);
+    let mut scrape = scrape.add_document(doc);
+
+    // the page in flight when the crawl was cancelled is finished but no more
+    // are followed
+    let outcomes = scrape.execute().await.expect("scrape executed");
+    let pages: Vec<String> = match &outcomes[0] {
+        PageOutcome::Ok(page) => page.pages.iter().map(|p| p.url.to_string()).collect(),
+        _ => panic!("expected the document to be scraped"),
+    };
+    assert_eq!(pages, vec!["https://api.test/items?page=2"]);
+}
+
+#[tokio::test]
+async fn requeued_urls_are_dequeued_again_unless_seen() {
+    let frontier = MemoryFrontier::new();
+    let a = Url::parse("https://dev.null/a").unwrap();
+    let b = Url::parse("https://dev.null/b").unwrap();
+    frontier.enqueue(&a).await.unwrap();
+    frontier.enqueue(&b).await.unwrap();
+    assert_eq!(frontier.dequeue().await.unwrap(), Some(a.clone()));
+    assert_eq!(frontier.dequeue().await.unwrap(), Some(b.clone()));
+    frontier.mark_seen(&b).await.unwrap();
+
+    // a page which was taken but never requested is queued again
+    frontier.requeue(&a).await.unwrap();
+    frontier.requeue(&b).await.unwrap();
+    assert_eq!(frontier.queued().await.unwrap(), 1);
+    assert_eq!(frontier.dequeue().await.unwrap(), Some(a));
+    assert_eq!(frontier.dequeue().await.unwrap(), None);
+}
+
+/// records the connections warmed up and the pages requested, in order
+struct WarmingFetcher(Arc<Mutex<Vec<String>>>);
+
+#[async_trait]
+impl Fetcher for WarmingFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        self.0.lock().unwrap().push(format!("fetch {}", url));
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"<html><title>Warm</title></html>"),
+        })
+    }
+
+    async fn warm(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<()> {
+        self.0.lock().unwrap().push(format!("warm {}", url));
+
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn busiest_hosts_are_warmed_up_before_the_crawl() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let mut scrape = ConcurrentScrape::new(ScrapeConfig::default().concurrency(1).warmup(2))
+        .fetcher(WarmingFetcher(log.clone()))
+        .add_urls(&[
+            "https://quiet.dev.null/",
+            "https://busy.dev.null/a",
+            "https://busy.dev.null/b",
+            "https://other.dev.null/a",
+            "https://busy.dev.null/c",
+            "https://other.dev.null/b",
+        ])
+        .expect("URLs added");
+    scrape.execute().await.expect("scrape executed");
+
+    let log = log.lock().unwrap();
+    assert_eq!(
+        log[..2],
+        [
+            "warm https://busy.dev.null/a",
+            "warm https://other.dev.null/a"
+        ]
+    );
+    assert_eq!(log.len(), 8);
+    assert!(log[2..].iter().all(|entry| entry.starts_with("fetch")));
+
+    let urls: Vec<Url> = ["https://a.test/1", "https://b.test/1", "https://b.test/2"]
+        .iter()
+        .map(|u| Url::parse(u).unwrap())
+        .collect();
+    let busiest = warmup::busiest_hosts(urls.iter().map(|u| (u, ())), 5);
+    assert_eq!(busiest.len(), 2);
+    assert_eq!(busiest[0].0.as_str(), "https://b.test/1");
+}
+
+#[test]
+fn single_selector_matches() {
+    let url = String::from("https://dev.null");
+    let contents =
+        fs::read_to_string("tests/fixtures/simple-doc.html").expect("Problem reading fixture file");
+    let doc = LoadedDocument::new(&url, &contents)
+        .expect("LoadedDoc prepped")
+        .parse_document()
+        .expect("LoadedDoc parsed")
+        .add_selector("h1", "h1");
+    let result = doc.get("h1");
+    assert_ok!(&result);
+    let result = result.unwrap();
+    assert_some!(&result);
+    let _result = result.expect("result has a value");
+    // assert_eq!(result, "My Title")
+}
+
+#[test]
+fn results_have_convenience_accessors() {
+    let html =
+        r#"<html><h1>Title</h1><nav><a href="/a">A</a><a>none</a><a href="/b">B</a></nav></html>"#;
+    let results = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("h1", "h1")
+        .add_selector("h2", "h2")
+        .add_selector_all("links", "nav a")
+        .add_expression("heading", "h1.text")
+        .results()
+        .expect("results successfully extracted");
+
+    let h1 = &results.data["h1"];
+    assert_eq!(
+        h1.as_element().and_then(|s| s.text.as_deref()),
+        Some("Title")
+    );
+    assert!(h1.as_list().is_none());
+    assert_eq!(h1.text(), Some("Title"));
+
+    let links = &results.data["links"];
+    assert_eq!(links.as_list().map(|l| l.len()), Some(3));
+    assert_eq!(links.texts(), vec!["A", "none", "B"]);
+    assert_eq!(links.hrefs(), vec!["/a", "/b"]);
+    assert_eq!(
+        links.full_hrefs(),
+        vec!["https://dev.null/a", "https://dev.null/b"]
+    );
+    assert_eq!(links.first_text_or("-"), "A");
+
+    assert_eq!(results.props["heading"], json!("Title"));
+    assert!(!results.data.contains_key("h2"));
+    assert_eq!(ResultKind::List(vec![]).first_text_or("-"), "-");
+    assert_eq!(ResultKind::Property(json!("p")).text(), Some("p"));
+}
+
+#[test]
+fn static_property_definition_available_in_results() {
+    let url = String::from("https://dev.null");
+    let contents =
+        fs::read_to_string("tests/fixtures/simple-doc.html").expect("Problem reading fixture file");
+    let results = LoadedDocument::new(&url, &contents)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_property("hello", |_| json!("world"))
+        .results()
+        .expect("results successfully extracted");
+
+    assert_some!(results.props.get("hello"));
+    let hello = results.props.get("hello").expect("hello prop exists");
+    assert_eq!(hello, &json!("world"));
+}
+
+/// serves a page linking to six children, each of which takes longer to
+/// respond than the next, and records the most children in flight at once
+#[derive(Default, Clone)]
+struct StaggeredFetcher {
+    in_flight: Arc<AtomicUsize>,
+    most: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Fetcher for StaggeredFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let body = match url.path().trim_start_matches('/').parse::<u64>() {
+            Ok(child) => {
+                let count = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.most.fetch_max(count, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis((6 - child) * 10)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                format!("<html><h1>{}</h1></html>", child)
+            }
+            Err(_) => (0..6)
+                .map(|i| format!(r#"<a href="https://staggered.test/{}">{}</a>"#, i, i))
+                .collect(),
+        };
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status: 200,
+            headers: HeaderMap::new(),
+            body: Bytes::from(body),
+        })
+    }
+}
+
+#[tokio::test]
+async fn children_are_fetched_concurrently_within_the_bound() {
+    let children = |config: ScrapeConfig| async move {
+        let fetcher = StaggeredFetcher::default();
+        let children: Vec<String> = Document::new("https://staggered.test/")
+            .expect("Document created")
+            .fetcher(fetcher.clone())
+            .config(config)
+            .load_document()
+            .await
+            .expect("document loaded")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector_all("links", "a")
+            .child_selectors(vec!["links"], ChildScope::Http())
+            .get_children()
+            .await
+            .expect("children scraped")
+            .iter()
+            .map(|c| c.url.path().to_string())
+            .collect();
+
+        (children, fetcher.most.load(Ordering::SeqCst))
+    };
+    let requested = vec!["/0", "/1", "/2", "/3", "/4", "/5"];
+
+    // the slowest children are requested first but returned in the page's order
+    let (ordered, most) = children(ScrapeConfig::default().concurrency(3)).await;
+    assert_eq!(ordered, requested);
+    assert_eq!(most, 3);
+
+    let (unordered, most) = children(ScrapeConfig::default().concurrency(6).ordered(false)).await;
+    assert_eq!(unordered, vec!["/5", "/4", "/3", "/2", "/1", "/0"]);
+    assert_eq!(most, 6);
+
+    let (_, most) = children(ScrapeConfig::default().concurrency(1)).await;
+    assert_eq!(most, 1);
+}
+
+#[test]
+fn scrape_config_is_deserialized_with_defaults() {
+    let config: ScrapeConfig = serde_json::from_str("{}").unwrap();
+    assert_eq!(config, ScrapeConfig::default());
+    // the handles to the state of a crawl aren't settings
+    let cancel = Cancellation::new();
+    cancel.cancel();
+    assert_eq!(config.clone().cancellation(cancel), config);
+
+    let config: ScrapeConfig =
+        serde_json::from_str(r#"{"concurrency": 3, "timeout": "5s", "user_agent": "scraped/1.0"}"#)
+            .unwrap();
+    assert_eq!(
+        config,
+        ScrapeConfig::default()
+            .concurrency(3)
+            .timeout(Duration::from_secs(5))
+            .user_agent("scraped/1.0")
+    );
+
+    // what's serialized is read back as the same config
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(serde_json::from_str::<ScrapeConfig>(&json).unwrap(), config);
+
+    let error = serde_json::from_str::<ScrapeConfig>(r#"{"concurency": 3}"#).unwrap_err();
+    assert!(
+        error.to_string().contains("unknown field `concurency`"),
+        "{}",
+        error
+    );
+    let error = serde_json::from_str::<ScrapeConfig>(r#"{"retry": {"attempts": 3}}"#).unwrap_err();
+    assert!(
+        error.to_string().contains("unknown field `attempts`"),
+        "{}",
+        error
+    );
+}
+
+/// serves a page linking to a page which loads, one which is missing and one
+/// whose server fails with a long error page
+struct ErrorFetcher;
+
+#[async_trait]
+impl Fetcher for ErrorFetcher {
+    async fn fetch(&self, url: &Url, _headers: &HeaderMap) -> color_eyre::Result<FetchedPage> {
+        let (status, body) = match url.path() {
+            "/" => (
+                200,
+                r#"<html><a href="https://errors.test/ok">ok</a>
+                <a href="https://errors.test/missing">missing</a>
+                <a href="https://errors.test/broken">broken</a></html>"#
+                    .to_string(),
+            ),
+            "/ok" => (200, "<html><h1>OK</h1></html>".to_string()),
+            "/missing" => (404, "Not Found".to_string()),
+            _ => (500, "é".repeat(MAX_ERROR_BODY)),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "text/html".parse()?);
+
+        Ok(FetchedPage {
+            url: url.clone(),
+            status,
+            headers,
+            body: Bytes::from(body),
+        })
+    }
+}
+
+/// the page whose children are served by the `ErrorFetcher`
+async fn error_page(config: ScrapeConfig) -> ParsedDoc {
+    Document::new("https://errors.test/")
+        .expect("Document created")
+        .fetcher(ErrorFetcher)
+        .config(config)
+        .load_document()
+        .await
+        .expect("document loaded")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector_all("links", "a")
+        .child_selectors(vec!["links"], ChildScope::Http())
+}
+
+#[tokio::test]
+async fn error_responses_are_recorded_as_failed_outcomes() {
+    let outcomes = error_page(ScrapeConfig::default())
+        .await
+        .get_child_outcomes()
+        .await
+        .expect("the crawl carries on past the failures");
+    let statuses: Vec<(String, Option<u16>)> = outcomes
+        .iter()
+        .map(|outcome| match outcome {
+            PageOutcome::Ok(page) => (page.url.path().to_string(), Some(200)),
+            PageOutcome::Failed(failure) => (failure.url.path().to_string(), failure.status),
+            PageOutcome::Blocked(_) => panic!("no page is blocked"),
+            PageOutcome::Cancelled(_) => panic!("the crawl isn't cancelled"),
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("/ok".to_string(), Some(200)),
+            ("/missing".to_string(), Some(404)),
+            ("/broken".to_string(), Some(500)),
+        ]
+    );
+    match &outcomes[1] {
+        PageOutcome::Failed(failure) => {
+            assert_eq!(failure.attempts, 1);
+            assert!(failure.error.contains("404"), "{}", failure.error);
+        }
+        _ => unreachable!(),
+    }
+
+    let error = error_page(ScrapeConfig::default().fail_fast(true))
+        .await
+        .get_child_outcomes()
+        .await
+        .expect_err("the crawl stops at the first failure");
+    assert_eq!(
+        error,
+        ScrapedError::Http {
+            url: Url::parse("https://errors.test/missing").unwrap(),
+            status: 404
+        }
+    );
+}
+
+#[tokio::test]
+async fn error_bodies_are_captured_truncated() {
+    let failure = |outcomes: Vec<PageOutcome>| match outcomes.into_iter().nth(2) {
+        Some(PageOutcome::Failed(failure)) => failure,
+        _ => panic!("expected the broken page to fail"),
+    };
+
+    let config = ScrapeConfig::default().capture_error_body(true);
+    let broken = failure(error_page(config).await.get_child_outcomes().await.unwrap());
+    assert_eq!(broken.status, Some(500));
+    let body = broken.body.expect("the body is captured");
+    assert_eq!(body, "é".repeat(MAX_ERROR_BODY / 2));
+    assert_eq!(
+        broken.headers.expect("the headers are captured")["content-type"],
+        "text/html"
+    );
+
+    let broken = failure(
+        error_page(ScrapeConfig::default())
+            .await
+            .get_child_outcomes()
+            .await
+            .unwrap(),
+    );
+    assert_eq!((broken.body, broken.headers), (None, None));
+
+    // the body is cut at the last whole character within the limit
+    let url = Url::parse("https://dev.null").unwrap();
+    let body = format!("a{}", "é".repeat(MAX_ERROR_BODY));
+    let captured = HttpError::new(&url, 500, &[], &body, true).body.unwrap();
+    assert_eq!(captured.len(), MAX_ERROR_BODY - 1);
+    assert_eq!(
+        HttpError::new(&url, 404, &[], "Not Found", true)
+            .body
+            .as_deref(),
+        Some("Not Found")
+    );
+}
+
+#[test]
+fn annotated_html_highlights_matches() {
+    let url = String::from("https://dev.null");
+    let contents =
+        fs::read_to_string("tests/fixtures/simple-doc.html").expect("Problem reading fixture file");
+    let html = LoadedDocument::new(&url, &contents)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("title", "h1")
+        .add_selector_all("items", "li")
+        .add_selector(r#"the "intro""#, "p")
+        .annotated_html();
+
+    assert!(html.contains(r#"<h1 data-scraped="title""#));
+    assert_eq!(html.matches(r#"<li data-scraped="items""#).count(), 3);
+    // the selectors' names are escaped within the attribute
+    assert!(html.contains(r#"<p data-scraped="the &quot;intro&quot;""#));
+    assert!(html.contains("id=\"scraped-legend\""));
+    assert!(html.contains("items (3)"));
+}
+
+#[test]
+fn report_includes_selectors_without_matches() {
+    let url = String::from("https://dev.null");
+    let contents =
+        fs::read_to_string("tests/fixtures/simple-doc.html").expect("Problem reading fixture file");
+    let doc = LoadedDocument::new(&url, &contents)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector_all("items", "li")
+        .add_selector("missing", "article");
+    let report = doc.report(&doc.results().expect("results extracted"));
+
+    assert_eq!(report.pages, 1);
+    let items = report.coverage.get("items").expect("items coverage");
+    assert_eq!(items.match_rate, 1.0);
+    assert_eq!(items.avg_matches, 3.0);
+    let missing = report.coverage.get("missing").expect("missing coverage");
+    assert_eq!(missing.pages_matched, 0);
+    assert_eq!(missing.zero_match_examples, vec!["https://dev.null/"]);
+}
+
+#[tokio::test]
+async fn crawl_properties_aggregate_every_page() {
+    let site = SiteFetcher(vec![
+        (
+            "https://crawl.test/",
+            r#"<html><a href="https://crawl.test/child">child</a>
+            <a href="https://example.com/x">x</a><img src="/a.png"><img></html>"#,
+        ),
+        (
+            "https://crawl.test/child",
+            r#"<html><a href="https://other.org/">other</a><img src=" "></html>"#,
+        ),
+    ]);
+    let pages: CrawlProperty = |pages| json!(pages.len());
+    let doc = Document::new("https://crawl.test/")
+        .expect("Document created")
+        .fetcher(site)
+        .load_document()
+        .await
+        .expect("document loaded")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_generic_selectors()
+        .child_selectors(vec!["links"], ChildScope::Http())
+        .add_crawl_property("external_domains", external_domains)
+        .add_crawl_property("broken_images", broken_images)
+        .add_crawl_property("pages", pages);
+    let results = doc.results_graph().await.expect("results extracted");
+    let report = doc.report(&results);
+
+    assert_eq!(
+        report.properties.get("external_domains"),
+        Some(&json!(["example.com", "other.org"]))
+    );
+    assert_eq!(report.properties.get("broken_images"), Some(&json!(2)));
+    assert_eq!(report.properties.get("pages"), Some(&json!(2)));
+}
+
+#[test]
+fn lookups_join_rows_to_pages() {
+    let html = r#"<html><span class="sku">A-1</span></html>"#;
+    let by_url = Lookup::from_csv(
+        "url,catalog_id,title\nhttps://dev.null/item?utm_source=x,42,Catalog title\n",
+        "url",
+        LookupKey::Url,
+    )
+    .expect("CSV lookup parsed");
+    let by_sku = Lookup::from_json(
+        r#"[{"sku": "A-1", "stock": 3}, {"sku": "B-2", "stock": 0}]"#,
+        "sku",
+        LookupKey::Field("sku".into()),
+    )
+    .expect("JSON lookup parsed");
+    let title: PropertyCallback = |_| json!("Page title");
+
+    let results = LoadedDocument::new("https://dev.null/item", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("sku", ".sku")
+        .add_property("title", title)
+        .add_lookup(by_url)
+        .add_lookup(by_sku)
+        .results()
+        .expect("results successfully extracted");
+
+    assert_eq!(results.props.get("catalog_id"), Some(&json!("42")));
+    assert_eq!(results.props.get("title"), Some(&json!("Page title")));
+    assert_eq!(results.props.get("stock"), Some(&json!(3)));
+    assert_err!(Lookup::from_json(r#"[{"id": 1}]"#, "sku", LookupKey::Url));
+}
+
+#[tokio::test]
+async fn property_callbacks_can_capture_state_and_await() {
+    let html = r#"<html><h1 class="sku">A-1</h1></html>"#;
+    let prefix = String::from("sku:");
+    let stock = Arc::new(HashMap::from([("A-1".to_string(), 3)]));
+
+    let doc = LoadedDocument::new("https://dev.null/item", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("sku", ".sku")
+        .add_property("key", move |sel| match sel.get("sku") {
+            Some(ResultKind::Item(s)) => json!(format!("{}{}", prefix, s.text.clone().unwrap())),
+            _ => Value::Null,
+        })
+        .add_async_property("stock", move |sel| {
+            let stock = stock.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                match sel.get("sku") {
+                    Some(ResultKind::Item(s)) => json!(stock.get(s.text.as_deref().unwrap())),
+                    _ => Value::Null,
+                }
+            }
+        });
+
+    let results = doc.results().expect("results successfully extracted");
+    assert_eq!(results.props.get("key"), Some(&json!("sku:A-1")));
+    assert!(!results.props.contains_key("stock"));
+
+    let results = doc
+        .results_async()
+        .await
+        .expect("results successfully extracted");
+    assert_eq!(results.props.get("key"), Some(&json!("sku:A-1")));
+    assert_eq!(results.props.get("stock"), Some(&json!(3)));
+}
+
+#[test]
+fn page_ids_are_stable() {
+    let html = r#"<html><span class="sku">A-1</span></html>"#;
+    let id = |url: &str, strategy: IdStrategy| {
+        LoadedDocument::new(url, html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .config(ScrapeConfig::default().id(strategy))
+            .add_selector("sku", ".sku")
+            .results()
+            .expect("results successfully extracted")
+            .id
+    };
+
+    assert_eq!(id("https://dev.null/item", IdStrategy::None), None);
+    let hash = id("https://dev.null/item", IdStrategy::UrlHash).expect("hashed id");
+    assert_eq!(hash.len(), 16);
+    assert_eq!(
+        id(
+            "https://dev.null/item/?utm_source=x#top",
+            IdStrategy::UrlHash
+        ),
+        Some(hash)
+    );
+    assert_eq!(
+        id(
+            "https://dev.null/item/?utm_source=x",
+            IdStrategy::CanonicalUrl
+        )
+        .as_deref(),
+        Some("https://dev.null/item")
+    );
+    assert_eq!(
+        id("https://dev.null/item", IdStrategy::Property("sku".into())).as_deref(),
+        Some("A-1")
+    );
+    assert_eq!(
+        id("https://dev.null/item", IdStrategy::Property("slug".into())),
+        None
+    );
+}
+
+#[test]
+fn bot_challenges_are_detected() {
+    let cloudflare = "<html><head><title>Just a moment...</title></head></html>";
+    assert_eq!(
+        BotChallenge::detect(503, &[("server", "cloudflare")], cloudflare),
+        Some(BotChallenge::Cloudflare)
+    );
+    let captcha = r#"<div class="g-recaptcha"></div>"#;
+    assert_eq!(
+        BotChallenge::detect(403, &[], captcha),
+        Some(BotChallenge::Captcha)
+    );
+    // a contact form with a CAPTCHA on a page which was served is not a challenge
+    assert_eq!(BotChallenge::detect(200, &[], captcha), None);
+}
+
+#[test]
+fn client_redirects_are_detected() {
+    let base = Url::parse("https://dev.null/old/").unwrap();
+    let refresh = r#"<meta http-equiv="Refresh" content="0; URL='/new'">"#;
+    assert_eq!(
+        client_redirect(refresh, &base).map(String::from),
+        Some("https://dev.null/new".to_string())
+    );
+    let script = r#"<script>window.location.href = "page.html";</script>"#;
+    assert_eq!(
+        client_redirect(script, &base).map(String::from),
+        Some("https://dev.null/old/page.html".to_string())
+    );
+    // a redirect which is conditional isn't a trivial redirect
+    let conditional = r#"<script>if (x) { window.location = "/new"; }</script>"#;
+    assert_eq!(client_redirect(conditional, &base), None);
+}
+
+#[test]
+fn hreflang_alternates_are_followed_for_selected_locales() {
+    let html = r#"<html><head>
+        <link rel="alternate" hreflang="de-DE" href="/de/">
+        <link rel="alternate" hreflang="fr" href="https://dev.null/fr/">
+        <link rel="alternate" hreflang="x-default" href="/">
+    </head></html>"#;
+    let doc = LoadedDocument::new("https://dev.null/en/", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created");
+    let results = doc.results().expect("results successfully extracted");
+    assert_eq!(results.alternates.len(), 3);
+    assert!(doc.get_child_urls().is_empty());
+
+    let doc = doc.config(ScrapeConfig::default().locales(vec!["de".to_string()]));
+    let children: Vec<String> = doc.get_child_urls().into_iter().map(String::from).collect();
+    assert_eq!(children, vec!["https://dev.null/de/"]);
+}
+
+#[test]
+fn child_rules_narrow_the_followed_urls() {
+    let html = r#"<html><body>
+        <nav>
+            <a href="https://www.example.co.uk/docs/intro">intro</a>
+            <a href="https://blog.example.co.uk/docs/news">news</a>
+            <a href="https://other.co.uk/docs/elsewhere">elsewhere</a>
+            <a href="https://www.example.co.uk/login">login</a>
+            <a href="https://www.example.co.uk/docs/login">docs login</a>
+        </nav>
+        <a class="next" href="https://blog.example.co.uk/about">about</a>
+    </body></html>"#;
+    let doc = || {
+        LoadedDocument::new("https://www.example.co.uk/docs/", html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector_all("links", "nav a")
+            .add_selector("next", "a.next")
+    };
+    let children = |doc: &ParsedDoc| -> Vec<String> {
+        let mut urls: Vec<String> = doc.get_child_urls().into_iter().map(String::from).collect();
+        urls.sort();
+        urls
+    };
+
+    let all = doc().child_selectors(vec!["links", "next"], ChildScope::Http());
+    assert_eq!(children(&all).len(), 6);
+
+    let same_host = doc().child_selectors_with_rules(
+        vec!["links"],
+        ChildScope::Http(),
+        ChildRules::new().same_host(),
+    );
+    assert_eq!(
+        children(&same_host),
+        vec![
+            "https://www.example.co.uk/docs/intro",
+            "https://www.example.co.uk/docs/login",
+            "https://www.example.co.uk/login",
+        ]
+    );
+
+    let scoped = doc()
+        .child_selectors_with_rules(
+            vec!["links"],
+            ChildScope::Http(),
+            ChildRules::new()
+                .same_domain()
+                .path_prefix("/docs/")
+                .deny("/login$"),
+        )
+        .child_selectors_with_rules(
+            vec!["next"],
+            ChildScope::Http(),
+            ChildRules::new().allow("/about$"),
+        );
+    assert_eq!(
+        children(&scoped),
+        vec![
+            "https://blog.example.co.uk/about",
+            "https://blog.example.co.uk/docs/news",
+            "https://www.example.co.uk/docs/intro",
+        ]
+    );
+
+    assert_err!(ChildRules::new().deny("(unclosed").validate());
+    assert_eq!(registrable_domain("www.example.co.uk"), "example.co.uk");
+    assert_eq!(registrable_domain("127.0.0.1"), "127.0.0.1");
 }
 
 #[test]
-fn single_selector_matches() {
-    let url = String::from("https://dev.null");
-    let contents =
-        fs::read_to_string("tests/fixtures/simple-doc.html").expect("Problem reading fixture file");
-    let doc = LoadedDocument::new(&url, &contents)
-        .expect("LoadedDoc prepped")
+fn duplicate_child_urls_are_requested_once() {
+    let url = |u: &str| Url::parse(u).unwrap();
+    assert_eq!(
+        url_norm::canonicalize(&url(
+            "HTTPS://Dev.Null:443/a/../docs/?b=2&utm_source=x&a=1#top"
+        ))
+        .as_str(),
+        "https://dev.null/docs?a=1&b=2"
+    );
+    assert_eq!(
+        url_norm::dedupe(vec![
+            url("https://dev.null/docs/?utm_medium=email"),
+            url("https://dev.null/about"),
+            url("https://dev.null/docs#intro"),
+        ]),
+        vec![
+            url("https://dev.null/docs/?utm_medium=email"),
+            url("https://dev.null/about")
+        ]
+    );
+
+    let html = r#"<html><body>
+        <a href="https://dev.null/docs?b=2&a=1">docs</a>
+        <a href="https://dev.null/docs?a=1&b=2&utm_campaign=launch">docs again</a>
+        <a href="https://dev.null/docs?a=1&b=2#usage">usage</a>
+        <a href="https://dev.null/start#top">top</a>
+        <a href="https://dev.null/about">about</a>
+    </body></html>"#;
+    let doc = LoadedDocument::new("https://dev.null/start", html)
+        .expect("LoadedDoc created")
         .parse_document()
-        .expect("LoadedDoc parsed")
-        .add_selector("h1", "h1");
-    let result = doc.get("h1");
-    assert_ok!(&result);
-    let result = result.unwrap();
-    assert_some!(&result);
-    let _result = result.expect("result has a value");
-    // assert_eq!(result, "My Title")
+        .expect("ParsedDoc created")
+        .add_selector_all("links", "a")
+        .child_selectors(vec!["links"], ChildScope::Http());
+    let children: Vec<String> = doc.get_child_urls().into_iter().map(String::from).collect();
+    assert_eq!(
+        children,
+        vec!["https://dev.null/docs?b=2&a=1", "https://dev.null/about"]
+    );
 }
 
 #[test]
-fn static_property_definition_available_in_results() {
-    let url = String::from("https://dev.null");
-    let contents =
-        fs::read_to_string("tests/fixtures/simple-doc.html").expect("Problem reading fixture file");
-    let results = LoadedDocument::new(&url, &contents)
+fn extractors_emit_contact_properties() {
+    let html = r#"<html><body>
+        <p>Write to support [at] dev [dot] null or call +1 (555) 123-4567.</p>
+        <p>Released on 2024-01-03 with 1200 downloads.</p>
+        <a href="tel:020 7946 0018">call</a>
+        <a href="https://twitter.com/scraped">twitter</a>
+        <a href="https://twitter.com/intent/tweet?text=hi">share</a>
+    </body></html>"#;
+    let results = LoadedDocument::new("https://dev.null", html)
         .expect("LoadedDoc created")
         .parse_document()
         .expect("ParsedDoc created")
-        .add_property("hello", |_| json!("world"))
+        .add_extractor(Extractor::Emails)
+        .add_extractor(Extractor::Phones)
+        .add_extractor(Extractor::Social)
         .results()
         .expect("results successfully extracted");
 
-    assert_some!(results.props.get("hello"));
-    let hello = results.props.get("hello").expect("hello prop exists");
-    assert_eq!(hello, &json!("world"));
+    assert_eq!(results.props["emails"], json!(["support@dev.null"]));
+    assert_eq!(
+        results.props["phones"],
+        json!(["02079460018", "+15551234567"])
+    );
+    assert_eq!(
+        results.props["social"],
+        json!({ "twitter": ["https://twitter.com/scraped"] })
+    );
+}
+
+#[test]
+fn human_dates_are_normalized() {
+    let now = Utc.with_ymd_and_hms(2024, 1, 10, 12, 0, 0).unwrap();
+    let en = DateHints::default();
+    let de = DateHints::locale("de");
+
+    assert_eq!(
+        parse_date_at("Jan 3, 2024", &en, now).as_deref(),
+        Some("2024-01-03T00:00:00Z")
+    );
+    assert_eq!(
+        parse_date_at("3. Januar 2024 10:30", &de, now).as_deref(),
+        Some("2024-01-03T10:30:00Z")
+    );
+    assert_eq!(
+        parse_date_at("03/01/2024", &de, now).as_deref(),
+        Some("2024-01-03T00:00:00Z")
+    );
+    assert_eq!(
+        parse_date_at("03/01/2024", &en, now).as_deref(),
+        Some("2024-03-01T00:00:00Z")
+    );
+    assert_eq!(
+        parse_date_at("vor 2 Tagen", &de, now).as_deref(),
+        Some("2024-01-08T12:00:00Z")
+    );
+    assert_eq!(
+        parse_date_at("2024-01-03T10:00:00+01:00", &en, now).as_deref(),
+        Some("2024-01-03T10:00:00+01:00")
+    );
+    assert_eq!(parse_date_at("not a date", &en, now), None);
+}
+
+#[test]
+fn prices_are_parsed_with_locale_separators() {
+    let hints = PriceHints::default();
+    let price = |text: &str| {
+        let price = parse_price(text, &hints).expect("price parsed");
+        (price.amount, price.currency)
+    };
+
+    assert_eq!(price("€1.299,00"), (1299.0, Some("EUR".to_string())));
+    assert_eq!(price("$12.99"), (12.99, Some("USD".to_string())));
+    assert_eq!(price("12,99 zł"), (12.99, Some("PLN".to_string())));
+    assert_eq!(price("CHF 1'200.50"), (1200.5, Some("CHF".to_string())));
+    assert_eq!(price("1,299"), (1299.0, None));
+    assert_eq!(parse_price("free", &hints), None);
+}
+
+#[test]
+fn numbers_with_units_are_parsed() {
+    assert_eq!(parse_number("1.2k stars", None), Some(1200.0));
+    assert_eq!(parse_number("3.4M downloads", None), Some(3_400_000.0));
+    assert_eq!(parse_number("85%", None), Some(0.85));
+    assert_eq!(parse_number("12,345 views", None), Some(12345.0));
+    assert_eq!(parse_number("no stars", None), None);
+
+    assert_eq!(parse_duration("2 min read"), Some(120.0));
+    assert_eq!(parse_duration("1h 30m"), Some(5400.0));
+    assert_eq!(parse_duration("1:05:30"), Some(3930.0));
+}
+
+#[test]
+fn url_properties_are_derived_from_the_page_url() {
+    let results = LoadedDocument::new(
+        "https://dev.null/blog/2024/hello-world.html?b=2&utm_source=x&a=1#intro",
+        "",
+    )
+    .expect("LoadedDoc created")
+    .parse_document()
+    .expect("ParsedDoc created")
+    .add_extractor(Extractor::Url)
+    .results()
+    .expect("results successfully extracted");
+
+    let url = &results.props["url"];
+    assert_eq!(url["segments"], json!(["blog", "2024", "hello-world.html"]));
+    assert_eq!(url["slug"], json!("hello-world"));
+    assert_eq!(url["query"]["a"], json!(["1"]));
+    assert_eq!(
+        url["canonical"],
+        json!("https://dev.null/blog/2024/hello-world.html?a=1&b=2")
+    );
+}
+
+#[test]
+fn malformed_html_is_repaired() {
+    let html = "<html><head><title>Broken\0 &amp;amp; Co</head><body><h1>Found</h1><!-- oops <p>after</p></body></html>";
+    let repaired = repair_html(html);
+    assert_eq!(
+        repaired,
+        "<html><head><title>Broken &amp; Co</title></head><body><h1>Found</h1><!-- oops --><p>after</p></body></html>"
+    );
+
+    let h1 = LoadedDocument::new("https://dev.null", &repaired)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("h1", "h1")
+        .results()
+        .expect("results successfully extracted");
+    assert_some!(h1.data.get("h1"));
+}
+
+#[test]
+fn text_is_normalized_by_the_policy() {
+    let html = "<p>\u{201c}Caf\u{e9}\u{a0}&amp;amp;\u{a0}Bar\u{201d} \u{2014} open\u{2026}</p>";
+    let text = |policy: TextPolicy| {
+        let results = LoadedDocument::new("https://dev.null", html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .config(ScrapeConfig::default().text(policy))
+            .add_selector("p", "p")
+            .results()
+            .expect("results successfully extracted");
+        match results.data.get("p") {
+            Some(ResultKind::Item(p)) => p.text.clone().unwrap_or_default(),
+            _ => panic!("expected the paragraph"),
+        }
+    };
+
+    assert_eq!(
+        text(TextPolicy::default()),
+        "\u{201c}Caf\u{e9}\u{a0}&\u{a0}Bar\u{201d} \u{2014} open\u{2026}"
+    );
+    assert_eq!(
+        text(TextPolicy::normalized()),
+        "\"Caf\u{e9} & Bar\" - open..."
+    );
+}
+
+#[test]
+fn template_markup_is_selected_by_policy() {
+    let html = r#"<html><body>
+        <p>light</p>
+        <template><p>inert</p></template>
+        <div><template shadowrootmode="open"><p>shadow</p></template></div>
+    </body></html>"#;
+    let texts = |policy: TemplatePolicy| {
+        let results = LoadedDocument::new("https://dev.null", html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .config(ScrapeConfig::default().templates(policy))
+            .add_selector_all("p", "p")
+            .results()
+            .expect("results successfully extracted");
+        match results.data.get("p") {
+            Some(ResultKind::List(list)) => list
+                .iter()
+                .map(|p| (p.text.clone().unwrap_or_default(), p.template))
+                .collect::<Vec<_>>(),
+            _ => panic!("expected a list of paragraphs"),
+        }
+    };
+    let light = ("light".to_string(), None);
+    let inert = ("inert".to_string(), Some(TemplateKind::Template));
+    let shadow = ("shadow".to_string(), Some(TemplateKind::ShadowRoot));
+
+    assert_eq!(
+        texts(TemplatePolicy::default()),
+        vec![light.clone(), shadow.clone()]
+    );
+    assert_eq!(texts(TemplatePolicy::LightDom), vec![light.clone()]);
+    assert_eq!(texts(TemplatePolicy::All), vec![light, inert, shadow]);
+}
+
+#[test]
+fn list_selections_are_post_processed() {
+    let html = r#"<html><body>
+        <a href="/b">Beta</a><a href="/a">Alpha</a><a href="/b">Beta again</a>
+        <a href="/login">Login</a><a>Anchor</a>
+    </body></html>"#;
+    let links = |steps: Vec<PostProcessor>| {
+        let doc = LoadedDocument::new("https://dev.null", html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector_all("links", "a");
+        let results = steps
+            .into_iter()
+            .fold(doc, |doc, step| doc.add_post_processor("links", step))
+            .results()
+            .expect("results successfully extracted");
+        match results.data.get("links") {
+            Some(ResultKind::List(list)) => list
+                .iter()
+                .map(|s| s.text.clone().unwrap_or_default())
+                .collect::<Vec<_>>(),
+            _ => panic!("expected a list of links"),
+        }
+    };
+
+    assert_eq!(
+        links(vec![
+            PostProcessor::Unique { by: "href".into() },
+            PostProcessor::Filter {
+                by: "href".into(),
+                pattern: "login".into(),
+                exclude: true
+            },
+            PostProcessor::Sort {
+                by: "text".into(),
+                descending: false
+            },
+        ]),
+        vec!["Alpha", "Anchor", "Beta"]
+    );
+    assert_eq!(
+        links(vec![PostProcessor::Sort {
+            by: "href".into(),
+            descending: false
+        }]),
+        vec!["Alpha", "Beta", "Beta again", "Login", "Anchor"]
+    );
+    assert_err!(PostProcessor::Filter {
+        by: "text".into(),
+        pattern: "(".into(),
+        exclude: false
+    }
+    .validate());
+}
+
+#[test]
+fn selector_chains_select_structured_data() {
+    let html = r#"<html><body>
+        <article><h2><a href="/one">One</a></h2></article>
+        <article><h2><a href="/two">Two</a></h2></article>
+        <div class="card"><span class="t"> First </span><a href="/first">more</a>
+            <i class="tag">x</i><i class="tag">y</i></div>
+        <div class="card"><span class="t">Second</span></div>
+    </body></html>"#;
+    let results = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_chain("links", "article h2 >> a@href")
+        .add_chain("heading", "h2 a")
+        .add_chain(
+            "cards",
+            ".card >> {title: .t@text, link: a@href, tags: .tag >> @text}",
+        )
+        .results()
+        .expect("results successfully extracted");
+    let data = |name: &str| match results.data.get(name) {
+        Some(ResultKind::Property(v)) => v.clone(),
+        _ => panic!("expected the {} chain", name),
+    };
+
+    assert_eq!(data("links"), json!(["/one", "/two"]));
+    assert_eq!(data("heading"), json!("One"));
+    assert_eq!(
+        data("cards"),
+        json!([
+            {"title": "First", "link": "/first", "tags": ["x", "y"]},
+            {"title": "Second", "link": null, "tags": []}
+        ])
+    );
+    assert!(Chain::from_str(".card >> {title .t}").is_err());
+    assert!(Chain::from_str("article >> ").is_err());
+}
+
+#[test]
+fn hydration_payloads_are_selections() {
+    let html = r#"<html><body><div id="app"></div>
+        <script id="__NEXT_DATA__" type="application/json">{"props":{"pageProps":{"title":"Next"}}}</script>
+        <script>window.__INITIAL_STATE__ = {"user":{"name":"Ada","tags":["a","}"]}};</script>
+        <script>window.__APOLLO_STATE__ = JSON.parse("{\"count\":2}");</script>
+    </body></html>"#;
+    let doc = |hydration: bool| {
+        LoadedDocument::new("https://dev.null", html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .config(ScrapeConfig::default().hydration(hydration))
+            .add_expression("title", "next_data.props.pageProps.title")
+            .add_expression("name", "initial_state.user.name")
+            .results()
+            .expect("results successfully extracted")
+    };
+
+    assert!(doc(false).data.is_empty());
+    let results = doc(true);
+    assert_eq!(results.props.get("title"), Some(&json!("Next")));
+    assert_eq!(results.props.get("name"), Some(&json!("Ada")));
+    match results.data.get("apollo_state") {
+        Some(ResultKind::Property(state)) => assert_eq!(state, &json!({"count": 2})),
+        _ => panic!("expected the apollo state"),
+    }
+}
+
+#[test]
+fn page_metadata_is_parsed() {
+    let html = r#"<html><head>
+        <meta property="og:title" content="The Title">
+        <meta property="og:type" content="article">
+        <meta property="og:image" content="https://dev.null/a.png">
+        <meta property="og:image" content="https://dev.null/b.png">
+        <meta property="og:image:width" content="1200">
+        <meta name="twitter:card" content="summary_large_image">
+        <meta name="twitter:creator" content="@ada">
+        <meta name="description" content="not metadata">
+        <script type="application/ld+json">{"@context":"https://schema.org","@type":"Article","headline":"The Title"}</script>
+        <script type="application/ld+json">{"@graph":[{"@type":["Product","Thing"],"sku":"A1"},{"@type":"Offer"}]}</script>
+        <script type="application/ld+json">{ not json </script>
+    </head><body></body></html>"#;
+    let doc = |metadata: bool| {
+        LoadedDocument::new("https://dev.null", html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .config(ScrapeConfig::default().metadata(metadata))
+    };
+
+    let metadata: Metadata = doc(false).metadata();
+    assert_eq!(metadata.open_graph.title.as_deref(), Some("The Title"));
+    assert_eq!(metadata.open_graph.type_.as_deref(), Some("article"));
+    assert_eq!(
+        metadata.open_graph.image.as_deref(),
+        Some("https://dev.null/a.png")
+    );
+    assert_eq!(
+        metadata
+            .open_graph
+            .other
+            .get("image:width")
+            .map(|w| w.as_str()),
+        Some("1200")
+    );
+    assert_eq!(
+        metadata.twitter.card.as_deref(),
+        Some("summary_large_image")
+    );
+    assert_eq!(metadata.twitter.creator.as_deref(), Some("@ada"));
+    assert_eq!(metadata.json_ld.len(), 3);
+    assert_eq!(
+        metadata.json_ld_of_type("Product").next(),
+        Some(&json!({"@type": ["Product", "Thing"], "sku": "A1"}))
+    );
+
+    assert!(doc(false).results().unwrap().metadata.is_none());
+    let results = json!(doc(true).results().unwrap());
+    assert_eq!(results["metadata"]["open_graph"]["type"], json!("article"));
+    assert_eq!(
+        results["metadata"]["json_ld"][0]["headline"],
+        json!("The Title")
+    );
+}
+
+#[test]
+fn tables_are_extracted_into_rows_and_columns() {
+    let html = r#"<html><body>
+        <table id="plans">
+            <caption>Plans</caption>
+            <thead>
+                <tr><th rowspan="2">Plan</th><th colspan="2">Price</th></tr>
+                <tr><th>Monthly</th><th>Yearly</th></tr>
+            </thead>
+            <tbody>
+                <tr><td>Basic</td><td>$5</td><td rowspan="2">$50</td></tr>
+                <tr><td>Pro
+                    <table><tr><td>nested</td></tr></table></td><td>$9</td></tr>
+                <tr><td colspan="3">Contact us</td></tr>
+            </tbody>
+        </table>
+        <table id="bare"><tr><td>a</td><td>b</td></tr><tr><td>c</td></tr></table>
+    </body></html>"#;
+    let results = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_table_selector("plans", "#plans")
+        .add_table_selector("bare", "#bare")
+        .add_table_selector("missing", "#missing")
+        .results()
+        .expect("results successfully extracted");
+
+    let plans = match results.data.get("plans") {
+        Some(ResultKind::Table(table)) => table,
+        _ => panic!("expected a table"),
+    };
+    assert_eq!(plans.caption.as_deref(), Some("Plans"));
+    assert_eq!(plans.headers, vec!["Plan", "Price Monthly", "Price Yearly"]);
+    assert_eq!(
+        plans.rows,
+        vec![
+            vec!["Basic", "$5", "$50"],
+            vec!["Pro nested", "$9", "$50"],
+            vec!["Contact us", "Contact us", "Contact us"],
+        ]
+    );
+    assert_eq!(
+        plans.column("Price Monthly"),
+        Some(vec!["$5", "$9", "Contact us"])
+    );
+    assert_eq!(plans.records()[0]["Plan"], "Basic");
+
+    let bare: &Table = match results.data.get("bare") {
+        Some(ResultKind::Table(table)) => table,
+        _ => panic!("expected a table"),
+    };
+    assert!(bare.headers.is_empty());
+    assert_eq!(bare.rows, vec![vec!["a", "b"], vec!["c", ""]]);
+    assert!(!results.data.contains_key("missing"));
+    assert_eq!(
+        json!(bare),
+        json!({"headers": [], "rows": [["a", "b"], ["c", ""]]})
+    );
+}
+
+#[test]
+fn csv_rows_are_keyed_by_the_header() {
+    let csv = "name,version\nscraped,0.1.0\nscraper,0.12.0\n";
+    let results = LoadedDocument::new("https://dev.null/crates.csv", csv)
+        .expect("LoadedDoc created")
+        .content(Content::Csv)
+        .parse_document()
+        .expect("ParsedDoc created")
+        .results()
+        .expect("results successfully extracted");
+
+    assert_eq!(
+        json!(results.data.get("columns")),
+        json!(["name", "version"])
+    );
+    match results.data.get("rows") {
+        Some(ResultKind::List(rows)) => {
+            assert_eq!(rows.len(), 2);
+            assert_eq!(rows[1].other.get("name"), Some(&json!("scraper")));
+        }
+        _ => panic!("expected a list of rows"),
+    }
+}
+
+#[cfg(feature = "pdf")]
+#[test]
+fn pdf_text_and_metadata_are_extracted() {
+    use scraped::content::extract_pdf;
+
+    let bytes = fs::read("tests/fixtures/simple.pdf").expect("Problem reading fixture file");
+    let (text, content) = extract_pdf(&bytes).expect("PDF extracted");
+    assert!(text.contains("Hello from a PDF"));
+
+    let info = match content {
+        Content::Pdf(info) => info,
+        _ => panic!("expected PDF content"),
+    };
+    assert_eq!(info.pages, 1);
+    assert_eq!(info.title.as_deref(), Some("Simple PDF"));
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test(flavor = "multi_thread")]
+async fn otlp_layer_is_built() {
+    use scraped::telemetry::{otlp_layer, shutdown, DEFAULT_ENDPOINT};
+
+    assert_ok!(otlp_layer::<tracing_subscriber::Registry>(
+        DEFAULT_ENDPOINT,
+        "scraped-test"
+    ));
+    shutdown();
+}
+
+#[test]
+fn complex_selectors_fall_back_to_the_dom() {
+    let doc = LoadedDocument::new(
+        "https://dev.null",
+        "<h2>Heading</h2><p>first</p><p>second</p>",
+    )
+    .expect("LoadedDoc created");
+    let selectors = Selectors::new().add_selector("lead", "h2 + p");
+    assert_eq!(selectors.backend(), ParserBackend::Dom);
+
+    let results = doc.select(&selectors).expect("selectors evaluated");
+    match results.get("lead") {
+        Some(ResultKind::Item(lead)) => assert_eq!(lead.text.as_deref(), Some("first")),
+        _ => panic!("expected the paragraph after the heading"),
+    }
+}
+
+#[test]
+fn compiled_selectors_are_shared() {
+    let first = compile_selector("article > h2.shared-title").expect("selector compiled");
+    let second = compile_selector("article > h2.shared-title").expect("selector compiled");
+    assert!(Arc::ptr_eq(&first, &second));
+
+    let other = compile_selector("article > h3.shared-title").expect("selector compiled");
+    assert!(!Arc::ptr_eq(&first, &other));
+
+    match compile_selector("article >") {
+        Err(e) => assert!(matches!(
+            ScrapedError::from(e),
+            ScrapedError::InvalidSelector { .. }
+        )),
+        Ok(_) => panic!("expected an invalid selector"),
+    }
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn simple_selectors_are_streamed() {
+    let html = r#"<html><head><title>Streamed &amp; parsed</title></head><body>
+        <nav><a class="home" href="/">Home</a> <a href="https://dev.null/docs">Docs</a></nav>
+        <template><a href="/hidden">Hidden</a></template>
+        <div class="card"><h2>One <b>bold</b></h2></div>
+    </body></html>"#;
+    let doc = LoadedDocument::new("https://dev.null/start", html).expect("LoadedDoc created");
+    let selectors = |backend| {
+        Selectors::new()
+            .add_selector("title", "title")
+            .add_selector("heading", "div.card > h2")
+            .add_selector_all("links", "a[href]")
+            .add_selector("missing", "h6")
+            .parser(backend)
+    };
+    assert_eq!(
+        Selectors::new()
+            .add_selector_all("links", "a[href]")
+            .backend(),
+        ParserBackend::Streaming
+    );
+
+    let streamed = doc
+        .select(&selectors(ParserBackend::Streaming))
+        .expect("selectors streamed");
+    let parsed = doc
+        .select(&selectors(ParserBackend::Dom))
+        .expect("selectors parsed");
+
+    let summary = |results: &HashMap<String, ResultKind>| match (
+        results.get("title"),
+        results.get("heading"),
+        results.get("links"),
+    ) {
+        (
+            Some(ResultKind::Item(title)),
+            Some(ResultKind::Item(heading)),
+            Some(ResultKind::List(links)),
+        ) => json!({
+            "title": title.text,
+            "heading": heading.text,
+            "links": links
+                .iter()
+                .map(|l| json!([l.text, l.class, l.full_href]))
+                .collect::<Vec<_>>(),
+            "missing": results.contains_key("missing"),
+        }),
+        _ => panic!("expected every selector to match"),
+    };
+    assert_eq!(summary(&streamed), summary(&parsed));
+    assert_eq!(
+        summary(&streamed),
+        json!({
+            "title": "Streamed & parsed",
+            "heading": "One bold",
+            "links": [
+                ["Home", "home", "https://dev.null/"],
+                ["Docs", null, "https://dev.null/docs"],
+            ],
+            "missing": false,
+        })
+    );
 }
 
 // fn single_selector_without_match() {
 //     //
 // }
+
+#[test]
+fn unresolvable_hrefs_have_no_full_href() {
+    let html = r#"<html><body>
+        <a href="//[::1">broken</a>
+        <a href="/docs">docs</a>
+    </body></html>"#;
+    let doc = LoadedDocument::new("https://dev.null/start", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector_all("links", "a")
+        .child_selectors(vec!["links"], ChildScope::All());
+    let links = doc
+        .get("links")
+        .expect("links selected")
+        .expect("links found");
+    let full_hrefs: Vec<Option<&str>> = links
+        .selections()
+        .iter()
+        .map(|s| s.full_href.as_deref())
+        .collect();
+    assert_eq!(full_hrefs, vec![None, Some("https://dev.null/docs")]);
+    assert_eq!(doc.get_child_urls(), Vec::<Url>::new());
+}
+
+#[cfg(feature = "testing")]
+mod properties {
+    use proptest::prelude::*;
+    use scraped::{testing, ChildScope, LoadedDocument};
+
+    proptest! {
+        #[test]
+        fn hrefs_are_resolved_and_classified_without_panicking(
+            base in testing::base_url(),
+            hrefs in prop::collection::vec(testing::href(), 1..8),
+        ) {
+            let html = testing::page_with_links(&hrefs);
+            let scopes = [
+                ChildScope::All(),
+                ChildScope::Relative(),
+                ChildScope::Absolute(),
+                ChildScope::Http(),
+                ChildScope::File(),
+            ];
+            for scope in scopes {
+                let doc = LoadedDocument::new(base.as_str(), &html)
+                    .expect("LoadedDoc created")
+                    .parse_document()
+                    .expect("ParsedDoc created")
+                    .add_selector_all("links", "a.link")
+                    .child_selectors(vec!["links"], scope.clone());
+
+                let links = doc.get("links").expect("links selected").expect("links found");
+                prop_assert_eq!(links.selections().len(), hrefs.len());
+                for selection in links.selections() {
+                    let href = selection.href.as_deref().expect("every link has an href");
+                    let resolvable = href.starts_with("http") || base.join(href).is_ok();
+                    prop_assert_eq!(selection.full_href.is_some(), resolvable);
+                }
+
+                for child in doc.get_child_urls() {
+                    match scope {
+                        ChildScope::Http() => prop_assert!(child.as_str().starts_with("http")),
+                        ChildScope::File() => prop_assert!(child.as_str().starts_with("file")),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn results_are_written_as_json_lines() {
+    let html = r#"<html><body><h1>Hello</h1></body></html>"#;
+    let page = LoadedDocument::new("https://dev.null/start", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("title", "h1")
+        .results()
+        .expect("results");
+    let mut child = page.clone();
+    child.url = Url::parse("https://dev.null/child").unwrap();
+    let mut results = page.clone();
+    results.children.push(child);
+
+    let mut writer = JsonLinesWriter::new(Vec::new());
+    writer.write_flat(&results).await.expect("pages written");
+    writer
+        .write(&json!({"done": true}))
+        .await
+        .expect("record written");
+    assert_eq!(writer.lines(), 3);
+
+    let written = String::from_utf8(writer.finish().await.expect("flushed")).unwrap();
+    let lines: Vec<Value> = written
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("a line of JSON"))
+        .collect();
+    assert!(written.ends_with('\n'));
+    assert_eq!(lines[0]["url"], "https://dev.null/start");
+    assert_eq!(lines[1]["url"], "https://dev.null/child");
+    assert_eq!(lines[1]["data"]["title"]["text"], "Hello");
+    assert_eq!(lines[2], json!({"done": true}));
+}
+
+#[tokio::test]
+async fn personal_data_is_scrubbed_from_output() {
+    let record = json!({
+        "url": "https://dev.null/contact",
+        "data": {
+            "contact": {"text": "Mail Jane@Dev.null or call +1 555 123 4567 from 192.168.1.10."},
+            "emails": ["jane@dev.null", "bob@dev.null"],
+        },
+        "props": {"path": "Foo::bar at fe80::1", "total": 12},
+    });
+
+    let masked = Scrubber::all().scrub(record.clone());
+    assert_eq!(
+        masked["data"]["contact"]["text"],
+        "Mail [email] or call [phone] from [ip]."
+    );
+    assert_eq!(masked["data"]["emails"], json!(["[email]", "[email]"]));
+    assert_eq!(masked["props"]["path"], "Foo::bar at [ip]");
+    assert_eq!(masked["props"]["total"], 12);
+    assert_eq!(masked["url"], "https://dev.null/contact");
+    // nothing is scrubbed unless it's asked for
+    assert_eq!(Scrubber::default().scrub(record.clone()), record);
+    assert_eq!(
+        Scrubber::default()
+            .ips(true)
+            .scrub_text("jane@dev.null at 10.0.0.1"),
+        "jane@dev.null at [ip]"
+    );
+
+    // the same value has the same hash -- whatever its case -- unless the salt differs
+    let hashed = Scrubber::all().hashed(true).salt("pepper");
+    let a = hashed.scrub_text("jane@dev.null");
+    assert!(a.starts_with("[email:") && a.len() == "[email:]".len() + 16);
+    assert_eq!(hashed.scrub_text("Jane@Dev.null"), a);
+    assert_ne!(hashed.scrub_text("bob@dev.null"), a);
+    assert_ne!(Scrubber::all().hashed(true).scrub_text("jane@dev.null"), a);
+    assert!(!format!("{:?}", hashed).contains("pepper"));
+
+    let mut writer = JsonLinesWriter::new(Vec::new()).scrub(Scrubber::all());
+    writer.write(&record).await.expect("record written");
+    let written = String::from_utf8(writer.finish().await.expect("flushed")).unwrap();
+    assert!(!written.contains("jane@dev.null"));
+    assert!(written.contains("[phone]"));
+}
+
+/// a preset of the selectors, properties and config a page is scraped with
+type Preset = fn(LoadedDocument) -> ParsedDoc;
+
+/// the pages of the golden corpus: the fixture, the URL it's scraped as and
+/// the preset run against it
+fn corpus() -> Vec<(&'static str, &'static str, Preset)> {
+    vec![
+        (
+            "docs-rs.html",
+            "https://docs.rs/semver/latest/semver/",
+            |doc| doc.for_docs_rs(),
+        ),
+        (
+            "wikipedia.html",
+            "https://en.wikipedia.org/wiki/Ferris_(mascot)",
+            |doc| {
+                ParsedDoc::from(doc)
+                    .config(ScrapeConfig::default().metadata(true))
+                    .add_selector("title", "h1#firstHeading")
+                    .add_selector_all("sections", "h2 .mw-headline")
+                    .add_table_selector("infobox", "table.infobox")
+                    .add_selector_all("links", "#mw-content-text p a[href^='/wiki/']")
+                    .add_selector_all("references", "ol.references cite a.external")
+                    .add_selector_all("categories", "#mw-normal-catlinks li a")
+                    .add_expression("first_reference", "references[0].full_href")
+            },
+        ),
+        (
+            "product.html",
+            "https://shop.dev.null/products/ferris-plush?variant=30",
+            |doc| {
+                ParsedDoc::from(doc)
+                    .config(ScrapeConfig::default().metadata(true))
+                    .add_selector("name", "h1.product__title")
+                    .add_selector("price", ".price__sale")
+                    .add_selector("compare_at", ".price__compare")
+                    .add_selector_all("images", "img.product__image")
+                    .add_selector_all("buttons", "form.product-form button")
+                    .add_table_selector("specs", "table.product__specs")
+                    .add_chain(
+                        "reviews",
+                        "article.review >> {title: .review__title@text, stars: .review__stars@data-rating}",
+                    )
+                    .add_extractor(Extractor::Emails)
+                    .add_extractor(Extractor::Phones)
+                    .add_extractor(Extractor::Social)
+                    .add_extractor(Extractor::Url)
+            },
+        ),
+        ("feed.rss", "https://this-week-in-rust.org/rss.xml", |doc| {
+            ParsedDoc::from(doc)
+                .add_selector("channel", "channel > title")
+                .add_selector_all("titles", "item > title")
+                .add_selector_all("links", "item > link")
+                .add_selector_all("guids", "item > guid")
+                .add_selector_all("published", "item > pubdate")
+        }),
+        (
+            "malformed.html",
+            "http://intranet.dev.null/directory.asp",
+            |doc| {
+                ParsedDoc::from(doc)
+                    .add_selector("title", "title")
+                    .add_table_selector("directory", "table.directory")
+                    .add_selector_all("headings", "h2")
+                    .add_selector_all("links", "ul a")
+                    .add_selector("note", "p.note")
+                    .child_selectors(vec!["links"], ChildScope::All())
+            },
+        ),
+    ]
+}
+
+/// Removes the `html` and `class` of each selection: the parser keeps an
+/// element's attributes and classes in hash maps so the order they're
+/// serialized in varies from one run to the next.
+fn unordered_attributes(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.remove("html");
+            map.remove("class");
+            map.values_mut().for_each(unordered_attributes);
+        }
+        Value::Array(list) => list.iter_mut().for_each(unordered_attributes),
+        _ => {}
+    }
+}
+
+/// Runs each preset against its page of the corpus and compares the results
+/// with the expected output committed alongside it; run with `SCRAPED_BLESS=1`
+/// to write the current results as the expected output once a change to the
+/// extraction is intentional.
+#[test]
+fn corpus_pages_match_their_golden_outputs() {
+    let bless = std::env::var_os("SCRAPED_BLESS").is_some();
+    let mut changed = vec![];
+    for (fixture, url, preset) in corpus() {
+        let html = fs::read_to_string(format!("tests/fixtures/corpus/{}", fixture))
+            .expect("Problem reading fixture file");
+        let doc = preset(LoadedDocument::new(url, &html).expect("LoadedDoc created"));
+        // the child URLs are in the order of the selectors, which isn't stable
+        let mut children: Vec<String> =
+            doc.get_child_urls().into_iter().map(String::from).collect();
+        children.sort();
+        let mut results = json!({
+            "results": doc.results().expect("results"),
+            "children": children,
+        });
+        unordered_attributes(&mut results);
+
+        let stem = fixture.rsplit_once('.').map_or(fixture, |(stem, _)| stem);
+        let expected = format!("tests/fixtures/corpus/expected/{}.json", stem);
+        if bless {
+            let json = serde_json::to_string_pretty(&results).unwrap();
+            fs::write(&expected, json + "\n").expect("Problem writing expected output");
+            continue;
+        }
+        let golden: Value = serde_json::from_str(
+            &fs::read_to_string(&expected).expect("Problem reading expected output"),
+        )
+        .expect("expected output is JSON");
+        if golden != results {
+            changed.push(fixture);
+        }
+    }
+
+    assert!(
+        changed.is_empty(),
+        "the results of {:?} no longer match their expected output; run with SCRAPED_BLESS=1 to accept the changes",
+        changed
+    );
+}
+
+#[test]
+fn duplicate_selector_names_follow_the_policy() {
+    let html = r#"<html><body><h1>Heading</h1><h2>Subheading</h2></body></html>"#;
+    let title = |policy: DuplicatePolicy| {
+        let doc = LoadedDocument::new("https://dev.null", html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .config(ScrapeConfig::default().duplicate_selectors(policy))
+            .add_selector("title", "h1")
+            .add_selector_all("title", "h2");
+        let title = doc
+            .get("title")
+            .expect("title selected")
+            .expect("title found");
+
+        title.text().map(String::from)
+    };
+
+    assert_eq!(
+        title(DuplicatePolicy::default()).as_deref(),
+        Some("Subheading")
+    );
+    assert_eq!(
+        title(DuplicatePolicy::Replace).as_deref(),
+        Some("Subheading")
+    );
+    assert_eq!(
+        title(DuplicatePolicy::KeepFirst).as_deref(),
+        Some("Heading")
+    );
+    // the infallible builders panic rather than dropping either selector
+    assert!(std::panic::catch_unwind(|| title(DuplicatePolicy::Error)).is_err());
+    // while a preset's selector which is taken again is an error to `try_add_*`
+    let taken = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .config(ScrapeConfig::default().duplicate_selectors(DuplicatePolicy::Error))
+        .add_generic_selectors()
+        .try_add_selector("title", "h1");
+    let e = taken.err().expect("the name is taken");
+    assert!(matches!(
+        e,
+        ScrapedError::DuplicateSelector { name, .. } if name == "title"
+    ));
+
+    // a chain shares the names of the selectors
+    let doc = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .config(ScrapeConfig::default().duplicate_selectors(DuplicatePolicy::KeepFirst))
+        .add_selector("title", "h1")
+        .add_chain("title", "h2@text");
+    assert_eq!(
+        doc.results().expect("results").data["title"].text(),
+        Some("Heading")
+    );
+}
+
+#[test]
+fn results_are_flattened_depth_first() {
+    let page = |path: &str| {
+        LoadedDocument::new(&format!("https://dev.null{}", path), "<h1>Hello</h1>")
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector("title", "h1")
+            .results()
+            .expect("results")
+    };
+    let mut root = page("/");
+    let mut docs = page("/docs");
+    docs.children.push(page("/docs/intro"));
+    docs.frames.push(page("/docs/frame"));
+    root.children.push(docs);
+    root.children.push(page("/about"));
+
+    let urls: Vec<String> = root.flatten().iter().map(|p| p.url.to_string()).collect();
+    assert_eq!(
+        urls,
+        vec![
+            "https://dev.null/",
+            "https://dev.null/docs",
+            "https://dev.null/docs/frame",
+            "https://dev.null/docs/intro",
+            "https://dev.null/about",
+        ]
+    );
+    assert_eq!(root.flatten()[3].data["title"].text(), Some("Hello"));
+    assert_eq!(FlatResult::flatten(&root).len(), 5);
+}
+
+#[test]
+fn selections_record_the_source_of_their_selector() {
+    let html = r#"<html><head><title>Page</title></head><body><h1>Heading</h1></body></html>"#;
+    let doc = LoadedDocument::new("https://dev.null", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_generic_selectors()
+        .selector_source(SelectorSource::config("scraped.yaml"))
+        .add_selector("title", "h1")
+        .selector_source(SelectorSource::Programmatic)
+        .add_selector("heading", "h1");
+
+    assert_eq!(
+        doc.source_of("links"),
+        Some(&SelectorSource::preset("generic"))
+    );
+    assert_eq!(
+        doc.source_of("title"),
+        Some(&SelectorSource::config("scraped.yaml"))
+    );
+    assert_eq!(
+        doc.source_of("heading"),
+        Some(&SelectorSource::Programmatic)
+    );
+
+    let docs_rs = LoadedDocument::new("https://docs.rs/scraped", html)
+        .expect("LoadedDoc created")
+        .for_docs_rs()
+        .add_selector("heading", "h1");
+    assert_eq!(
+        docs_rs.source_of("h1"),
+        Some(&SelectorSource::preset("docs_rs"))
+    );
+    assert_eq!(
+        docs_rs.source_of("heading"),
+        Some(&SelectorSource::default())
+    );
+    // the sources are only included in the results when asked for
+    assert!(doc.results().expect("results").sources.is_empty());
+
+    let results = doc
+        .config(ScrapeConfig::default().provenance(true))
+        .results()
+        .expect("results");
+    let json = serde_json::to_value(&results).expect("serialized");
+    assert_eq!(json["sources"]["title"], json!("config:scraped.yaml"));
+    assert_eq!(json["sources"]["images"], json!("preset:generic"));
+    assert_eq!(json["sources"]["heading"], json!("programmatic"));
+    assert_eq!(results.flatten()[0].sources.len(), results.sources.len());
+}
+
+#[tokio::test]
+async fn dry_runs_explain_which_child_urls_would_be_followed() {
+    let html = r#"<html><body>
+        <a href="https://dev.null/docs/intro">intro</a>
+        <a href="https://dev.null/docs/intro#usage">usage</a>
+        <a href="https://dev.null/login">login</a>
+        <a href="https://elsewhere.org/docs">elsewhere</a>
+        <a href="mailto:hello@dev.null">mail</a>
+    </body></html>"#;
+    let doc = LoadedDocument::new("https://dev.null/docs/", html)
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .config(ScrapeConfig::default().safety(CrawlSafety::default().allow_domains(["dev.null"])))
+        .add_selector_all("links", "a")
+        .child_selectors_with_rules(
+            vec!["links"],
+            ChildScope::Http(),
+            ChildRules::new().deny("/login$"),
+        );
+
+    let verdicts: Vec<(String, Verdict)> = doc
+        .dry_run()
+        .await
+        .into_iter()
+        .map(|c| (c.href, c.verdict))
+        .collect();
+    assert_eq!(
+        verdicts,
+        vec![
+            ("https://dev.null/docs/intro".to_string(), Verdict::Included),
+            (
+                "https://dev.null/docs/intro#usage".to_string(),
+                Verdict::Duplicate
+            ),
+            ("https://dev.null/login".to_string(), Verdict::Rules),
+            ("https://elsewhere.org/docs".to_string(), Verdict::Unsafe),
+            ("mailto:hello@dev.null".to_string(), Verdict::OutOfScope),
+        ]
+    );
+    assert_eq!(
+        doc.get_child_urls(),
+        vec![Url::parse("https://dev.null/docs/intro").unwrap()]
+    );
+
+    let plan = doc.plan_children();
+    assert_eq!(plan[0].to_string(), "+ https://dev.null/docs/intro [links]");
+    assert_eq!(
+        plan[2].to_string(),
+        "- https://dev.null/login [links]: excluded by the child rules"
+    );
+}
+
+#[test]
+fn child_urls_are_sampled_deterministically() {
+    let mut paths: Vec<String> = (0..5).map(|i| format!("/blog/2021/{}", i)).collect();
+    paths.extend((0..3).map(|i| format!("/blog/2022/{}", i)));
+    paths.push("/about".to_string());
+    let children = |paths: &[String], sample: Sampling| -> Vec<String> {
+        let links: String = paths
+            .iter()
+            .map(|p| format!(r#"<a href="https://dev.null{}">link</a>"#, p))
+            .collect();
+        let mut urls: Vec<String> = LoadedDocument::new("https://dev.null/", &links)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .config(ScrapeConfig::default().sample(sample))
+            .add_selector_all("links", "a")
+            .child_selectors(vec!["links"], ChildScope::Http())
+            .get_child_urls()
+            .into_iter()
+            .map(|url| url.path().to_string())
+            .collect();
+        urls.sort();
+        urls
+    };
+
+    assert_eq!(children(&paths, Sampling::new()).len(), 9);
+    assert_eq!(children(&paths, Sampling::new().fraction(1.0)).len(), 9);
+    assert!(children(&paths, Sampling::new().fraction(0.0)).is_empty());
+
+    // the same pages are sampled whatever the order of the links
+    let sampled = children(&paths, Sampling::new().per_directory(2));
+    let reversed: Vec<String> = paths.iter().rev().cloned().collect();
+    assert_eq!(
+        sampled,
+        children(&reversed, Sampling::new().per_directory(2))
+    );
+    let count = |prefix: &str| sampled.iter().filter(|p| p.starts_with(prefix)).count();
+    assert_eq!((count("/blog/2021/"), count("/blog/2022/")), (2, 2));
+    assert!(sampled.contains(&"/about".to_string()));
+
+    let half = children(&paths, Sampling::new().fraction(0.5));
+    assert_eq!(half, children(&reversed, Sampling::new().fraction(0.5)));
+    assert!(half.iter().all(|p| paths.contains(p)));
+}
+
+#[test]
+fn results_are_diffed_page_by_page() {
+    let page = |path: &str, html: &str| {
+        LoadedDocument::new(&format!("https://dev.null{}", path), html)
+            .expect("LoadedDoc created")
+            .parse_document()
+            .expect("ParsedDoc created")
+            .add_selector("title", "h1")
+            .add_selector("price", ".price")
+            .add_selector("stock", ".stock")
+            .add_expression("name", "title.text")
+            .results()
+            .expect("results")
+    };
+    let mut old = page(
+        "/",
+        r#"<h1>Widget</h1><p class="price">$10</p><p class="stock">in stock</p>"#,
+    );
+    old.children.push(page("/gone", "<h1>Gone</h1>"));
+    let mut new = page("/", r#"<h1>Widget Pro</h1><p class="price">$10</p>"#);
+    new.children.push(page("/new", "<h1>New</h1>"));
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added_pages, vec!["https://dev.null/new"]);
+    assert_eq!(diff.removed_pages, vec!["https://dev.null/gone"]);
+    let root = &diff.pages["https://dev.null/"];
+    assert!(root.selections.added.is_empty());
+    assert!(root.selections.removed.contains_key("stock"));
+    assert_eq!(
+        root.selections.changed.keys().collect::<Vec<_>>(),
+        vec!["title"]
+    );
+    assert_eq!(root.properties.changed["name"].old, json!("Widget"));
+    assert_eq!(root.properties.changed["name"].new, json!("Widget Pro"));
+
+    // flattened results compare the same as the tree they were flattened from
+    let flat = serde_json::to_value(old.flatten()).expect("serialized");
+    assert_eq!(ResultsDiff::between(&flat, &json!(new)), diff);
+    assert!(new.diff(&new).is_empty());
+}
+
+#[test]
+fn crawls_are_estimated_per_host() {
+    let urls: Vec<Url> = (0..10)
+        .map(|i| Url::parse(&format!("https://slow.dev.null/{}", i)).unwrap())
+        .chain((0..4).map(|i| Url::parse(&format!("https://fast.dev.null/{}", i)).unwrap()))
+        .collect();
+    let latency = Duration::from_millis(100);
+
+    // a host limited to 2 requests a second waits half a second for each
+    // request after the first
+    let config = ScrapeConfig::default()
+        .concurrency(2)
+        .rate_limit(RateLimit::per_second(2.0));
+    let estimate = Estimate::new(&urls, &config, latency);
+    assert_eq!(estimate.requests, 14);
+    assert_eq!(estimate.hosts["slow.dev.null"].requests, 10);
+    assert_eq!(estimate.hosts["slow.dev.null"].per_second, Some(2.0));
+    assert_eq!(
+        estimate.hosts["slow.dev.null"].duration,
+        Duration::from_millis(4600)
+    );
+    assert_eq!(
+        estimate.hosts["fast.dev.null"].duration,
+        Duration::from_millis(1600)
+    );
+    assert_eq!(estimate.duration, Duration::from_millis(4600));
+
+    // without a rate limit the concurrency bounds the crawl
+    let estimate = Estimate::new(&urls, &ScrapeConfig::default().concurrency(2), latency);
+    assert_eq!(estimate.hosts["slow.dev.null"].per_second, None);
+    assert_eq!(estimate.duration, Duration::from_millis(700));
+}
+
+#[tokio::test]
+async fn watches_report_only_changes_and_persist_their_snapshot() {
+    let page = |price: &str| {
+        LoadedDocument::new(
+            "https://dev.null",
+            &format!(r#"<p class="price">{}</p>"#, price),
+        )
+        .expect("LoadedDoc created")
+        .parse_document()
+        .expect("ParsedDoc created")
+        .add_selector("price", ".price")
+        .results()
+        .expect("results")
+    };
+    let state = std::env::temp_dir().join(format!("scraped-watch-{}.json", std::process::id()));
+    let _ = fs::remove_file(&state);
+    let clock = Arc::new(MockClock::default());
+    let config = ScrapeConfig::default().clock(clock.clone());
+
+    let mut watcher = Watcher::new(Duration::from_secs(300))
+        .config(config.clone())
+        .state(&state);
+    assert!(watcher
+        .check(&page("$10"))
+        .await
+        .expect("checked")
+        .is_none());
+    let diff = watcher.check(&page("$12")).await.expect("checked");
+    assert!(diff.expect("a diff").pages["https://dev.null/"]
+        .selections
+        .changed
+        .contains_key("price"));
+
+    // a restarted watch compares with the persisted snapshot
+    let mut restarted = Watcher::new(Duration::from_secs(300))
+        .config(config)
+        .state(&state)
+        .checks(3);
+    let prices = Mutex::new(vec!["$12", "$12", "$15"].into_iter());
+    let prices = &prices;
+    let mut changes = vec![];
+    restarted
+        .watch(
+            move || async move { Ok(page(prices.lock().unwrap().next().unwrap())) },
+            |diff| changes.push(diff.clone()),
+        )
+        .await
+        .expect("watched");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[0].pages["https://dev.null/"].selections.changed["price"].new,
+        json!(page("$15").data["price"])
+    );
+    assert_eq!(clock.sleeps(), vec![Duration::from_secs(300); 2]);
+    let _ = fs::remove_file(&state);
+}
+
+#[tokio::test]
+async fn selector_configs_are_compared_on_cached_pages() {
+    let dir = std::env::temp_dir().join(format!("scraped-compare-{}", std::process::id()));
+    let cache = FsCache::new(&dir);
+    let pages = [
+        (
+            "https://dev.null/a",
+            r#"<h1>A</h1><div class="price">$1</div>"#,
+        ),
+        (
+            "https://dev.null/b",
+            r#"<h1>B</h1><span class="cost">$2</span>"#,
+        ),
+    ];
+    for (url, html) in pages {
+        let page = CachedPage {
+            url: url.to_string(),
+            status: 200,
+            headers: [("content-type".to_string(), "text/html".to_string())].into(),
+            stored_at: None,
+            body: Bytes::from(html),
+        };
+        cache.put(&Url::parse(url).unwrap(), &page).await.unwrap();
+    }
+    let docs: Vec<LoadedDocument> = cache
+        .pages()
+        .await
+        .expect("cached pages")
+        .iter()
+        .map(|p| p.to_document().expect("document"))
+        .collect();
+    assert_eq!(docs.len(), 2);
+
+    // the redesigned site moved the price, which the new selector finds on both pages
+    let comparison = Comparison::new(
+        &docs,
+        |doc| {
+            Ok(doc
+                .add_selector("h1", "h1")
+                .add_selector("price", "div.price"))
+        },
+        |doc| {
+            Ok(doc
+                .add_selector("h1", "h1")
+                .add_selector("price", ".price, .cost"))
+        },
+    )
+    .expect("compared");
+    assert_eq!(comparison.pages, 2);
+    let price = &comparison.selectors["price"];
+    assert_eq!(price.old.as_ref().unwrap().match_rate, 0.5);
+    assert_eq!(price.new.as_ref().unwrap().match_rate, 1.0);
+    assert_eq!(price.pages_differing, 1);
+    assert_eq!(comparison.selectors["h1"].pages_differing, 0);
+    assert_eq!(
+        comparison.diff.pages.keys().collect::<Vec<_>>(),
+        vec!["https://dev.null/b"]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// an HTTP endpoint on localhost which records each request it's sent and
+/// answers the first with a `503 Service Unavailable`
+async fn stub_ingest() -> (Url, Arc<Mutex<Vec<String>>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(vec![]));
+    let recorded = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = vec![];
+            let mut buf = [0; 4096];
+            // reads the headers and then as much of the body as they announce
+            loop {
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_lowercase();
+                let complete = text.split_once("\r\n\r\n").is_some_and(|(head, body)| {
+                    let length = head
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:"))
+                        .and_then(|l| l.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    body.len() >= length
+                });
+                if n == 0 || complete {
+                    break;
+                }
+            }
+            let status = {
+                let mut requests = recorded.lock().unwrap();
+                requests.push(String::from_utf8_lossy(&request).to_string());
+                match requests.len() {
+                    1 => "503 Service Unavailable",
+                    _ => "202 Accepted",
+                }
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    (
+        Url::parse(&format!("http://{}/ingest", addr)).unwrap(),
+        requests,
+    )
+}
+
+#[tokio::test]
+async fn scraped_pages_are_posted_to_a_webhook() {
+    let (endpoint, requests) = stub_ingest().await;
+    let clock = Arc::new(MockClock::default());
+    let sink = WebhookSink::new(endpoint)
+        .auth("Bearer s3cret")
+        .config(ScrapeConfig::default().clock(clock.clone()));
+    let site = SiteFetcher(vec![
+        ("https://dev.null/a", "<html><h1>A</h1></html>"),
+        ("https://dev.null/b", "<html><h1>B</h1></html>"),
+    ]);
+
+    let mut scrape = ConcurrentScrape::default()
+        .fetcher(site)
+        .add_urls(&[
+            "https://dev.null/a",
+            "https://dev.null/b",
+            "https://dev.null/missing",
+        ])
+        .unwrap()
+        .add_sink(sink);
+    scrape.execute().await.unwrap();
+
+    // the page which was turned away is sent again while the missing page isn't sent at all
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(|r| r.starts_with("POST /ingest ")));
+    assert!(requests
+        .iter()
+        .all(|r| r.to_lowercase().contains("authorization: bearer s3cret")));
+    let mut accepted: Vec<String> = requests[1..]
+        .iter()
+        .map(|r| {
+            let body: Value = serde_json::from_str(r.split_once("\r\n\r\n").unwrap().1).unwrap();
+            body["url"].as_str().unwrap().to_string()
+        })
+        .collect();
+    accepted.sort();
+    assert_eq!(accepted, vec!["https://dev.null/a", "https://dev.null/b"]);
+    assert_eq!(clock.sleeps(), vec![Duration::from_millis(500)]);
+}
+
+#[test]
+fn transformed_values_are_written_as_the_format_policy_says() {
+    let html = r#"<time>3. Januar 2024</time><p class="price">1.299,50 €</p>
+        <li>2,5</li><li>10</li>"#;
+    let results = |format: FormatPolicy| {
+        let mut doc = LoadedDocument::new("https://dev.null", html).expect("LoadedDoc created");
+        doc.config = ScrapeConfig::default().format(format);
+        doc.parse_document()
+            .expect("ParsedDoc created")
+            .add_selector("published", "time")
+            .add_selector("price", ".price")
+            .add_selector_all("ratings", "li")
+            .add_transform(
+                "date",
+                "published",
+                Transform::Date(DateHints::locale("de")),
+            )
+            .add_transform("cost", "price", Transform::Price(PriceHints::default()))
+            .add_transform(
+                "scores",
+                "ratings",
+                Transform::Number {
+                    decimal_comma: Some(true),
+                },
+            )
+            .results()
+            .expect("results")
+            .props
+    };
+
+    let iso = results(FormatPolicy::default());
+    assert_eq!(iso["date"], json!("2024-01-03T00:00:00Z"));
+    assert_eq!(iso["cost"], json!({ "amount": 1299.5, "currency": "EUR" }));
+    assert_eq!(iso["scores"], json!([2.5, 10.0]));
+
+    let us = results(FormatPolicy::default().dates(DateFormat::Locale("en-US".to_string())));
+    assert_eq!(us["date"], json!("01/03/2024"));
+    let unix = results(FormatPolicy::default().dates(DateFormat::Unix));
+    assert_eq!(unix["date"], json!(1704240000));
+
+    let comma = results(
+        FormatPolicy::default()
+            .dates(DateFormat::Date)
+            .decimal_separator(','),
+    );
+    assert_eq!(comma["date"], json!("2024-01-03"));
+    assert_eq!(
+        comma["cost"],
+        json!({ "amount": "1299,5", "currency": "EUR" })
+    );
+    assert_eq!(comma["scores"], json!(["2,5", "10"]));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn page_outcomes_are_stored_in_sqlite() {
+    use scraped::sqlite::SqliteSink;
+
+    let path = std::env::temp_dir().join(format!("scraped-{}.sqlite", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+    let sink = SqliteSink::open(&path)
+        .expect("database opened")
+        .clock(Arc::new(MockClock::new(now)));
+    let site = SiteFetcher(vec![(
+        "https://dev.null/a",
+        "<html><title>A</title><h1>A</h1></html>",
+    )]);
+    let mut scrape = ConcurrentScrape::default()
+        .fetcher(site)
+        .add_urls(&["https://dev.null/a", "https://dev.null/missing"])
+        .unwrap()
+        .add_sink(sink.clone());
+    scrape.execute().await.unwrap();
+
+    let a = Url::parse("https://dev.null/a").unwrap();
+    assert_eq!(sink.fetched_at(&a).unwrap(), Some(now));
+    assert_eq!(
+        sink.fetched_at(&Url::parse("https://dev.null/never").unwrap())
+            .unwrap(),
+        None
+    );
+
+    // the schema is only created once and the rows can be queried with SQL
+    drop(sink);
+    assert_ok!(SqliteSink::open(&path));
+    let db = rusqlite::Connection::open(&path).unwrap();
+    let rows: Vec<(String, String, Option<u16>)> = db
+        .prepare("SELECT url, outcome, status FROM pages ORDER BY url")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            ("https://dev.null/a".to_string(), "ok".to_string(), None),
+            (
+                "https://dev.null/missing".to_string(),
+                "failed".to_string(),
+                Some(404)
+            ),
+        ]
+    );
+    let selections: String = db
+        .query_row(
+            "SELECT selections FROM pages WHERE outcome = 'ok'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let selections: Value = serde_json::from_str(&selections).unwrap();
+    assert_eq!(selections["title"]["text"], json!("A"));
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn the_frontier_is_kept_in_sqlite() {
+    use scraped::sqlite::SqliteFrontier;
+
+    let path = std::env::temp_dir().join(format!("scraped-{}.frontier.sqlite", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let url = |u: &str| Url::parse(u).unwrap();
+    let frontier = SqliteFrontier::open(&path).expect("database opened");
+    assert!(frontier.enqueue(&url("https://dev.null/a")).await.unwrap());
+    assert!(frontier
+        .enqueue(&url("https://dev.null/b?utm_source=x"))
+        .await
+        .unwrap());
+    assert!(!frontier.enqueue(&url("https://dev.null/b")).await.unwrap());
+    assert_eq!(
+        frontier.dequeue().await.unwrap(),
+        Some(url("https://dev.null/a"))
+    );
+    frontier
+        .mark_seen(&url("https://dev.null/a"))
+        .await
+        .unwrap();
+    assert_eq!(
+        frontier.dequeue().await.unwrap(),
+        Some(url("https://dev.null/b?utm_source=x"))
+    );
+    assert_eq!(frontier.dequeue().await.unwrap(), None);
+    drop(frontier);
+
+    // the page being requested is queued again while the one seen isn't
+    let frontier = SqliteFrontier::open(&path).expect("database reopened");
+    assert_eq!(frontier.queued().await.unwrap(), 1);
+    assert!(!frontier.enqueue(&url("https://dev.null/a")).await.unwrap());
+    assert_eq!(
+        frontier.dequeue().await.unwrap(),
+        Some(url("https://dev.null/b?utm_source=x"))
+    );
+    assert!(frontier
+        .enqueue_from(&url("https://dev.null/c"), Discovery::Sitemap)
+        .await
+        .unwrap());
+    assert_eq!(
+        frontier
+            .discovery(&url("https://dev.null/c#top"))
+            .await
+            .unwrap(),
+        Some(Discovery::Sitemap)
+    );
+    assert_eq!(
+        frontier
+            .discovery(&url("https://dev.null/a"))
+            .await
+            .unwrap(),
+        Some(Discovery::Seed)
+    );
+    // the frontier and the pages share a database
+    assert_ok!(scraped::sqlite::SqliteSink::open(&path));
+    drop(frontier);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn failed_migrations_are_rolled_back() {
+    use scraped::sqlite::SqliteSink;
+
+    let path = std::env::temp_dir().join(format!("scraped-{}.migrate.sqlite", std::process::id()));
+    let _ = fs::remove_file(&path);
+    // a table in the way of the frontier's migration
+    let db = rusqlite::Connection::open(&path).unwrap();
+    db.execute_batch("CREATE TABLE frontier (url TEXT)")
+        .unwrap();
+    drop(db);
+
+    assert_err!(SqliteSink::open(&path));
+    let db = rusqlite::Connection::open(&path).unwrap();
+    let version: usize = db
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, 1);
+    let columns: usize = db
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('frontier')",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(columns, 1);
+    drop(db);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn results_are_encrypted_at_rest() {
+    use scraped::encryption::EncryptionKey;
+
+    let key = EncryptionKey::generate();
+    assert_eq!(
+        EncryptionKey::from_hex(&key.to_hex()).unwrap().to_hex(),
+        key.to_hex()
+    );
+    assert_err!(EncryptionKey::from_hex("not a key"));
+    assert!(!format!("{:?}", key).contains(&key.to_hex()));
+
+    let mut writer = JsonLinesWriter::new(Vec::new()).encrypt(key.clone());
+    writer
+        .write(&json!({"email": "jane@dev.null"}))
+        .await
+        .expect("record written");
+    writer
+        .write(&json!({"done": true}))
+        .await
+        .expect("record written");
+    let written = String::from_utf8(writer.finish().await.expect("flushed")).unwrap();
+    assert_eq!(written.lines().count(), 2);
+    assert!(!written.contains("jane@dev.null"));
+
+    let decrypted = key.decrypt_lines(&written).expect("decrypted");
+    assert_eq!(
+        decrypted,
+        "{\"email\":\"jane@dev.null\"}\n{\"done\":true}\n"
+    );
+    // records are sealed with their own nonce, and only open with their key
+    assert_ne!(key.encrypt(b"same").unwrap(), key.encrypt(b"same").unwrap());
+    assert_err!(EncryptionKey::generate().decrypt_lines(&written));
+}
+
+#[cfg(feature = "warc")]
+#[tokio::test]
+async fn requests_and_responses_are_archived_as_warc() {
+    use flate2::read::MultiGzDecoder;
+    use scraped::warc::{WarcFetcher, WarcWriter};
+    use std::io::Read;
+
+    let path = std::env::temp_dir().join(format!("scraped-{}.warc.gz", std::process::id()));
+    let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+    let writer = WarcWriter::create(&path)
+        .await
+        .expect("archive created")
+        .clock(Arc::new(MockClock::new(now)));
+    let site = SiteFetcher(vec![("https://dev.null/a", "<html><h1>A</h1></html>")]);
+    let mut scrape = ConcurrentScrape::default()
+        .fetcher(WarcFetcher::new(writer, SharedFetcher::new(site)))
+        .add_urls(&["https://dev.null/a"])
+        .unwrap();
+    scrape.execute().await.unwrap();
+
+    // each record is a gzip member of its own
+    let mut warc = String::new();
+    MultiGzDecoder::new(fs::File::open(&path).unwrap())
+        .read_to_string(&mut warc)
+        .unwrap();
+    let _ = fs::remove_file(&path);
+    let records: Vec<&str> = warc.split("WARC/1.1\r\n").skip(1).collect();
+    assert_eq!(records.len(), 3);
+    assert!(records[0].starts_with("WARC-Type: warcinfo\r\n"));
+    assert!(records[1].starts_with("WARC-Type: response\r\n"));
+    assert!(records[2].starts_with("WARC-Type: request\r\n"));
+    for record in &records[1..] {
+        assert!(record.contains("WARC-Date: 2024-01-03T12:00:00Z\r\n"));
+        assert!(record.contains("WARC-Target-URI: https://dev.null/a\r\n"));
+    }
+    assert!(records[1].contains("HTTP/1.1 200 OK\r\ncontent-length: 23\r\n"));
+    assert!(records[1].contains("\r\n\r\n<html><h1>A</h1></html>"));
+    assert!(records[2].contains("GET /a HTTP/1.1\r\nHost: dev.null\r\n"));
+
+    // the request is tied to its response
+    let response_id = records[1]
+        .lines()
+        .find_map(|l| l.strip_prefix("WARC-Record-ID: "))
+        .unwrap();
+    assert!(records[2].contains(&format!("WARC-Concurrent-To: {}\r\n", response_id)));
+}